@@ -0,0 +1,341 @@
+// @keyframes rules and the easing functions used by transitions and animations
+use crate::{Condition, Declaration, MediaEnvironment, Parser, Stylesheet, Value};
+
+/// A `@keyframes` rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframes {
+    pub name: String,
+    /// In source order. The animation sorts them by offset.
+    pub frames: Vec<Keyframe>,
+    pub conditions: Vec<Condition>,
+}
+
+/// A block in a `@keyframes` rule, which may apply at several offsets, such as `from, to`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe {
+    /// Positions in the animation, from 0 to 1
+    pub offsets: Vec<f32>,
+    pub declarations: Vec<Declaration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EasingFunction {
+    /// `linear()`, with its control points as (input progress, output) pairs. Plain
+    /// `linear` has no points.
+    Linear(Vec<(f32, f32)>),
+    /// `cubic-bezier(x1, y1, x2, y2)`, and the `ease` keywords
+    CubicBezier(f32, f32, f32, f32),
+    Steps(u32, StepPosition),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepPosition {
+    JumpStart,
+    JumpEnd,
+    JumpNone,
+    JumpBoth,
+}
+
+impl Stylesheet {
+    /// The `@keyframes` rule called `name` that applies in `env`. Later rules win.
+    pub fn find_keyframes(&self, name: &str, env: &MediaEnvironment) -> Option<&Keyframes> {
+        self.keyframes
+            .iter()
+            .rev()
+            .find(|keyframes| keyframes.name == name && keyframes.conditions.iter().all(|c| c.matches(env, &[])))
+    }
+}
+
+impl EasingFunction {
+    pub const EASE: EasingFunction = EasingFunction::CubicBezier(0.25, 0.1, 0.25, 1.0);
+
+    /// The easing function a `*-timing-function` value stands for, which may be a keyword.
+    pub fn from_value(value: &Value) -> Option<EasingFunction> {
+        let keyword = match *value {
+            Value::Easing(ref easing) => return Some(easing.clone()),
+            Value::Keyword(ref keyword) => keyword.to_ascii_lowercase(),
+            _ => return None,
+        };
+        Some(match &*keyword {
+            "linear" => EasingFunction::Linear(Vec::new()),
+            "ease" => EasingFunction::EASE,
+            "ease-in" => EasingFunction::CubicBezier(0.42, 0.0, 1.0, 1.0),
+            "ease-out" => EasingFunction::CubicBezier(0.0, 0.0, 0.58, 1.0),
+            "ease-in-out" => EasingFunction::CubicBezier(0.42, 0.0, 0.58, 1.0),
+            "step-start" => EasingFunction::Steps(1, StepPosition::JumpStart),
+            "step-end" => EasingFunction::Steps(1, StepPosition::JumpEnd),
+            _ => return None,
+        })
+    }
+
+    /// Map input progress, from 0 to 1, to output progress.
+    pub fn apply(&self, x: f32) -> f32 {
+        match *self {
+            EasingFunction::Linear(ref points) if points.len() < 2 => x,
+            EasingFunction::Linear(ref points) => {
+                // Outside the points, the first or last segment is extended
+                let i = points[1..points.len() - 1].iter().take_while(|&&(input, _)| input <= x).count();
+                let ((x1, y1), (x2, y2)) = (points[i], points[i + 1]);
+                if x2 <= x1 {
+                    return y2;
+                }
+                y1 + (y2 - y1) * (x - x1) / (x2 - x1)
+            }
+            EasingFunction::CubicBezier(x1, y1, x2, y2) => {
+                let t = solve_bezier(x1, x2, x);
+                bezier(y1, y2, t)
+            }
+            EasingFunction::Steps(steps, position) => {
+                let steps = steps as f32;
+                let mut step = (x * steps).floor();
+                if matches!(position, StepPosition::JumpStart | StepPosition::JumpBoth) {
+                    step += 1.0;
+                }
+                let jumps = match position {
+                    StepPosition::JumpBoth => steps + 1.0,
+                    StepPosition::JumpNone => steps - 1.0,
+                    _ => steps,
+                };
+                if x >= 0.0 && step < 0.0 {
+                    step = 0.0;
+                }
+                if x <= 1.0 && step > jumps {
+                    step = jumps;
+                }
+                step / jumps
+            }
+        }
+    }
+}
+
+/// One coordinate of a cubic Bézier curve from 0 to 1 with control points `p1` and `p2`.
+fn bezier(p1: f32, p2: f32, t: f32) -> f32 {
+    let u = 1.0 - t;
+    3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+}
+
+/// Find the parameter at which the curve's x coordinate is `x`, by bisection. The x
+/// control points are between 0 and 1, so x only increases along the curve.
+fn solve_bezier(x1: f32, x2: f32, x: f32) -> f32 {
+    let (mut low, mut high) = (0.0, 1.0);
+    if x <= 0.0 || x >= 1.0 {
+        return x.clamp(0.0, 1.0);
+    }
+    for _ in 0..32 {
+        let t = (low + high) / 2.0;
+        if bezier(x1, x2, t) < x {
+            low = t;
+        } else {
+            high = t;
+        }
+    }
+    (low + high) / 2.0
+}
+
+impl Parser {
+    /// Parse a `@keyframes` rule, positioned at its `{`. Keyframe blocks with an invalid
+    /// selector are dropped.
+    pub(crate) fn parse_keyframes(&mut self, prelude: &str, conditions: &[Condition]) -> Option<Keyframes> {
+        let Some(name) = keyframes_name(prelude) else {
+            self.skip_balanced();
+            return None;
+        };
+        self.consume_char(); // consume '{'
+        let mut frames = Vec::new();
+        loop {
+            self.consume_whitespace();
+            if self.eof() || self.next_char() == '}' {
+                break;
+            }
+            let selector = self.parse_prelude();
+            if self.next_char() != '{' {
+                if self.next_char() == ';' {
+                    self.consume_char();
+                }
+                continue;
+            }
+            self.consume_char(); // consume '{'
+            let declarations = self.parse_declaration_list();
+            if !self.eof() {
+                self.consume_char(); // consume '}'
+            }
+            if let Some(offsets) = parse_keyframe_selectors(&selector) {
+                frames.push(Keyframe { offsets, declarations });
+            }
+        }
+        if !self.eof() {
+            self.consume_char(); // consume '}'
+        }
+        Some(Keyframes {
+            name,
+            frames,
+            conditions: conditions.to_vec(),
+        })
+    }
+
+    /// Parse `cubic-bezier()`, `steps()` or `linear()`, positioned at the `(`.
+    pub(crate) fn parse_easing_function(&mut self, name: &str) -> Option<Value> {
+        let start = self.pos + 1;
+        self.skip_balanced();
+        let arguments: Vec<&str> = self.input[start..self.pos - 1].split(',').map(str::trim).collect();
+        let easing = match &*name.to_ascii_lowercase() {
+            "cubic-bezier" => {
+                let numbers: Vec<f32> = arguments.iter().map(|a| a.parse().ok()).collect::<Option<_>>()?;
+                let [x1, y1, x2, y2] = numbers[..] else {
+                    return None;
+                };
+                if !(0.0..=1.0).contains(&x1) || !(0.0..=1.0).contains(&x2) {
+                    return None;
+                }
+                EasingFunction::CubicBezier(x1, y1, x2, y2)
+            }
+            "steps" => {
+                let steps: u32 = arguments[0].parse().ok()?;
+                let position = match arguments.get(1).map(|a| a.to_ascii_lowercase()).as_deref() {
+                    None | Some("end") | Some("jump-end") => StepPosition::JumpEnd,
+                    Some("start") | Some("jump-start") => StepPosition::JumpStart,
+                    Some("jump-none") => StepPosition::JumpNone,
+                    Some("jump-both") => StepPosition::JumpBoth,
+                    Some(_) => return None,
+                };
+                let minimum = if position == StepPosition::JumpNone { 2 } else { 1 };
+                if steps < minimum || arguments.len() > 2 {
+                    return None;
+                }
+                EasingFunction::Steps(steps, position)
+            }
+            _ => EasingFunction::Linear(parse_linear_stops(&arguments)?),
+        };
+        Some(Value::Easing(easing))
+    }
+}
+
+/// A keyframes name is an identifier or a string.
+fn keyframes_name(prelude: &str) -> Option<String> {
+    let quoted = prelude.len() >= 2
+        && (prelude.starts_with('"') && prelude.ends_with('"') || prelude.starts_with('\'') && prelude.ends_with('\''));
+    if quoted {
+        return Some(prelude[1..prelude.len() - 1].to_string());
+    }
+    let valid = !prelude.is_empty()
+        && prelude.chars().all(crate::valid_identifier_char)
+        && !prelude.starts_with(|c: char| c.is_ascii_digit())
+        && !matches!(&*prelude.to_ascii_lowercase(), "none" | "initial" | "inherit" | "unset" | "default");
+    valid.then(|| prelude.to_string())
+}
+
+/// Parse `from`, `to` and percentages into offsets from 0 to 1.
+fn parse_keyframe_selectors(selector: &str) -> Option<Vec<f32>> {
+    selector
+        .split(',')
+        .map(|offset| match &*offset.trim().to_ascii_lowercase() {
+            "from" => Some(0.0),
+            "to" => Some(1.0),
+            percentage => {
+                let value: f32 = percentage.strip_suffix('%')?.parse().ok()?;
+                (0.0..=100.0).contains(&value).then_some(value / 100.0)
+            }
+        })
+        .collect()
+}
+
+/// Parse the stops of `linear()`, each an output with up to two input percentages, and
+/// fill in missing inputs so that they are evenly spread and never decrease.
+fn parse_linear_stops(arguments: &[&str]) -> Option<Vec<(f32, f32)>> {
+    let mut points: Vec<(Option<f32>, f32)> = Vec::new();
+    for argument in arguments {
+        let mut parts = argument.split_whitespace();
+        let output: f32 = parts.next()?.parse().ok()?;
+        let inputs: Vec<f32> = parts
+            .map(|part| part.strip_suffix('%')?.parse::<f32>().ok().map(|p| p / 100.0))
+            .collect::<Option<_>>()?;
+        match inputs[..] {
+            [] => points.push((None, output)),
+            [input] => points.push((Some(input), output)),
+            [from, to] => points.extend([(Some(from), output), (Some(to), output)]),
+            _ => return None,
+        }
+    }
+    if points.len() < 2 {
+        return None;
+    }
+
+    let last = points.len() - 1;
+    points[0].0.get_or_insert(0.0);
+    let mut largest = f32::MIN;
+    for point in &mut points {
+        if let Some(ref mut input) = point.0 {
+            *input = input.max(largest);
+            largest = *input;
+        }
+    }
+    if points[last].0.is_none() {
+        points[last].0 = Some(largest.max(1.0));
+    }
+    let mut resolved = Vec::with_capacity(points.len());
+    let mut i = 0;
+    while i < points.len() {
+        let Some(input) = points[i].0 else {
+            // Spread a run of missing inputs between the known ones around it
+            let end = i + points[i..].iter().take_while(|point| point.0.is_none()).count();
+            let (from, to) = (resolved.last().map_or(0.0, |&(input, _)| input), points[end].0.unwrap());
+            for (j, point) in points[i..end].iter().enumerate() {
+                let fraction = (j + 1) as f32 / (end - i + 1) as f32;
+                resolved.push((from + (to - from) * fraction, point.1));
+            }
+            i = end;
+            continue;
+        };
+        resolved.push((input, points[i].1));
+        i += 1;
+    }
+    Some(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_keyframes_and_timing_values() {
+        let stylesheet = parse(
+            "@keyframes pulse { from, to { color: #000000; } 50% { color: #ffffff; } 120% { color: red; } }
+             @keyframes none { from { color: red; } }
+             p { transition-duration: 200ms, 1s; animation-timing-function: steps(4, jump-start);
+                 animation-iteration-count: infinite; transition-timing-function: linear(0, 0.25 75%, 1); }",
+        );
+        assert_eq!(stylesheet.keyframes.len(), 1);
+        let pulse = stylesheet.find_keyframes("pulse", &MediaEnvironment::default()).unwrap();
+        let offsets: Vec<_> = pulse.frames.iter().map(|frame| frame.offsets.clone()).collect();
+        assert_eq!(offsets, [vec![0.0, 1.0], vec![0.5]]);
+
+        let values: Vec<_> = stylesheet.rules[0].declarations.iter().map(|d| d.value.clone()).collect();
+        assert_eq!(values[0], Value::List(vec![Value::Time(0.2), Value::Time(1.0)]));
+        assert_eq!(values[1], Value::Easing(EasingFunction::Steps(4, StepPosition::JumpStart)));
+        assert_eq!(values[2], Value::Keyword("infinite".to_string()));
+        assert_eq!(
+            values[3],
+            Value::Easing(EasingFunction::Linear(vec![(0.0, 0.0), (0.75, 0.25), (1.0, 1.0)]))
+        );
+    }
+
+    #[test]
+    fn test_easing_functions() {
+        let ease = |value: &str, x: f32| {
+            let easing = EasingFunction::from_value(&parse_value(value).unwrap()).unwrap();
+            (easing.apply(x) * 1000.0).round() / 1000.0
+        };
+        assert_eq!(ease("linear", 0.3), 0.3);
+        assert_eq!(ease("ease-in-out", 0.5), 0.5);
+        assert_eq!(ease("ease", 0.25), 0.409);
+        assert_eq!(ease("cubic-bezier(0, 0, 1, 1)", 0.7), 0.7);
+        assert_eq!(ease("steps(4)", 0.3), 0.25);
+        assert_eq!(ease("steps(4, jump-start)", 0.3), 0.5);
+        assert_eq!(ease("steps(3, jump-none)", 0.5), 0.5);
+        assert_eq!(ease("step-end", 0.99), 0.0);
+        assert_eq!(ease("linear(0, 0.25 75%, 1)", 0.375), 0.125);
+        assert_eq!(ease("linear(0, 0.25 75%, 1)", 0.875), 0.625);
+        assert_eq!(parse_value("steps(0)"), None);
+        assert_eq!(parse_value("cubic-bezier(2, 0, 1, 1)"), None);
+    }
+}
@@ -0,0 +1,258 @@
+// Math functions: calc(), min(), max() and clamp()
+use crate::{unit_from_ident, Parser, Unit, Value};
+
+/// A math function expression. Parenthesised sub-expressions and nested `calc()` calls
+/// are flattened into the tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcNode {
+    Number(f32),
+    /// A length or percentage
+    Dimension(f32, Unit),
+    Add(Box<CalcNode>, Box<CalcNode>),
+    Sub(Box<CalcNode>, Box<CalcNode>),
+    Mul(Box<CalcNode>, Box<CalcNode>),
+    Div(Box<CalcNode>, Box<CalcNode>),
+    Min(Vec<CalcNode>),
+    Max(Vec<CalcNode>),
+    /// `clamp(min, value, max)`
+    Clamp(Box<CalcNode>, Box<CalcNode>, Box<CalcNode>),
+}
+
+/// The type of a math expression, per CSS Values 4.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CalcType {
+    Number,
+    Length,
+    Percentage,
+    LengthPercentage,
+}
+
+impl CalcType {
+    /// The type of a sum, or `None` if the operands can't be added.
+    fn add(self, other: CalcType) -> Option<CalcType> {
+        match (self, other) {
+            (a, b) if a == b => Some(a),
+            (CalcType::Number, _) | (_, CalcType::Number) => None,
+            _ => Some(CalcType::LengthPercentage),
+        }
+    }
+}
+
+impl CalcNode {
+    /// Type-check the expression, returning `None` if it is invalid.
+    pub fn calc_type(&self) -> Option<CalcType> {
+        match *self {
+            CalcNode::Number(_) => Some(CalcType::Number),
+            CalcNode::Dimension(_, Unit::Percent) => Some(CalcType::Percentage),
+            CalcNode::Dimension(..) => Some(CalcType::Length),
+            CalcNode::Add(ref a, ref b) | CalcNode::Sub(ref a, ref b) => a.calc_type()?.add(b.calc_type()?),
+            // At least one side of a product has to be a plain number
+            CalcNode::Mul(ref a, ref b) => match (a.calc_type()?, b.calc_type()?) {
+                (CalcType::Number, other) | (other, CalcType::Number) => Some(other),
+                _ => None,
+            },
+            // And the divisor always has to be a number
+            CalcNode::Div(ref a, ref b) => match (a.calc_type()?, b.calc_type()?) {
+                (dividend, CalcType::Number) => Some(dividend),
+                _ => None,
+            },
+            CalcNode::Min(ref args) | CalcNode::Max(ref args) => {
+                let mut result = args.first()?.calc_type()?;
+                for arg in &args[1..] {
+                    result = result.add(arg.calc_type()?)?;
+                }
+                Some(result)
+            }
+            CalcNode::Clamp(ref min, ref value, ref max) => {
+                min.calc_type()?.add(value.calc_type()?)?.add(max.calc_type()?)
+            }
+        }
+    }
+
+    /// Evaluate the expression, using `resolve` to convert each dimension to px.
+    /// Returns `None` if any dimension can't be resolved yet.
+    pub fn evaluate(&self, resolve: &dyn Fn(f32, Unit) -> Option<f32>) -> Option<f32> {
+        Some(match *self {
+            CalcNode::Number(n) => n,
+            CalcNode::Dimension(v, unit) => resolve(v, unit)?,
+            CalcNode::Add(ref a, ref b) => a.evaluate(resolve)? + b.evaluate(resolve)?,
+            CalcNode::Sub(ref a, ref b) => a.evaluate(resolve)? - b.evaluate(resolve)?,
+            CalcNode::Mul(ref a, ref b) => a.evaluate(resolve)? * b.evaluate(resolve)?,
+            CalcNode::Div(ref a, ref b) => a.evaluate(resolve)? / b.evaluate(resolve)?,
+            CalcNode::Min(ref args) => args
+                .iter()
+                .map(|arg| arg.evaluate(resolve))
+                .try_fold(f32::INFINITY, |min, v| Some(min.min(v?)))?,
+            CalcNode::Max(ref args) => args
+                .iter()
+                .map(|arg| arg.evaluate(resolve))
+                .try_fold(f32::NEG_INFINITY, |max, v| Some(max.max(v?)))?,
+            // The minimum wins if it is larger than the maximum
+            CalcNode::Clamp(ref min, ref value, ref max) => {
+                let (min, value, max) = (min.evaluate(resolve)?, value.evaluate(resolve)?, max.evaluate(resolve)?);
+                min.max(value.min(max))
+            }
+        })
+    }
+}
+
+/// Whether `name` is a math function we parse into a `CalcNode`.
+pub(crate) fn is_math_function(name: &str) -> bool {
+    matches!(&*name.to_ascii_lowercase(), "calc" | "min" | "max" | "clamp")
+}
+
+impl Parser {
+    /// Parse a math function whose name has already been consumed. Expects to be
+    /// positioned at the `(`. Returns `None` for syntax and type errors.
+    pub(crate) fn parse_math_function(&mut self, name: &str) -> Option<Value> {
+        let node = self.parse_math_arguments(name)?;
+        node.calc_type()?;
+        Some(Value::Calc(node))
+    }
+
+    fn parse_math_arguments(&mut self, name: &str) -> Option<CalcNode> {
+        self.consume_char(); // consume '('
+        let mut args = Vec::new();
+        loop {
+            self.consume_whitespace();
+            args.push(self.parse_calc_sum()?);
+            self.consume_whitespace();
+            match self.consume_char() {
+                ',' => continue,
+                ')' => break,
+                _ => return None,
+            }
+        }
+
+        match (&*name.to_ascii_lowercase(), args.len()) {
+            ("calc", 1) => args.pop(),
+            ("min", _) => Some(CalcNode::Min(args)),
+            ("max", _) => Some(CalcNode::Max(args)),
+            ("clamp", 3) => {
+                let max = args.pop()?;
+                let value = args.pop()?;
+                let min = args.pop()?;
+                Some(CalcNode::Clamp(Box::new(min), Box::new(value), Box::new(max)))
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_calc_sum(&mut self) -> Option<CalcNode> {
+        let mut node = self.parse_calc_product()?;
+        loop {
+            let start = self.pos;
+            self.consume_whitespace();
+            let op = self.next_char();
+            // `+` and `-` must be surrounded by whitespace, to tell them apart from signs
+            if start == self.pos || !matches!(op, '+' | '-') {
+                self.pos = start;
+                return Some(node);
+            }
+            self.consume_char();
+            if !self.next_char().is_whitespace() {
+                return None;
+            }
+            self.consume_whitespace();
+            let rhs = Box::new(self.parse_calc_product()?);
+            node = match op {
+                '+' => CalcNode::Add(Box::new(node), rhs),
+                _ => CalcNode::Sub(Box::new(node), rhs),
+            };
+        }
+    }
+
+    fn parse_calc_product(&mut self) -> Option<CalcNode> {
+        let mut node = self.parse_calc_value()?;
+        loop {
+            let start = self.pos;
+            self.consume_whitespace();
+            let op = self.next_char();
+            if !matches!(op, '*' | '/') {
+                self.pos = start;
+                return Some(node);
+            }
+            self.consume_char();
+            self.consume_whitespace();
+            let rhs = Box::new(self.parse_calc_value()?);
+            node = match op {
+                '*' => CalcNode::Mul(Box::new(node), rhs),
+                _ => CalcNode::Div(Box::new(node), rhs),
+            };
+        }
+    }
+
+    fn parse_calc_value(&mut self) -> Option<CalcNode> {
+        if self.next_char() == '(' {
+            return self.parse_math_arguments("calc");
+        }
+        if self.starts_number() {
+            let number = self.parse_number();
+            if self.next_char() == '%' {
+                self.consume_char();
+                return Some(CalcNode::Dimension(number, Unit::Percent));
+            }
+            let unit = self.parse_identifier();
+            if unit.is_empty() {
+                return Some(CalcNode::Number(number));
+            }
+            return Some(CalcNode::Dimension(number, unit_from_ident(&unit.to_ascii_lowercase())?));
+        }
+        let name = self.parse_identifier();
+        if self.next_char() == '(' && is_math_function(&name) {
+            return self.parse_math_arguments(&name);
+        }
+        match &*name.to_ascii_lowercase() {
+            "pi" => Some(CalcNode::Number(std::f32::consts::PI)),
+            "e" => Some(CalcNode::Number(std::f32::consts::E)),
+            "infinity" => Some(CalcNode::Number(f32::INFINITY)),
+            "-infinity" => Some(CalcNode::Number(f32::NEG_INFINITY)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn calc(source: &str) -> Option<CalcNode> {
+        match parse_value(source) {
+            Some(Value::Calc(node)) => Some(node),
+            _ => None,
+        }
+    }
+
+    fn px(v: f32, unit: Unit) -> Option<f32> {
+        match unit {
+            Unit::Px => Some(v),
+            Unit::Em => Some(v * 10.0),
+            Unit::Percent => Some(v * 2.0),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_and_evaluate() {
+        let node = calc("calc(100% - 2 * (1em + 5px))").unwrap();
+        assert_eq!(node.calc_type(), Some(CalcType::LengthPercentage));
+        assert_eq!(node.evaluate(&px), Some(170.0));
+
+        let node = calc("clamp(10px, 50% / 2, max(1em, 30px))").unwrap();
+        assert_eq!(node.calc_type(), Some(CalcType::LengthPercentage));
+        assert_eq!(node.evaluate(&px), Some(30.0));
+
+        assert_eq!(calc("min(3em, calc(-1 * 4px))").unwrap().evaluate(&px), Some(-4.0));
+        assert_eq!(calc("calc(2 * pi)").unwrap().calc_type(), Some(CalcType::Number));
+    }
+
+    #[test]
+    fn test_type_errors() {
+        assert_eq!(calc("calc(1px + 2)"), None);
+        assert_eq!(calc("calc(1px * 2px)"), None);
+        assert_eq!(calc("calc(10 / 2px)"), None);
+        assert_eq!(calc("calc(1px -2px)"), None);
+        assert_eq!(calc("clamp(1px, 2px)"), None);
+        assert_eq!(calc("calc(1px +2px)"), None);
+    }
+}
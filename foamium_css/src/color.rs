@@ -0,0 +1,277 @@
+// Named colors, hex color notation and rgb()
+use crate::{Color, Parser};
+
+impl Color {
+    /// Look up a CSS named color, including `transparent`.
+    pub fn from_name(name: &str) -> Option<Color> {
+        let name = name.to_ascii_lowercase();
+        if name == "transparent" {
+            return Some(Color::new(0, 0, 0, 0));
+        }
+        let index = NAMED_COLORS.binary_search_by_key(&&*name, |&(n, _)| n).ok()?;
+        let rgb = NAMED_COLORS[index].1;
+        Some(Color::new((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8, 255))
+    }
+
+    /// The shortest name for this color, if it has one.
+    pub(crate) fn shortest_name(&self) -> Option<&'static str> {
+        if *self == Color::new(0, 0, 0, 0) {
+            return Some("transparent");
+        }
+        if self.a != 255 {
+            return None;
+        }
+        let rgb = (self.r as u32) << 16 | (self.g as u32) << 8 | self.b as u32;
+        NAMED_COLORS
+            .iter()
+            .filter(|&&(_, value)| value == rgb)
+            .map(|&(name, _)| name)
+            .min_by_key(|name| name.len())
+    }
+
+    /// Parse the digits of a `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa` color.
+    pub fn from_hex(digits: &str) -> Option<Color> {
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit = |i: usize| u8::from_str_radix(&digits[i..i + 1], 16).ok().map(|d| d * 17);
+        let pair = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+        match digits.len() {
+            3 => Some(Color::new(digit(0)?, digit(1)?, digit(2)?, 255)),
+            4 => Some(Color::new(digit(0)?, digit(1)?, digit(2)?, digit(3)?)),
+            6 => Some(Color::new(pair(0)?, pair(2)?, pair(4)?, 255)),
+            8 => Some(Color::new(pair(0)?, pair(2)?, pair(4)?, pair(6)?)),
+            _ => None,
+        }
+    }
+}
+
+impl Parser {
+    /// Parse the arguments of `rgb()` or `rgba()`, in either the legacy comma separated
+    /// syntax or the modern space separated one. Expects to be positioned at the `(`.
+    pub(crate) fn parse_rgb_function(&mut self) -> Option<Color> {
+        self.consume_char(); // consume '('
+        let arguments = self.consume_while(|c| c != ')');
+        if self.consume_char() != ')' {
+            return None;
+        }
+
+        let (channels, alpha): (Vec<&str>, Option<&str>) = if arguments.contains(',') {
+            let mut parts: Vec<&str> = arguments.split(',').collect();
+            let alpha = if parts.len() == 4 { parts.pop() } else { None };
+            (parts, alpha)
+        } else {
+            let (channels, alpha) = match arguments.split_once('/') {
+                Some((channels, alpha)) => (channels, Some(alpha)),
+                None => (&*arguments, None),
+            };
+            (channels.split_whitespace().collect(), alpha)
+        };
+        if channels.len() != 3 {
+            return None;
+        }
+
+        let channel = |s: &str| Some((component(s, 255.0 / 100.0)?.clamp(0.0, 255.0)).round() as u8);
+        let alpha = match alpha {
+            Some(alpha) => (component(alpha, 1.0 / 100.0)?.clamp(0.0, 1.0) * 255.0).round() as u8,
+            None => 255,
+        };
+        Some(Color::new(channel(channels[0])?, channel(channels[1])?, channel(channels[2])?, alpha))
+    }
+}
+
+/// Parse a number, or a percentage scaled by `percent`.
+fn component(s: &str, percent: f32) -> Option<f32> {
+    let s = s.trim();
+    if !s.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '.' | '+' | '-')) {
+        return None;
+    }
+    match s.strip_suffix('%') {
+        Some(percentage) => Some(percentage.parse::<f32>().ok()? * percent),
+        None => s.parse().ok(),
+    }
+}
+
+/// The CSS named colors, sorted by name.
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_and_hex_colors() {
+        assert!(NAMED_COLORS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(Color::from_name("Red"), Some(Color::new(255, 0, 0, 255)));
+        assert_eq!(Color::from_name("rebeccapurple"), Some(Color::new(0x66, 0x33, 0x99, 255)));
+        assert_eq!(Color::from_name("bogus"), None);
+        assert_eq!(Color::from_hex("fff"), Some(Color::new(255, 255, 255, 255)));
+        assert_eq!(Color::from_hex("3584e480"), Some(Color::new(0x35, 0x84, 0xe4, 0x80)));
+        assert_eq!(Color::from_hex("zzzzzz"), None);
+        assert_eq!(Color::new(0, 255, 255, 255).shortest_name(), Some("aqua"));
+        assert_eq!(Color::new(0, 255, 255, 128).shortest_name(), None);
+    }
+
+    #[test]
+    fn test_rgb_functions() {
+        let color = |source: &str| match crate::parse_value(source) {
+            Some(crate::Value::ColorValue(color)) => Some(color),
+            _ => None,
+        };
+        assert_eq!(color("rgb(255, 0, 0)"), Some(Color::new(255, 0, 0, 255)));
+        assert_eq!(color("rgba(0, 0, 0, 0.5)"), Some(Color::new(0, 0, 0, 128)));
+        assert_eq!(color("rgb(100% 50% 0 / 25%)"), Some(Color::new(255, 128, 0, 64)));
+        assert_eq!(color("RGB(300, -5, 0)"), Some(Color::new(255, 0, 0, 255)));
+        assert_eq!(color("rgb(1, 2)"), None);
+        assert_eq!(color("rgb(red, 0, 0)"), None);
+    }
+}
@@ -0,0 +1,139 @@
+// @container rules and size queries
+use crate::media::{font_relative_px, FeatureSource, FeatureValue};
+use crate::{MediaCondition, MediaEnvironment, Parser, Unit};
+
+/// The prelude of an `@container` rule: an optional container name and a size query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerQuery {
+    pub name: Option<String>,
+    pub condition: MediaCondition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContainerType {
+    /// Queries can test both axes
+    Size,
+    /// Queries can only test the inline axis
+    InlineSize,
+}
+
+/// An ancestor element that size queries can be evaluated against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryContainer {
+    /// The element's `container-name`s
+    pub names: Vec<String>,
+    pub container_type: ContainerType,
+    /// The width of the container's content box in px
+    pub width: f32,
+    /// The height of the content box, for `size` containers
+    pub height: Option<f32>,
+    /// The container's font size, which relative lengths in the query resolve against
+    pub font_size: f32,
+}
+
+impl ContainerQuery {
+    /// Evaluate against the nearest matching container in `containers`, which lists
+    /// the element's query containers from outermost to innermost. With no matching
+    /// container, the query doesn't match.
+    pub fn matches(&self, containers: &[QueryContainer], env: &MediaEnvironment) -> bool {
+        let container = containers
+            .iter()
+            .rev()
+            .find(|container| self.name.as_ref().is_none_or(|name| container.names.contains(name)));
+        let Some(container) = container else {
+            return false;
+        };
+        self.condition
+            .evaluate_in(&ContainerFeatures { container, env })
+            .unwrap_or(false)
+    }
+}
+
+struct ContainerFeatures<'a> {
+    container: &'a QueryContainer,
+    env: &'a MediaEnvironment,
+}
+
+impl FeatureSource for ContainerFeatures<'_> {
+    fn feature_value(&self, name: &str) -> Option<FeatureValue> {
+        let width = self.container.width;
+        // The block axis is unknown in an `inline-size` container
+        let height = self.container.height;
+        let value = match name {
+            "width" | "inline-size" => FeatureValue::Number(width),
+            "height" | "block-size" => FeatureValue::Number(height?),
+            "aspect-ratio" => FeatureValue::Number(width / height?),
+            "orientation" => FeatureValue::Ident(if height? >= width { "portrait" } else { "landscape" }),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn unit_px(&self, unit: Unit) -> Option<f32> {
+        unit.absolute_px()
+            .or_else(|| unit.viewport_px(self.env.viewport_width, self.env.viewport_height))
+            .or_else(|| font_relative_px(unit, self.container.font_size))
+    }
+}
+
+impl Parser {
+    /// Parse the prelude of an `@container` rule. Returns `None` if it is invalid, in
+    /// which case the rule is ignored.
+    pub(crate) fn parse_container_query(prelude: &str) -> Option<ContainerQuery> {
+        let mut parser = Parser::new(prelude);
+        let start = parser.pos;
+        let ident = parser.parse_identifier();
+        let name = match &*ident.to_ascii_lowercase() {
+            "" | "not" | "and" | "or" | "none" => None,
+            // A function, like `style()`, starts the condition
+            _ if parser.next_char() == '(' => None,
+            _ => Some(ident),
+        };
+        if name.is_none() {
+            parser.pos = start;
+        }
+
+        let condition = parser.parse_media_condition()?;
+        parser.consume_whitespace();
+        if !parser.eof() {
+            return None;
+        }
+        Some(ContainerQuery { name, condition })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(name: &str, container_type: ContainerType, width: f32, height: Option<f32>) -> QueryContainer {
+        QueryContainer {
+            names: vec![name.to_string()],
+            container_type,
+            width,
+            height,
+            font_size: 20.0,
+        }
+    }
+
+    #[test]
+    fn test_container_queries() {
+        let env = MediaEnvironment::default();
+        let containers = [
+            container("page", ContainerType::Size, 1000.0, Some(800.0)),
+            container("card", ContainerType::InlineSize, 300.0, None),
+        ];
+        let query = |prelude: &str| Parser::parse_container_query(prelude).unwrap();
+
+        // The nearest container wins unless the query names another one
+        assert!(query("(width < 400px)").matches(&containers, &env));
+        assert!(query("page (width > 40em)").matches(&containers, &env));
+        assert!(!query("card (inline-size > 20em)").matches(&containers, &env));
+        // An inline-size container can't answer questions about its height
+        assert!(!query("(height > 0px)").matches(&containers, &env));
+        assert!(query("page (orientation: landscape)").matches(&containers, &env));
+        assert!(!query("sidebar (width > 0px)").matches(&containers, &env));
+        assert!(!query("(width > 0px)").matches(&[], &env));
+        assert_eq!(Parser::parse_container_query("card"), None);
+    }
+}
@@ -0,0 +1,387 @@
+// @font-face rules and their descriptors
+use crate::{Condition, Parser, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFace {
+    pub family: String,
+    pub sources: Vec<FontSource>,
+    pub weight: FontWeightRange,
+    /// Font stretch range as percentages of the normal width
+    pub stretch: (f32, f32),
+    pub style: FontFaceStyle,
+    pub unicode_range: Vec<UnicodeRange>,
+    pub display: FontDisplay,
+    pub conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontSource {
+    Url { url: String, format: Option<String> },
+    Local(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontWeightRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FontFaceStyle {
+    Normal,
+    Italic,
+    /// Range of slant angles in degrees
+    Oblique(f32, f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnicodeRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FontDisplay {
+    Auto,
+    Block,
+    Swap,
+    Fallback,
+    Optional,
+}
+
+/// Font formats we know how to decode; sources hinting at anything else are skipped.
+pub(crate) const SUPPORTED_FORMATS: &[&str] = &["woff2", "woff", "truetype", "opentype", "collection"];
+
+impl FontFace {
+    /// Build a font face from its raw descriptors. Returns `None` if `font-family` or `src`
+    /// is missing or invalid, in which case the whole rule is ignored.
+    fn from_descriptors(descriptors: &[(String, String)], conditions: &[Condition]) -> Option<FontFace> {
+        let mut face = FontFace {
+            family: String::new(),
+            sources: Vec::new(),
+            weight: FontWeightRange { min: 400.0, max: 400.0 },
+            stretch: (100.0, 100.0),
+            style: FontFaceStyle::Normal,
+            unicode_range: vec![UnicodeRange { start: 0, end: 0x10FFFF }],
+            display: FontDisplay::Auto,
+            conditions: conditions.to_vec(),
+        };
+
+        // Invalid descriptors are ignored, leaving the initial value in place
+        for (name, value) in descriptors {
+            match &*name.to_ascii_lowercase() {
+                "font-family" => face.family = parse_family_name(value).unwrap_or_default(),
+                "src" => face.sources = parse_sources(value),
+                "font-weight" => {
+                    if let Some(weight) = parse_weight_range(value) {
+                        face.weight = weight;
+                    }
+                }
+                "font-stretch" => {
+                    if let Some(stretch) = parse_stretch_range(value) {
+                        face.stretch = stretch;
+                    }
+                }
+                "font-style" => {
+                    if let Some(style) = parse_style(value) {
+                        face.style = style;
+                    }
+                }
+                "unicode-range" => {
+                    if let Some(ranges) = parse_unicode_ranges(value) {
+                        face.unicode_range = ranges;
+                    }
+                }
+                "font-display" => {
+                    if let Some(display) = parse_display(value) {
+                        face.display = display;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if face.family.is_empty() || face.sources.is_empty() {
+            return None;
+        }
+        Some(face)
+    }
+
+    /// Whether this face has a glyph range covering `c`.
+    pub fn covers(&self, c: char) -> bool {
+        let c = c as u32;
+        self.unicode_range.iter().any(|range| range.start <= c && c <= range.end)
+    }
+
+    /// The sources worth trying, in order: local fonts and URLs in a supported format.
+    pub fn loadable_sources(&self) -> impl Iterator<Item = &FontSource> {
+        self.sources.iter().filter(|source| match source {
+            FontSource::Url { format: Some(format), .. } => SUPPORTED_FORMATS.contains(&&**format),
+            _ => true,
+        })
+    }
+}
+
+impl FontWeightRange {
+    pub fn contains(&self, weight: f32) -> bool {
+        self.min <= weight && weight <= self.max
+    }
+}
+
+impl Parser {
+    /// Parse the body of an `@font-face` rule.
+    pub(crate) fn parse_font_face(&mut self, conditions: &[Condition]) -> Option<FontFace> {
+        let descriptors = self.parse_raw_declarations();
+        FontFace::from_descriptors(&descriptors, conditions)
+    }
+}
+
+fn parse_family_name(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.starts_with('"') || value.starts_with('\'') {
+        let mut parser = Parser::new(value);
+        let name = parser.parse_string();
+        parser.consume_whitespace();
+        return if parser.eof() { Some(name) } else { None };
+    }
+    // An unquoted family name is a sequence of identifiers
+    let idents: Vec<&str> = value.split_whitespace().collect();
+    if idents.is_empty() || value.contains(',') {
+        return None;
+    }
+    Some(idents.join(" "))
+}
+
+/// Parse a `font-family` list. Unquoted names of a single identifier, which include the
+/// generic families, stay keywords; other names become strings.
+pub(crate) fn parse_font_family(value: &str) -> Option<Value> {
+    let mut families = Vec::new();
+    for item in split_top_level(value, ',') {
+        let name = parse_family_name(item)?;
+        let quoted = item.trim_start().starts_with(['"', '\'']);
+        families.push(if quoted || name.contains(' ') {
+            Value::String(name)
+        } else {
+            Value::Keyword(name)
+        });
+    }
+    match families.len() {
+        1 => families.pop(),
+        _ => Some(Value::List(families)),
+    }
+}
+
+fn parse_sources(value: &str) -> Vec<FontSource> {
+    let mut sources = Vec::new();
+    for source in split_top_level(value, ',') {
+        let mut parser = Parser::new(source.trim());
+        let function = parser.parse_identifier().to_ascii_lowercase();
+        if parser.next_char() != '(' {
+            continue;
+        }
+        let argument = parser.parse_function_argument();
+        parser.consume_whitespace();
+        match &*function {
+            "url" => {
+                let mut format = None;
+                if parser.parse_identifier().eq_ignore_ascii_case("format") && parser.next_char() == '(' {
+                    format = Some(parser.parse_function_argument().to_ascii_lowercase());
+                }
+                sources.push(FontSource::Url { url: argument, format });
+            }
+            "local" => sources.push(FontSource::Local(argument)),
+            _ => {}
+        }
+    }
+    sources
+}
+
+fn parse_weight_range(value: &str) -> Option<FontWeightRange> {
+    let weights = value
+        .split_whitespace()
+        .map(|word| match &*word.to_ascii_lowercase() {
+            "normal" => Some(400.0),
+            "bold" => Some(700.0),
+            number => number.parse::<f32>().ok().filter(|w| (1.0..=1000.0).contains(w)),
+        })
+        .collect::<Option<Vec<f32>>>()?;
+    match *weights {
+        [weight] => Some(FontWeightRange { min: weight, max: weight }),
+        [a, b] => Some(FontWeightRange { min: a.min(b), max: a.max(b) }),
+        _ => None,
+    }
+}
+
+fn parse_stretch_range(value: &str) -> Option<(f32, f32)> {
+    let stretches = value
+        .split_whitespace()
+        .map(|word| match &*word.to_ascii_lowercase() {
+            "ultra-condensed" => Some(50.0),
+            "extra-condensed" => Some(62.5),
+            "condensed" => Some(75.0),
+            "semi-condensed" => Some(87.5),
+            "normal" => Some(100.0),
+            "semi-expanded" => Some(112.5),
+            "expanded" => Some(125.0),
+            "extra-expanded" => Some(150.0),
+            "ultra-expanded" => Some(200.0),
+            percentage => percentage.strip_suffix('%')?.parse::<f32>().ok().filter(|p| *p >= 0.0),
+        })
+        .collect::<Option<Vec<f32>>>()?;
+    match *stretches {
+        [stretch] => Some((stretch, stretch)),
+        [a, b] => Some((a.min(b), a.max(b))),
+        _ => None,
+    }
+}
+
+fn parse_style(value: &str) -> Option<FontFaceStyle> {
+    let words: Vec<String> = value.split_whitespace().map(str::to_ascii_lowercase).collect();
+    let angle = |word: &str| -> Option<f32> {
+        let degrees = word.strip_suffix("deg")?.parse::<f32>().ok()?;
+        (-90.0..=90.0).contains(&degrees).then_some(degrees)
+    };
+    match words.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["normal"] => Some(FontFaceStyle::Normal),
+        ["italic"] => Some(FontFaceStyle::Italic),
+        ["oblique"] => Some(FontFaceStyle::Oblique(14.0, 14.0)),
+        ["oblique", a] => angle(a).map(|a| FontFaceStyle::Oblique(a, a)),
+        ["oblique", a, b] => {
+            let (a, b) = (angle(a)?, angle(b)?);
+            Some(FontFaceStyle::Oblique(a.min(b), a.max(b)))
+        }
+        _ => None,
+    }
+}
+
+fn parse_unicode_ranges(value: &str) -> Option<Vec<UnicodeRange>> {
+    split_top_level(value, ',')
+        .into_iter()
+        .map(|range| {
+            let range = range.trim();
+            let digits = range.strip_prefix("U+").or_else(|| range.strip_prefix("u+"))?;
+            let parse_hex = |s: &str| u32::from_str_radix(s, 16).ok();
+            let (start, end) = if let Some((start, end)) = digits.split_once('-') {
+                (parse_hex(start)?, parse_hex(end)?)
+            } else if digits.contains('?') {
+                // Wildcards: U+4?? is U+400-4FF
+                (parse_hex(&digits.replace('?', "0"))?, parse_hex(&digits.replace('?', "F"))?)
+            } else {
+                let code_point = parse_hex(digits)?;
+                (code_point, code_point)
+            };
+            (start <= end && end <= 0x10FFFF).then_some(UnicodeRange { start, end })
+        })
+        .collect()
+}
+
+fn parse_display(value: &str) -> Option<FontDisplay> {
+    match &*value.trim().to_ascii_lowercase() {
+        "auto" => Some(FontDisplay::Auto),
+        "block" => Some(FontDisplay::Block),
+        "swap" => Some(FontDisplay::Swap),
+        "fallback" => Some(FontDisplay::Fallback),
+        "optional" => Some(FontDisplay::Optional),
+        _ => None,
+    }
+}
+
+/// Split `value` on `separator`, ignoring separators inside strings and brackets.
+pub(crate) fn split_top_level(value: &str, separator: char) -> Vec<&str> {
+    let mut parser = Parser::new(value);
+    let mut parts = Vec::new();
+    let mut start = 0;
+    while !parser.eof() {
+        match parser.next_char() {
+            c if c == separator => {
+                parts.push(&value[start..parser.pos]);
+                parser.consume_char();
+                start = parser.pos;
+            }
+            '(' | '[' | '{' | '"' | '\'' => parser.skip_balanced(),
+            _ => {
+                parser.consume_char();
+            }
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_font_face_descriptors() {
+        let css = r#"
+            @font-face {
+                font-family: 'Stack Sans Text';
+                src: url('StackSansText-Light.woff2') format('woff2'),
+                    url(StackSansText-Light.woff) format("woff"), local(Stack Sans);
+                font-weight: 100 900;
+                font-style: oblique 10deg 20deg;
+                font-stretch: condensed 125%;
+                unicode-range: U+0000-00FF, U+4??, U+20AC;
+                font-display: swap;
+            }
+            p { color: #000000; }
+        "#;
+        let stylesheet = parse(css);
+        assert_eq!(stylesheet.rules.len(), 1);
+        assert_eq!(stylesheet.font_faces.len(), 1);
+
+        let face = &stylesheet.font_faces[0];
+        assert_eq!(face.family, "Stack Sans Text");
+        assert_eq!(
+            face.sources,
+            vec![
+                FontSource::Url {
+                    url: "StackSansText-Light.woff2".to_string(),
+                    format: Some("woff2".to_string()),
+                },
+                FontSource::Url {
+                    url: "StackSansText-Light.woff".to_string(),
+                    format: Some("woff".to_string()),
+                },
+                FontSource::Local("Stack Sans".to_string()),
+            ]
+        );
+        assert_eq!(face.weight, FontWeightRange { min: 100.0, max: 900.0 });
+        assert_eq!(face.style, FontFaceStyle::Oblique(10.0, 20.0));
+        assert_eq!(face.stretch, (75.0, 125.0));
+        assert_eq!(face.display, FontDisplay::Swap);
+        assert!(face.covers('a'));
+        assert!(face.covers('\u{4A1}'));
+        assert!(face.covers('€'));
+        assert!(!face.covers('\u{3042}'));
+    }
+
+    #[test]
+    fn test_font_face_requires_family_and_src() {
+        let stylesheet = parse("@font-face { font-family: Foo; font-weight: bold; }");
+        assert!(stylesheet.font_faces.is_empty());
+
+        let stylesheet = parse("@font-face { font-family: Foo; src: url(a.ttf) format('svg'), url(b.ttf); }");
+        let face = &stylesheet.font_faces[0];
+        assert_eq!(face.weight, FontWeightRange { min: 400.0, max: 400.0 });
+        assert_eq!(face.loadable_sources().count(), 1);
+    }
+
+    #[test]
+    fn test_font_family_property() {
+        let stylesheet = parse("p { font-family: 'Open Sans', Times New Roman, serif; } a { font-family: a, ; }");
+        assert_eq!(stylesheet.rules[1].declarations.len(), 0);
+        let value = &stylesheet.rules[0].declarations[0].value;
+        assert_eq!(
+            *value,
+            Value::List(vec![
+                Value::String("Open Sans".to_string()),
+                Value::String("Times New Roman".to_string()),
+                Value::Keyword("serif".to_string()),
+            ])
+        );
+        assert_eq!(value.to_string(), "\"Open Sans\", \"Times New Roman\", serif");
+        assert_eq!(parse(&stylesheet.to_string()), stylesheet);
+    }
+}
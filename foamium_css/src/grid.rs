@@ -0,0 +1,652 @@
+// Grid values: track lists, template areas, line placements and auto-placement flow
+use crate::font_face::split_top_level;
+use crate::properties::CSS_WIDE_KEYWORDS;
+use crate::{Parser, Unit, Value};
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GridValue {
+    /// A track list for `grid-template-rows` and `grid-template-columns`, or the track
+    /// sizes of `grid-auto-rows` and `grid-auto-columns`. `none` stays a keyword.
+    Tracks(Vec<TrackListItem>),
+    /// `grid-template-areas`; `none` stays a keyword
+    Areas(TemplateAreas),
+    /// A line for `grid-row-start` and the other placement properties
+    Line(GridLine),
+    /// `grid-auto-flow`
+    AutoFlow { column: bool, dense: bool },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrackListItem {
+    /// `[a b]`: names for the line between two tracks
+    LineNames(Vec<String>),
+    Track(TrackSize),
+    /// `repeat()`, holding line names and track sizes but no other repeats
+    Repeat(RepeatCount, Vec<TrackListItem>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RepeatCount {
+    Count(u32),
+    /// As many repetitions as fit
+    AutoFill,
+    /// As many as fit, with the ones left empty collapsed
+    AutoFit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrackSize {
+    Breadth(TrackBreadth),
+    /// `minmax()`, whose minimum is never flexible
+    MinMax(TrackBreadth, TrackBreadth),
+    /// `fit-content()`, with a length or percentage
+    FitContent(Value),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrackBreadth {
+    /// A length, percentage or math function
+    Length(Value),
+    /// A flexible length in `fr`
+    Flex(f32),
+    MinContent,
+    MaxContent,
+    Auto,
+}
+
+impl TrackSize {
+    /// Whether the track has a fixed minimum or maximum, as every track must in a
+    /// list with `auto-fill` or `auto-fit`.
+    fn is_fixed(&self) -> bool {
+        let fixed = |breadth: &TrackBreadth| matches!(breadth, TrackBreadth::Length(_));
+        match *self {
+            TrackSize::Breadth(ref breadth) => fixed(breadth),
+            TrackSize::MinMax(ref min, ref max) => fixed(min) || fixed(max),
+            TrackSize::FitContent(_) => false,
+        }
+    }
+}
+
+/// The cells of `grid-template-areas`, row by row, with `None` for the `.` cells that
+/// belong to no area. Every row has the same number of cells and every area is a
+/// rectangle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateAreas {
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+impl TemplateAreas {
+    /// The rows and columns the area `name` covers, as indexes of the cells.
+    pub fn bounds(&self, name: &str) -> Option<(Range<usize>, Range<usize>)> {
+        let mut bounds: Option<(Range<usize>, Range<usize>)> = None;
+        for (row, cells) in self.rows.iter().enumerate() {
+            for (column, _) in cells.iter().enumerate().filter(|(_, cell)| cell.as_deref() == Some(name)) {
+                bounds = Some(match bounds {
+                    None => (row..row + 1, column..column + 1),
+                    Some((rows, columns)) => {
+                        (rows.start..row + 1, columns.start.min(column)..columns.end.max(column + 1))
+                    }
+                });
+            }
+        }
+        bounds
+    }
+
+    pub fn columns(&self) -> usize {
+        self.rows.first().map_or(0, Vec::len)
+    }
+
+    /// The area names, each once, in the order they first appear.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for name in self.rows.iter().flatten().flatten() {
+            if !names.contains(&&**name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GridLine {
+    Auto,
+    /// A name on its own, which is an area's line before it is a named line
+    Named(String),
+    /// The nth line, or the nth line with the name, counting back from the end if negative
+    Line(i32, Option<String>),
+    /// Spanning n tracks, or to the nth line with the name
+    Span(u32, Option<String>),
+}
+
+fn wide_keyword(source: &str) -> Option<Value> {
+    CSS_WIDE_KEYWORDS
+        .contains(&&*source.to_ascii_lowercase())
+        .then(|| Value::Keyword(source.to_string()))
+}
+
+/// Parse the value of one of the grid properties, or `None` if it's invalid.
+pub(crate) fn parse_grid_value(name: &str, source: &str) -> Option<Value> {
+    if let Some(keyword) = wide_keyword(source) {
+        return Some(keyword);
+    }
+    let none = source.eq_ignore_ascii_case("none");
+    let value = match &*name.to_ascii_lowercase() {
+        "grid-template-rows" | "grid-template-columns" if none => return Some(Value::Keyword("none".to_string())),
+        "grid-template-rows" | "grid-template-columns" => GridValue::Tracks(parse_track_list(source, true)?),
+        "grid-template-areas" if none => return Some(Value::Keyword("none".to_string())),
+        "grid-template-areas" => GridValue::Areas(parse_template_areas(source)?),
+        "grid-auto-rows" | "grid-auto-columns" => {
+            let mut parser = Parser::new(source);
+            let mut tracks = Vec::new();
+            while !parser.eof() {
+                tracks.push(TrackListItem::Track(parser.parse_track_size()?));
+                parser.consume_whitespace();
+            }
+            (!tracks.is_empty()).then_some(GridValue::Tracks(tracks))?
+        }
+        "grid-auto-flow" => parse_auto_flow(source)?,
+        _ => GridValue::Line(parse_grid_line(source)?),
+    };
+    Some(Value::Grid(value))
+}
+
+/// The longhands of `grid-row`, `grid-column`, `grid-area` and `grid-template`, in the
+/// order `shorthand::longhands` lists them.
+pub(crate) fn parse_grid_shorthand(name: &str, source: &str, longhands: usize) -> Option<Vec<Value>> {
+    if let Some(keyword) = wide_keyword(source) {
+        return Some(vec![keyword; longhands]);
+    }
+    let parts: Vec<&str> = split_top_level(source, '/').into_iter().map(str::trim).collect();
+    if name.eq_ignore_ascii_case("grid-template") {
+        return parse_grid_template(&parts);
+    }
+    if parts.len() > longhands {
+        return None;
+    }
+    let mut lines = parts.iter().map(|part| parse_grid_line(part)).collect::<Option<Vec<_>>>()?;
+    // A line left out is the same name as its opposite, or else `auto`
+    while lines.len() < longhands {
+        let i = lines.len();
+        let opposite = if longhands == 4 { i.saturating_sub(2) } else { i - 1 };
+        lines.push(match lines[opposite] {
+            GridLine::Named(ref name) => GridLine::Named(name.clone()),
+            _ => GridLine::Auto,
+        });
+    }
+    Some(lines.into_iter().map(|line| Value::Grid(GridValue::Line(line))).collect())
+}
+
+/// `grid-template`: `none`, rows and columns either side of a `/`, or rows written as
+/// area strings, each followed by its size, optionally then `/` and columns.
+fn parse_grid_template(parts: &[&str]) -> Option<Vec<Value>> {
+    let none = || Value::Keyword("none".to_string());
+    let tracks = |source: &str, auto_repeat| parse_track_list(source, auto_repeat).map(|tracks| Value::Grid(GridValue::Tracks(tracks)));
+    match *parts {
+        [only] if only.eq_ignore_ascii_case("none") => Some(vec![none(), none(), none()]),
+        [rows, columns] if !rows.contains(['"', '\'']) => Some(vec![tracks(rows, true)?, tracks(columns, true)?, none()]),
+        [rows] | [rows, _] => {
+            let columns = match parts.get(1) {
+                Some(columns) => tracks(columns, false)?,
+                None => none(),
+            };
+            let (rows, areas) = parse_area_rows(rows)?;
+            Some(vec![Value::Grid(GridValue::Tracks(rows)), columns, Value::Grid(GridValue::Areas(areas))])
+        }
+        _ => None,
+    }
+}
+
+/// The row tracks and areas of the strings form of `grid-template`. A row without a
+/// size is `auto`, and names either side of a row boundary are merged.
+fn parse_area_rows(source: &str) -> Option<(Vec<TrackListItem>, TemplateAreas)> {
+    let mut parser = Parser::new(source);
+    let mut tracks: Vec<TrackListItem> = Vec::new();
+    let mut rows = Vec::new();
+    let mut sized = true;
+    loop {
+        parser.consume_whitespace();
+        if parser.eof() {
+            break;
+        }
+        match parser.next_char() {
+            '[' => {
+                if !sized {
+                    tracks.push(TrackListItem::Track(TrackSize::Breadth(TrackBreadth::Auto)));
+                    sized = true;
+                }
+                let names = parser.parse_line_names()?;
+                match tracks.last_mut() {
+                    Some(TrackListItem::LineNames(before)) => before.extend(names),
+                    _ => tracks.push(TrackListItem::LineNames(names)),
+                }
+            }
+            '"' | '\'' => {
+                if !sized {
+                    tracks.push(TrackListItem::Track(TrackSize::Breadth(TrackBreadth::Auto)));
+                }
+                rows.push(parse_area_row(&parser.parse_string())?);
+                sized = false;
+            }
+            _ if !sized => {
+                tracks.push(TrackListItem::Track(parser.parse_track_size()?));
+                sized = true;
+            }
+            _ => return None,
+        }
+    }
+    if !sized {
+        tracks.push(TrackListItem::Track(TrackSize::Breadth(TrackBreadth::Auto)));
+    }
+    let areas = TemplateAreas { rows };
+    valid_areas(&areas).then_some((tracks, areas))
+}
+
+fn parse_track_list(source: &str, auto_repeat: bool) -> Option<Vec<TrackListItem>> {
+    let mut parser = Parser::new(source);
+    let tracks = parser.parse_track_list(false)?;
+    if !parser.eof() {
+        return None;
+    }
+    let auto_repeats = tracks
+        .iter()
+        .filter(|item| matches!(item, TrackListItem::Repeat(RepeatCount::AutoFill | RepeatCount::AutoFit, _)))
+        .count();
+    if auto_repeats > 0 {
+        // One automatic repeat, and nothing whose size depends on the content
+        let fixed = |items: &[TrackListItem]| {
+            items.iter().all(|item| match *item {
+                TrackListItem::Track(ref size) => size.is_fixed(),
+                _ => true,
+            })
+        };
+        let all_fixed = fixed(&tracks)
+            && tracks.iter().all(|item| match *item {
+                TrackListItem::Repeat(_, ref items) => fixed(items),
+                _ => true,
+            });
+        if !auto_repeat || auto_repeats > 1 || !all_fixed {
+            return None;
+        }
+    }
+    Some(tracks)
+}
+
+fn parse_template_areas(source: &str) -> Option<TemplateAreas> {
+    let mut parser = Parser::new(source);
+    let mut rows = Vec::new();
+    while !parser.eof() {
+        if !matches!(parser.next_char(), '"' | '\'') {
+            return None;
+        }
+        rows.push(parse_area_row(&parser.parse_string())?);
+        parser.consume_whitespace();
+    }
+    let areas = TemplateAreas { rows };
+    valid_areas(&areas).then_some(areas)
+}
+
+/// The cells of one row of `grid-template-areas`: names, and runs of `.` for no area.
+fn parse_area_row(row: &str) -> Option<Vec<Option<String>>> {
+    let mut parser = Parser::new(row);
+    let mut cells = Vec::new();
+    loop {
+        parser.consume_whitespace();
+        if parser.eof() {
+            break;
+        }
+        if parser.next_char() == '.' {
+            parser.consume_while(|c| c == '.');
+            cells.push(None);
+            continue;
+        }
+        let name = parser.parse_identifier();
+        if name.is_empty() {
+            return None;
+        }
+        cells.push(Some(name));
+    }
+    Some(cells)
+}
+
+/// Whether there is at least one cell, every row has as many, and each area's cells
+/// fill a rectangle.
+fn valid_areas(areas: &TemplateAreas) -> bool {
+    let columns = areas.columns();
+    columns > 0
+        && areas.rows.iter().all(|row| row.len() == columns)
+        && areas.names().into_iter().all(|name| {
+            let (rows, columns) = areas.bounds(name).unwrap();
+            rows.flat_map(|row| columns.clone().map(move |column| (row, column)))
+                .all(|(row, column)| areas.rows[row][column].as_deref() == Some(name))
+        })
+}
+
+fn parse_grid_line(source: &str) -> Option<GridLine> {
+    let components = Parser::new(source).parse_components()?;
+    let is_name = |value: &Value| matches!(value, Value::Keyword(ref name) if !name.eq_ignore_ascii_case("span") && !name.eq_ignore_ascii_case("auto"));
+    let is_span = |value: &Value| matches!(value, Value::Keyword(ref keyword) if keyword.eq_ignore_ascii_case("span"));
+    let integer = |value: &Value| match *value {
+        Value::Number(n) if n.fract() == 0.0 && n != 0.0 => Some(n as i32),
+        _ => None,
+    };
+    let name = |value: &Value| match *value {
+        Value::Keyword(ref name) => Some(name.clone()),
+        _ => None,
+    };
+    let line = match components[..] {
+        [Value::Keyword(ref keyword)] if keyword.eq_ignore_ascii_case("auto") => GridLine::Auto,
+        [ref only] if is_name(only) => GridLine::Named(name(only)?),
+        [ref only] => GridLine::Line(integer(only)?, None),
+        [ref a, ref b] if is_span(a) || is_span(b) => {
+            let other = if is_span(a) { b } else { a };
+            match integer(other) {
+                Some(count) if count > 0 => GridLine::Span(count as u32, None),
+                Some(_) => return None,
+                None if is_name(other) => GridLine::Span(1, name(other)),
+                None => return None,
+            }
+        }
+        [ref a, ref b] => match (integer(a), integer(b)) {
+            (Some(n), None) if is_name(b) => GridLine::Line(n, name(b)),
+            (None, Some(n)) if is_name(a) => GridLine::Line(n, name(a)),
+            _ => return None,
+        },
+        // `span` comes first or last, with a count and a name in either order
+        [ref a, ref b, ref c] => {
+            let (a, b) = if is_span(a) {
+                (b, c)
+            } else if is_span(c) {
+                (a, b)
+            } else {
+                return None;
+            };
+            let (count, name) = match (integer(a), integer(b)) {
+                (Some(n), None) if is_name(b) => (n, name(b)),
+                (None, Some(n)) if is_name(a) => (n, name(a)),
+                _ => return None,
+            };
+            if count <= 0 {
+                return None;
+            }
+            GridLine::Span(count as u32, name)
+        }
+        _ => return None,
+    };
+    Some(line)
+}
+
+fn parse_auto_flow(source: &str) -> Option<GridValue> {
+    let components = Parser::new(source).parse_components()?;
+    let mut direction = None;
+    let mut dense = false;
+    for component in components {
+        let Value::Keyword(ref keyword) = component else {
+            return None;
+        };
+        match &*keyword.to_ascii_lowercase() {
+            "row" | "column" if direction.is_none() => direction = Some(keyword.eq_ignore_ascii_case("column")),
+            "dense" if !dense => dense = true,
+            _ => return None,
+        }
+    }
+    Some(GridValue::AutoFlow {
+        column: direction.unwrap_or(false),
+        dense,
+    })
+}
+
+impl Parser {
+    /// Parse line names, track sizes and `repeat()`s up to the end of input, or up to
+    /// the `)` closing a `repeat()`. Two lists of names can't be next to each other.
+    fn parse_track_list(&mut self, in_repeat: bool) -> Option<Vec<TrackListItem>> {
+        let mut items = Vec::new();
+        loop {
+            self.consume_whitespace();
+            if self.eof() || in_repeat && self.next_char() == ')' {
+                break;
+            }
+            if self.next_char() == '[' {
+                if matches!(items.last(), Some(TrackListItem::LineNames(_))) {
+                    return None;
+                }
+                items.push(TrackListItem::LineNames(self.parse_line_names()?));
+                continue;
+            }
+            let start = self.pos;
+            if self.parse_identifier().eq_ignore_ascii_case("repeat") && self.next_char() == '(' {
+                if in_repeat {
+                    return None;
+                }
+                items.push(self.parse_repeat()?);
+                continue;
+            }
+            self.pos = start;
+            items.push(TrackListItem::Track(self.parse_track_size()?));
+        }
+        items.iter().any(|item| !matches!(item, TrackListItem::LineNames(_))).then_some(items)
+    }
+
+    /// Parse `[a b]`, positioned at the `[`.
+    fn parse_line_names(&mut self) -> Option<Vec<String>> {
+        self.consume_char(); // consume '['
+        let mut names = Vec::new();
+        loop {
+            self.consume_whitespace();
+            if self.next_char() == ']' {
+                self.consume_char();
+                return Some(names);
+            }
+            let name = self.parse_identifier();
+            if name.is_empty() || name.eq_ignore_ascii_case("span") || name.eq_ignore_ascii_case("auto") {
+                return None;
+            }
+            names.push(name);
+        }
+    }
+
+    /// Parse the arguments of `repeat()`, positioned at the `(`.
+    fn parse_repeat(&mut self) -> Option<TrackListItem> {
+        self.consume_char(); // consume '('
+        self.consume_whitespace();
+        let count = if self.starts_number() {
+            let count = self.parse_number();
+            if count < 1.0 || count.fract() != 0.0 {
+                return None;
+            }
+            RepeatCount::Count(count as u32)
+        } else {
+            match &*self.parse_identifier().to_ascii_lowercase() {
+                "auto-fill" => RepeatCount::AutoFill,
+                "auto-fit" => RepeatCount::AutoFit,
+                _ => return None,
+            }
+        };
+        self.consume_whitespace();
+        if self.consume_char() != ',' {
+            return None;
+        }
+        let items = self.parse_track_list(true)?;
+        if self.consume_char() != ')' {
+            return None;
+        }
+        Some(TrackListItem::Repeat(count, items))
+    }
+
+    fn parse_track_size(&mut self) -> Option<TrackSize> {
+        let start = self.pos;
+        let function = self.parse_identifier().to_ascii_lowercase();
+        if self.next_char() != '(' || !matches!(&*function, "minmax" | "fit-content") {
+            self.pos = start;
+            return self.parse_track_breadth().map(TrackSize::Breadth);
+        }
+        self.consume_char(); // consume '('
+        self.consume_whitespace();
+        let size = if function == "minmax" {
+            let min = self.parse_track_breadth()?;
+            self.consume_whitespace();
+            if self.consume_char() != ',' || matches!(min, TrackBreadth::Flex(_)) {
+                return None;
+            }
+            self.consume_whitespace();
+            TrackSize::MinMax(min, self.parse_track_breadth()?)
+        } else {
+            match self.parse_track_breadth()? {
+                TrackBreadth::Length(length) => TrackSize::FitContent(length),
+                _ => return None,
+            }
+        };
+        self.consume_whitespace();
+        (self.consume_char() == ')').then_some(size)
+    }
+
+    /// Parse a non-negative length or percentage, a flexible length or a keyword.
+    fn parse_track_breadth(&mut self) -> Option<TrackBreadth> {
+        if self.starts_number() {
+            let start = self.pos;
+            let number = self.parse_number();
+            if self.parse_identifier().eq_ignore_ascii_case("fr") {
+                return (number >= 0.0).then_some(TrackBreadth::Flex(number));
+            }
+            self.pos = start;
+        }
+        let breadth = match self.parse_value()? {
+            Value::Keyword(keyword) => match &*keyword.to_ascii_lowercase() {
+                "min-content" => TrackBreadth::MinContent,
+                "max-content" => TrackBreadth::MaxContent,
+                "auto" => TrackBreadth::Auto,
+                _ => return None,
+            },
+            Value::Number(0.0) => TrackBreadth::Length(Value::Length(0.0, Unit::Px)),
+            Value::Length(n, _) if n < 0.0 => return None,
+            length @ (Value::Length(..) | Value::Calc(_)) => TrackBreadth::Length(length),
+            _ => return None,
+        };
+        Some(breadth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn declarations(css: &str) -> Vec<String> {
+        parse_inline_style(css).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_track_lists() {
+        let declarations = parse_inline_style(
+            "grid-template-columns: [full-start] minmax(1em, 1fr) [main-start] repeat(2, [col] 100px) fit-content(40%) [main-end]",
+        );
+        let Value::Grid(GridValue::Tracks(ref tracks)) = declarations[0].value else {
+            panic!("{:?}", declarations);
+        };
+        assert_eq!(tracks.len(), 6);
+        assert_eq!(
+            tracks[1],
+            TrackListItem::Track(TrackSize::MinMax(
+                TrackBreadth::Length(Value::Length(1.0, Unit::Em)),
+                TrackBreadth::Flex(1.0)
+            ))
+        );
+        assert_eq!(
+            tracks[3],
+            TrackListItem::Repeat(
+                RepeatCount::Count(2),
+                vec![
+                    TrackListItem::LineNames(vec!["col".to_string()]),
+                    TrackListItem::Track(TrackSize::Breadth(TrackBreadth::Length(Value::Length(100.0, Unit::Px))))
+                ]
+            )
+        );
+        assert_eq!(
+            self::declarations("grid-template-rows: repeat(auto-fill, minmax(100px, 1fr)) 20px; grid-auto-rows: min-content 2fr"),
+            [
+                "grid-template-rows: repeat(auto-fill, minmax(100px, 1fr)) 20px;",
+                "grid-auto-rows: min-content 2fr;"
+            ]
+        );
+
+        // Flexible minimums, automatic repeats of intrinsic sizes, two automatic repeats
+        // and names next to each other are all invalid
+        for invalid in [
+            "minmax(1fr, 100px)",
+            "repeat(auto-fit, auto)",
+            "repeat(auto-fill, 10px) repeat(auto-fit, 10px)",
+            "[a] [b] 10px",
+            "-10px",
+            "repeat(0, 10px)",
+            "10px 1foo",
+        ] {
+            assert!(parse_inline_style(&format!("grid-template-columns: {}", invalid)).is_empty(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_template_areas() {
+        let declarations = parse_inline_style(r#"grid-template-areas: "head head" "nav main" ". main""#);
+        let Value::Grid(GridValue::Areas(ref areas)) = declarations[0].value else {
+            panic!("{:?}", declarations);
+        };
+        assert_eq!(areas.bounds("head"), Some((0..1, 0..2)));
+        assert_eq!(areas.bounds("main"), Some((1..3, 1..2)));
+        assert_eq!(areas.rows[2][0], None);
+        assert_eq!(areas.names(), ["head", "nav", "main"]);
+
+        // Ragged rows and areas that aren't rectangles
+        assert!(parse_inline_style(r#"grid-template-areas: "a b" "c""#).is_empty());
+        assert!(parse_inline_style(r#"grid-template-areas: "a a" "a b""#).is_empty());
+    }
+
+    #[test]
+    fn test_placement() {
+        assert_eq!(
+            declarations("grid-area: main; grid-column: 2 / span 3; grid-row: span 2 foo / -1"),
+            [
+                "grid-row-start: main;",
+                "grid-column-start: main;",
+                "grid-row-end: main;",
+                "grid-column-end: main;",
+                "grid-column-start: 2;",
+                "grid-column-end: span 3;",
+                "grid-row-start: span 2 foo;",
+                "grid-row-end: -1;",
+            ]
+        );
+        assert_eq!(
+            declarations("grid-area: 1 / 2 / 3; grid-auto-flow: dense column"),
+            [
+                "grid-row-start: 1;",
+                "grid-column-start: 2;",
+                "grid-row-end: 3;",
+                "grid-column-end: auto;",
+                "grid-auto-flow: column dense;",
+            ]
+        );
+        for invalid in ["grid-row: 0", "grid-row: span -1", "grid-column: span", "grid-row: 1 / 2 / 3", "grid-row-start: a b"] {
+            assert!(parse_inline_style(invalid).is_empty(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_grid_template_shorthand() {
+        assert_eq!(
+            declarations(r#"grid-template: [top] "a a" 40px [mid] "b c" [bottom] / 1fr 2fr"#),
+            [
+                "grid-template-rows: [top] 40px [mid] auto [bottom];",
+                "grid-template-columns: 1fr 2fr;",
+                r#"grid-template-areas: "a a" "b c";"#,
+            ]
+        );
+        assert_eq!(
+            declarations("grid-template: auto 1fr / repeat(3, 10px)"),
+            [
+                "grid-template-rows: auto 1fr;",
+                "grid-template-columns: repeat(3, 10px);",
+                "grid-template-areas: none;",
+            ]
+        );
+    }
+}
@@ -0,0 +1,502 @@
+// Image values: url(), gradients and image-set()
+use crate::font_face::split_top_level;
+use crate::{Color, Parser, Unit, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Image {
+    Url(String),
+    Gradient(Box<Gradient>),
+    /// `image-set()`, from whose options the renderer picks by resolution and type
+    ImageSet(Vec<ImageSetOption>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub shape: GradientShape,
+    /// For the `repeating-` variants
+    pub repeating: bool,
+    pub stops: Vec<GradientStop>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GradientShape {
+    Linear(GradientLine),
+    Radial {
+        ending_shape: EndingShape,
+        size: RadialSize,
+        position: Position,
+    },
+    Conic {
+        /// The starting angle in degrees, clockwise from up
+        from: f32,
+        position: Position,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradientLine {
+    /// Degrees clockwise from up, so `to right` is 90
+    Angle(f32),
+    /// `to top left` and so on, whose angle depends on the shape of the box
+    Corner(HorizontalSide, VerticalSide),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HorizontalSide {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerticalSide {
+    Top,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EndingShape {
+    Circle,
+    Ellipse,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RadialSize {
+    ClosestSide,
+    FarthestSide,
+    ClosestCorner,
+    FarthestCorner,
+    /// A circle's radius, which is a length
+    Radius(Value),
+    /// An ellipse's horizontal and vertical radii, which may be percentages
+    Radii(Value, Value),
+}
+
+/// A point in the box, as offsets from its top left corner. Keywords are percentages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub x: Value,
+    pub y: Value,
+}
+
+impl Position {
+    pub fn center() -> Position {
+        Position {
+            x: Value::Length(50.0, Unit::Percent),
+            y: Value::Length(50.0, Unit::Percent),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GradientStop {
+    /// A color and where it is on the gradient line: a length or percentage, or for
+    /// conic gradients an angle or percentage
+    Color(Color, Option<Value>),
+    /// A transition hint, moving the midpoint between the colors either side of it
+    Hint(Value),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageSetOption {
+    pub image: Image,
+    /// In dots per px, from `2x`, `2dppx`, `192dpi` and so on
+    pub resolution: f32,
+    /// From `type()`. Options with a type the renderer can't decode are skipped.
+    pub mime_type: Option<String>,
+}
+
+pub(crate) fn is_image_function(name: &str) -> bool {
+    matches!(
+        &*name.to_ascii_lowercase(),
+        "url"
+            | "linear-gradient"
+            | "repeating-linear-gradient"
+            | "radial-gradient"
+            | "repeating-radial-gradient"
+            | "conic-gradient"
+            | "repeating-conic-gradient"
+            | "image-set"
+            | "-webkit-image-set"
+    )
+}
+
+impl Parser {
+    /// Parse `url()`, a gradient or `image-set()`, positioned at the `(`.
+    pub(crate) fn parse_image_function(&mut self, name: &str) -> Option<Value> {
+        let name = name.to_ascii_lowercase();
+        if name == "url" {
+            return Some(Value::Image(Image::Url(self.parse_function_argument())));
+        }
+        let start = self.pos + 1;
+        self.skip_balanced();
+        let arguments = split_top_level(&self.input[start..self.pos - 1], ',');
+        let image = match name.trim_start_matches("-webkit-") {
+            "image-set" => Image::ImageSet(arguments.iter().map(|option| parse_image_set_option(option)).collect::<Option<_>>()?),
+            gradient => Image::Gradient(Box::new(parse_gradient(gradient, &arguments)?)),
+        };
+        Some(Value::Image(image))
+    }
+}
+
+/// Parse the space-separated values in `source`.
+fn components(source: &str) -> Option<Vec<Value>> {
+    let mut parser = Parser::new(source);
+    let mut values = Vec::new();
+    loop {
+        parser.consume_whitespace();
+        if parser.eof() {
+            return Some(values);
+        }
+        match parser.parse_value()? {
+            Value::Keyword(ref keyword) if keyword.is_empty() => return None,
+            value => values.push(value),
+        }
+    }
+}
+
+fn is_keyword(value: &Value, keyword: &str) -> bool {
+    matches!(*value, Value::Keyword(ref k) if k.eq_ignore_ascii_case(keyword))
+}
+
+fn to_color(value: &Value) -> Option<Color> {
+    match *value {
+        Value::ColorValue(color) => Some(color),
+        Value::Keyword(ref name) => Color::from_name(&name.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+fn parse_gradient(name: &str, arguments: &[&str]) -> Option<Gradient> {
+    let repeating = name.starts_with("repeating-");
+    let first = components(arguments.first()?)?;
+    // The first argument configures the gradient unless it is already a color stop
+    let configured = !first.iter().any(|value| to_color(value).is_some());
+    let config: &[Value] = if configured { &first } else { &[] };
+    let stops = if configured { &arguments[1..] } else { arguments };
+
+    let (shape, angular) = match name.trim_start_matches("repeating-") {
+        "linear-gradient" => (GradientShape::Linear(parse_gradient_line(config)?), false),
+        "radial-gradient" => (parse_radial_shape(config)?, false),
+        "conic-gradient" => (parse_conic_shape(config)?, true),
+        _ => return None,
+    };
+    Some(Gradient {
+        shape,
+        repeating,
+        stops: parse_stops(stops, angular)?,
+    })
+}
+
+fn parse_gradient_line(config: &[Value]) -> Option<GradientLine> {
+    match config {
+        [] => Some(GradientLine::Angle(180.0)),
+        [Value::Angle(degrees)] => Some(GradientLine::Angle(*degrees)),
+        [Value::Number(n)] if *n == 0.0 => Some(GradientLine::Angle(0.0)),
+        [to, sides @ ..] if is_keyword(to, "to") => {
+            let (mut horizontal, mut vertical) = (None, None);
+            for side in sides {
+                let Value::Keyword(side) = side else {
+                    return None;
+                };
+                match &*side.to_ascii_lowercase() {
+                    "left" if horizontal.is_none() => horizontal = Some(HorizontalSide::Left),
+                    "right" if horizontal.is_none() => horizontal = Some(HorizontalSide::Right),
+                    "top" if vertical.is_none() => vertical = Some(VerticalSide::Top),
+                    "bottom" if vertical.is_none() => vertical = Some(VerticalSide::Bottom),
+                    _ => return None,
+                }
+            }
+            Some(match (horizontal, vertical) {
+                (Some(horizontal), Some(vertical)) => GradientLine::Corner(horizontal, vertical),
+                (Some(HorizontalSide::Left), None) => GradientLine::Angle(270.0),
+                (Some(HorizontalSide::Right), None) => GradientLine::Angle(90.0),
+                (None, Some(VerticalSide::Top)) => GradientLine::Angle(0.0),
+                (None, Some(VerticalSide::Bottom)) => GradientLine::Angle(180.0),
+                (None, None) => return None,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Split a gradient's configuration at `at`, parsing the position after it.
+fn split_position(config: &[Value]) -> Option<(&[Value], Position)> {
+    match config.iter().position(|value| is_keyword(value, "at")) {
+        Some(at) => Some((&config[..at], parse_position(&config[at + 1..])?)),
+        None => Some((config, Position::center())),
+    }
+}
+
+fn parse_radial_shape(config: &[Value]) -> Option<GradientShape> {
+    let (config, position) = split_position(config)?;
+    let mut ending_shape = None;
+    let mut size_keyword = None;
+    let mut lengths = Vec::new();
+    for value in config {
+        match *value {
+            Value::Keyword(ref keyword) => match &*keyword.to_ascii_lowercase() {
+                "circle" if ending_shape.is_none() => ending_shape = Some(EndingShape::Circle),
+                "ellipse" if ending_shape.is_none() => ending_shape = Some(EndingShape::Ellipse),
+                "closest-side" if size_keyword.is_none() => size_keyword = Some(RadialSize::ClosestSide),
+                "farthest-side" if size_keyword.is_none() => size_keyword = Some(RadialSize::FarthestSide),
+                "closest-corner" if size_keyword.is_none() => size_keyword = Some(RadialSize::ClosestCorner),
+                "farthest-corner" if size_keyword.is_none() => size_keyword = Some(RadialSize::FarthestCorner),
+                _ => return None,
+            },
+            Value::Length(..) | Value::Calc(_) | Value::Number(0.0) => lengths.push(value.clone()),
+            _ => return None,
+        }
+    }
+
+    let ending_shape = ending_shape.unwrap_or(match lengths.len() {
+        1 => EndingShape::Circle,
+        _ => EndingShape::Ellipse,
+    });
+    let size = match (ending_shape, size_keyword, &lengths[..]) {
+        (_, Some(size), []) => size,
+        (_, None, []) => RadialSize::FarthestCorner,
+        (EndingShape::Circle, None, [radius]) if !matches!(radius, Value::Length(_, Unit::Percent)) => {
+            RadialSize::Radius(radius.clone())
+        }
+        (EndingShape::Ellipse, None, [x, y]) => RadialSize::Radii(x.clone(), y.clone()),
+        _ => return None,
+    };
+    Some(GradientShape::Radial {
+        ending_shape,
+        size,
+        position,
+    })
+}
+
+fn parse_conic_shape(config: &[Value]) -> Option<GradientShape> {
+    let (config, position) = split_position(config)?;
+    let from = match config {
+        [] => 0.0,
+        [from, Value::Angle(degrees)] if is_keyword(from, "from") => *degrees,
+        [from, Value::Number(n)] if is_keyword(from, "from") && *n == 0.0 => 0.0,
+        _ => return None,
+    };
+    Some(GradientShape::Conic { from, position })
+}
+
+/// One component of a position.
+enum PositionComponent {
+    /// `left` or `right`
+    Horizontal(Value),
+    /// `top` or `bottom`
+    Vertical(Value),
+    Center,
+    Length(Value),
+}
+
+impl PositionComponent {
+    fn parse(value: &Value) -> Option<PositionComponent> {
+        let percent = |p: f32| Value::Length(p, Unit::Percent);
+        Some(match *value {
+            Value::Keyword(ref keyword) => match &*keyword.to_ascii_lowercase() {
+                "left" => PositionComponent::Horizontal(percent(0.0)),
+                "right" => PositionComponent::Horizontal(percent(100.0)),
+                "top" => PositionComponent::Vertical(percent(0.0)),
+                "bottom" => PositionComponent::Vertical(percent(100.0)),
+                "center" => PositionComponent::Center,
+                _ => return None,
+            },
+            Value::Length(..) | Value::Calc(_) | Value::Number(0.0) => PositionComponent::Length(value.clone()),
+            _ => return None,
+        })
+    }
+
+    fn value(self) -> Value {
+        match self {
+            PositionComponent::Horizontal(value) | PositionComponent::Vertical(value) | PositionComponent::Length(value) => {
+                value
+            }
+            PositionComponent::Center => Value::Length(50.0, Unit::Percent),
+        }
+    }
+}
+
+/// Parse one or two position components. A single value is centered in the other axis,
+/// and two keywords may come in either order, as in `top left`.
+fn parse_position(values: &[Value]) -> Option<Position> {
+    use PositionComponent::*;
+    match values {
+        [value] => match PositionComponent::parse(value)? {
+            Vertical(y) => Some(Position { x: Center.value(), y }),
+            x => Some(Position { x: x.value(), y: Center.value() }),
+        },
+        [first, second] => match (PositionComponent::parse(first)?, PositionComponent::parse(second)?) {
+            (x @ (Horizontal(_) | Center | Length(_)), y @ (Vertical(_) | Center | Length(_))) => {
+                Some(Position { x: x.value(), y: y.value() })
+            }
+            (y @ Vertical(_), x @ (Horizontal(_) | Center)) | (y @ Center, x @ Horizontal(_)) => {
+                Some(Position { x: x.value(), y: y.value() })
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Parse a color stop list. Stops may have two positions, which makes two stops, and
+/// hints must sit between two colors.
+fn parse_stops(arguments: &[&str], angular: bool) -> Option<Vec<GradientStop>> {
+    let valid_position = |value: &Value| match *value {
+        Value::Length(_, Unit::Percent) | Value::Calc(_) | Value::Number(0.0) => true,
+        Value::Length(..) => !angular,
+        Value::Angle(_) => angular,
+        _ => false,
+    };
+    let mut stops = Vec::new();
+    for argument in arguments {
+        let values = components(argument)?;
+        let color = values.iter().position(|value| to_color(value).is_some());
+        let positions: Vec<&Value> = values
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| Some(i) != color)
+            .map(|(_, value)| value)
+            .collect();
+        if !positions.iter().all(|position| valid_position(position)) {
+            return None;
+        }
+        match (color.map(|i| to_color(&values[i]).unwrap()), &positions[..]) {
+            (None, [hint]) => {
+                if !matches!(stops.last(), Some(GradientStop::Color(..))) {
+                    return None;
+                }
+                stops.push(GradientStop::Hint((*hint).clone()));
+            }
+            (Some(color), []) => stops.push(GradientStop::Color(color, None)),
+            (Some(color), [position]) => stops.push(GradientStop::Color(color, Some((*position).clone()))),
+            (Some(color), [first, second]) => {
+                stops.push(GradientStop::Color(color, Some((*first).clone())));
+                stops.push(GradientStop::Color(color, Some((*second).clone())));
+            }
+            _ => return None,
+        }
+    }
+    let colors = stops.iter().filter(|stop| matches!(stop, GradientStop::Color(..))).count();
+    if colors < 2 || matches!(stops.last(), Some(GradientStop::Hint(_))) {
+        return None;
+    }
+    Some(stops)
+}
+
+/// Parse an `image-set()` option: an image or a URL string, then an optional
+/// resolution and `type()` in either order.
+fn parse_image_set_option(source: &str) -> Option<ImageSetOption> {
+    let mut parser = Parser::new(source.trim());
+    let image = match parser.next_char() {
+        '"' | '\'' => Image::Url(parser.parse_string()),
+        _ => match parser.parse_value()? {
+            Value::Image(Image::ImageSet(_)) => return None,
+            Value::Image(image) => image,
+            _ => return None,
+        },
+    };
+    let mut resolution = None;
+    let mut mime_type = None;
+    loop {
+        parser.consume_whitespace();
+        if parser.eof() {
+            break;
+        }
+        if parser.starts_number() && resolution.is_none() {
+            let number = parser.parse_number();
+            let dppx = match &*parser.parse_identifier().to_ascii_lowercase() {
+                "x" | "dppx" => number,
+                "dpi" => number / 96.0,
+                "dpcm" => number * 2.54 / 96.0,
+                _ => return None,
+            };
+            resolution = Some(dppx).filter(|dppx| *dppx > 0.0);
+            resolution?;
+        } else if parser.parse_identifier().eq_ignore_ascii_case("type") && parser.next_char() == '(' && mime_type.is_none() {
+            mime_type = Some(parser.parse_function_argument());
+        } else {
+            return None;
+        }
+    }
+    Some(ImageSetOption {
+        image,
+        resolution: resolution.unwrap_or(1.0),
+        mime_type,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_value;
+
+    fn image(source: &str) -> Option<Image> {
+        match parse_value(source)? {
+            Value::Image(image) => Some(image),
+            _ => None,
+        }
+    }
+
+    fn gradient(source: &str) -> Option<Gradient> {
+        match image(source)? {
+            Image::Gradient(gradient) => Some(*gradient),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_urls_and_image_sets() {
+        assert_eq!(image("url(\"a b.png\")"), Some(Image::Url("a b.png".to_string())));
+        assert_eq!(image("url(tile.gif)"), Some(Image::Url("tile.gif".to_string())));
+        let Some(Image::ImageSet(options)) = image("image-set(\"a.avif\" type(\"image/avif\"), url(a.png) 192dpi)") else {
+            panic!("expected an image set");
+        };
+        assert_eq!(options[0].image, Image::Url("a.avif".to_string()));
+        assert_eq!(options[0].mime_type.as_deref(), Some("image/avif"));
+        assert_eq!(options[0].resolution, 1.0);
+        assert_eq!(options[1].resolution, 2.0);
+        assert_eq!(image("image-set(\"a.png\" 2x 3x)"), None);
+    }
+
+    #[test]
+    fn test_gradients() {
+        let linear = gradient("linear-gradient(to top left, red, 30%, #0000ff 50% 70%)").unwrap();
+        assert_eq!(linear.shape, GradientShape::Linear(GradientLine::Corner(HorizontalSide::Left, VerticalSide::Top)));
+        let red = Color::new(255, 0, 0, 255);
+        let blue = Color::new(0, 0, 255, 255);
+        assert_eq!(
+            linear.stops,
+            [
+                GradientStop::Color(red, None),
+                GradientStop::Hint(Value::Length(30.0, Unit::Percent)),
+                GradientStop::Color(blue, Some(Value::Length(50.0, Unit::Percent))),
+                GradientStop::Color(blue, Some(Value::Length(70.0, Unit::Percent))),
+            ]
+        );
+        assert_eq!(gradient("linear-gradient(0.25turn, red, blue)").unwrap().shape, GradientShape::Linear(GradientLine::Angle(90.0)));
+        assert_eq!(gradient("linear-gradient(red, blue)").unwrap().shape, GradientShape::Linear(GradientLine::Angle(180.0)));
+
+        let radial = gradient("repeating-radial-gradient(10px at 20px top, red, blue 20px)").unwrap();
+        assert!(radial.repeating);
+        assert_eq!(
+            radial.shape,
+            GradientShape::Radial {
+                ending_shape: EndingShape::Circle,
+                size: RadialSize::Radius(Value::Length(10.0, Unit::Px)),
+                position: Position {
+                    x: Value::Length(20.0, Unit::Px),
+                    y: Value::Length(0.0, Unit::Percent),
+                },
+            }
+        );
+        let conic = gradient("conic-gradient(from 90deg at 25% 75%, red 0deg, blue 50%)").unwrap();
+        assert!(matches!(conic.shape, GradientShape::Conic { from: 90.0, .. }));
+
+        // Invalid stop lists and configurations
+        assert_eq!(gradient("linear-gradient(red)"), None);
+        assert_eq!(gradient("linear-gradient(red, 10%)"), None);
+        assert_eq!(gradient("radial-gradient(circle 10%, red, blue)"), None);
+        assert_eq!(gradient("conic-gradient(red 10px, blue)"), None);
+        assert_eq!(gradient("linear-gradient(to left right, red, blue)"), None);
+    }
+}
@@ -0,0 +1,282 @@
+// @import rules and resolving them through a loader callback
+use crate::layer::{rename_imported_anonymous_layers, valid_layer_name};
+use crate::{parse, parse_media_query_list, Condition, MediaQueryList, Parser, Stylesheet, SupportsCondition};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportRule {
+    pub url: String,
+    /// The cascade layer the imported rules go in, from `layer(<name>)`, or an
+    /// anonymous layer for a bare `layer`
+    pub layer: Option<String>,
+    /// From `supports(...)`: the imported rules only apply when this holds
+    pub supports: Option<SupportsCondition>,
+    pub media: MediaQueryList,
+}
+
+/// A stylesheet fetched on behalf of an `@import` rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedStylesheet {
+    /// The absolute URL the sheet was loaded from, which its own imports resolve against
+    pub url: String,
+    pub source: String,
+}
+
+/// Imports nested deeper than this are ignored.
+const MAX_IMPORT_DEPTH: usize = 16;
+
+impl Parser {
+    /// Parse the prelude of an `@import` rule: a URL followed by an optional `layer`
+    /// or `layer(<name>)`, an optional `supports(...)` and an optional media query
+    /// list. The import's layer is declared at this point in the layer order.
+    pub(crate) fn parse_import(&mut self, prelude: &str) -> Option<ImportRule> {
+        let mut parser = Parser::new(prelude);
+        let url = match parser.next_char() {
+            '"' | '\'' => parser.parse_string(),
+            _ => {
+                if !parser.parse_identifier().eq_ignore_ascii_case("url") || parser.next_char() != '(' {
+                    return None;
+                }
+                parser.parse_function_argument()
+            }
+        };
+
+        parser.consume_whitespace();
+        let mut layer = None;
+        if parser.consume_keyword("layer") {
+            if parser.next_char() == '(' {
+                let name = parser.parse_function_argument();
+                layer = Some(valid_layer_name(&name)?.to_string());
+            } else {
+                layer = Some(self.anonymous_layer());
+            }
+            parser.consume_whitespace();
+        }
+
+        let mut supports = None;
+        if parser.peek_keyword("supports") {
+            parser.parse_identifier();
+            if parser.next_char() != '(' {
+                return None;
+            }
+            let start = parser.pos + 1;
+            parser.skip_balanced();
+            // The argument is either a condition or a bare declaration
+            let argument = parser.input[start..parser.pos - 1].trim();
+            let condition = Parser::parse_supports_condition(argument)
+                .or_else(|| Parser::parse_supports_condition(&format!("({})", argument)))?;
+            supports = Some(condition);
+            parser.consume_whitespace();
+        }
+
+        let media = parse_media_query_list(&parser.input[parser.pos..]);
+        if let Some(ref layer) = layer {
+            self.declare_layer(layer);
+        }
+        Some(ImportRule { url, layer, supports, media })
+    }
+}
+
+/// Parse `source` and replace each of its `@import` rules with the rules of the imported
+/// sheet, recursively. `load` is called with the URL as written in the importing sheet and
+/// the URL of that sheet; returning `None` skips the import.
+pub fn parse_with_imports<F>(source: &str, base_url: &str, mut load: F) -> Stylesheet
+where
+    F: FnMut(&str, &str) -> Option<ImportedStylesheet>,
+{
+    let mut ancestors = vec![base_url.to_string()];
+    resolve_imports(parse(source), &mut load, &mut ancestors)
+}
+
+fn resolve_imports<F>(sheet: Stylesheet, load: &mut F, ancestors: &mut Vec<String>) -> Stylesheet
+where
+    F: FnMut(&str, &str) -> Option<ImportedStylesheet>,
+{
+    if sheet.imports.is_empty() || ancestors.len() > MAX_IMPORT_DEPTH {
+        return sheet;
+    }
+
+    let base_url = ancestors.last().cloned().unwrap_or_default();
+    let mut rules = Vec::new();
+    let mut font_faces = Vec::new();
+    let mut keyframes = Vec::new();
+    let mut layers: Vec<String> = Vec::new();
+    for (index, import) in sheet.imports.iter().enumerate() {
+        let Some(loaded) = load(&import.url, &base_url) else {
+            continue;
+        };
+        // A sheet importing one of its own ancestors would recurse forever
+        if ancestors.contains(&loaded.url) {
+            continue;
+        }
+
+        ancestors.push(loaded.url);
+        let imported = resolve_imports(parse(&loaded.source), load, ancestors);
+        ancestors.pop();
+
+        // The import's conditions go on everything imported through it, outside the
+        // imported sheet's own
+        let mut conditions = Vec::new();
+        if !import.media.queries.is_empty() {
+            conditions.push(Condition::Media(import.media.clone()));
+        }
+        if let Some(ref supports) = import.supports {
+            conditions.push(Condition::Supports(supports.clone()));
+        }
+        if let Some(ref layer) = import.layer {
+            conditions.push(Condition::Layer(layer.clone()));
+        }
+        let import_conditions = |imported: Vec<Condition>| -> Vec<Condition> {
+            let imported = imported.into_iter().map(|condition| match condition {
+                Condition::Layer(name) => Condition::Layer(rename_imported_anonymous_layers(&name, index + 1)),
+                condition => condition,
+            });
+            conditions.iter().cloned().chain(imported).collect()
+        };
+        for mut rule in imported.rules {
+            rule.conditions = import_conditions(rule.conditions);
+            rules.push(rule);
+        }
+        for mut face in imported.font_faces {
+            face.conditions = import_conditions(face.conditions);
+            font_faces.push(face);
+        }
+        for mut rule in imported.keyframes {
+            rule.conditions = import_conditions(rule.conditions);
+            keyframes.push(rule);
+        }
+        // Layers declared by imported sheets come first in the layer order, nested in
+        // the import's layer
+        for layer in imported.layers {
+            let layer = rename_imported_anonymous_layers(&layer, index + 1);
+            let layer = match import.layer {
+                Some(ref parent) => format!("{}.{}", parent, layer),
+                None => layer,
+            };
+            if !layers.contains(&layer) {
+                layers.push(layer);
+            }
+        }
+    }
+
+    rules.extend(sheet.rules);
+    font_faces.extend(sheet.font_faces);
+    keyframes.extend(sheet.keyframes);
+    for layer in sheet.layers {
+        if !layers.contains(&layer) {
+            layers.push(layer);
+        }
+    }
+    Stylesheet {
+        rules,
+        font_faces,
+        keyframes,
+        imports: sheet.imports,
+        layers,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_import_prelude() {
+        let stylesheet = parse(
+            "@import url(\"dark.css\") (prefers-color-scheme: dark);
+             @import 'base.css';
+             p { color: #000000; }
+             @import 'late.css';",
+        );
+        assert_eq!(stylesheet.imports.len(), 2);
+        assert_eq!(stylesheet.imports[0].url, "dark.css");
+        assert_eq!(stylesheet.imports[0].media.queries.len(), 1);
+        assert_eq!(stylesheet.imports[1].url, "base.css");
+        assert!(stylesheet.imports[1].media.queries.is_empty());
+        assert_eq!(stylesheet.rules.len(), 1);
+    }
+
+    #[test]
+    fn test_imports_are_resolved_in_order() {
+        let source = "@import 'a.css' print; @import 'b.css'; p { margin: 3px; }";
+        let mut requested = Vec::new();
+        let stylesheet = parse_with_imports(source, "https://example.com/main.css", |url, base| {
+            requested.push((url.to_string(), base.to_string()));
+            let (url, source) = match url {
+                "a.css" => ("https://example.com/a.css", "@import 'main.css'; h1 { margin: 1px; }"),
+                "b.css" => ("https://example.com/b.css", "@font-face { font-family: X; src: url(x.woff); }"),
+                "main.css" => ("https://example.com/main.css", "h6 { margin: 0px; }"),
+                _ => return None,
+            };
+            Some(ImportedStylesheet {
+                url: url.to_string(),
+                source: source.to_string(),
+            })
+        });
+
+        assert_eq!(requested.len(), 3);
+        assert_eq!(requested[1], ("main.css".to_string(), "https://example.com/a.css".to_string()));
+        // The cyclic import of main.css is skipped
+        assert_eq!(stylesheet.rules.len(), 2);
+        assert_eq!(stylesheet.rules[0].declarations[0].value, Value::Length(1.0, Unit::Px));
+        assert_eq!(stylesheet.rules[0].conditions.len(), 1);
+        assert!(!stylesheet.rules[0].applies_to(&MediaEnvironment::default(), &[]));
+        assert_eq!(stylesheet.font_faces.len(), 1);
+    }
+
+    #[test]
+    fn test_import_layer_and_supports() {
+        let stylesheet = parse(
+            "@import url(a.css) layer(base);
+             @import url(b.css) supports(display: grid) screen;
+             @import 'c.css' layer supports((display: block) and (color: banana));",
+        );
+        assert_eq!(stylesheet.imports.len(), 3);
+        assert_eq!(stylesheet.imports[0].layer.as_deref(), Some("base"));
+        assert!(stylesheet.imports[0].media.queries.is_empty());
+        let supports = stylesheet.imports[1].supports.as_ref().unwrap();
+        assert_eq!(supports, &SupportsCondition::Declaration("display".to_string(), "grid".to_string()));
+        assert_eq!(stylesheet.imports[1].layer, None);
+        assert_eq!(stylesheet.imports[1].media.queries.len(), 1);
+        assert!(stylesheet.imports[2].layer.is_some());
+        assert!(!stylesheet.imports[2].supports.as_ref().unwrap().evaluate());
+        assert_eq!(stylesheet.layers.len(), 2);
+        assert_eq!(parse(&stylesheet.to_string()), stylesheet);
+
+        let source = "@layer x;
+             @import 'a.css' layer(base) supports(color: red);
+             @import 'b.css' supports(color: banana);
+             @layer x { p { color: blue; } }";
+        let stylesheet = parse_with_imports(source, "main.css", |url, _| {
+            let source = match url {
+                "a.css" => "@layer reset { p { margin: 0; } } h1 { margin: 1px; }",
+                "b.css" => "p { margin: 2px; }",
+                _ => return None,
+            };
+            Some(ImportedStylesheet {
+                url: url.to_string(),
+                source: source.to_string(),
+            })
+        });
+        let layers: Vec<_> = stylesheet.rules.iter().map(Rule::layer).collect();
+        assert_eq!(layers, [Some("base.reset".to_string()), Some("base".to_string()), None, Some("x".to_string())]);
+        assert_eq!(stylesheet.layer_order(), ["x", "base.reset", "base"]);
+        let environment = MediaEnvironment::default();
+        assert!(stylesheet.rules[0].applies_to(&environment, &[]));
+        // Rules imported where `supports()` is false don't apply
+        assert!(!stylesheet.rules[2].applies_to(&environment, &[]));
+    }
+
+    #[test]
+    fn test_imported_anonymous_layers_are_distinct() {
+        let source = "@import 'a.css'; @layer { p { color: blue; } }";
+        let stylesheet = parse_with_imports(source, "main.css", |url, _| {
+            Some(ImportedStylesheet {
+                url: url.to_string(),
+                source: "@layer { p { color: red; } } @layer x { p { color: green; } }".to_string(),
+            })
+        });
+        assert_eq!(stylesheet.layer_order(), ["#1-1", "x", "#1"]);
+        let layers: Vec<_> = stylesheet.rules.iter().map(Rule::layer).collect();
+        assert_eq!(layers, [Some("#1-1".to_string()), Some("x".to_string()), Some("#1".to_string())]);
+    }
+}
@@ -0,0 +1,156 @@
+// Cascade layers from @layer rules
+use crate::{valid_identifier_char, Condition, Parser, Rule, Selector, Stylesheet};
+
+/// Anonymous layers get a name no style sheet can refer to, starting with this.
+const ANONYMOUS_LAYER_PREFIX: char = '#';
+
+pub(crate) fn is_anonymous_layer(name: &str) -> bool {
+    name.starts_with(ANONYMOUS_LAYER_PREFIX)
+}
+
+impl Stylesheet {
+    /// The style sheet's cascade layers from lowest to highest priority. A layer's
+    /// sublayers come before it, and unlayered rules beat every layer.
+    pub fn layer_order(&self) -> Vec<&str> {
+        let mut order = Vec::new();
+        self.push_sublayers(None, &mut order);
+        order
+    }
+
+    fn push_sublayers<'a>(&'a self, parent: Option<&str>, order: &mut Vec<&'a str>) {
+        for layer in &self.layers {
+            if layer.rsplit_once('.').map(|(parent, _)| parent) == parent {
+                self.push_sublayers(Some(layer), order);
+                order.push(layer);
+            }
+        }
+    }
+}
+
+impl Rule {
+    /// The full name of the rule's cascade layer, such as `framework.base`.
+    pub fn layer(&self) -> Option<String> {
+        layer_name(&self.conditions)
+    }
+}
+
+/// Join the names of the layers in `conditions`.
+fn layer_name(conditions: &[Condition]) -> Option<String> {
+    let names: Vec<&str> = conditions
+        .iter()
+        .filter_map(|condition| match condition {
+            Condition::Layer(name) => Some(&**name),
+            _ => None,
+        })
+        .collect();
+    (!names.is_empty()).then(|| names.join("."))
+}
+
+impl Parser {
+    /// Parse an `@layer` rule: either a statement declaring the order of some layers,
+    /// or a block of rules in a layer, which may be anonymous. Nested in a style rule
+    /// with selectors `parent`, the block may hold declarations too.
+    pub(crate) fn parse_layer_rule(
+        &mut self,
+        prelude: &str,
+        conditions: &[Condition],
+        parent: Option<&[Selector]>,
+    ) -> Vec<Rule> {
+        let enclosing = layer_name(conditions);
+        let full_name = |name: &str| match enclosing {
+            Some(ref enclosing) => format!("{}.{}", enclosing, name),
+            None => name.to_string(),
+        };
+
+        if self.next_char() != '{' {
+            let names: Option<Vec<&str>> = prelude.split(',').map(|name| valid_layer_name(name.trim())).collect();
+            for name in names.unwrap_or_default() {
+                self.declare_layer(&full_name(name));
+            }
+            self.skip_at_rule_end();
+            return Vec::new();
+        }
+
+        let name = if prelude.is_empty() {
+            self.anonymous_layer()
+        } else if let Some(name) = valid_layer_name(prelude) {
+            name.to_string()
+        } else {
+            self.skip_balanced();
+            return Vec::new();
+        };
+        self.declare_layer(&full_name(&name));
+        let mut conditions = conditions.to_vec();
+        conditions.push(Condition::Layer(name));
+        self.parse_nested_rules(&conditions, parent)
+    }
+
+    /// Name a new anonymous layer.
+    pub(crate) fn anonymous_layer(&mut self) -> String {
+        self.anonymous_layers += 1;
+        format!("{}{}", ANONYMOUS_LAYER_PREFIX, self.anonymous_layers)
+    }
+
+    /// Add a layer to the declaration order, if it is new. Declaring `a.b` declares `a` too.
+    pub(crate) fn declare_layer(&mut self, name: &str) {
+        let ends = name.match_indices('.').map(|(end, _)| end).chain([name.len()]);
+        for end in ends {
+            if !self.layers.iter().any(|layer| *layer == name[..end]) {
+                self.layers.push(name[..end].to_string());
+            }
+        }
+    }
+}
+
+/// Anonymous layer names are only unique within one style sheet, so those in a sheet's
+/// `index`th import are renamed from `#1` to `#<index>-1`, and so on.
+pub(crate) fn rename_imported_anonymous_layers(name: &str, index: usize) -> String {
+    let segments: Vec<String> = name
+        .split('.')
+        .map(|segment| match segment.strip_prefix(ANONYMOUS_LAYER_PREFIX) {
+            Some(rest) => format!("{}{}-{}", ANONYMOUS_LAYER_PREFIX, index, rest),
+            None => segment.to_string(),
+        })
+        .collect();
+    segments.join(".")
+}
+
+/// A layer name is one or more identifiers separated by dots.
+pub(crate) fn valid_layer_name(name: &str) -> Option<&str> {
+    let valid = name.split('.').all(|segment| {
+        !segment.is_empty()
+            && segment.chars().all(valid_identifier_char)
+            && !segment.starts_with(|c: char| c.is_ascii_digit())
+    });
+    valid.then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_layer_order() {
+        let stylesheet = parse(
+            "@layer reset, framework;
+             @layer framework.base { p { color: #000000; } }
+             @layer { p { color: #111111; } }
+             @layer reset { p { color: #222222; } }
+             @media print { @layer framework { p { color: #333333; } } }
+             p { color: #444444; }",
+        );
+        assert_eq!(stylesheet.layers, ["reset", "framework", "framework.base", "#1"]);
+        assert_eq!(stylesheet.layer_order(), ["reset", "framework.base", "framework", "#1"]);
+        let layers: Vec<_> = stylesheet.rules.iter().map(Rule::layer).collect();
+        assert_eq!(
+            layers,
+            [
+                Some("framework.base".to_string()),
+                Some("#1".to_string()),
+                Some("reset".to_string()),
+                Some("framework".to_string()),
+                None,
+            ]
+        );
+    }
+}
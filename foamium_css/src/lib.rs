@@ -1,36 +1,147 @@
-use std::collections::HashMap;
+mod animation;
+mod calc;
+mod color;
+mod container;
+mod font_face;
+mod grid;
+mod image;
+mod import;
+mod layer;
+mod media;
+mod nesting;
+mod properties;
+mod serialize;
+mod shorthand;
+mod supports;
+mod tokenizer;
+mod variables;
+
+pub use animation::{EasingFunction, Keyframe, Keyframes, StepPosition};
+pub use calc::{CalcNode, CalcType};
+pub use container::{ContainerQuery, ContainerType, QueryContainer};
+pub use font_face::{FontDisplay, FontFace, FontFaceStyle, FontSource, FontWeightRange, UnicodeRange};
+pub use grid::{GridLine, GridValue, RepeatCount, TemplateAreas, TrackBreadth, TrackListItem, TrackSize};
+pub use image::{
+    EndingShape, Gradient, GradientLine, GradientShape, GradientStop, HorizontalSide, Image, ImageSetOption, Position,
+    RadialSize, VerticalSide,
+};
+pub use import::{parse_with_imports, ImportRule, ImportedStylesheet};
+pub use media::{
+    parse_media_query_list, ColorScheme, Comparison, MediaCondition, MediaEnvironment,
+    MediaFeature, MediaQuery, MediaQueryList, MediaType, MediaValue,
+};
+pub use properties::is_inherited;
+pub use shorthand::parse_shorthand;
+pub use supports::SupportsCondition;
+pub use tokenizer::{serialize_tokens, tokenize, Token};
+pub use variables::{compute_custom_properties, contains_variables, substitute_variables, CustomProperties};
+
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Stylesheet {
     pub rules: Vec<Rule>,
+    pub font_faces: Vec<FontFace>,
+    pub keyframes: Vec<Keyframes>,
+    pub imports: Vec<ImportRule>,
+    /// Cascade layer names in the order they were first declared; see `layer_order`
+    pub layers: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Rule {
     pub selectors: Vec<Selector>,
     pub declarations: Vec<Declaration>,
+    /// The enclosing at-rules, outermost first. Their conditions must all hold for the
+    /// rule to apply.
+    pub conditions: Vec<Condition>,
+}
+
+impl Rule {
+    /// Whether the rule applies in `env` to an element whose query containers are
+    /// `containers`, outermost first.
+    pub fn applies_to(&self, env: &MediaEnvironment, containers: &[QueryContainer]) -> bool {
+        self.conditions.iter().all(|condition| condition.matches(env, containers))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Media(MediaQueryList),
+    Supports(SupportsCondition),
+    Container(ContainerQuery),
+    /// Not a condition, but a cascade layer, which puts the rule in that layer. Holds the
+    /// name as written, relative to any enclosing layer.
+    Layer(String),
+}
+
+impl Condition {
+    pub fn matches(&self, env: &MediaEnvironment, containers: &[QueryContainer]) -> bool {
+        match *self {
+            Condition::Media(ref media) => media.matches(env),
+            Condition::Supports(ref supports) => supports.evaluate(),
+            Condition::Container(ref query) => query.matches(containers, env),
+            Condition::Layer(_) => true,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Selector {
     Simple(SimpleSelector),
+    /// A compound selector and the selector that must match an element related to it,
+    /// such as `nav > a`
+    Complex(Box<Selector>, Combinator, SimpleSelector),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Combinator {
+    /// Whitespace: an ancestor
+    Descendant,
+    /// `>`: the parent
+    Child,
+    /// `+`: the previous sibling
+    NextSibling,
+    /// `~`: any previous sibling
+    SubsequentSibling,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct SimpleSelector {
     pub tag_name: Option<String>,
     pub id: Option<String>,
     pub class: Vec<String>,
+    /// `:is()` pseudo-classes, each matching if any selector in its list does. Nested
+    /// rules are desugared into these.
+    pub is: Vec<Vec<Selector>>,
 }
 
 pub type Specificity = (usize, usize, usize);
 
 impl Selector {
     pub fn specificity(&self) -> Specificity {
-        let Selector::Simple(ref simple) = *self;
-        let a = simple.id.iter().count();
-        let b = simple.class.len();
-        let c = simple.tag_name.iter().count();
+        match *self {
+            Selector::Simple(ref simple) => simple.specificity(),
+            Selector::Complex(ref left, _, ref simple) => {
+                let (a, b, c) = left.specificity();
+                let (x, y, z) = simple.specificity();
+                (a + x, b + y, c + z)
+            }
+        }
+    }
+}
+
+impl SimpleSelector {
+    pub fn specificity(&self) -> Specificity {
+        let mut a = self.id.iter().count();
+        let mut b = self.class.len();
+        let mut c = self.tag_name.iter().count();
+        for list in &self.is {
+            // `:is()` is as specific as its most specific argument
+            let (x, y, z) = list.iter().map(Selector::specificity).max().unwrap_or_default();
+            a += x;
+            b += y;
+            c += z;
+        }
         (a, b, c)
     }
 }
@@ -39,24 +150,136 @@ impl Selector {
 pub struct Declaration {
     pub name: String,
     pub value: Value,
+    /// Whether the declaration is `!important`
+    pub important: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Keyword(String),
+    /// A quoted string, such as a font family name
+    String(String),
+    Number(f32),
     Length(f32, Unit),
     ColorValue(Color),
+    /// The value of a custom property, kept as tokens until it is substituted somewhere
+    Custom(Vec<Token>),
+    /// A value containing `var()`, which can only be parsed once the variables are known
+    WithVariables(Vec<Token>),
+    /// A longhand set by a shorthand containing `var()`: the shorthand's name and value,
+    /// to be expanded once the variables are known
+    ShorthandWithVariables(String, Vec<Token>),
+    /// A math function, resolved once percentages and font sizes are known
+    Calc(CalcNode),
+    /// A time in seconds
+    Time(f32),
+    /// An angle in degrees
+    Angle(f32),
+    Image(Image),
+    /// An easing function such as `cubic-bezier()`; the keywords like `ease` stay keywords
+    Easing(EasingFunction),
+    /// A track list, template areas, line or flow for the grid properties
+    Grid(GridValue),
+    /// A comma-separated list, for properties that take one value per item, such as
+    /// `transition-duration`. A single item isn't wrapped in a list.
+    List(Vec<Value>),
+}
+
+impl Value {
+    /// The items of a comma-separated list, or the value itself if it isn't one.
+    pub fn items(&self) -> &[Value] {
+        match *self {
+            Value::List(ref items) => items,
+            _ => std::slice::from_ref(self),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Unit {
+    // Absolute lengths
     Px,
     Pt,
+    Pc,
+    In,
+    Cm,
+    Mm,
+    Q,
+    // Relative to the element's font, or the root element's for the `r` variants
     Em,
     Rem,
+    Ex,
+    Rex,
+    Ch,
+    Rch,
+    Cap,
+    Rcap,
+    Ic,
+    Ric,
+    Lh,
+    Rlh,
+    // Relative to the viewport, with small, large and dynamic variants
+    Vw,
+    Vh,
+    Vi,
+    Vb,
+    Vmin,
+    Vmax,
+    Svw,
+    Svh,
+    Svi,
+    Svb,
+    Svmin,
+    Svmax,
+    Lvw,
+    Lvh,
+    Lvi,
+    Lvb,
+    Lvmin,
+    Lvmax,
+    Dvw,
+    Dvh,
+    Dvi,
+    Dvb,
+    Dvmin,
+    Dvmax,
     Percent,
 }
 
+impl Unit {
+    /// The size in px of one unit of an absolute length.
+    pub fn absolute_px(self) -> Option<f32> {
+        match self {
+            Unit::Px => Some(1.0),
+            Unit::Pt => Some(96.0 / 72.0),
+            Unit::Pc => Some(16.0),
+            Unit::In => Some(96.0),
+            Unit::Cm => Some(96.0 / 2.54),
+            Unit::Mm => Some(96.0 / 25.4),
+            Unit::Q => Some(96.0 / 101.6),
+            _ => None,
+        }
+    }
+
+    /// The size in px of one unit of a viewport-percentage length. We have no
+    /// retractable UI, so the small, large and dynamic viewports are all the same, and
+    /// the inline and block axes are horizontal and vertical.
+    pub fn viewport_px(self, width: f32, height: f32) -> Option<f32> {
+        let dimension = match self {
+            Unit::Vw | Unit::Vi | Unit::Svw | Unit::Svi | Unit::Lvw | Unit::Lvi | Unit::Dvw | Unit::Dvi => width,
+            Unit::Vh | Unit::Vb | Unit::Svh | Unit::Svb | Unit::Lvh | Unit::Lvb | Unit::Dvh | Unit::Dvb => height,
+            Unit::Vmin | Unit::Svmin | Unit::Lvmin | Unit::Dvmin => width.min(height),
+            Unit::Vmax | Unit::Svmax | Unit::Lvmax | Unit::Dvmax => width.max(height),
+            _ => return None,
+        };
+        Some(dimension / 100.0)
+    }
+
+    pub fn name(self) -> &'static str {
+        UNITS.iter().find(|&&(_, unit)| unit == self).map_or("", |&(name, _)| name)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct Color {
     pub r: u8,
@@ -72,31 +295,88 @@ impl Color {
 }
 
 pub fn parse(source: &str) -> Stylesheet {
-    let mut parser = Parser {
-        pos: 0,
-        input: source.to_string(),
-    };
+    let mut parser = Parser::new(source);
+    let rules = parser.parse_rules();
     Stylesheet {
-        rules: parser.parse_rules(),
+        rules,
+        font_faces: parser.font_faces,
+        keyframes: parser.keyframes,
+        imports: parser.imports,
+        layers: parser.layers,
+    }
+}
+
+/// Parse the declarations of a `style` attribute.
+pub fn parse_inline_style(source: &str) -> Vec<Declaration> {
+    Parser::new(source).parse_declaration_list()
+}
+
+/// Parse a single property value, such as the result of `var()` substitution.
+/// Returns `None` if the source doesn't start with a value.
+pub fn parse_value(source: &str) -> Option<Value> {
+    let mut parser = Parser::new(source.trim());
+    if parser.eof() {
+        return None;
+    }
+    match parser.parse_value()? {
+        Value::Keyword(ref keyword) if keyword.is_empty() => None,
+        value => Some(value),
     }
 }
 
 struct Parser {
     pos: usize,
     input: String,
+    font_faces: Vec<FontFace>,
+    keyframes: Vec<Keyframes>,
+    imports: Vec<ImportRule>,
+    /// `@import` is only valid before any other rules
+    imports_allowed: bool,
+    layers: Vec<String>,
+    anonymous_layers: usize,
 }
 
 impl Parser {
+    fn new(source: &str) -> Parser {
+        Parser {
+            pos: 0,
+            input: source.to_string(),
+            font_faces: Vec::new(),
+            keyframes: Vec::new(),
+            imports: Vec::new(),
+            imports_allowed: true,
+            layers: Vec::new(),
+            anonymous_layers: 0,
+        }
+    }
+
     fn parse_rules(&mut self) -> Vec<Rule> {
+        let mut rules = Vec::new();
+        while !self.eof() {
+            rules.extend(self.parse_rule_list(&[]));
+            // A stray closing brace at the top level; skip it and carry on
+            if !self.eof() {
+                self.consume_char();
+            }
+        }
+        rules
+    }
+
+    /// Parse rules until the end of input or the `}` closing the enclosing block.
+    fn parse_rule_list(&mut self, conditions: &[Condition]) -> Vec<Rule> {
         let mut rules = Vec::new();
         loop {
             self.consume_whitespace();
-            if self.eof() {
+            if self.eof() || self.next_char() == '}' {
                 break;
             }
-            if let Some(rule) = self.parse_rule() {
-                rules.push(rule);
+            let start = self.pos;
+            if self.next_char() == '@' {
+                rules.extend(self.parse_at_rule(conditions, None));
             } else {
+                rules.extend(self.parse_rule(conditions, None));
+            }
+            if self.pos == start {
                 // Skip invalid token to make progress
                 self.consume_char();
             }
@@ -104,97 +384,385 @@ impl Parser {
         rules
     }
 
-    fn parse_rule(&mut self) -> Option<Rule> {
-        let selectors = self.parse_selectors();
-        if selectors.is_empty() {
-            return None;
+    /// Parse a style rule. It is flattened into the rule itself followed by its nested
+    /// rules, or nothing if its selector is invalid. `parent` holds the selectors of the
+    /// enclosing style rule, if any.
+    fn parse_rule(&mut self, conditions: &[Condition], parent: Option<&[Selector]>) -> Vec<Rule> {
+        self.imports_allowed = false;
+        let prelude = self.parse_prelude();
+        if self.next_char() != '{' {
+            if self.next_char() == ';' {
+                self.consume_char();
+            }
+            return Vec::new();
+        }
+        match Parser::new(&prelude).parse_selectors(parent) {
+            Some(selectors) => self.parse_style_block(&selectors, conditions),
+            None => {
+                self.skip_balanced();
+                Vec::new()
+            }
         }
-        let declarations = self.parse_declarations();
-        Some(Rule {
-            selectors,
-            declarations,
-        })
     }
 
-    fn parse_selectors(&mut self) -> Vec<Selector> {
-        let mut selectors = Vec::new();
+    /// Parse an at-rule. Inside a style rule, `parent` holds its selectors, and the
+    /// blocks of conditional rules may contain declarations.
+    fn parse_at_rule(&mut self, conditions: &[Condition], parent: Option<&[Selector]>) -> Vec<Rule> {
+        self.consume_char(); // consume '@'
+        let name = self.parse_identifier().to_ascii_lowercase();
+        let prelude = self.parse_prelude();
+        // `@layer` statements may come before imports too
+        let layer_statement = name == "layer" && self.next_char() != '{';
+        if name != "import" && name != "charset" && !layer_statement {
+            self.imports_allowed = false;
+        }
+
+        match &*name {
+            "import" if self.imports_allowed && self.next_char() != '{' => {
+                if let Some(import) = self.parse_import(&prelude) {
+                    self.imports.push(import);
+                }
+                self.skip_at_rule_end();
+                Vec::new()
+            }
+            "font-face" if parent.is_none() && self.next_char() == '{' => {
+                if let Some(face) = self.parse_font_face(conditions) {
+                    self.font_faces.push(face);
+                }
+                Vec::new()
+            }
+            "keyframes" | "-webkit-keyframes" if parent.is_none() && self.next_char() == '{' => {
+                if let Some(keyframes) = self.parse_keyframes(&prelude, conditions) {
+                    self.keyframes.push(keyframes);
+                }
+                Vec::new()
+            }
+            "media" if self.next_char() == '{' => {
+                let mut conditions = conditions.to_vec();
+                conditions.push(Condition::Media(parse_media_query_list(&prelude)));
+                self.parse_nested_rules(&conditions, parent)
+            }
+            "supports" if self.next_char() == '{' => match Parser::parse_supports_condition(&prelude) {
+                Some(supports) => {
+                    let mut conditions = conditions.to_vec();
+                    conditions.push(Condition::Supports(supports));
+                    self.parse_nested_rules(&conditions, parent)
+                }
+                None => {
+                    self.skip_balanced();
+                    Vec::new()
+                }
+            },
+            "container" if self.next_char() == '{' => match Parser::parse_container_query(&prelude) {
+                Some(query) => {
+                    let mut conditions = conditions.to_vec();
+                    conditions.push(Condition::Container(query));
+                    self.parse_nested_rules(&conditions, parent)
+                }
+                None => {
+                    self.skip_balanced();
+                    Vec::new()
+                }
+            },
+            "layer" => self.parse_layer_rule(&prelude, conditions, parent),
+            _ => {
+                // Unknown at-rules are dropped along with their block, if any
+                self.skip_at_rule_end();
+                Vec::new()
+            }
+        }
+    }
+
+    /// Skip the rest of an at-rule: its `{ ... }` block or terminating `;`.
+    fn skip_at_rule_end(&mut self) {
+        if self.next_char() == '{' {
+            self.skip_balanced();
+        } else if self.next_char() == ';' {
+            self.consume_char();
+        }
+    }
+
+    /// Parse a `{ name: value; ... }` block without interpreting the values, which are
+    /// returned as trimmed source text.
+    fn parse_raw_declarations(&mut self) -> Vec<(String, String)> {
+        let mut declarations = Vec::new();
+        if self.next_char() != '{' {
+            return declarations;
+        }
+        self.consume_char(); // consume '{'
         loop {
-            if let Some(selector) = self.parse_simple_selector() {
-                selectors.push(Selector::Simple(selector));
+            self.consume_whitespace();
+            if self.eof() || self.next_char() == '}' {
+                if !self.eof() {
+                    self.consume_char();
+                }
+                break;
             }
-            
+            let name = self.parse_identifier();
             self.consume_whitespace();
+            if name.is_empty() || self.next_char() != ':' {
+                self.consume_until_delimiter();
+                continue;
+            }
+            self.consume_char(); // consume ':'
+            let value = self.consume_raw_value();
+            declarations.push((name, value.trim().to_string()));
+            if self.next_char() == ';' {
+                self.consume_char();
+            }
+        }
+        declarations
+    }
+
+    /// Consume a declaration value up to the `;` or `}` that ends it, returning its source.
+    fn consume_raw_value(&mut self) -> String {
+        let start = self.pos;
+        while !self.eof() && !matches!(self.next_char(), ';' | '}') {
             match self.next_char() {
-                ',' => {
+                '(' | '[' | '{' | '"' | '\'' => self.skip_balanced(),
+                _ => {
                     self.consume_char();
-                    self.consume_whitespace();
                 }
-                '{' => break,
+            }
+        }
+        self.input[start..self.pos].to_string()
+    }
+
+    /// Parse a quoted string, returning its contents with escapes removed.
+    fn parse_string(&mut self) -> String {
+        let quote = self.consume_char();
+        let mut result = String::new();
+        while !self.eof() {
+            match self.consume_char() {
+                '\\' => result.push(self.consume_char()),
+                c if c == quote => break,
+                c => result.push(c),
+            }
+        }
+        result
+    }
+
+    /// Parse the single argument of a function such as `url(...)` or `format(...)`, which
+    /// may be a quoted string or raw text. Expects to be positioned at the `(`.
+    fn parse_function_argument(&mut self) -> String {
+        self.consume_char(); // consume '('
+        self.consume_whitespace();
+        let argument = match self.next_char() {
+            '"' | '\'' => self.parse_string(),
+            _ => self.consume_while(|c| c != ')').trim_end().to_string(),
+        };
+        self.consume_whitespace();
+        if self.next_char() == ')' {
+            self.consume_char();
+        }
+        argument
+    }
+
+    /// Parse the `{ ... }` block of a conditional group rule, which holds declarations
+    /// as well as rules when nested in the style rule with selectors `parent`.
+    fn parse_nested_rules(&mut self, conditions: &[Condition], parent: Option<&[Selector]>) -> Vec<Rule> {
+        self.consume_char(); // consume '{'
+        let rules = match parent {
+            Some(selectors) => self.parse_block_contents(selectors, conditions, false),
+            None => self.parse_rule_list(conditions),
+        };
+        if !self.eof() {
+            self.consume_char(); // consume '}'
+        }
+        rules
+    }
+
+    /// Consume an at-rule prelude, up to the `{` or `;` that ends it.
+    fn parse_prelude(&mut self) -> String {
+        let start = self.pos;
+        while !self.eof() {
+            match self.next_char() {
+                '{' | ';' | '}' => break,
+                '(' | '[' | '"' | '\'' => self.skip_balanced(),
                 _ => {
-                    // Unexpected char, stop parsing selectors
-                    break; 
+                    self.consume_char();
+                }
+            }
+        }
+        self.input[start..self.pos].trim().to_string()
+    }
+
+    /// Skip a string or a bracketed block, including anything nested inside it.
+    fn skip_balanced(&mut self) {
+        let open = self.consume_char();
+        let close = match open {
+            '(' => ')',
+            '[' => ']',
+            '{' => '}',
+            '"' | '\'' => {
+                while !self.eof() {
+                    match self.consume_char() {
+                        '\\' => {
+                            self.consume_char();
+                        }
+                        c if c == open => break,
+                        _ => {}
+                    }
+                }
+                return;
+            }
+            _ => return,
+        };
+        while !self.eof() {
+            match self.next_char() {
+                c if c == close => {
+                    self.consume_char();
+                    break;
                 }
+                '(' | '[' | '{' | '"' | '\'' => self.skip_balanced(),
+                _ => {
+                    self.consume_char();
+                }
+            }
+        }
+    }
+
+    /// Parse a selector list making up the whole input. Returns `None` if any selector
+    /// in it is invalid or unsupported, which invalidates the rule. Inside a nested
+    /// rule, `parent` holds the enclosing rule's selectors; see `nesting`.
+    fn parse_selectors(&mut self, parent: Option<&[Selector]>) -> Option<Vec<Selector>> {
+        let (mut selectors, _) = self.parse_selector_list(parent, parent.is_some())?;
+        if !self.eof() {
+            return None;
+        }
+        selectors.sort_by_key(|s| std::cmp::Reverse(s.specificity()));
+        Some(selectors)
+    }
+
+    /// Parse comma-separated selectors up to the end of input or a `)`, also returning
+    /// whether any of them contain `&`.
+    fn parse_selector_list(&mut self, parent: Option<&[Selector]>, relative: bool) -> Option<(Vec<Selector>, bool)> {
+        let mut selectors = Vec::new();
+        let mut nests = false;
+        loop {
+            self.consume_whitespace();
+            let (selector, nested) = self.parse_complex_selector(parent, relative)?;
+            selectors.push(selector);
+            nests |= nested;
+            self.consume_whitespace();
+            if self.next_char() != ',' {
+                return Some((selectors, nests));
+            }
+            self.consume_char();
+        }
+    }
+
+    fn parse_complex_selector(&mut self, parent: Option<&[Selector]>, relative: bool) -> Option<(Selector, bool)> {
+        let leading = if relative { self.parse_combinator() } else { None };
+        let mut nests = false;
+        let mut selector = None;
+        let mut combinator = Combinator::Descendant;
+        loop {
+            let (compound, nested) = self.parse_compound_selector(parent)?;
+            nests |= nested;
+            selector = Some(match selector {
+                None => nesting::splice_leading_nesting(compound),
+                Some(left) => Selector::Complex(Box::new(left), combinator, compound),
+            });
+
+            let start = self.pos;
+            self.consume_whitespace();
+            match self.parse_combinator() {
+                Some(next) => combinator = next,
+                None if self.eof() || matches!(self.next_char(), ',' | ')') => break,
+                None if self.pos > start => combinator = Combinator::Descendant,
+                None => return None,
             }
-            if self.eof() { break; }
         }
-        selectors.sort_by(|a, b| b.specificity().cmp(&a.specificity()));
-        selectors
+
+        let selector = match parent {
+            // A relative selector, or one with no `&`, is nested under the parent
+            Some(parent) if relative && (leading.is_some() || !nests) => nesting::prepend(
+                selector?,
+                nesting::nesting_selector(parent),
+                leading.unwrap_or(Combinator::Descendant),
+            ),
+            _ => selector?,
+        };
+        Some((selector, nests))
     }
 
-    fn parse_simple_selector(&mut self) -> Option<SimpleSelector> {
-        let mut selector = SimpleSelector {
-            tag_name: None,
-            id: None,
-            class: Vec::new(),
+    /// Consume a `>`, `+` or `~` combinator and the whitespace after it.
+    fn parse_combinator(&mut self) -> Option<Combinator> {
+        let combinator = match self.next_char() {
+            '>' => Combinator::Child,
+            '+' => Combinator::NextSibling,
+            '~' => Combinator::SubsequentSibling,
+            _ => return None,
         };
+        self.consume_char();
+        self.consume_whitespace();
+        Some(combinator)
+    }
+
+    /// Parse a compound selector such as `p.note#intro`, returning it and whether it
+    /// contains the `&` nesting selector.
+    fn parse_compound_selector(&mut self, parent: Option<&[Selector]>) -> Option<(SimpleSelector, bool)> {
+        let mut selector = SimpleSelector::default();
         let mut found_something = false;
-        
+        let mut nested = false;
+
         while !self.eof() {
             match self.next_char() {
-                '#' => {
+                '#' | '.' => {
+                    let kind = self.consume_char();
+                    let name = self.parse_identifier();
+                    if name.is_empty() {
+                        return None;
+                    }
+                    if kind == '#' {
+                        selector.id = Some(name);
+                    } else {
+                        selector.class.push(name);
+                    }
+                }
+                '*' if !found_something => {
                     self.consume_char();
-                    selector.id = Some(self.parse_identifier());
-                    found_something = true;
                 }
-                '.' => {
+                '&' => {
                     self.consume_char();
-                    selector.class.push(self.parse_identifier());
-                    found_something = true;
+                    nesting::add_nesting_selector(&mut selector, parent?);
+                    nested = true;
                 }
-                '*' => {
+                ':' => {
                     self.consume_char();
-                    found_something = true;
+                    if !self.parse_identifier().eq_ignore_ascii_case("is") || self.next_char() != '(' {
+                        return None;
+                    }
+                    self.consume_char(); // consume '('
+                    let (list, nests) = self.parse_selector_list(parent, false)?;
+                    if self.consume_char() != ')' {
+                        return None;
+                    }
+                    nested |= nests;
+                    selector.is.push(list);
                 }
-                c if valid_identifier_char(c) => {
+                c if valid_identifier_char(c) && !found_something => {
                     selector.tag_name = Some(self.parse_identifier());
-                    found_something = true;
                 }
                 _ => break,
             }
+            found_something = true;
         }
-        
-        if found_something {
-            Some(selector)
-        } else {
-            None
-        }
+
+        found_something.then_some((selector, nested))
     }
 
-    fn parse_declarations(&mut self) -> Vec<Declaration> {
-        if self.next_char() != '{' {
-            return Vec::new();
-        }
-        self.consume_char(); // consume '{'
-        
+    /// Parse declarations up to the end of input or a closing `}`.
+    fn parse_declaration_list(&mut self) -> Vec<Declaration> {
         let mut declarations = Vec::new();
         loop {
             self.consume_whitespace();
             if self.eof() || self.next_char() == '}' {
-                if !self.eof() { self.consume_char(); }
                 break;
             }
-            if let Some(decl) = self.parse_declaration() {
-                declarations.push(decl);
+            if let Some(parsed) = self.parse_declaration() {
+                declarations.extend(parsed);
             } else {
                 // Skip to next semicolon or brace to recover
                 self.consume_until_delimiter();
@@ -203,16 +771,17 @@ impl Parser {
         declarations
     }
     
+    /// Skip the rest of an invalid declaration, leaving any `}` for the caller.
     fn consume_until_delimiter(&mut self) {
-        while !self.eof() {
-            let c = self.consume_char();
-            if c == ';' || c == '}' {
+        while !self.eof() && self.next_char() != '}' {
+            if self.consume_char() == ';' {
                 break;
             }
         }
     }
 
-    fn parse_declaration(&mut self) -> Option<Declaration> {
+    /// Parse a declaration, expanding shorthands into their longhands.
+    fn parse_declaration(&mut self) -> Option<Vec<Declaration>> {
         let property_name = self.parse_identifier();
         self.consume_whitespace();
         
@@ -222,73 +791,138 @@ impl Parser {
         self.consume_char(); // consume ':'
         
         self.consume_whitespace();
-        let value = self.parse_value();
+        let raw = self.consume_raw_value();
+        let (raw, important) = split_important(&raw);
+        let longhands = shorthand::longhands(&property_name);
+        let declarations = if property_name.starts_with("--") {
+            vec![Declaration {
+                name: property_name,
+                value: Value::Custom(tokenize(raw.trim())),
+                important,
+            }]
+        } else if raw.to_ascii_lowercase().contains("var(") {
+            let tokens = tokenize(raw.trim());
+            match longhands {
+                // Each longhand keeps the whole shorthand until the variables are substituted
+                Some(longhands) => longhands
+                    .into_iter()
+                    .map(|name| Declaration {
+                        name,
+                        value: Value::ShorthandWithVariables(property_name.to_ascii_lowercase(), tokens.clone()),
+                        important,
+                    })
+                    .collect(),
+                None => vec![Declaration {
+                    name: property_name,
+                    value: Value::WithVariables(tokens),
+                    important,
+                }],
+            }
+        } else {
+            let mut declarations = properties::parse_declarations(&property_name, raw)?;
+            for declaration in &mut declarations {
+                declaration.important = important;
+            }
+            declarations
+        };
         self.consume_whitespace();
-        
+
         if self.next_char() == ';' {
             self.consume_char();
         }
-        
-        Some(Declaration {
-            name: property_name,
-            value,
+
+        Some(declarations)
+    }
+
+    /// Parse comma-separated values. Returns `None` if any of them is invalid.
+    fn parse_value_list(&mut self) -> Option<Value> {
+        let mut items = Vec::new();
+        loop {
+            match self.parse_value()? {
+                Value::Keyword(ref keyword) if keyword.is_empty() => return None,
+                value => items.push(value),
+            }
+            self.consume_whitespace();
+            if self.next_char() != ',' {
+                break;
+            }
+            self.consume_char();
+            self.consume_whitespace();
+        }
+        Some(match items.len() {
+            1 => items.pop().unwrap(),
+            _ => Value::List(items),
         })
     }
 
-    fn parse_value(&mut self) -> Value {
+    /// Parse a value. Returns `None` only for values we recognise but find invalid.
+    fn parse_value(&mut self) -> Option<Value> {
         match self.next_char() {
-            '0'..='9' => self.parse_length(),
+            _ if self.starts_number() => self.parse_length(),
             '#' => self.parse_color(),
-            _ => Value::Keyword(self.parse_identifier()),
+            '"' | '\'' => Some(Value::String(self.parse_string())),
+            _ => {
+                let ident = self.parse_identifier();
+                if self.next_char() == '(' && calc::is_math_function(&ident) {
+                    return self.parse_math_function(&ident);
+                }
+                if self.next_char() == '(' && matches!(&*ident.to_ascii_lowercase(), "rgb" | "rgba") {
+                    return self.parse_rgb_function().map(Value::ColorValue);
+                }
+                if self.next_char() == '(' && matches!(&*ident.to_ascii_lowercase(), "cubic-bezier" | "steps" | "linear") {
+                    return self.parse_easing_function(&ident);
+                }
+                if self.next_char() == '(' && image::is_image_function(&ident) {
+                    return self.parse_image_function(&ident);
+                }
+                Some(Value::Keyword(ident))
+            }
         }
     }
 
-    fn parse_length(&mut self) -> Value {
-        Value::Length(self.parse_float(), self.parse_unit())
-    }
-
-    fn parse_float(&mut self) -> f32 {
-        let s = self.consume_while(|c| matches!(c, '0'..='9' | '.'));
-        s.parse().unwrap_or(0.0)
-    }
-
-    fn parse_unit(&mut self) -> Unit {
-        let ident = self.parse_identifier();
-        match &*ident.to_ascii_lowercase() {
-            "px" => Unit::Px,
-            "pt" => Unit::Pt,
-            "em" => Unit::Em,
-            "rem" => Unit::Rem,
-            "%" => Unit::Percent,
-            _ => Unit::Px, // Default to px
+    /// Parse a number, percentage, length, time or angle. Unknown units make the value invalid.
+    fn parse_length(&mut self) -> Option<Value> {
+        let number = self.parse_number();
+        if self.next_char() == '%' {
+            self.consume_char();
+            return Some(Value::Length(number, Unit::Percent));
+        }
+        let unit = self.parse_identifier();
+        if unit.is_empty() {
+            return Some(Value::Number(number));
+        }
+        match &*unit.to_ascii_lowercase() {
+            "s" => Some(Value::Time(number)),
+            "ms" => Some(Value::Time(number / 1000.0)),
+            "deg" => Some(Value::Angle(number)),
+            "grad" => Some(Value::Angle(number * 0.9)),
+            "rad" => Some(Value::Angle(number.to_degrees())),
+            "turn" => Some(Value::Angle(number * 360.0)),
+            unit => unit_from_ident(unit).map(|unit| Value::Length(number, unit)),
         }
     }
 
-    fn parse_color(&mut self) -> Value {
+    fn parse_color(&mut self) -> Option<Value> {
         self.consume_char(); // consume '#'
-        Value::ColorValue(Color {
-            r: self.parse_hex_pair(),
-            g: self.parse_hex_pair(),
-            b: self.parse_hex_pair(),
-            a: 255,
-        })
-    }
-
-    fn parse_hex_pair(&mut self) -> u8 {
-        if self.pos + 2 > self.input.len() {
-            return 0;
-        }
-        let s = &self.input[self.pos..self.pos + 2];
-        self.pos += 2;
-        u8::from_str_radix(s, 16).unwrap_or(0)
+        Color::from_hex(&self.parse_identifier()).map(Value::ColorValue)
     }
 
     fn parse_identifier(&mut self) -> String {
         self.consume_while(valid_identifier_char)
     }
 
+    /// Skip whitespace and comments.
     fn consume_whitespace(&mut self) {
-        self.consume_while(char::is_whitespace);
+        loop {
+            self.consume_while(char::is_whitespace);
+            if !self.input[self.pos..].starts_with("/*") {
+                break;
+            }
+            match self.input[self.pos + 2..].find("*/") {
+                Some(end) => self.pos += end + 4,
+                None => self.pos = self.input.len(),
+            }
+        }
     }
 
     fn consume_while<F>(&mut self, test: F) -> String
@@ -321,6 +955,70 @@ impl Parser {
     }
 }
 
+/// Every unit we parse, with its canonical (lowercase) name.
+const UNITS: &[(&str, Unit)] = &[
+    ("px", Unit::Px),
+    ("pt", Unit::Pt),
+    ("pc", Unit::Pc),
+    ("in", Unit::In),
+    ("cm", Unit::Cm),
+    ("mm", Unit::Mm),
+    ("q", Unit::Q),
+    ("em", Unit::Em),
+    ("rem", Unit::Rem),
+    ("ex", Unit::Ex),
+    ("rex", Unit::Rex),
+    ("ch", Unit::Ch),
+    ("rch", Unit::Rch),
+    ("cap", Unit::Cap),
+    ("rcap", Unit::Rcap),
+    ("ic", Unit::Ic),
+    ("ric", Unit::Ric),
+    ("lh", Unit::Lh),
+    ("rlh", Unit::Rlh),
+    ("vw", Unit::Vw),
+    ("vh", Unit::Vh),
+    ("vi", Unit::Vi),
+    ("vb", Unit::Vb),
+    ("vmin", Unit::Vmin),
+    ("vmax", Unit::Vmax),
+    ("svw", Unit::Svw),
+    ("svh", Unit::Svh),
+    ("svi", Unit::Svi),
+    ("svb", Unit::Svb),
+    ("svmin", Unit::Svmin),
+    ("svmax", Unit::Svmax),
+    ("lvw", Unit::Lvw),
+    ("lvh", Unit::Lvh),
+    ("lvi", Unit::Lvi),
+    ("lvb", Unit::Lvb),
+    ("lvmin", Unit::Lvmin),
+    ("lvmax", Unit::Lvmax),
+    ("dvw", Unit::Dvw),
+    ("dvh", Unit::Dvh),
+    ("dvi", Unit::Dvi),
+    ("dvb", Unit::Dvb),
+    ("dvmin", Unit::Dvmin),
+    ("dvmax", Unit::Dvmax),
+    ("%", Unit::Percent),
+];
+
+fn unit_from_ident(ident: &str) -> Option<Unit> {
+    UNITS.iter().find(|&&(name, _)| name == ident).map(|&(_, unit)| unit)
+}
+
+/// Split a trailing `!important` off a declaration's value.
+fn split_important(raw: &str) -> (&str, bool) {
+    let value = raw.trim_end();
+    let start = value.len().saturating_sub("important".len());
+    if value.is_char_boundary(start) && value[start..].eq_ignore_ascii_case("important") {
+        if let Some(value) = value[..start].trim_end().strip_suffix('!') {
+            return (value, true);
+        }
+    }
+    (raw, false)
+}
+
 fn valid_identifier_char(c: char) -> bool {
     matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_')
 }
@@ -335,7 +1033,8 @@ mod tests {
         let stylesheet = parse(css);
         assert_eq!(stylesheet.rules.len(), 1);
         assert_eq!(stylesheet.rules[0].selectors.len(), 2);
-        assert_eq!(stylesheet.rules[0].declarations.len(), 2);
+        // `margin` expands to its four longhands
+        assert_eq!(stylesheet.rules[0].declarations.len(), 5);
     }
     
     #[test]
@@ -345,4 +1044,77 @@ mod tests {
         // Should not panic and produce something
         assert!(!stylesheet.rules.is_empty());
     }
+
+    #[test]
+    fn test_lengths() {
+        let declarations = parse_inline_style(
+            "width: 50%; margin-left: -4px; padding-top: .5em; height: 2.54CM; min-height: 100dvh; line-height: 1.6;
+             max-width: 3Q; width: 100unknown; min-width: 1ch",
+        );
+        let values: Vec<_> = declarations.iter().map(|d| d.value.clone()).collect();
+        assert_eq!(
+            values,
+            vec![
+                Value::Length(50.0, Unit::Percent),
+                Value::Length(-4.0, Unit::Px),
+                Value::Length(0.5, Unit::Em),
+                Value::Length(2.54, Unit::Cm),
+                Value::Length(100.0, Unit::Dvh),
+                Value::Number(1.6),
+                Value::Length(3.0, Unit::Q),
+                Value::Length(1.0, Unit::Ch),
+            ]
+        );
+        assert_eq!(Unit::In.absolute_px(), Some(96.0));
+        assert_eq!(Unit::Dvmin.viewport_px(800.0, 600.0), Some(6.0));
+    }
+
+    #[test]
+    fn test_inline_style() {
+        let declarations = parse_inline_style("color: red; ; bogus; background-color: #fff");
+        assert_eq!(declarations.len(), 2);
+        assert_eq!(declarations[0].value, Value::Keyword("red".to_string()));
+        assert_eq!(declarations[1].value, Value::ColorValue(Color::new(255, 255, 255, 255)));
+    }
+
+    #[test]
+    fn test_comments() {
+        let css = "/* :root { color: #ff0000; } */ h1 /* heading */ { /* a */ margin-left: 1px; }";
+        let stylesheet = parse(css);
+        assert_eq!(stylesheet.rules.len(), 1);
+        assert_eq!(stylesheet.rules[0].declarations.len(), 1);
+    }
+
+    #[test]
+    fn test_custom_properties() {
+        let css = "html { --bg-color: #fafafa; --font: 'Stack Sans', sans-serif; background-color: var(--bg-color); }";
+        let stylesheet = parse(css);
+        let declarations = &stylesheet.rules[0].declarations;
+        assert_eq!(declarations.len(), 3);
+        assert_eq!(declarations[0].name, "--bg-color");
+        assert_eq!(declarations[0].value, Value::Custom(vec![Token::Hash("fafafa".to_string())]));
+        assert!(matches!(declarations[1].value, Value::Custom(ref tokens) if tokens.len() == 4));
+        assert!(matches!(declarations[2].value, Value::WithVariables(_)));
+    }
+
+    #[test]
+    fn test_media_rules() {
+        let css = "p { color: #000000; }
+            @media (prefers-color-scheme: dark) { p { color: #ffffff; } }
+            @charset \"utf-8\";
+            @unknown foo { h1 { color: #ff0000; } }
+            h2 { margin: 1px; }";
+        let stylesheet = parse(css);
+        assert_eq!(stylesheet.rules.len(), 3);
+        assert!(stylesheet.rules[0].conditions.is_empty());
+        assert_eq!(stylesheet.rules[1].conditions.len(), 1);
+        assert_eq!(stylesheet.rules[2].selectors.len(), 1);
+
+        let dark = MediaEnvironment {
+            color_scheme: ColorScheme::Dark,
+            ..MediaEnvironment::default()
+        };
+        assert!(stylesheet.rules[1].applies_to(&dark, &[]));
+        assert!(!stylesheet.rules[1].applies_to(&MediaEnvironment::default(), &[]));
+    }
 }
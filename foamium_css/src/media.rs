@@ -0,0 +1,649 @@
+// Media Queries Level 4: parsing and evaluation against a MediaEnvironment
+use crate::{unit_from_ident, Parser, Unit};
+
+/// The properties of the output device that media queries are evaluated against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaEnvironment {
+    pub media_type: MediaType,
+    pub viewport_width: f32,
+    pub viewport_height: f32,
+    /// Device pixels per CSS pixel
+    pub resolution: f32,
+    pub color_scheme: ColorScheme,
+    pub reduced_motion: bool,
+}
+
+impl Default for MediaEnvironment {
+    fn default() -> Self {
+        MediaEnvironment {
+            media_type: MediaType::Screen,
+            viewport_width: 1280.0,
+            viewport_height: 720.0,
+            resolution: 1.0,
+            color_scheme: ColorScheme::Light,
+            reduced_motion: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaType {
+    All,
+    Screen,
+    Print,
+    /// Deprecated or unknown media types, which never match
+    Other(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+/// A comma separated list of media queries. An empty list matches everything.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MediaQueryList {
+    pub queries: Vec<MediaQuery>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQuery {
+    pub negated: bool,
+    pub media_type: MediaType,
+    pub condition: Option<MediaCondition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaCondition {
+    Feature(MediaFeature),
+    Not(Box<MediaCondition>),
+    And(Vec<MediaCondition>),
+    Or(Vec<MediaCondition>),
+    /// Syntax we don't understand (`<general-enclosed>`), which evaluates to unknown.
+    /// Holds the source text so the query can be serialized.
+    Unknown(String),
+}
+
+/// A media feature test. Plain (`min-width: 10px`) and range (`10px < width`) forms are
+/// normalised so that every comparison reads `feature <op> value`. A feature with no
+/// comparisons is evaluated in a boolean context.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaFeature {
+    pub name: String,
+    pub comparisons: Vec<(Comparison, MediaValue)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaValue {
+    Number(f32),
+    Length(f32, Unit),
+    /// Always stored in dppx
+    Resolution(f32),
+    Ratio(f32, f32),
+    Ident(String),
+}
+
+impl MediaQueryList {
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        self.queries.is_empty() || self.queries.iter().any(|query| query.matches(env))
+    }
+}
+
+impl MediaQuery {
+    /// The query `not all`, which invalid queries are replaced with.
+    fn not_all() -> MediaQuery {
+        MediaQuery {
+            negated: true,
+            media_type: MediaType::All,
+            condition: None,
+        }
+    }
+
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        let type_matches = match self.media_type {
+            MediaType::All => true,
+            MediaType::Other(_) => false,
+            ref media_type => *media_type == env.media_type,
+        };
+        let result = match self.condition {
+            Some(ref condition) if type_matches => condition.evaluate(env),
+            _ => Some(type_matches),
+        };
+        let result = if self.negated { result.map(|r| !r) } else { result };
+        result.unwrap_or(false)
+    }
+}
+
+impl MediaCondition {
+    /// Three-valued evaluation: `None` means unknown.
+    pub fn evaluate(&self, env: &MediaEnvironment) -> Option<bool> {
+        self.evaluate_in(env)
+    }
+
+    /// Evaluate against the features of `source`, which is shared with container queries.
+    pub(crate) fn evaluate_in(&self, source: &dyn FeatureSource) -> Option<bool> {
+        match *self {
+            MediaCondition::Feature(ref feature) => feature.evaluate_in(source),
+            MediaCondition::Not(ref inner) => inner.evaluate_in(source).map(|r| !r),
+            MediaCondition::And(ref conditions) => {
+                let results: Vec<_> = conditions.iter().map(|c| c.evaluate_in(source)).collect();
+                if results.contains(&Some(false)) {
+                    Some(false)
+                } else if results.contains(&None) {
+                    None
+                } else {
+                    Some(true)
+                }
+            }
+            MediaCondition::Or(ref conditions) => {
+                let results: Vec<_> = conditions.iter().map(|c| c.evaluate_in(source)).collect();
+                if results.contains(&Some(true)) {
+                    Some(true)
+                } else if results.contains(&None) {
+                    None
+                } else {
+                    Some(false)
+                }
+            }
+            MediaCondition::Unknown(_) => None,
+        }
+    }
+}
+
+/// The value of a feature in the current environment.
+pub(crate) enum FeatureValue {
+    Number(f32),
+    Ident(&'static str),
+}
+
+/// Where the features a condition tests come from: the media environment, or a query
+/// container.
+pub(crate) trait FeatureSource {
+    /// The value of a feature, or `None` if it is unknown here.
+    fn feature_value(&self, name: &str) -> Option<FeatureValue>;
+    /// The size in px of one `unit`, for lengths in the condition.
+    fn unit_px(&self, unit: Unit) -> Option<f32>;
+}
+
+impl MediaFeature {
+    pub fn evaluate(&self, env: &MediaEnvironment) -> Option<bool> {
+        self.evaluate_in(env)
+    }
+
+    fn evaluate_in(&self, source: &dyn FeatureSource) -> Option<bool> {
+        let actual = source.feature_value(&self.name)?;
+        if self.comparisons.is_empty() {
+            return Some(match actual {
+                FeatureValue::Number(n) => n != 0.0,
+                FeatureValue::Ident(ident) => ident != "none" && ident != "no-preference",
+            });
+        }
+        let mut result = true;
+        for &(op, ref value) in &self.comparisons {
+            let matched = match actual {
+                FeatureValue::Number(n) => {
+                    let expected = value.to_number(&self.name, source)?;
+                    match op {
+                        Comparison::Eq => (n - expected).abs() < 0.001,
+                        Comparison::Lt => n < expected,
+                        Comparison::Le => n <= expected,
+                        Comparison::Gt => n > expected,
+                        Comparison::Ge => n >= expected,
+                    }
+                }
+                FeatureValue::Ident(ident) => match (op, value) {
+                    (Comparison::Eq, MediaValue::Ident(expected)) => ident == expected,
+                    _ => return None,
+                },
+            };
+            result &= matched;
+        }
+        Some(result)
+    }
+}
+
+impl MediaValue {
+    /// Convert to the canonical number used to compare against `feature`.
+    fn to_number(&self, feature: &str, source: &dyn FeatureSource) -> Option<f32> {
+        let resolution_feature = feature == "resolution";
+        match *self {
+            MediaValue::Number(n) if !resolution_feature => Some(n),
+            MediaValue::Ratio(a, b) if feature == "aspect-ratio" && b != 0.0 => Some(a / b),
+            MediaValue::Resolution(dppx) if resolution_feature => Some(dppx),
+            MediaValue::Length(v, unit) if !resolution_feature => Some(v * source.unit_px(unit)?),
+            _ => None,
+        }
+    }
+}
+
+/// The size in px of one font-relative `unit`, using the fallback font metrics.
+pub(crate) fn font_relative_px(unit: Unit, font_size: f32) -> Option<f32> {
+    let ratio = match unit {
+        Unit::Em | Unit::Rem | Unit::Ic | Unit::Ric => 1.0,
+        Unit::Ex | Unit::Rex | Unit::Ch | Unit::Rch => 0.5,
+        Unit::Cap | Unit::Rcap => 0.7,
+        Unit::Lh | Unit::Rlh => 1.2,
+        _ => return None,
+    };
+    Some(font_size * ratio)
+}
+
+impl FeatureSource for MediaEnvironment {
+    fn feature_value(&self, name: &str) -> Option<FeatureValue> {
+        feature_value(name, self)
+    }
+
+    fn unit_px(&self, unit: Unit) -> Option<f32> {
+        // Font-relative lengths in media queries use the initial font
+        unit.absolute_px()
+            .or_else(|| unit.viewport_px(self.viewport_width, self.viewport_height))
+            .or_else(|| font_relative_px(unit, 16.0))
+    }
+}
+
+fn feature_value(name: &str, env: &MediaEnvironment) -> Option<FeatureValue> {
+    let value = match name {
+        "width" => FeatureValue::Number(env.viewport_width),
+        "height" => FeatureValue::Number(env.viewport_height),
+        "aspect-ratio" => FeatureValue::Number(env.viewport_width / env.viewport_height),
+        "resolution" => FeatureValue::Number(env.resolution),
+        "color" => FeatureValue::Number(8.0),
+        "monochrome" | "grid" => FeatureValue::Number(0.0),
+        "orientation" => FeatureValue::Ident(if env.viewport_height >= env.viewport_width {
+            "portrait"
+        } else {
+            "landscape"
+        }),
+        "prefers-color-scheme" => FeatureValue::Ident(match env.color_scheme {
+            ColorScheme::Light => "light",
+            ColorScheme::Dark => "dark",
+        }),
+        "prefers-reduced-motion" => FeatureValue::Ident(if env.reduced_motion {
+            "reduce"
+        } else {
+            "no-preference"
+        }),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Parse the prelude of an `@media` rule (or the media list of an `@import`).
+pub fn parse_media_query_list(source: &str) -> MediaQueryList {
+    let mut parser = Parser::new(source);
+    let mut queries = Vec::new();
+    loop {
+        parser.consume_whitespace();
+        if parser.eof() {
+            break;
+        }
+        let query = parser.parse_media_query();
+        parser.consume_whitespace();
+        match query {
+            Some(query) if parser.eof() || parser.next_char() == ',' => queries.push(query),
+            _ => {
+                // Only the malformed query is discarded, not the whole list
+                queries.push(MediaQuery::not_all());
+                parser.skip_to_query_end();
+            }
+        }
+        if !parser.eof() {
+            parser.consume_char(); // consume ','
+        }
+    }
+    MediaQueryList { queries }
+}
+
+impl Parser {
+    fn parse_media_query(&mut self) -> Option<MediaQuery> {
+        if self.next_char() == '(' || self.peek_keyword("not") && self.keyword_followed_by_paren() {
+            return Some(MediaQuery {
+                negated: false,
+                media_type: MediaType::All,
+                condition: Some(self.parse_media_condition()?),
+            });
+        }
+
+        let mut negated = false;
+        if self.consume_keyword("not") {
+            negated = true;
+        } else {
+            self.consume_keyword("only");
+        }
+        self.consume_whitespace();
+        let media_type = match &*self.parse_identifier().to_ascii_lowercase() {
+            "" | "not" | "only" | "and" | "or" | "layer" => return None,
+            "all" => MediaType::All,
+            "screen" => MediaType::Screen,
+            "print" => MediaType::Print,
+            other => MediaType::Other(other.to_string()),
+        };
+
+        self.consume_whitespace();
+        let condition = if self.consume_keyword("and") {
+            match self.parse_media_condition()? {
+                // `or` can't follow a media type without extra parentheses
+                MediaCondition::Or(_) => return None,
+                condition => Some(condition),
+            }
+        } else {
+            None
+        };
+        Some(MediaQuery {
+            negated,
+            media_type,
+            condition,
+        })
+    }
+
+    pub(crate) fn parse_media_condition(&mut self) -> Option<MediaCondition> {
+        self.consume_whitespace();
+        if self.consume_keyword("not") {
+            let inner = self.parse_media_in_parens()?;
+            return Some(MediaCondition::Not(Box::new(inner)));
+        }
+
+        let first = self.parse_media_in_parens()?;
+        let mut conditions = vec![first];
+        let mut combinator = None;
+        loop {
+            self.consume_whitespace();
+            let keyword = if self.consume_keyword("and") {
+                "and"
+            } else if self.consume_keyword("or") {
+                "or"
+            } else {
+                break;
+            };
+            // `and` and `or` can't be mixed at the same level
+            if combinator.is_some_and(|c| c != keyword) {
+                return None;
+            }
+            combinator = Some(keyword);
+            conditions.push(self.parse_media_in_parens()?);
+        }
+
+        Some(match combinator {
+            None => conditions.pop().unwrap(),
+            Some("and") => MediaCondition::And(conditions),
+            Some(_) => MediaCondition::Or(conditions),
+        })
+    }
+
+    fn parse_media_in_parens(&mut self) -> Option<MediaCondition> {
+        self.consume_whitespace();
+        let start = self.pos;
+        if self.next_char() != '(' {
+            // A function is general-enclosed; anything else is a syntax error
+            if self.parse_identifier().is_empty() || self.next_char() != '(' {
+                return None;
+            }
+            self.skip_balanced();
+            return Some(MediaCondition::Unknown(self.input[start..self.pos].to_string()));
+        }
+
+        self.consume_char(); // consume '('
+        self.consume_whitespace();
+        let nested = self.next_char() == '(' || self.peek_keyword("not");
+        let condition = if nested {
+            self.parse_media_condition()
+        } else {
+            self.parse_media_feature().map(MediaCondition::Feature)
+        };
+        self.consume_whitespace();
+        if let Some(condition) = condition {
+            if self.next_char() == ')' {
+                self.consume_char();
+                return Some(condition);
+            }
+        }
+
+        self.pos = start;
+        self.skip_balanced();
+        Some(MediaCondition::Unknown(self.input[start..self.pos].to_string()))
+    }
+
+    fn parse_media_feature(&mut self) -> Option<MediaFeature> {
+        if self.next_char().is_ascii_alphabetic() || self.next_char() == '-' {
+            let name = self.parse_identifier().to_ascii_lowercase();
+            self.consume_whitespace();
+            if self.next_char() == ')' {
+                return Some(MediaFeature {
+                    name,
+                    comparisons: Vec::new(),
+                });
+            }
+            if self.next_char() == ':' {
+                self.consume_char();
+                self.consume_whitespace();
+                let value = self.parse_media_value()?;
+                let (op, name) = if let Some(name) = name.strip_prefix("min-") {
+                    (Comparison::Ge, name.to_string())
+                } else if let Some(name) = name.strip_prefix("max-") {
+                    (Comparison::Le, name.to_string())
+                } else {
+                    (Comparison::Eq, name)
+                };
+                return Some(MediaFeature {
+                    name,
+                    comparisons: vec![(op, value)],
+                });
+            }
+            let op = self.parse_comparison()?;
+            self.consume_whitespace();
+            let value = self.parse_media_value()?;
+            return Some(MediaFeature {
+                name,
+                comparisons: vec![(op, value)],
+            });
+        }
+
+        // `value op name` or `value op name op value`
+        let first = self.parse_media_value()?;
+        self.consume_whitespace();
+        let first_op = self.parse_comparison()?;
+        self.consume_whitespace();
+        let name = self.parse_identifier().to_ascii_lowercase();
+        if name.is_empty() {
+            return None;
+        }
+        let mut comparisons = vec![(first_op.flip(), first)];
+        self.consume_whitespace();
+        if self.next_char() != ')' {
+            let second_op = self.parse_comparison()?;
+            // Both comparisons must point the same way
+            let ascending = matches!(first_op, Comparison::Lt | Comparison::Le);
+            let descending = matches!(first_op, Comparison::Gt | Comparison::Ge);
+            let second_ascending = matches!(second_op, Comparison::Lt | Comparison::Le);
+            let second_descending = matches!(second_op, Comparison::Gt | Comparison::Ge);
+            if !(ascending && second_ascending || descending && second_descending) {
+                return None;
+            }
+            self.consume_whitespace();
+            comparisons.push((second_op, self.parse_media_value()?));
+        }
+        Some(MediaFeature { name, comparisons })
+    }
+
+    fn parse_comparison(&mut self) -> Option<Comparison> {
+        let op = match self.next_char() {
+            '=' => {
+                self.consume_char();
+                return Some(Comparison::Eq);
+            }
+            '<' => Comparison::Lt,
+            '>' => Comparison::Gt,
+            _ => return None,
+        };
+        self.consume_char();
+        if self.next_char() == '=' {
+            self.consume_char();
+            return Some(match op {
+                Comparison::Lt => Comparison::Le,
+                _ => Comparison::Ge,
+            });
+        }
+        Some(op)
+    }
+
+    fn parse_media_value(&mut self) -> Option<MediaValue> {
+        if !matches!(self.next_char(), '0'..='9' | '.') {
+            let ident = self.parse_identifier();
+            if ident.is_empty() {
+                return None;
+            }
+            return Some(MediaValue::Ident(ident.to_ascii_lowercase()));
+        }
+
+        let number = self.parse_number();
+        let unit = self.parse_identifier().to_ascii_lowercase();
+        match &*unit {
+            "" => {
+                let start = self.pos;
+                self.consume_whitespace();
+                if self.next_char() != '/' {
+                    self.pos = start;
+                    return Some(MediaValue::Number(number));
+                }
+                self.consume_char();
+                self.consume_whitespace();
+                if !matches!(self.next_char(), '0'..='9' | '.') {
+                    return None;
+                }
+                Some(MediaValue::Ratio(number, self.parse_number()))
+            }
+            "dppx" | "x" => Some(MediaValue::Resolution(number)),
+            "dpi" => Some(MediaValue::Resolution(number / 96.0)),
+            "dpcm" => Some(MediaValue::Resolution(number * 2.54 / 96.0)),
+            unit => Some(MediaValue::Length(number, unit_from_ident(unit)?)),
+        }
+    }
+
+    /// Check for a keyword without consuming it.
+    pub(crate) fn peek_keyword(&mut self, keyword: &str) -> bool {
+        let start = self.pos;
+        let found = self.parse_identifier().eq_ignore_ascii_case(keyword);
+        self.pos = start;
+        found
+    }
+
+    pub(crate) fn consume_keyword(&mut self, keyword: &str) -> bool {
+        let start = self.pos;
+        if self.parse_identifier().eq_ignore_ascii_case(keyword) {
+            return true;
+        }
+        self.pos = start;
+        false
+    }
+
+    /// Whether the keyword at the current position is followed by `(`, which
+    /// distinguishes `not (color)` from `not screen`.
+    fn keyword_followed_by_paren(&mut self) -> bool {
+        let start = self.pos;
+        self.parse_identifier();
+        self.consume_whitespace();
+        let result = self.next_char() == '(';
+        self.pos = start;
+        result
+    }
+
+    fn skip_to_query_end(&mut self) {
+        while !self.eof() && self.next_char() != ',' {
+            if self.next_char() == '(' {
+                self.skip_balanced();
+            } else {
+                self.consume_char();
+            }
+        }
+    }
+}
+
+impl Comparison {
+    /// The comparison with its operands swapped, turning `value < name` into `name > value`.
+    pub(crate) fn flip(self) -> Comparison {
+        match self {
+            Comparison::Eq => Comparison::Eq,
+            Comparison::Lt => Comparison::Gt,
+            Comparison::Le => Comparison::Ge,
+            Comparison::Gt => Comparison::Lt,
+            Comparison::Ge => Comparison::Le,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(width: f32, height: f32) -> MediaEnvironment {
+        MediaEnvironment {
+            viewport_width: width,
+            viewport_height: height,
+            ..MediaEnvironment::default()
+        }
+    }
+
+    #[test]
+    fn test_plain_and_range_features() {
+        let narrow = env(500.0, 800.0);
+        let wide = env(1000.0, 800.0);
+
+        let query = parse_media_query_list("screen and (min-width: 600px)");
+        assert!(!query.matches(&narrow));
+        assert!(query.matches(&wide));
+
+        let query = parse_media_query_list("(400px <= width < 600px)");
+        assert!(query.matches(&narrow));
+        assert!(!query.matches(&wide));
+
+        let query = parse_media_query_list("(width > 37.5em) and (orientation: landscape)");
+        assert!(!query.matches(&narrow));
+        assert!(query.matches(&wide));
+    }
+
+    #[test]
+    fn test_environment_features() {
+        let dark = MediaEnvironment {
+            color_scheme: ColorScheme::Dark,
+            reduced_motion: true,
+            ..MediaEnvironment::default()
+        };
+        let query = parse_media_query_list("(prefers-color-scheme: dark)");
+        assert!(query.matches(&dark));
+        assert!(!query.matches(&MediaEnvironment::default()));
+
+        let query = parse_media_query_list("(prefers-reduced-motion)");
+        assert!(query.matches(&dark));
+
+        let query = parse_media_query_list("print");
+        assert!(!query.matches(&dark));
+        let print = MediaEnvironment {
+            media_type: MediaType::Print,
+            ..MediaEnvironment::default()
+        };
+        assert!(query.matches(&print));
+        assert!(parse_media_query_list("not print").matches(&dark));
+    }
+
+    #[test]
+    fn test_unknown_and_invalid_queries() {
+        let env = MediaEnvironment::default();
+        // Unknown features are unknown, and `not unknown` is still unknown
+        assert!(!parse_media_query_list("(fancy-feature)").matches(&env));
+        assert!(!parse_media_query_list("not (fancy-feature)").matches(&env));
+        assert!(parse_media_query_list("(fancy-feature) or (color)").matches(&env));
+        // An invalid query becomes `not all` without affecting its neighbours
+        assert!(parse_media_query_list("screen and, (min-width: 0)").matches(&env));
+        assert!(!parse_media_query_list("(color) and (color) or (color)").matches(&env));
+    }
+}
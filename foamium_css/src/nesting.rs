@@ -0,0 +1,176 @@
+// CSS nesting: style rules inside style rules, desugared into flat rules
+//
+// A nested selector is relative to its parent rule's selectors, which `&` stands for.
+// `&` means `:is(<parent selectors>)`, so it matches the same elements and is as
+// specific as the most specific parent selector. When that doesn't change the meaning,
+// the parent is spliced in directly to keep the result simple: `.card { &.active {} }`
+// becomes `.card.active` rather than `:is(.card).active`.
+use crate::{Combinator, Condition, Parser, Rule, Selector, SimpleSelector};
+
+/// The selector that `&` alone stands for.
+pub(crate) fn nesting_selector(parent: &[Selector]) -> Selector {
+    match parent {
+        [selector] => selector.clone(),
+        _ => Selector::Simple(SimpleSelector {
+            is: vec![parent.to_vec()],
+            ..SimpleSelector::default()
+        }),
+    }
+}
+
+/// Add `&` to a compound selector.
+pub(crate) fn add_nesting_selector(selector: &mut SimpleSelector, parent: &[Selector]) {
+    if let [Selector::Simple(simple)] = parent {
+        let tags_agree = selector.tag_name.is_none() || simple.tag_name.is_none() || selector.tag_name == simple.tag_name;
+        let ids_agree = selector.id.is_none() || simple.id.is_none() || selector.id == simple.id;
+        if tags_agree && ids_agree {
+            selector.tag_name = selector.tag_name.take().or_else(|| simple.tag_name.clone());
+            selector.id = selector.id.take().or_else(|| simple.id.clone());
+            selector.class.extend(simple.class.iter().cloned());
+            selector.is.extend(simple.is.iter().cloned());
+            return;
+        }
+    }
+    selector.is.push(parent.to_vec());
+}
+
+/// Turn the leftmost compound of a selector into a selector. A compound that is only
+/// `:is()` with a single complex selector is that selector.
+pub(crate) fn splice_leading_nesting(mut compound: SimpleSelector) -> Selector {
+    let only_is = compound.tag_name.is_none() && compound.id.is_none() && compound.class.is_empty();
+    if only_is && compound.is.len() == 1 && compound.is[0].len() == 1 {
+        return compound.is.pop().unwrap().pop().unwrap();
+    }
+    Selector::Simple(compound)
+}
+
+/// Put `left` and `combinator` before the leftmost compound of `selector`.
+pub(crate) fn prepend(selector: Selector, left: Selector, combinator: Combinator) -> Selector {
+    match selector {
+        Selector::Simple(compound) => Selector::Complex(Box::new(left), combinator, compound),
+        Selector::Complex(inner, next, compound) => {
+            Selector::Complex(Box::new(prepend(*inner, left, combinator)), next, compound)
+        }
+    }
+}
+
+impl Parser {
+    /// Parse the `{ ... }` block of a style rule with the given selectors.
+    pub(crate) fn parse_style_block(&mut self, selectors: &[Selector], conditions: &[Condition]) -> Vec<Rule> {
+        self.consume_char(); // consume '{'
+        let rules = self.parse_block_contents(selectors, conditions, true);
+        if !self.eof() {
+            self.consume_char(); // consume '}'
+        }
+        rules
+    }
+
+    /// Parse declarations and nested rules up to the end of input or a closing `}`.
+    /// Each run of declarations becomes a rule with `selectors`, so that the cascade
+    /// sees everything in source order. With `keep_empty`, the first of those rules is
+    /// kept even if it has no declarations, as the style rule itself.
+    pub(crate) fn parse_block_contents(
+        &mut self,
+        selectors: &[Selector],
+        conditions: &[Condition],
+        keep_empty: bool,
+    ) -> Vec<Rule> {
+        let mut rules = Vec::new();
+        let mut declarations = Vec::new();
+        let mut keep_empty = keep_empty;
+        let flush = |rules: &mut Vec<Rule>, declarations: &mut Vec<_>, keep_empty: bool| {
+            if keep_empty || !declarations.is_empty() {
+                rules.push(Rule {
+                    selectors: selectors.to_vec(),
+                    declarations: std::mem::take(declarations),
+                    conditions: conditions.to_vec(),
+                });
+            }
+        };
+
+        loop {
+            self.consume_whitespace();
+            if self.eof() || self.next_char() == '}' {
+                break;
+            }
+            if self.next_char() != '@' && !self.starts_nested_rule() {
+                match self.parse_declaration() {
+                    Some(parsed) => declarations.extend(parsed),
+                    None => self.consume_until_delimiter(),
+                }
+                continue;
+            }
+            flush(&mut rules, &mut declarations, keep_empty);
+            keep_empty = false;
+            if self.next_char() == '@' {
+                rules.extend(self.parse_at_rule(conditions, Some(selectors)));
+            } else {
+                rules.extend(self.parse_rule(conditions, Some(selectors)));
+            }
+        }
+        flush(&mut rules, &mut declarations, keep_empty);
+        rules
+    }
+
+    /// Whether the item at the current position is a nested rule rather than a
+    /// declaration, which is the case when a `{` ends its prelude.
+    fn starts_nested_rule(&mut self) -> bool {
+        if self.input[self.pos..].starts_with("--") {
+            return false;
+        }
+        let start = self.pos;
+        self.parse_prelude();
+        let nested = self.next_char() == '{';
+        self.pos = start;
+        nested
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn selectors(rule: &Rule) -> Vec<String> {
+        rule.selectors.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_nesting() {
+        let stylesheet = parse(
+            ".card, #main {
+                color: #000000;
+                &.active { color: #111111; }
+                > h2 { color: #222222; }
+                .note & { color: #333333; }
+                @media (width > 600px) { width: 50%; p { width: 10px; } }
+                margin-left: 1px;
+            }
+            nav { & a:hover { color: #444444; } a { color: #555555; } }
+            & { color: #666666; }",
+        );
+        let rules: Vec<_> = stylesheet.rules.iter().map(selectors).collect();
+        assert_eq!(
+            rules,
+            [
+                vec!["#main", ".card"],
+                vec![".active:is(#main, .card)"],
+                vec![":is(#main, .card) > h2"],
+                vec![".note :is(#main, .card)"],
+                vec!["#main", ".card"],
+                vec![":is(#main, .card) p"],
+                vec!["#main", ".card"],
+                vec!["nav"],
+                vec!["nav a"],
+            ]
+        );
+        assert_eq!(stylesheet.rules[4].conditions.len(), 1);
+        assert_eq!(stylesheet.rules[6].declarations[0].name, "margin-left");
+
+        // `&` takes the specificity of the most specific parent selector
+        let nested = &stylesheet.rules[2].selectors[0];
+        assert_eq!(nested.specificity(), (1, 0, 1));
+        assert_eq!(stylesheet.rules[1].selectors[0].specificity(), (1, 1, 0));
+        let merged = parse("p.note { &#intro { color: #777777; } }");
+        assert_eq!(selectors(&merged.rules[1]), ["p#intro.note"]);
+    }
+}
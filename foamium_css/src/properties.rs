@@ -0,0 +1,440 @@
+// The longhand properties the engine computes, and the values each of them accepts
+//
+// This is the one list of properties. The parser looks up how to parse a value here,
+// `@supports` checks declarations against it, layout asks it which properties inherit,
+// and the shorthands in `shorthand` expand into these longhands.
+use crate::{font_face, grid, shorthand, CalcType, Color, Declaration, EasingFunction, Parser, Unit, Value};
+
+pub(crate) const CSS_WIDE_KEYWORDS: [&str; 5] = ["inherit", "initial", "unset", "revert", "revert-layer"];
+
+pub(crate) const BORDER_STYLES: [&str; 10] = [
+    "none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset", "outset",
+];
+
+const DISPLAY: &[&str] = &[
+    "inline",
+    "block",
+    "flow-root",
+    "none",
+    "contents",
+    "flex",
+    "inline-flex",
+    "grid",
+    "inline-grid",
+    "table",
+    "inline-table",
+    "table-caption",
+    "table-row-group",
+    "table-header-group",
+    "table-footer-group",
+    "table-row",
+    "table-column-group",
+    "table-column",
+    "table-cell",
+];
+
+const FONT_SIZES: &[&str] = &[
+    "xx-small", "x-small", "small", "medium", "large", "x-large", "xx-large", "xxx-large", "larger", "smaller",
+];
+
+const ALIGN_CONTENT: &[&str] = &[
+    "normal",
+    "start",
+    "end",
+    "left",
+    "right",
+    "flex-start",
+    "flex-end",
+    "center",
+    "space-between",
+    "space-around",
+    "space-evenly",
+    "stretch",
+];
+
+const ALIGN_ITEMS: &[&str] =
+    &["normal", "start", "self-start", "end", "self-end", "flex-start", "flex-end", "center", "baseline", "stretch"];
+
+const ALIGN_SELF: &[&str] = &[
+    "auto", "normal", "start", "self-start", "end", "self-end", "flex-start", "flex-end", "center", "baseline",
+    "stretch",
+];
+
+// Text runs left to right, so `justify-items` and `justify-self` take `left` and `right` too
+const JUSTIFY_ITEMS: &[&str] = &[
+    "normal", "start", "self-start", "end", "self-end", "flex-start", "flex-end", "center", "baseline", "stretch",
+    "left", "right",
+];
+
+const JUSTIFY_SELF: &[&str] = &[
+    "auto", "normal", "start", "self-start", "end", "self-end", "flex-start", "flex-end", "center", "baseline",
+    "stretch", "left", "right",
+];
+
+// The values for SVG act like `auto` on other boxes
+const POINTER_EVENTS: &[&str] = &[
+    "auto",
+    "none",
+    "bounding-box",
+    "visiblepainted",
+    "visiblefill",
+    "visiblestroke",
+    "visible",
+    "painted",
+    "fill",
+    "stroke",
+    "all",
+];
+
+const OVERFLOW: &[&str] = &["visible", "hidden", "clip", "scroll", "auto"];
+
+const VERTICAL_ALIGN: &[&str] = &["baseline", "sub", "super", "text-top", "text-bottom", "middle", "top", "bottom"];
+
+/// A kind of value a longhand accepts besides its keywords.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ValueType {
+    /// A length, or unitless zero
+    Length,
+    Percentage,
+    Number,
+    Integer,
+    /// A color, including `currentcolor`
+    Color,
+    Time,
+    /// An easing function or one of the keywords for one, such as `ease-in`
+    Easing,
+    Image,
+    /// A name the author chooses, such as an animation name
+    CustomIdent,
+}
+
+use ValueType::*;
+
+/// One of a longhand's keywords, or a value of one of its types. Lengths, percentages,
+/// numbers and times can only be negative if `negative` is set.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ValueSyntax {
+    keywords: &'static [&'static str],
+    types: &'static [ValueType],
+    negative: bool,
+}
+
+impl ValueSyntax {
+    const fn keywords(keywords: &'static [&'static str]) -> ValueSyntax {
+        ValueSyntax {
+            keywords,
+            types: &[],
+            negative: true,
+        }
+    }
+
+    const fn any(keywords: &'static [&'static str], types: &'static [ValueType]) -> ValueSyntax {
+        ValueSyntax {
+            keywords,
+            types,
+            negative: true,
+        }
+    }
+
+    const fn non_negative(keywords: &'static [&'static str], types: &'static [ValueType]) -> ValueSyntax {
+        ValueSyntax {
+            keywords,
+            types,
+            negative: false,
+        }
+    }
+
+    /// Whether `value` is one of the keywords or a value of one of the types.
+    fn accepts(&self, value: &Value) -> bool {
+        let has = |value_type| self.types.contains(&value_type);
+        let sign = |v: f32| self.negative || v >= 0.0;
+        match *value {
+            Value::Keyword(ref keyword) if self.keywords.iter().any(|k| k.eq_ignore_ascii_case(keyword)) => true,
+            Value::Keyword(ref keyword) if has(Color) && keyword.eq_ignore_ascii_case("currentcolor") => true,
+            Value::Keyword(ref keyword) if has(Color) && Color::from_name(keyword).is_some() => true,
+            Value::Keyword(_) if has(Easing) && EasingFunction::from_value(value).is_some() => true,
+            Value::Keyword(ref keyword) => {
+                has(CustomIdent)
+                    && !keyword.is_empty()
+                    && !keyword.eq_ignore_ascii_case("default")
+                    && !CSS_WIDE_KEYWORDS.contains(&&*keyword.to_ascii_lowercase())
+            }
+            Value::Length(v, Unit::Percent) => has(Percentage) && sign(v),
+            Value::Length(v, _) => has(Length) && sign(v),
+            // A bare number is only a valid length when it is zero
+            Value::Number(v) if v == 0.0 && has(Length) => true,
+            Value::Number(v) => (has(Number) || (has(Integer) && v.fract() == 0.0)) && sign(v),
+            // Math functions can't be checked for sign until they're resolved
+            Value::Calc(ref node) => match node.calc_type() {
+                Some(CalcType::Number) => has(Number) || has(Integer),
+                Some(CalcType::Length) => has(Length),
+                Some(CalcType::Percentage) => has(Percentage),
+                Some(CalcType::LengthPercentage) => has(Length) && has(Percentage),
+                None => false,
+            },
+            Value::ColorValue(_) => has(Color),
+            Value::Time(v) => has(Time) && sign(v),
+            Value::Easing(_) => has(Easing),
+            Value::Image(_) => has(Image),
+            _ => false,
+        }
+    }
+}
+
+/// The values a longhand accepts, other than the CSS-wide keywords.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Syntax {
+    Value(ValueSyntax),
+    /// A comma-separated list, with one item for each transition, animation or image layer
+    List(ValueSyntax),
+    /// A comma-separated list of family names, quoted or not
+    FontFamily,
+    /// A track list, template areas, grid line or auto flow, which `grid` checks as it
+    /// parses them
+    Grid,
+}
+
+#[derive(Debug)]
+pub(crate) struct Longhand {
+    pub(crate) name: &'static str,
+    pub(crate) inherited: bool,
+    pub(crate) syntax: Syntax,
+}
+
+const fn longhand(name: &'static str, inherited: bool, syntax: Syntax) -> Longhand {
+    Longhand { name, inherited, syntax }
+}
+
+const INHERITED: bool = true;
+const NOT_INHERITED: bool = false;
+
+const MARGIN: Syntax = Syntax::Value(ValueSyntax::any(&["auto"], &[Length, Percentage]));
+const PADDING: Syntax = Syntax::Value(ValueSyntax::non_negative(&[], &[Length, Percentage]));
+const BORDER_COLOR: Syntax = Syntax::Value(ValueSyntax::any(&[], &[Color]));
+const BORDER_STYLE: Syntax = Syntax::Value(ValueSyntax::keywords(&BORDER_STYLES));
+const BORDER_WIDTH: Syntax = Syntax::Value(ValueSyntax::non_negative(&["thin", "medium", "thick"], &[Length]));
+const BORDER_SPACING: Syntax = Syntax::Value(ValueSyntax::non_negative(&[], &[Length]));
+const SIZE: Syntax = Syntax::Value(ValueSyntax::non_negative(&["auto"], &[Length, Percentage]));
+const MAX_SIZE: Syntax = Syntax::Value(ValueSyntax::non_negative(&["none"], &[Length, Percentage]));
+const INSET: Syntax = Syntax::Value(ValueSyntax::any(&["auto"], &[Length, Percentage]));
+const GAP: Syntax = Syntax::Value(ValueSyntax::non_negative(&["normal"], &[Length, Percentage]));
+const FLEX_FACTOR: Syntax = Syntax::Value(ValueSyntax::non_negative(&[], &[Number]));
+const DURATION: Syntax = Syntax::List(ValueSyntax::non_negative(&[], &[Time]));
+const DELAY: Syntax = Syntax::List(ValueSyntax::any(&[], &[Time]));
+const EASING: Syntax = Syntax::List(ValueSyntax::any(&[], &[Easing]));
+
+/// Every longhand the engine computes, in alphabetical order.
+const LONGHANDS: &[Longhand] = &[
+    // `border-spacing` is split the way WebKit and Blink split it, so the two lengths
+    // can be kept apart
+    longhand("-webkit-border-horizontal-spacing", INHERITED, BORDER_SPACING),
+    longhand("-webkit-border-vertical-spacing", INHERITED, BORDER_SPACING),
+    longhand("align-content", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(ALIGN_CONTENT))),
+    longhand("align-items", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(ALIGN_ITEMS))),
+    longhand("align-self", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(ALIGN_SELF))),
+    longhand("animation-delay", NOT_INHERITED, DELAY),
+    longhand(
+        "animation-direction",
+        NOT_INHERITED,
+        Syntax::List(ValueSyntax::keywords(&["normal", "reverse", "alternate", "alternate-reverse"])),
+    ),
+    longhand("animation-duration", NOT_INHERITED, DURATION),
+    longhand(
+        "animation-fill-mode",
+        NOT_INHERITED,
+        Syntax::List(ValueSyntax::keywords(&["none", "forwards", "backwards", "both"])),
+    ),
+    longhand("animation-iteration-count", NOT_INHERITED, Syntax::List(ValueSyntax::non_negative(&["infinite"], &[Number]))),
+    longhand("animation-name", NOT_INHERITED, Syntax::List(ValueSyntax::any(&["none"], &[CustomIdent]))),
+    longhand("animation-play-state", NOT_INHERITED, Syntax::List(ValueSyntax::keywords(&["running", "paused"]))),
+    longhand("animation-timing-function", NOT_INHERITED, EASING),
+    longhand("background-color", NOT_INHERITED, Syntax::Value(ValueSyntax::any(&[], &[Color]))),
+    longhand("background-image", NOT_INHERITED, Syntax::List(ValueSyntax::any(&["none"], &[Image]))),
+    longhand("border-bottom-color", NOT_INHERITED, BORDER_COLOR),
+    longhand("border-bottom-style", NOT_INHERITED, BORDER_STYLE),
+    longhand("border-bottom-width", NOT_INHERITED, BORDER_WIDTH),
+    longhand("border-collapse", INHERITED, Syntax::Value(ValueSyntax::keywords(&["separate", "collapse"]))),
+    longhand("border-left-color", NOT_INHERITED, BORDER_COLOR),
+    longhand("border-left-style", NOT_INHERITED, BORDER_STYLE),
+    longhand("border-left-width", NOT_INHERITED, BORDER_WIDTH),
+    longhand("border-right-color", NOT_INHERITED, BORDER_COLOR),
+    longhand("border-right-style", NOT_INHERITED, BORDER_STYLE),
+    longhand("border-right-width", NOT_INHERITED, BORDER_WIDTH),
+    longhand("border-top-color", NOT_INHERITED, BORDER_COLOR),
+    longhand("border-top-style", NOT_INHERITED, BORDER_STYLE),
+    longhand("border-top-width", NOT_INHERITED, BORDER_WIDTH),
+    longhand("bottom", NOT_INHERITED, INSET),
+    longhand("box-sizing", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(&["content-box", "border-box"]))),
+    longhand("caption-side", INHERITED, Syntax::Value(ValueSyntax::keywords(&["top", "bottom"]))),
+    longhand("clear", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(&["none", "left", "right", "both"]))),
+    longhand("color", INHERITED, Syntax::Value(ValueSyntax::any(&[], &[Color]))),
+    longhand("column-gap", NOT_INHERITED, GAP),
+    longhand("container-name", NOT_INHERITED, Syntax::Value(ValueSyntax::any(&["none"], &[CustomIdent]))),
+    longhand("container-type", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(&["normal", "size", "inline-size"]))),
+    longhand("display", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(DISPLAY))),
+    longhand("flex-basis", NOT_INHERITED, Syntax::Value(ValueSyntax::non_negative(&["auto", "content"], &[Length, Percentage]))),
+    longhand(
+        "flex-direction",
+        NOT_INHERITED,
+        Syntax::Value(ValueSyntax::keywords(&["row", "row-reverse", "column", "column-reverse"])),
+    ),
+    longhand("flex-grow", NOT_INHERITED, FLEX_FACTOR),
+    longhand("flex-shrink", NOT_INHERITED, FLEX_FACTOR),
+    longhand("flex-wrap", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(&["nowrap", "wrap", "wrap-reverse"]))),
+    longhand("float", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(&["none", "left", "right"]))),
+    longhand("font-family", INHERITED, Syntax::FontFamily),
+    longhand("font-size", INHERITED, Syntax::Value(ValueSyntax::non_negative(FONT_SIZES, &[Length, Percentage]))),
+    longhand("grid-auto-columns", NOT_INHERITED, Syntax::Grid),
+    longhand("grid-auto-flow", NOT_INHERITED, Syntax::Grid),
+    longhand("grid-auto-rows", NOT_INHERITED, Syntax::Grid),
+    longhand("grid-column-end", NOT_INHERITED, Syntax::Grid),
+    longhand("grid-column-start", NOT_INHERITED, Syntax::Grid),
+    longhand("grid-row-end", NOT_INHERITED, Syntax::Grid),
+    longhand("grid-row-start", NOT_INHERITED, Syntax::Grid),
+    longhand("grid-template-areas", NOT_INHERITED, Syntax::Grid),
+    longhand("grid-template-columns", NOT_INHERITED, Syntax::Grid),
+    longhand("grid-template-rows", NOT_INHERITED, Syntax::Grid),
+    longhand("height", NOT_INHERITED, SIZE),
+    longhand("justify-content", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(ALIGN_CONTENT))),
+    longhand("justify-items", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(JUSTIFY_ITEMS))),
+    longhand("justify-self", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(JUSTIFY_SELF))),
+    longhand("left", NOT_INHERITED, INSET),
+    longhand("line-height", INHERITED, Syntax::Value(ValueSyntax::non_negative(&["normal"], &[Number, Length, Percentage]))),
+    longhand("margin-bottom", NOT_INHERITED, MARGIN),
+    longhand("margin-left", NOT_INHERITED, MARGIN),
+    longhand("margin-right", NOT_INHERITED, MARGIN),
+    longhand("margin-top", NOT_INHERITED, MARGIN),
+    longhand("max-height", NOT_INHERITED, MAX_SIZE),
+    longhand("max-width", NOT_INHERITED, MAX_SIZE),
+    longhand("min-height", NOT_INHERITED, SIZE),
+    longhand("min-width", NOT_INHERITED, SIZE),
+    longhand("order", NOT_INHERITED, Syntax::Value(ValueSyntax::any(&[], &[Integer]))),
+    longhand("overflow-x", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(OVERFLOW))),
+    longhand("overflow-y", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(OVERFLOW))),
+    longhand("padding-bottom", NOT_INHERITED, PADDING),
+    longhand("padding-left", NOT_INHERITED, PADDING),
+    longhand("padding-right", NOT_INHERITED, PADDING),
+    longhand("padding-top", NOT_INHERITED, PADDING),
+    longhand("pointer-events", INHERITED, Syntax::Value(ValueSyntax::keywords(POINTER_EVENTS))),
+    longhand(
+        "position",
+        NOT_INHERITED,
+        Syntax::Value(ValueSyntax::keywords(&["static", "relative", "absolute", "fixed", "sticky"])),
+    ),
+    longhand("right", NOT_INHERITED, INSET),
+    longhand("row-gap", NOT_INHERITED, GAP),
+    longhand("table-layout", NOT_INHERITED, Syntax::Value(ValueSyntax::keywords(&["auto", "fixed"]))),
+    longhand("text-align", INHERITED, Syntax::Value(ValueSyntax::keywords(&["left", "right", "start", "end", "center", "justify"]))),
+    longhand("top", NOT_INHERITED, INSET),
+    longhand("transition-delay", NOT_INHERITED, DELAY),
+    longhand("transition-duration", NOT_INHERITED, DURATION),
+    longhand("transition-property", NOT_INHERITED, Syntax::List(ValueSyntax::any(&["none", "all"], &[CustomIdent]))),
+    longhand("transition-timing-function", NOT_INHERITED, EASING),
+    longhand("vertical-align", NOT_INHERITED, Syntax::Value(ValueSyntax::any(VERTICAL_ALIGN, &[Length, Percentage]))),
+    longhand(
+        "white-space",
+        INHERITED,
+        Syntax::Value(ValueSyntax::keywords(&["normal", "nowrap", "pre", "pre-wrap", "pre-line"])),
+    ),
+    longhand("width", NOT_INHERITED, SIZE),
+    longhand("z-index", NOT_INHERITED, Syntax::Value(ValueSyntax::any(&["auto"], &[Integer]))),
+];
+
+/// The longhand called `name`, or `None` if the engine doesn't compute it.
+pub(crate) fn longhand_named(name: &str) -> Option<&'static Longhand> {
+    let name = name.to_ascii_lowercase();
+    LONGHANDS.binary_search_by(|longhand| longhand.name.cmp(&name)).ok().map(|i| &LONGHANDS[i])
+}
+
+/// Whether a property inherits by default.
+pub fn is_inherited(name: &str) -> bool {
+    longhand_named(name).is_some_and(|longhand| longhand.inherited)
+}
+
+/// Parse `value` for the longhand or shorthand `name` into declarations of longhands.
+/// Returns `None`, dropping the declaration, when the property isn't in the table or the
+/// value isn't valid for each of its longhands.
+pub(crate) fn parse_declarations(name: &str, value: &str) -> Option<Vec<Declaration>> {
+    let declarations = match shorthand::longhands(name) {
+        Some(_) => shorthand::parse_shorthand(name, value)?,
+        None => vec![Declaration {
+            name: name.to_string(),
+            value: longhand_named(name)?.parse(value)?,
+            important: false,
+        }],
+    };
+    let valid = declarations.iter().all(|declaration| {
+        longhand_named(&declaration.name).is_some_and(|longhand| longhand.accepts(&declaration.value))
+    });
+    valid.then_some(declarations)
+}
+
+impl Longhand {
+    /// Parse the whole of `source` as the longhand's value. Anything left over after the
+    /// value makes it invalid.
+    pub(crate) fn parse(&self, source: &str) -> Option<Value> {
+        let source = source.trim();
+        let mut parser = Parser::new(source);
+        let value = match self.syntax {
+            Syntax::FontFamily => return font_face::parse_font_family(source),
+            Syntax::Grid => return grid::parse_grid_value(self.name, source),
+            Syntax::List(_) => parser.parse_value_list()?,
+            Syntax::Value(_) => parser.parse_value()?,
+        };
+        parser.consume_whitespace();
+        parser.eof().then_some(value)
+    }
+
+    /// Whether `keyword` is a name the author chose, such as an animation name, rather than
+    /// one of the longhand's keywords. Names keep their case.
+    pub(crate) fn keeps_case(&self, keyword: &str) -> bool {
+        let syntax = match self.syntax {
+            Syntax::FontFamily => return true,
+            Syntax::Grid => return false,
+            Syntax::Value(ref syntax) | Syntax::List(ref syntax) => syntax,
+        };
+        syntax.types.contains(&CustomIdent)
+            && !syntax.keywords.iter().any(|k| k.eq_ignore_ascii_case(keyword))
+            && !CSS_WIDE_KEYWORDS.contains(&&*keyword.to_ascii_lowercase())
+    }
+
+    /// Whether `value` is valid for the longhand.
+    pub(crate) fn accepts(&self, value: &Value) -> bool {
+        if let Value::Keyword(ref keyword) = *value {
+            if CSS_WIDE_KEYWORDS.contains(&&*keyword.to_ascii_lowercase()) {
+                return true;
+            }
+        }
+        match self.syntax {
+            // The font family and grid parsers only return valid values
+            Syntax::FontFamily | Syntax::Grid => true,
+            Syntax::List(ref item) => value.items().iter().all(|value| item.accepts(value)),
+            Syntax::Value(ref syntax) => syntax.accepts(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shorthand;
+
+    #[test]
+    fn test_property_table() {
+        // `longhand_named` searches the table
+        assert!(LONGHANDS.windows(2).all(|pair| pair[0].name < pair[1].name));
+        assert!(longhand_named("Z-Index").is_some());
+        assert!(is_inherited("-webkit-border-vertical-spacing"));
+        assert!(!is_inherited("display"));
+        assert!(!is_inherited("cursor"));
+
+        // Every shorthand expands into longhands in the table
+        for name in [
+            "margin", "padding", "inset", "border", "border-left", "border-width", "border-style", "border-color", "flex",
+            "flex-flow", "gap", "overflow", "border-spacing", "grid-row", "grid-column", "grid-area", "grid-template",
+        ] {
+            let longhands = shorthand::longhands(name).unwrap();
+            assert!(longhands.iter().all(|longhand| longhand_named(longhand).is_some()), "{}", name);
+        }
+    }
+}
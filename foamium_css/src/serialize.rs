@@ -0,0 +1,1367 @@
+// Serializing style sheets back to CSS text, following the CSSOM serialization rules.
+// Formatting with the alternate flag (`{:#}`) writes the minified form instead.
+use crate::{shorthand, 
+    serialize_tokens, valid_identifier_char, CalcNode, Color, Combinator, Comparison, Condition, Declaration,
+    EasingFunction, EndingShape, FontDisplay, FontFace, FontFaceStyle, FontSource, Gradient, GradientLine, GradientShape,
+    GradientStop, GridLine, GridValue, HorizontalSide, Image, ImportRule, Keyframes, Position, RadialSize, VerticalSide, MediaCondition, MediaFeature, MediaQuery, MediaQueryList, MediaType, MediaValue, Rule,
+    Selector, SimpleSelector, StepPosition, Stylesheet, SupportsCondition, TrackBreadth, TrackListItem, TrackSize, RepeatCount, Value,
+};
+use crate::layer::is_anonymous_layer;
+use crate::properties;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter, Write};
+
+impl Stylesheet {
+    /// Serialize the style sheet as compactly as possible. Adjacent rules with the same
+    /// selectors or the same declarations are merged, and declarations overridden later in
+    /// the same rule are dropped, none of which changes the cascade.
+    pub fn minify(&self) -> String {
+        format!("{:#}", self.minified())
+    }
+
+    fn minified(&self) -> Stylesheet {
+        let mut rules: Vec<Rule> = Vec::new();
+        for rule in &self.rules {
+            let mut rule = rule.clone();
+            drop_overridden(&mut rule.declarations);
+            if rule.declarations.is_empty() {
+                continue;
+            }
+            match rules.last_mut() {
+                Some(last) if last.conditions == rule.conditions && last.selectors == rule.selectors => {
+                    last.declarations.extend(rule.declarations);
+                    drop_overridden(&mut last.declarations);
+                }
+                Some(last) if last.conditions == rule.conditions && last.declarations == rule.declarations => {
+                    for selector in rule.selectors {
+                        if !last.selectors.contains(&selector) {
+                            last.selectors.push(selector);
+                        }
+                    }
+                    last.selectors.sort_by_key(|s| std::cmp::Reverse(s.specificity()));
+                }
+                _ => rules.push(rule),
+            }
+        }
+        Stylesheet {
+            rules,
+            font_faces: self.font_faces.clone(),
+            keyframes: self
+                .keyframes
+                .iter()
+                .map(|keyframes| {
+                    let mut keyframes = keyframes.clone();
+                    for frame in &mut keyframes.frames {
+                        drop_overridden(&mut frame.declarations);
+                    }
+                    keyframes
+                })
+                .collect(),
+            imports: self.imports.clone(),
+            layers: self.layers.clone(),
+        }
+    }
+}
+
+/// Keep only the declaration of each property that wins: the last important one if there
+/// is one, or else the last one.
+fn drop_overridden(declarations: &mut Vec<Declaration>) {
+    let mut winners: HashMap<String, usize> = HashMap::new();
+    for (i, declaration) in declarations.iter().enumerate() {
+        let beats = |winner: &usize| declaration.important || !declarations[*winner].important;
+        if winners.get(&declaration.name).is_none_or(beats) {
+            winners.insert(declaration.name.clone(), i);
+        }
+    }
+    let mut i = 0;
+    declarations.retain(|declaration| {
+        i += 1;
+        winners[&declaration.name] == i - 1
+    });
+}
+
+/// A top-level item of a style sheet, in the order they are written.
+enum Item<'a> {
+    Import(&'a ImportRule),
+    /// An `@layer` statement declaring the layer order
+    Layers(Vec<&'a str>),
+    FontFace(&'a FontFace),
+    Keyframes(&'a Keyframes),
+    Rule(&'a Rule),
+}
+
+impl Item<'_> {
+    fn conditions(&self) -> &[Condition] {
+        match *self {
+            Item::Import(_) | Item::Layers(_) => &[],
+            Item::FontFace(face) => &face.conditions,
+            Item::Keyframes(keyframes) => &keyframes.conditions,
+            Item::Rule(rule) => &rule.conditions,
+        }
+    }
+}
+
+impl Display for Item<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Item::Import(import) => import.fmt(f),
+            Item::Layers(ref layers) => {
+                let separator = if f.alternate() { "," } else { ", " };
+                write!(f, "@layer {};", layers.join(separator))
+            }
+            Item::FontFace(face) => face.fmt(f),
+            Item::Keyframes(keyframes) => keyframes.fmt(f),
+            Item::Rule(rule) => rule.fmt(f),
+        }
+    }
+}
+
+/// Write items, wrapping runs that share a condition at `depth` in a conditional group rule.
+fn write_items(items: &[Item], depth: usize, f: &mut Formatter) -> fmt::Result {
+    let minify = f.alternate();
+    let indent = if minify { String::new() } else { "  ".repeat(depth) };
+    let mut i = 0;
+    while i < items.len() {
+        if i > 0 && !minify {
+            f.write_char('\n')?;
+        }
+        f.write_str(&indent)?;
+        let Some(condition) = items[i].conditions().get(depth) else {
+            items[i].fmt(f)?;
+            i += 1;
+            continue;
+        };
+        let end = i + items[i..]
+            .iter()
+            .take_while(|item| item.conditions().get(depth) == Some(condition))
+            .count();
+        condition.fmt(f)?;
+        f.write_str(if minify { "{" } else { " {\n" })?;
+        write_items(&items[i..end], depth + 1, f)?;
+        if !minify {
+            write!(f, "\n{}", indent)?;
+        }
+        f.write_char('}')?;
+        i = end;
+    }
+    Ok(())
+}
+
+impl Display for Stylesheet {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        // Anonymous layers are declared by their blocks
+        let layers: Vec<&str> = self
+            .layers
+            .iter()
+            .filter(|layer| !layer.split('.').any(is_anonymous_layer))
+            .map(String::as_str)
+            .collect();
+        // The layer statement goes first, since imports may declare layers too
+        let items: Vec<Item> = (!layers.is_empty())
+            .then_some(Item::Layers(layers))
+            .into_iter()
+            .chain(self.imports.iter().map(Item::Import))
+            .chain(self.font_faces.iter().map(Item::FontFace))
+            .chain(self.keyframes.iter().map(Item::Keyframes))
+            .chain(self.rules.iter().map(Item::Rule))
+            .collect();
+        write_items(&items, 0, f)
+    }
+}
+
+/// Writes the style rule itself; its conditions are written by the enclosing style sheet.
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write_selector_list(f, &self.selectors)?;
+        write_declaration_block(f, &self.declarations)
+    }
+}
+
+/// Write ` { a: b; c: d; }`, or `{a:b;c:d}` when minified.
+fn write_declaration_block(f: &mut Formatter, declarations: &[Declaration]) -> fmt::Result {
+    let entries = coalesce_shorthands(declarations);
+    if f.alternate() {
+        f.write_char('{')?;
+        for (i, entry) in entries.iter().enumerate() {
+            if i > 0 {
+                f.write_char(';')?;
+            }
+            entry.fmt(f)?;
+        }
+        return f.write_char('}');
+    }
+    f.write_str(" {")?;
+    for entry in &entries {
+        write!(f, " {}", entry)?;
+    }
+    f.write_str(" }")
+}
+
+/// The shorthands written in place of a complete run of their longhands, widest first.
+const SHORTHANDS: [&str; 16] = [
+    "border",
+    "border-top",
+    "border-right",
+    "border-bottom",
+    "border-left",
+    "border-width",
+    "border-style",
+    "border-color",
+    "border-spacing",
+    "flex",
+    "flex-flow",
+    "gap",
+    "inset",
+    "margin",
+    "overflow",
+    "padding",
+];
+
+/// A declaration as written: a property and the space-separated values it takes.
+struct Entry<'d> {
+    name: &'d str,
+    values: Vec<&'d Value>,
+    important: bool,
+}
+
+impl Display for Entry<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let minify = f.alternate();
+        f.write_str(self.name)?;
+        f.write_str(if minify { ":" } else { ": " })?;
+        for (i, value) in self.values.iter().enumerate() {
+            if i > 0 {
+                f.write_char(' ')?;
+            }
+            write_value(f, self.name, value)?;
+        }
+        write_priority(f, self.important)?;
+        if !minify {
+            f.write_char(';')?;
+        }
+        Ok(())
+    }
+}
+
+/// Write a property's value. Keywords are written in lowercase, apart from names the
+/// author chose, such as animation and font family names, whose case matters.
+fn write_value(f: &mut Formatter, name: &str, value: &Value) -> fmt::Result {
+    let longhand = properties::longhand_named(name);
+    match *value {
+        Value::Keyword(ref keyword) if longhand.is_some_and(|longhand| longhand.keeps_case(keyword)) => f.write_str(keyword),
+        Value::List(ref items) if longhand.is_some() => {
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(if f.alternate() { "," } else { ", " })?;
+                }
+                write_value(f, name, item)?;
+            }
+            Ok(())
+        }
+        _ => value.fmt(f),
+    }
+}
+
+/// Write ` !important` after an important declaration's value.
+fn write_priority(f: &mut Formatter, important: bool) -> fmt::Result {
+    match important {
+        true if f.alternate() => f.write_str("!important"),
+        true => f.write_str(" !important"),
+        false => Ok(()),
+    }
+}
+
+/// Turn runs of longhands that a shorthand expanded to back into the shorthand. Longhands
+/// still waiting on a shorthand's variables are written once, as that shorthand.
+fn coalesce_shorthands(declarations: &[Declaration]) -> Vec<Entry<'_>> {
+    let mut entries = Vec::new();
+    let mut i = 0;
+    'declarations: while i < declarations.len() {
+        let declaration = &declarations[i];
+        if let Value::ShorthandWithVariables(ref shorthand, _) = declaration.value {
+            let run = declarations[i..]
+                .iter()
+                .take_while(|next| next.value == declaration.value && next.important == declaration.important)
+                .count();
+            entries.push(Entry {
+                name: shorthand,
+                values: vec![&declaration.value],
+                important: declaration.important,
+            });
+            i += run;
+            continue;
+        }
+        for shorthand in SHORTHANDS {
+            let longhands = shorthand::longhands(shorthand).unwrap_or_default();
+            let Some(run) = declarations.get(i..i + longhands.len()) else {
+                continue;
+            };
+            // A shorthand sets all its longhands with the same importance
+            let important = declaration.important;
+            if run.iter().zip(&longhands).any(|(declaration, name)| declaration.name != *name || declaration.important != important) {
+                continue;
+            }
+            let values: Vec<&Value> = run.iter().map(|declaration| &declaration.value).collect();
+            let Some(values) = shorthand_values(shorthand, &values) else {
+                continue;
+            };
+            // Only if the shorthand reads back as the same longhands
+            let text = values.iter().map(ToString::to_string).collect::<Vec<_>>().join(" ");
+            let expanded = shorthand::parse_shorthand(shorthand, &text).unwrap_or_default();
+            if expanded.len() == run.len() && expanded.iter().zip(run).all(|(a, b)| a.name == b.name && a.value == b.value) {
+                entries.push(Entry { name: shorthand, values, important });
+                i += run.len();
+                continue 'declarations;
+            }
+        }
+        entries.push(Entry {
+            name: &declaration.name,
+            values: vec![&declaration.value],
+            important: declaration.important,
+        });
+        i += 1;
+    }
+    entries
+}
+
+/// The shortest value of a shorthand that expands to the longhand `values`, or `None`
+/// if there isn't one.
+fn shorthand_values<'d>(shorthand: &str, values: &[&'d Value]) -> Option<Vec<&'d Value>> {
+    let is_wide_keyword = |value: &Value| {
+        matches!(*value, Value::Keyword(ref keyword)
+            if matches!(&*keyword.to_ascii_lowercase(), "inherit" | "initial" | "unset" | "revert" | "revert-layer"))
+    };
+    if values.iter().any(|value| is_wide_keyword(value)) {
+        // Only a shorthand set to the keyword itself
+        return values.iter().all(|value| value == &values[0]).then(|| vec![values[0]]);
+    }
+    match shorthand {
+        "flex" => return Some(values.to_vec()),
+        "gap" | "border-spacing" | "overflow" => return Some(values[..if values[0] == values[1] { 1 } else { 2 }].to_vec()),
+        // `flex-flow` leaves out the initial direction or wrapping, but not both
+        "flex-flow" => {
+            let parts: Vec<&Value> = values
+                .iter()
+                .zip(["row", "nowrap"])
+                .filter(|(value, initial)| !matches!(**value, Value::Keyword(ref keyword) if keyword.eq_ignore_ascii_case(initial)))
+                .map(|(value, _)| *value)
+                .collect();
+            return Some(if parts.is_empty() { vec![values[0]] } else { parts });
+        }
+        _ => {}
+    }
+    if values.len() == 4 {
+        let [top, right, bottom, left] = [values[0], values[1], values[2], values[3]];
+        let count = if left != right {
+            4
+        } else if bottom != top {
+            3
+        } else if right != top {
+            2
+        } else {
+            1
+        };
+        return Some(values[..count].to_vec());
+    }
+    // `border` needs every side the same; then the width, style and colour of one side
+    if values.chunks(3).any(|side| side != &values[..3]) {
+        return None;
+    }
+    let initial = ["medium", "none", "currentcolor"];
+    let mut parts: Vec<&Value> = values[..3]
+        .iter()
+        .zip(initial)
+        .filter(|(value, initial)| !matches!(**value, Value::Keyword(ref keyword) if keyword.eq_ignore_ascii_case(initial)))
+        .map(|(value, _)| *value)
+        .collect();
+    if parts.is_empty() {
+        parts.push(values[1]);
+    }
+    Some(parts)
+}
+
+/// Writes `name: value;`, or `name:value` when minified, as the rule adds separators.
+impl Display for Declaration {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let minify = f.alternate();
+        match self.value {
+            Value::ShorthandWithVariables(ref shorthand, _) => f.write_str(shorthand)?,
+            _ => f.write_str(&self.name)?,
+        }
+        f.write_str(if minify { ":" } else { ": " })?;
+        write_value(f, &self.name, &self.value)?;
+        write_priority(f, self.important)?;
+        if !minify {
+            f.write_char(';')?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Selector {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Selector::Simple(ref simple) => simple.fmt(f),
+            Selector::Complex(ref left, combinator, ref simple) => {
+                left.fmt(f)?;
+                let combinator = match combinator {
+                    Combinator::Descendant => return write!(f, " {}", simple),
+                    Combinator::Child => '>',
+                    Combinator::NextSibling => '+',
+                    Combinator::SubsequentSibling => '~',
+                };
+                if f.alternate() {
+                    f.write_char(combinator)?;
+                } else {
+                    write!(f, " {} ", combinator)?;
+                }
+                simple.fmt(f)
+            }
+        }
+    }
+}
+
+fn write_selector_list(f: &mut Formatter, selectors: &[Selector]) -> fmt::Result {
+    for (i, selector) in selectors.iter().enumerate() {
+        if i > 0 {
+            f.write_str(if f.alternate() { "," } else { ", " })?;
+        }
+        selector.fmt(f)?;
+    }
+    Ok(())
+}
+
+impl Display for SimpleSelector {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.tag_name.is_none() && self.id.is_none() && self.class.is_empty() && self.is.is_empty() {
+            return f.write_char('*');
+        }
+        if let Some(ref tag_name) = self.tag_name {
+            f.write_str(tag_name)?;
+        }
+        if let Some(ref id) = self.id {
+            write!(f, "#{}", id)?;
+        }
+        for class in &self.class {
+            write!(f, ".{}", class)?;
+        }
+        for list in &self.is {
+            f.write_str(":is(")?;
+            write_selector_list(f, list)?;
+            f.write_char(')')?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Value::Keyword(ref keyword) => f.write_str(&keyword.to_ascii_lowercase()),
+            Value::String(ref string) => f.write_str(&quote(string)),
+            Value::Number(n) => write_number(f, n),
+            Value::Length(v, unit) => {
+                write_number(f, v)?;
+                f.write_str(unit.name())
+            }
+            Value::ColorValue(ref color) => color.fmt(f),
+            Value::Custom(ref tokens)
+            | Value::WithVariables(ref tokens)
+            | Value::ShorthandWithVariables(_, ref tokens) => f.write_str(&serialize_tokens(tokens)),
+            Value::Calc(ref node) => node.fmt(f),
+            Value::Time(seconds) => {
+                write_number(f, seconds)?;
+                f.write_char('s')
+            }
+            Value::Angle(degrees) => {
+                write_number(f, degrees)?;
+                f.write_str("deg")
+            }
+            Value::Image(ref image) => image.fmt(f),
+            Value::Easing(ref easing) => easing.fmt(f),
+            Value::Grid(ref grid) => grid.fmt(f),
+            Value::List(ref items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(if f.alternate() { "," } else { ", " })?;
+                    }
+                    item.fmt(f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for EasingFunction {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let separator = if f.alternate() { "," } else { ", " };
+        match *self {
+            EasingFunction::Linear(ref points) if points.is_empty() => f.write_str("linear"),
+            EasingFunction::Linear(ref points) => {
+                f.write_str("linear(")?;
+                for (i, &(input, output)) in points.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(separator)?;
+                    }
+                    write_number(f, output)?;
+                    f.write_char(' ')?;
+                    write_number(f, input * 100.0)?;
+                    f.write_char('%')?;
+                }
+                f.write_char(')')
+            }
+            EasingFunction::CubicBezier(x1, y1, x2, y2) => {
+                f.write_str("cubic-bezier(")?;
+                for (i, n) in [x1, y1, x2, y2].into_iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(separator)?;
+                    }
+                    write_number(f, n)?;
+                }
+                f.write_char(')')
+            }
+            EasingFunction::Steps(steps, position) => {
+                let position = match position {
+                    StepPosition::JumpEnd => return write!(f, "steps({})", steps),
+                    StepPosition::JumpStart => "jump-start",
+                    StepPosition::JumpNone => "jump-none",
+                    StepPosition::JumpBoth => "jump-both",
+                };
+                write!(f, "steps({}{}{})", steps, separator, position)
+            }
+        }
+    }
+}
+
+impl Display for Image {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let separator = if f.alternate() { "," } else { ", " };
+        match *self {
+            Image::Url(ref url) => Url(url).fmt(f),
+            Image::Gradient(ref gradient) => gradient.fmt(f),
+            Image::ImageSet(ref options) => {
+                f.write_str("image-set(")?;
+                for (i, option) in options.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(separator)?;
+                    }
+                    option.image.fmt(f)?;
+                    if option.resolution != 1.0 {
+                        f.write_char(' ')?;
+                        write_number(f, option.resolution)?;
+                        f.write_char('x')?;
+                    }
+                    if let Some(ref mime_type) = option.mime_type {
+                        write!(f, " type({})", quote(mime_type))?;
+                    }
+                }
+                f.write_char(')')
+            }
+        }
+    }
+}
+
+/// Leaves out the parts of the configuration that have their default values.
+impl Display for Gradient {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let separator = if f.alternate() { "," } else { ", " };
+        if self.repeating {
+            f.write_str("repeating-")?;
+        }
+        let mut config = Vec::new();
+        let name = match self.shape {
+            GradientShape::Linear(line) => {
+                match line {
+                    GradientLine::Angle(180.0) => {}
+                    GradientLine::Angle(degrees) => config.push(Value::Angle(degrees).to_string()),
+                    GradientLine::Corner(horizontal, vertical) => {
+                        let vertical = if vertical == VerticalSide::Top { "top" } else { "bottom" };
+                        let horizontal = if horizontal == HorizontalSide::Left { "left" } else { "right" };
+                        config.push(format!("to {} {}", vertical, horizontal));
+                    }
+                }
+                "linear"
+            }
+            GradientShape::Radial {
+                ending_shape,
+                ref size,
+                ref position,
+            } => {
+                let keyword = match *size {
+                    RadialSize::ClosestSide => Some("closest-side"),
+                    RadialSize::FarthestSide => Some("farthest-side"),
+                    RadialSize::ClosestCorner => Some("closest-corner"),
+                    RadialSize::FarthestCorner => None,
+                    RadialSize::Radius(ref radius) => {
+                        config.push(radius.to_string());
+                        None
+                    }
+                    RadialSize::Radii(ref x, ref y) => {
+                        config.push(format!("{} {}", x, y));
+                        None
+                    }
+                };
+                let implied = matches!(size, RadialSize::Radius(_)) || ending_shape == EndingShape::Ellipse;
+                if !implied {
+                    config.push("circle".to_string());
+                }
+                config.extend(keyword.map(str::to_string));
+                if *position != Position::center() {
+                    config.push(format!("at {} {}", position.x, position.y));
+                }
+                "radial"
+            }
+            GradientShape::Conic { from, ref position } => {
+                if from != 0.0 {
+                    config.push(format!("from {}", Value::Angle(from)));
+                }
+                if *position != Position::center() {
+                    config.push(format!("at {} {}", position.x, position.y));
+                }
+                "conic"
+            }
+        };
+        write!(f, "{}-gradient(", name)?;
+        if !config.is_empty() {
+            write!(f, "{}{}", config.join(" "), separator)?;
+        }
+        for (i, stop) in self.stops.iter().enumerate() {
+            if i > 0 {
+                f.write_str(separator)?;
+            }
+            match *stop {
+                GradientStop::Color(color, ref position) => {
+                    color.fmt(f)?;
+                    if let Some(ref position) = *position {
+                        f.write_char(' ')?;
+                        position.fmt(f)?;
+                    }
+                }
+                GradientStop::Hint(ref position) => position.fmt(f)?,
+            }
+        }
+        f.write_char(')')
+    }
+}
+
+impl Display for GridValue {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            GridValue::Tracks(ref tracks) => write_track_list(f, tracks),
+            GridValue::Areas(ref areas) => {
+                for (i, row) in areas.rows.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(' ')?;
+                    }
+                    let cells: Vec<&str> = row.iter().map(|cell| cell.as_deref().unwrap_or(".")).collect();
+                    f.write_str(&quote(&cells.join(" ")))?;
+                }
+                Ok(())
+            }
+            GridValue::Line(ref line) => line.fmt(f),
+            GridValue::AutoFlow { column, dense } => {
+                f.write_str(if column { "column" } else { "row" })?;
+                if dense {
+                    f.write_str(" dense")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn write_track_list(f: &mut Formatter, tracks: &[TrackListItem]) -> fmt::Result {
+    let separator = if f.alternate() { "," } else { ", " };
+    for (i, item) in tracks.iter().enumerate() {
+        if i > 0 {
+            f.write_char(' ')?;
+        }
+        match *item {
+            TrackListItem::LineNames(ref names) => write!(f, "[{}]", names.join(" "))?,
+            TrackListItem::Track(ref size) => size.fmt(f)?,
+            TrackListItem::Repeat(count, ref items) => {
+                f.write_str("repeat(")?;
+                match count {
+                    RepeatCount::Count(count) => write!(f, "{}", count)?,
+                    RepeatCount::AutoFill => f.write_str("auto-fill")?,
+                    RepeatCount::AutoFit => f.write_str("auto-fit")?,
+                }
+                f.write_str(separator)?;
+                write_track_list(f, items)?;
+                f.write_char(')')?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Display for TrackSize {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let separator = if f.alternate() { "," } else { ", " };
+        match *self {
+            TrackSize::Breadth(ref breadth) => breadth.fmt(f),
+            TrackSize::MinMax(ref min, ref max) => {
+                f.write_str("minmax(")?;
+                min.fmt(f)?;
+                f.write_str(separator)?;
+                max.fmt(f)?;
+                f.write_char(')')
+            }
+            TrackSize::FitContent(ref limit) => {
+                f.write_str("fit-content(")?;
+                limit.fmt(f)?;
+                f.write_char(')')
+            }
+        }
+    }
+}
+
+impl Display for TrackBreadth {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            TrackBreadth::Length(ref length) => length.fmt(f),
+            TrackBreadth::Flex(fr) => {
+                write_number(f, fr)?;
+                f.write_str("fr")
+            }
+            TrackBreadth::MinContent => f.write_str("min-content"),
+            TrackBreadth::MaxContent => f.write_str("max-content"),
+            TrackBreadth::Auto => f.write_str("auto"),
+        }
+    }
+}
+
+/// Leaves out a span's count of one when it has a name.
+impl Display for GridLine {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            GridLine::Auto => f.write_str("auto"),
+            GridLine::Named(ref name) => f.write_str(name),
+            GridLine::Line(n, ref name) => {
+                write!(f, "{}", n)?;
+                match *name {
+                    Some(ref name) => write!(f, " {}", name),
+                    None => Ok(()),
+                }
+            }
+            GridLine::Span(1, Some(ref name)) => write!(f, "span {}", name),
+            GridLine::Span(n, ref name) => {
+                write!(f, "span {}", n)?;
+                match *name {
+                    Some(ref name) => write!(f, " {}", name),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Writes the name as an identifier, or a string if it isn't one. Offsets are percentages,
+/// except that `to` is shorter when minifying.
+impl Display for Keyframes {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let minify = f.alternate();
+        f.write_str("@keyframes ")?;
+        let identifier = !self.name.is_empty()
+            && self.name.chars().all(valid_identifier_char)
+            && !self.name.starts_with(|c: char| c.is_ascii_digit());
+        if identifier {
+            f.write_str(&self.name)?;
+        } else {
+            f.write_str(&quote(&self.name))?;
+        }
+        f.write_str(if minify { "{" } else { " {" })?;
+        for frame in &self.frames {
+            if !minify {
+                f.write_char(' ')?;
+            }
+            for (i, &offset) in frame.offsets.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(if minify { "," } else { ", " })?;
+                }
+                if minify && offset == 1.0 {
+                    f.write_str("to")?;
+                } else {
+                    write_number(f, offset * 100.0)?;
+                    f.write_char('%')?;
+                }
+            }
+            write_declaration_block(f, &frame.declarations)?;
+        }
+        f.write_str(if minify { "}" } else { " }" })
+    }
+}
+
+/// Write a number in its shortest form, without a negative zero. When minifying, the
+/// leading zero before a decimal point is dropped too.
+fn write_number(f: &mut Formatter, n: f32) -> fmt::Result {
+    let n = if n == 0.0 { 0.0 } else { n };
+    let s = n.to_string();
+    if f.alternate() {
+        if let Some(fraction) = s.strip_prefix("0.") {
+            return write!(f, ".{}", fraction);
+        }
+        if let Some(fraction) = s.strip_prefix("-0.") {
+            return write!(f, "-.{}", fraction);
+        }
+    }
+    f.write_str(&s)
+}
+
+/// Writes `rgb()` or `rgba()`, as CSSOM specifies, or the shortest of the hex and named
+/// forms when minified.
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if f.alternate() {
+            let short = [self.r, self.g, self.b, self.a].iter().all(|c| c % 17 == 0);
+            let hex = match (short, self.a == 255) {
+                (true, true) => format!("#{:x}{:x}{:x}", self.r / 17, self.g / 17, self.b / 17),
+                (true, false) => format!("#{:x}{:x}{:x}{:x}", self.r / 17, self.g / 17, self.b / 17, self.a / 17),
+                (false, true) => format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b),
+                (false, false) => format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a),
+            };
+            return match self.shortest_name() {
+                Some(name) if name.len() < hex.len() => f.write_str(name),
+                _ => f.write_str(&hex),
+            };
+        }
+        if self.a == 255 {
+            return write!(f, "rgb({}, {}, {})", self.r, self.g, self.b);
+        }
+        // The alpha is written with two decimals, or three if that doesn't round-trip
+        let mut alpha = (self.a as f32 / 255.0 * 100.0).round() / 100.0;
+        if (alpha * 255.0).round() as u8 != self.a {
+            alpha = (self.a as f32 / 255.0 * 1000.0).round() / 1000.0;
+        }
+        write!(f, "rgba({}, {}, {}, {})", self.r, self.g, self.b, alpha)
+    }
+}
+
+/// Writes the outermost math function, with nested sums and products in the shortest
+/// form that parses back to the same tree.
+impl Display for CalcNode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            CalcNode::Min(_) | CalcNode::Max(_) | CalcNode::Clamp(..) => write_calc(self, f),
+            _ => {
+                f.write_str("calc(")?;
+                write_calc(self, f)?;
+                f.write_char(')')
+            }
+        }
+    }
+}
+
+fn write_calc(node: &CalcNode, f: &mut Formatter) -> fmt::Result {
+    let separator = if f.alternate() { "," } else { ", " };
+    let is_sum = |node: &CalcNode| matches!(node, CalcNode::Add(..) | CalcNode::Sub(..));
+    let is_product = |node: &CalcNode| matches!(node, CalcNode::Mul(..) | CalcNode::Div(..));
+    let operand = |f: &mut Formatter, node: &CalcNode, parenthesize: bool| {
+        if parenthesize {
+            f.write_char('(')?;
+            write_calc(node, f)?;
+            f.write_char(')')
+        } else {
+            write_calc(node, f)
+        }
+    };
+    match *node {
+        CalcNode::Number(n) if n == f32::INFINITY => f.write_str("infinity"),
+        CalcNode::Number(n) if n == f32::NEG_INFINITY => f.write_str("-infinity"),
+        CalcNode::Number(n) => write_number(f, n),
+        CalcNode::Dimension(v, unit) => {
+            write_number(f, v)?;
+            f.write_str(unit.name())
+        }
+        // `+` and `-` always need whitespace around them
+        CalcNode::Add(ref a, ref b) => {
+            write_calc(a, f)?;
+            f.write_str(" + ")?;
+            write_calc(b, f)
+        }
+        CalcNode::Sub(ref a, ref b) => {
+            write_calc(a, f)?;
+            f.write_str(" - ")?;
+            operand(f, b, is_sum(b))
+        }
+        CalcNode::Mul(ref a, ref b) => {
+            operand(f, a, is_sum(a))?;
+            f.write_str(if f.alternate() { "*" } else { " * " })?;
+            operand(f, b, is_sum(b))
+        }
+        CalcNode::Div(ref a, ref b) => {
+            operand(f, a, is_sum(a))?;
+            f.write_str(if f.alternate() { "/" } else { " / " })?;
+            operand(f, b, is_sum(b) || is_product(b))
+        }
+        CalcNode::Min(ref args) | CalcNode::Max(ref args) => {
+            f.write_str(if matches!(node, CalcNode::Min(_)) { "min(" } else { "max(" })?;
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(separator)?;
+                }
+                write_calc(arg, f)?;
+            }
+            f.write_char(')')
+        }
+        CalcNode::Clamp(ref min, ref value, ref max) => {
+            f.write_str("clamp(")?;
+            write_calc(min, f)?;
+            f.write_str(separator)?;
+            write_calc(value, f)?;
+            f.write_str(separator)?;
+            write_calc(max, f)?;
+            f.write_char(')')
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Condition::Media(ref media) if media.queries.is_empty() => f.write_str("@media all"),
+            Condition::Media(ref media) => {
+                f.write_str("@media ")?;
+                media.fmt(f)
+            }
+            Condition::Supports(ref supports) => {
+                f.write_str("@supports ")?;
+                supports.fmt(f)
+            }
+            Condition::Container(ref query) => {
+                f.write_str("@container ")?;
+                if let Some(ref name) = query.name {
+                    write!(f, "{} ", name)?;
+                }
+                query.condition.fmt(f)
+            }
+            Condition::Layer(ref name) if is_anonymous_layer(name) => f.write_str("@layer"),
+            Condition::Layer(ref name) => write!(f, "@layer {}", name),
+        }
+    }
+}
+
+impl Display for SupportsCondition {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let in_parens = |condition: &SupportsCondition, f: &mut Formatter| match condition {
+            SupportsCondition::Not(_) | SupportsCondition::And(_) | SupportsCondition::Or(_) => {
+                f.write_char('(')?;
+                condition.fmt(f)?;
+                f.write_char(')')
+            }
+            _ => condition.fmt(f),
+        };
+        match *self {
+            SupportsCondition::Not(ref inner) => {
+                f.write_str("not ")?;
+                in_parens(inner, f)
+            }
+            SupportsCondition::And(ref conditions) | SupportsCondition::Or(ref conditions) => {
+                let combinator = if matches!(self, SupportsCondition::And(_)) { " and " } else { " or " };
+                for (i, condition) in conditions.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(combinator)?;
+                    }
+                    in_parens(condition, f)?;
+                }
+                Ok(())
+            }
+            SupportsCondition::Declaration(ref name, ref value) if f.alternate() => write!(f, "({}:{})", name, value),
+            SupportsCondition::Declaration(ref name, ref value) => write!(f, "({}: {})", name, value),
+            SupportsCondition::Selector(ref selector) => write!(f, "selector({})", selector),
+            SupportsCondition::FontFormat(ref format) => write!(f, "font-format({})", format),
+            SupportsCondition::Unknown(ref source) => f.write_str(source),
+        }
+    }
+}
+
+impl Display for MediaQueryList {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (i, query) in self.queries.iter().enumerate() {
+            if i > 0 {
+                f.write_str(if f.alternate() { "," } else { ", " })?;
+            }
+            query.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for MediaQuery {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if let (false, MediaType::All, Some(condition)) = (self.negated, &self.media_type, &self.condition) {
+            return condition.fmt(f);
+        }
+        if self.negated {
+            f.write_str("not ")?;
+        }
+        f.write_str(match self.media_type {
+            MediaType::All => "all",
+            MediaType::Screen => "screen",
+            MediaType::Print => "print",
+            MediaType::Other(ref name) => name,
+        })?;
+        if let Some(ref condition) = self.condition {
+            f.write_str(" and ")?;
+            write_media_in_parens(condition, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for MediaCondition {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            MediaCondition::Feature(ref feature) => feature.fmt(f),
+            MediaCondition::Not(ref inner) => {
+                f.write_str("not ")?;
+                write_media_in_parens(inner, f)
+            }
+            MediaCondition::And(ref conditions) | MediaCondition::Or(ref conditions) => {
+                let combinator = if matches!(self, MediaCondition::And(_)) { " and " } else { " or " };
+                for (i, condition) in conditions.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(combinator)?;
+                    }
+                    write_media_in_parens(condition, f)?;
+                }
+                Ok(())
+            }
+            MediaCondition::Unknown(ref source) => f.write_str(source),
+        }
+    }
+}
+
+fn write_media_in_parens(condition: &MediaCondition, f: &mut Formatter) -> fmt::Result {
+    match condition {
+        MediaCondition::Not(_) | MediaCondition::And(_) | MediaCondition::Or(_) => {
+            f.write_char('(')?;
+            condition.fmt(f)?;
+            f.write_char(')')
+        }
+        _ => condition.fmt(f),
+    }
+}
+
+/// Writes the range syntax, which is shorter than `min-` and `max-` prefixes.
+impl Display for MediaFeature {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let minify = f.alternate();
+        f.write_char('(')?;
+        match self.comparisons[..] {
+            [] => f.write_str(&self.name)?,
+            [(Comparison::Eq, ref value)] => {
+                f.write_str(&self.name)?;
+                f.write_str(if minify { ":" } else { ": " })?;
+                value.fmt(f)?;
+            }
+            [(op, ref value)] => {
+                write!(f, "{} {} ", self.name, op)?;
+                value.fmt(f)?;
+            }
+            [(first_op, ref first), (second_op, ref second), ..] => {
+                first.fmt(f)?;
+                write!(f, " {} {} {} ", first_op.flip(), self.name, second_op)?;
+                second.fmt(f)?;
+            }
+        }
+        f.write_char(')')
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Comparison::Eq => "=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        })
+    }
+}
+
+impl Display for MediaValue {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            MediaValue::Number(n) => write_number(f, n),
+            MediaValue::Length(v, unit) => {
+                write_number(f, v)?;
+                f.write_str(unit.name())
+            }
+            MediaValue::Resolution(dppx) => {
+                write_number(f, dppx)?;
+                f.write_str("dppx")
+            }
+            MediaValue::Ratio(a, b) => {
+                write_number(f, a)?;
+                f.write_str(if f.alternate() { "/" } else { " / " })?;
+                write_number(f, b)
+            }
+            MediaValue::Ident(ref ident) => f.write_str(ident),
+        }
+    }
+}
+
+impl Display for ImportRule {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("@import ")?;
+        Url(&self.url).fmt(f)?;
+        match self.layer {
+            Some(ref layer) if is_anonymous_layer(layer) => f.write_str(" layer")?,
+            Some(ref layer) => write!(f, " layer({})", layer)?,
+            None => {}
+        }
+        if let Some(ref supports) = self.supports {
+            f.write_str(" supports(")?;
+            supports.fmt(f)?;
+            f.write_char(')')?;
+        }
+        if !self.media.queries.is_empty() {
+            f.write_char(' ')?;
+            self.media.fmt(f)?;
+        }
+        f.write_char(';')
+    }
+}
+
+/// Writes the descriptors that differ from their initial values.
+impl Display for FontFace {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let minify = f.alternate();
+        let mut descriptors = Vec::new();
+
+        descriptors.push(("font-family", quote(&self.family)));
+        let mut sources = String::new();
+        for (i, source) in self.sources.iter().enumerate() {
+            if i > 0 {
+                sources.push_str(if minify { "," } else { ", " });
+            }
+            match *source {
+                FontSource::Url { ref url, ref format } => {
+                    sources.push_str(&if minify { format!("{:#}", Url(url)) } else { Url(url).to_string() });
+                    if let Some(format) = format {
+                        write!(sources, " format({})", quote(format))?;
+                    }
+                }
+                FontSource::Local(ref name) => write!(sources, "local({})", quote(name))?,
+            }
+        }
+        descriptors.push(("src", sources));
+
+        if self.weight.min != 400.0 || self.weight.max != 400.0 {
+            descriptors.push(("font-weight", range(self.weight.min, self.weight.max, "")));
+        }
+        if self.stretch != (100.0, 100.0) {
+            descriptors.push(("font-stretch", range(self.stretch.0, self.stretch.1, "%")));
+        }
+        match self.style {
+            FontFaceStyle::Normal => {}
+            FontFaceStyle::Italic => descriptors.push(("font-style", "italic".to_string())),
+            FontFaceStyle::Oblique(min, max) => {
+                descriptors.push(("font-style", format!("oblique {}", range(min, max, "deg"))))
+            }
+        }
+        if self.unicode_range.len() != 1 || (self.unicode_range[0].start, self.unicode_range[0].end) != (0, 0x10FFFF) {
+            let ranges: Vec<_> = self
+                .unicode_range
+                .iter()
+                .map(|r| match r.start == r.end {
+                    true => format!("U+{:X}", r.start),
+                    false => format!("U+{:X}-{:X}", r.start, r.end),
+                })
+                .collect();
+            descriptors.push(("unicode-range", ranges.join(if minify { "," } else { ", " })));
+        }
+        let display = match self.display {
+            FontDisplay::Auto => None,
+            FontDisplay::Block => Some("block"),
+            FontDisplay::Swap => Some("swap"),
+            FontDisplay::Fallback => Some("fallback"),
+            FontDisplay::Optional => Some("optional"),
+        };
+        if let Some(display) = display {
+            descriptors.push(("font-display", display.to_string()));
+        }
+
+        if minify {
+            let descriptors: Vec<_> = descriptors.iter().map(|(name, value)| format!("{}:{}", name, value)).collect();
+            return write!(f, "@font-face{{{}}}", descriptors.join(";"));
+        }
+        f.write_str("@font-face {")?;
+        for (name, value) in descriptors {
+            write!(f, " {}: {};", name, value)?;
+        }
+        f.write_str(" }")
+    }
+}
+
+/// A descriptor range, written as a single value when both ends are equal.
+fn range(min: f32, max: f32, unit: &str) -> String {
+    if min == max {
+        format!("{}{}", min, unit)
+    } else {
+        format!("{}{} {}{}", min, unit, max, unit)
+    }
+}
+
+/// Quote a string, escaping quotes and backslashes.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A `url()`, which is only left unquoted when minifying and the URL allows it.
+struct Url<'a>(&'a str);
+
+impl Display for Url<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let plain = !self.0.is_empty()
+            && !self.0.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '(' | ')' | '\\'));
+        if f.alternate() && plain {
+            write!(f, "url({})", self.0)
+        } else {
+            write!(f, "url({})", quote(self.0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_serialize() {
+        let source = "@import url(\"base.css\") screen;
+@font-face { font-family: \"Inter\"; src: url(\"inter.woff2\") format(\"woff2\"); font-weight: 100 900; }
+p, .note { color: #ff000080; margin: -0.5em; width: calc(100% - 2 * (1em + 5px)); }
+@media (min-width: 600px) and (prefers-color-scheme: dark) {
+  body { --gap: 4px 8px; background-color: #3584e4; font-size: max(1rem, 2vw); }
+}";
+        let stylesheet = parse(source);
+        let text = stylesheet.to_string();
+        assert_eq!(
+            text,
+            "@import url(\"base.css\") screen;
+@font-face { font-family: \"Inter\"; src: url(\"inter.woff2\") format(\"woff2\"); font-weight: 100 900; }
+.note, p { color: rgba(255, 0, 0, 0.5); margin: -0.5em; width: calc(100% - 2 * (1em + 5px)); }
+@media (width >= 600px) and (prefers-color-scheme: dark) {
+  body { --gap: 4px 8px; background-color: rgb(53, 132, 228); font-size: max(1rem, 2vw); }
+}"
+        );
+        // Serializing is lossless, so the text parses back to the same style sheet
+        assert_eq!(parse(&text), stylesheet);
+        assert_eq!(parse(&stylesheet.minify()).rules, stylesheet.rules);
+    }
+
+    #[test]
+    fn test_serialize_at_rules() {
+        let stylesheet = parse(
+            "@layer base { p { color: red; } }
+             @layer { @supports (display: block) and (not selector(a > b)) { p { color: blue; } } }
+             @container card (width > 30em) { h2 { font-size: 2em; } }",
+        );
+        let text = stylesheet.to_string();
+        assert_eq!(
+            text,
+            "@layer base;
+@layer base {
+  p { color: red; }
+}
+@layer {
+  @supports (display: block) and (not selector(a > b)) {
+    p { color: blue; }
+  }
+}
+@container card (width > 30em) {
+  h2 { font-size: 2em; }
+}"
+        );
+        assert_eq!(parse(&text), stylesheet);
+    }
+
+    #[test]
+    fn test_serialize_animations() {
+        let stylesheet = parse(
+            "@keyframes fade { from { color: #000000; } 50%, to { color: #ffffff; } }
+             p { transition-duration: 200ms, 1.5s; transition-timing-function: steps(2, start), cubic-bezier(0.1, 0.7, 1, 0.1); }",
+        );
+        assert_eq!(
+            stylesheet.to_string(),
+            "@keyframes fade { 0% { color: rgb(0, 0, 0); } 50%, 100% { color: rgb(255, 255, 255); } }
+p { transition-duration: 0.2s, 1.5s; transition-timing-function: steps(2, jump-start), cubic-bezier(0.1, 0.7, 1, 0.1); }"
+        );
+        assert_eq!(
+            stylesheet.minify(),
+            "@keyframes fade{0%{color:#000}50%,to{color:#fff}}p{transition-duration:.2s,1.5s;transition-timing-function:steps(2,jump-start),cubic-bezier(.1,.7,1,.1)}"
+        );
+        assert_eq!(parse(&stylesheet.to_string()), stylesheet);
+    }
+
+    #[test]
+    fn test_serialize_images() {
+        let stylesheet = parse(
+            "p { background-image: url(a.png), linear-gradient(to right, red 10%, 40%, #00f); }
+             div { background-image: repeating-radial-gradient(circle closest-side at left 20px, red, blue 5px); }
+             nav { background-image: conic-gradient(from 0.5turn, red, blue), image-set(\"a.png\" 1x, url(b.png) 2x type(\"image/png\")); }",
+        );
+        assert_eq!(
+            stylesheet.to_string(),
+            "p { background-image: url(\"a.png\"), linear-gradient(90deg, rgb(255, 0, 0) 10%, 40%, rgb(0, 0, 255)); }
+div { background-image: repeating-radial-gradient(circle closest-side at 0% 20px, rgb(255, 0, 0), rgb(0, 0, 255) 5px); }
+nav { background-image: conic-gradient(from 180deg, rgb(255, 0, 0), rgb(0, 0, 255)), image-set(url(\"a.png\"), url(\"b.png\") 2x type(\"image/png\")); }"
+        );
+        assert_eq!(parse(&stylesheet.to_string()), stylesheet);
+        assert_eq!(parse(&stylesheet.minify()).rules, stylesheet.rules);
+    }
+
+    #[test]
+    fn test_minify() {
+        let stylesheet = parse(
+            "a { color: #ffffff; color: #ff0000; }
+             a { margin: 0.5px; }
+             b { margin: 0.5px; }
+             i { }
+             @media print { p { color: rgb(0, 0, 255); } }
+             @media print { h1 { color: #aabbcc; } }",
+        );
+        assert_eq!(
+            stylesheet.minify(),
+            "a{color:red;margin:.5px}b{margin:.5px}@media print{p{color:#00f}h1{color:#abc}}"
+        );
+        let stylesheet = parse("a { color: red; } b { color: red; }");
+        assert_eq!(stylesheet.minify(), "a,b{color:red}");
+    }
+
+    #[test]
+    fn test_serialize_important() {
+        let stylesheet = parse(
+            "p { color: red !important; margin: 0 ! IMPORTANT; --x: 1 !important; }
+             p { color: blue; padding: 1px; padding-left: 2px !important; }",
+        );
+        let text = stylesheet.to_string();
+        assert_eq!(
+            text,
+            "p { color: red !important; margin: 0 !important; --x: 1 !important; }
+p { color: blue; padding: 1px; padding-left: 2px !important; }"
+        );
+        assert_eq!(parse(&text), stylesheet);
+        // The important declaration wins over the later one, so it's the one kept
+        assert_eq!(
+            stylesheet.minify(),
+            "p{color:red!important;margin:0!important;--x:1!important;padding-top:1px;padding-right:1px;padding-bottom:1px;padding-left:2px!important}"
+        );
+        assert_eq!(parse(&stylesheet.minify()).rules, stylesheet.minified().rules);
+    }
+
+    #[test]
+    fn test_serialize_keyword_case() {
+        // Keywords are lowercased, but names the author chose keep their case
+        let stylesheet = parse("p { color: RED; display: Block; animation-name: Spin, NONE; container-name: Card; }");
+        assert_eq!(
+            stylesheet.to_string(),
+            "p { color: red; display: block; animation-name: Spin, none; container-name: Card; }"
+        );
+        assert_eq!(stylesheet.minify(), "p{color:red;display:block;animation-name:Spin,none;container-name:Card}");
+    }
+}
@@ -0,0 +1,353 @@
+// Shorthand properties, expanded into their longhands as they're parsed
+//
+// A shorthand containing `var()` can't be split up until the variables are known, so
+// each of its longhands holds the whole shorthand until then.
+use crate::properties::{BORDER_STYLES, CSS_WIDE_KEYWORDS};
+use crate::{grid, Color, Declaration, Parser, Unit, Value};
+
+const SIDES: [&str; 4] = ["top", "right", "bottom", "left"];
+
+/// The longhands a shorthand sets, or `None` if `name` isn't a shorthand.
+pub(crate) fn longhands(name: &str) -> Option<Vec<String>> {
+    let name = name.to_ascii_lowercase();
+    let longhands = match &*name {
+        "margin" | "padding" => SIDES.iter().map(|side| format!("{}-{}", name, side)).collect(),
+        "inset" => names(&SIDES),
+        "border-width" | "border-style" | "border-color" => {
+            let part = &name["border-".len()..];
+            SIDES.iter().map(|side| format!("border-{}-{}", side, part)).collect()
+        }
+        "border" => SIDES.iter().flat_map(|side| border_side(side)).collect(),
+        "flex" => names(&["flex-grow", "flex-shrink", "flex-basis"]),
+        "flex-flow" => names(&["flex-direction", "flex-wrap"]),
+        "gap" => names(&["row-gap", "column-gap"]),
+        "overflow" => names(&["overflow-x", "overflow-y"]),
+        // One property in CSS, split the way WebKit and Blink split it so the two lengths
+        // can be kept apart
+        "border-spacing" => names(&["-webkit-border-horizontal-spacing", "-webkit-border-vertical-spacing"]),
+        "grid-row" | "grid-column" => names(&[&format!("{}-start", name), &format!("{}-end", name)]),
+        "grid-area" => names(&["grid-row-start", "grid-column-start", "grid-row-end", "grid-column-end"]),
+        "grid-template" => names(&["grid-template-rows", "grid-template-columns", "grid-template-areas"]),
+        _ => {
+            let side = name.strip_prefix("border-").filter(|side| SIDES.contains(side))?;
+            border_side(side).collect()
+        }
+    };
+    Some(longhands)
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(ToString::to_string).collect()
+}
+
+fn border_side(side: &str) -> impl Iterator<Item = String> + '_ {
+    ["width", "style", "color"].into_iter().map(move |part| format!("border-{}-{}", side, part))
+}
+
+/// Expand a shorthand into its longhands. Returns `None` if `name` isn't a shorthand
+/// or `source` isn't a valid value for it.
+pub fn parse_shorthand(name: &str, source: &str) -> Option<Vec<Declaration>> {
+    let longhands = longhands(name)?;
+    let declaration = |name: &String, value: Value| Declaration {
+        name: name.clone(),
+        value,
+        important: false,
+    };
+    // The grid shorthands separate their parts with `/`
+    if name.to_ascii_lowercase().starts_with("grid-") {
+        let values = grid::parse_grid_shorthand(name, source.trim(), longhands.len())?;
+        return Some(longhands.iter().zip(values).map(|(name, value)| declaration(name, value)).collect());
+    }
+    let components = Parser::new(source.trim()).parse_components()?;
+
+    if let [Value::Keyword(ref keyword)] = components[..] {
+        if CSS_WIDE_KEYWORDS.contains(&&*keyword.to_ascii_lowercase()) {
+            return Some(longhands.iter().map(|name| declaration(name, components[0].clone())).collect());
+        }
+    }
+
+    let name = name.to_ascii_lowercase();
+    let values = match &*name {
+        "margin" => box_sides(components, |value| is_length(value) || is_keyword(value, &["auto"]))?,
+        "padding" => box_sides(components, is_length)?,
+        "inset" => box_sides(components, |value| is_length(value) || is_keyword(value, &["auto"]))?,
+        "border-width" => box_sides(components, is_border_width)?,
+        "border-style" => box_sides(components, is_border_style)?,
+        "border-color" => box_sides(components, is_color)?,
+        "flex" => flex(components)?,
+        "flex-flow" => flex_flow(components)?,
+        "gap" => {
+            // Unitless zero is the only number that's a length
+            let gap = |value: &Value| {
+                !matches!(value, Value::Number(n) if *n != 0.0) && is_length(value) || is_keyword(value, &["normal"])
+            };
+            match &components[..] {
+                [both] if gap(both) => vec![both.clone(), both.clone()],
+                [row, column] if gap(row) && gap(column) => vec![row.clone(), column.clone()],
+                _ => return None,
+            }
+        }
+        "overflow" => {
+            let overflow = |value: &Value| is_keyword(value, &["visible", "hidden", "clip", "scroll", "auto"]);
+            match &components[..] {
+                [both] if overflow(both) => vec![both.clone(), both.clone()],
+                [x, y] if overflow(x) && overflow(y) => vec![x.clone(), y.clone()],
+                _ => return None,
+            }
+        }
+        "border-spacing" => {
+            let spacing = |value: &Value| match *value {
+                Value::Length(n, unit) => n >= 0.0 && unit != Unit::Percent,
+                Value::Number(n) => n == 0.0,
+                Value::Calc(_) => true,
+                _ => false,
+            };
+            match &components[..] {
+                [both] if spacing(both) => vec![both.clone(), both.clone()],
+                [horizontal, vertical] if spacing(horizontal) && spacing(vertical) => {
+                    vec![horizontal.clone(), vertical.clone()]
+                }
+                _ => return None,
+            }
+        }
+        // `border` and `border-<side>` set the same width, style and colour on each side
+        _ => {
+            let [width, style, color] = border(components)?;
+            let sides = longhands.len() / 3;
+            (0..sides).flat_map(|_| [width.clone(), style.clone(), color.clone()]).collect()
+        }
+    };
+    Some(longhands.iter().zip(values).map(|(name, value)| declaration(name, value)).collect())
+}
+
+/// Expand one to four values to the top, right, bottom and left sides.
+fn box_sides(components: Vec<Value>, valid: impl Fn(&Value) -> bool) -> Option<Vec<Value>> {
+    if !components.iter().all(valid) {
+        return None;
+    }
+    let [top, right, bottom, left] = match &components[..] {
+        [all] => [all, all, all, all],
+        [vertical, horizontal] => [vertical, horizontal, vertical, horizontal],
+        [top, horizontal, bottom] => [top, horizontal, bottom, horizontal],
+        [top, right, bottom, left] => [top, right, bottom, left],
+        _ => return None,
+    };
+    Some(vec![top.clone(), right.clone(), bottom.clone(), left.clone()])
+}
+
+/// The grow factor, shrink factor and basis of a `flex` value. The factors come together,
+/// before or after the basis, and a basis left out is `0%`.
+fn flex(components: Vec<Value>) -> Option<Vec<Value>> {
+    let keyword = |keyword: &str| Value::Keyword(keyword.to_string());
+    if let [ref only] = components[..] {
+        if is_keyword(only, &["none"]) {
+            return Some(vec![Value::Number(0.0), Value::Number(0.0), keyword("auto")]);
+        }
+    }
+    let is_basis = |value: &Value| {
+        !matches!(value, Value::Number(_)) && is_length(value) || is_keyword(value, &["auto", "content"])
+    };
+    let pattern: String = components
+        .iter()
+        .map(|component| match component {
+            Value::Number(n) if *n >= 0.0 => Some('n'),
+            component if is_basis(component) => Some('b'),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    let mut factors = components.iter().filter(|component| matches!(component, Value::Number(_))).cloned();
+    let basis = components.iter().find(|component| is_basis(component)).cloned();
+    match &*pattern {
+        "n" | "nn" | "b" | "nb" | "nnb" | "bn" | "bnn" => Some(vec![
+            factors.next().unwrap_or(Value::Number(1.0)),
+            factors.next().unwrap_or(Value::Number(1.0)),
+            basis.unwrap_or(Value::Length(0.0, Unit::Percent)),
+        ]),
+        _ => None,
+    }
+}
+
+/// The direction and wrapping of a `flex-flow` value, in either order.
+fn flex_flow(components: Vec<Value>) -> Option<Vec<Value>> {
+    let mut direction = None;
+    let mut wrap = None;
+    for component in components {
+        let part = if is_keyword(&component, &["row", "row-reverse", "column", "column-reverse"]) {
+            &mut direction
+        } else if is_keyword(&component, &["nowrap", "wrap", "wrap-reverse"]) {
+            &mut wrap
+        } else {
+            return None;
+        };
+        if part.replace(component).is_some() {
+            return None;
+        }
+    }
+    let keyword = |keyword: &str| Value::Keyword(keyword.to_string());
+    Some(vec![direction.unwrap_or_else(|| keyword("row")), wrap.unwrap_or_else(|| keyword("nowrap"))])
+}
+
+/// The width, style and colour of a `border` value, which may come in any order.
+/// Those left out take their initial values.
+fn border(components: Vec<Value>) -> Option<[Value; 3]> {
+    let mut parts: [Option<Value>; 3] = [None, None, None];
+    for component in components {
+        let part = if is_border_width(&component) {
+            0
+        } else if is_border_style(&component) {
+            1
+        } else if is_color(&component) {
+            2
+        } else {
+            return None;
+        };
+        if parts[part].is_some() {
+            return None;
+        }
+        parts[part] = Some(component);
+    }
+    let [width, style, color] = parts;
+    let keyword = |keyword: &str| Value::Keyword(keyword.to_string());
+    Some([
+        width.unwrap_or_else(|| keyword("medium")),
+        style.unwrap_or_else(|| keyword("none")),
+        color.unwrap_or_else(|| keyword("currentcolor")),
+    ])
+}
+
+fn is_length(value: &Value) -> bool {
+    matches!(value, Value::Length(..) | Value::Number(_) | Value::Calc(_))
+}
+
+fn is_keyword(value: &Value, keywords: &[&str]) -> bool {
+    matches!(value, Value::Keyword(ref keyword) if keywords.contains(&&*keyword.to_ascii_lowercase()))
+}
+
+fn is_border_width(value: &Value) -> bool {
+    is_length(value) || is_keyword(value, &["thin", "medium", "thick"])
+}
+
+fn is_border_style(value: &Value) -> bool {
+    is_keyword(value, &BORDER_STYLES)
+}
+
+fn is_color(value: &Value) -> bool {
+    match value {
+        Value::ColorValue(_) => true,
+        Value::Keyword(keyword) => {
+            keyword.eq_ignore_ascii_case("currentcolor") || Color::from_name(keyword).is_some()
+        }
+        _ => false,
+    }
+}
+
+impl Parser {
+    /// Parse the whitespace-separated components of a value up to the end of input.
+    pub(crate) fn parse_components(&mut self) -> Option<Vec<Value>> {
+        let mut components = Vec::new();
+        loop {
+            self.consume_whitespace();
+            if self.eof() {
+                break;
+            }
+            match self.parse_value()? {
+                Value::Keyword(ref keyword) if keyword.is_empty() => return None,
+                value => components.push(value),
+            }
+        }
+        (!components.is_empty()).then_some(components)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn declarations(css: &str) -> Vec<String> {
+        parse_inline_style(css).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_box_shorthands() {
+        assert_eq!(
+            declarations("margin: 1px auto 3px"),
+            [
+                "margin-top: 1px;",
+                "margin-right: auto;",
+                "margin-bottom: 3px;",
+                "margin-left: auto;"
+            ]
+        );
+        assert_eq!(declarations("padding: 0 calc(1em + 2px)")[3], "padding-left: calc(1em + 2px);");
+        assert_eq!(declarations("border-style: solid dashed")[2], "border-bottom-style: solid;");
+        assert_eq!(declarations("margin: inherit")[1], "margin-right: inherit;");
+        assert_eq!(declarations("inset: 0 auto"), ["top: 0;", "right: auto;", "bottom: 0;", "left: auto;"]);
+        assert_eq!(declarations("overflow: hidden auto"), ["overflow-x: hidden;", "overflow-y: auto;"]);
+        assert_eq!(parse("div { overflow: clip clip; }").to_string(), "div { overflow: clip; }");
+        // Invalid values drop the whole declaration
+        assert!(declarations("padding: auto").is_empty());
+        assert!(declarations("margin: 1px 2px 3px 4px 5px").is_empty());
+        assert!(declarations("border-color: red 2px").is_empty());
+        assert!(declarations("overflow: scroll 1px").is_empty());
+    }
+
+    #[test]
+    fn test_border_shorthands() {
+        assert_eq!(
+            declarations("border-top: dashed #ff0000"),
+            [
+                "border-top-width: medium;",
+                "border-top-style: dashed;",
+                "border-top-color: rgb(255, 0, 0);"
+            ]
+        );
+        let border = declarations("border: 2px solid");
+        assert_eq!(border.len(), 12);
+        assert_eq!(border[9..], ["border-left-width: 2px;", "border-left-style: solid;", "border-left-color: currentcolor;"]);
+        assert!(declarations("border: solid solid").is_empty());
+        assert_eq!(
+            declarations("border-spacing: 2px 0"),
+            ["-webkit-border-horizontal-spacing: 2px;", "-webkit-border-vertical-spacing: 0;"]
+        );
+        assert!(declarations("border-spacing: -1px").is_empty());
+
+        // Complete runs of longhands serialize as the shorthand again
+        let stylesheet = parse("p { border: 2px solid red; border-left: none; padding: 1px 2px 1px; margin-top: 1px; }");
+        assert_eq!(
+            stylesheet.to_string(),
+            "p { border: 2px solid red; border-left: none; padding: 1px 2px; margin-top: 1px; }"
+        );
+        assert_eq!(parse("a { border: thick dotted; }").minify(), "a{border:thick dotted}");
+        assert_eq!(parse("table { border-spacing: 2px 2px; }").to_string(), "table { border-spacing: 2px; }");
+    }
+
+    #[test]
+    fn test_flex_shorthands() {
+        assert_eq!(declarations("flex: 1"), ["flex-grow: 1;", "flex-shrink: 1;", "flex-basis: 0%;"]);
+        assert_eq!(declarations("flex: none")[1..], ["flex-shrink: 0;", "flex-basis: auto;"]);
+        assert_eq!(declarations("flex: 10em 2")[..], ["flex-grow: 2;", "flex-shrink: 1;", "flex-basis: 10em;"]);
+        assert!(declarations("flex: 1 10px 2").is_empty());
+        assert_eq!(declarations("flex-flow: wrap column"), ["flex-direction: column;", "flex-wrap: wrap;"]);
+        assert!(declarations("flex-flow: row column").is_empty());
+        assert_eq!(declarations("gap: 1rem 5%"), ["row-gap: 1rem;", "column-gap: 5%;"]);
+        assert!(declarations("gap: 1").is_empty());
+
+        let stylesheet = parse("a { flex: auto; flex-flow: row wrap; gap: 4px; }");
+        assert_eq!(stylesheet.to_string(), "a { flex: 1 1 auto; flex-flow: wrap; gap: 4px; }");
+    }
+
+    #[test]
+    fn test_shorthand_with_variables() {
+        let stylesheet = parse("p { margin: var(--gap) 0; color: red; }");
+        let rule = &stylesheet.rules[0];
+        assert_eq!(rule.declarations.len(), 5);
+        assert!(matches!(rule.declarations[2].value, Value::ShorthandWithVariables(ref name, _) if name == "margin"));
+        // The longhands serialize back as the shorthand
+        assert_eq!(stylesheet.to_string(), "p { margin: var(--gap) 0; color: red; }");
+        assert_eq!(parse(&stylesheet.minify()), stylesheet);
+
+        let expanded = parse_shorthand("margin", "4px 0").unwrap();
+        assert_eq!(expanded[0].value, Value::Length(4.0, Unit::Px));
+        assert_eq!(expanded[3].value, Value::Number(0.0));
+    }
+}
@@ -0,0 +1,209 @@
+// @supports rules, evaluated against what this crate can actually parse
+use crate::font_face::SUPPORTED_FORMATS;
+use crate::{contains_variables, properties, shorthand, split_important, tokenize, Parser};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SupportsCondition {
+    Not(Box<SupportsCondition>),
+    And(Vec<SupportsCondition>),
+    Or(Vec<SupportsCondition>),
+    /// `(property: value)`
+    Declaration(String, String),
+    /// `selector(...)`
+    Selector(String),
+    /// `font-format(...)`
+    FontFormat(String),
+    /// Anything else in parentheses or a function, such as `font-tech()`, which is false.
+    /// Holds the source text so the condition can be serialized.
+    Unknown(String),
+}
+
+impl SupportsCondition {
+    pub fn evaluate(&self) -> bool {
+        match *self {
+            SupportsCondition::Not(ref inner) => !inner.evaluate(),
+            SupportsCondition::And(ref conditions) => conditions.iter().all(SupportsCondition::evaluate),
+            SupportsCondition::Or(ref conditions) => conditions.iter().any(SupportsCondition::evaluate),
+            SupportsCondition::Declaration(ref name, ref value) => supports_declaration(name, value),
+            SupportsCondition::Selector(ref selector) => Parser::new(selector.trim()).parse_selectors(None).is_some(),
+            SupportsCondition::FontFormat(ref format) => SUPPORTED_FORMATS.contains(&&*format.to_ascii_lowercase()),
+            SupportsCondition::Unknown(_) => false,
+        }
+    }
+}
+
+/// Whether a declaration of `name` with `value` would be kept by the parser and used: the
+/// property is a longhand the engine computes or a shorthand for them, and the value is
+/// valid for each longhand.
+fn supports_declaration(name: &str, value: &str) -> bool {
+    if name.starts_with("--") {
+        return true;
+    }
+    let (value, _) = split_important(value);
+    // A value with `var()` can't be checked until the variables are known
+    if contains_variables(&tokenize(value)) {
+        let names = shorthand::longhands(name).unwrap_or_else(|| vec![name.to_string()]);
+        return names.iter().all(|name| properties::longhand_named(name).is_some());
+    }
+    properties::parse_declarations(name, value).is_some()
+}
+
+impl Parser {
+    /// Parse the prelude of an `@supports` rule. Returns `None` for a syntax error, in
+    /// which case the rule is ignored.
+    pub(crate) fn parse_supports_condition(prelude: &str) -> Option<SupportsCondition> {
+        let mut parser = Parser::new(prelude);
+        let condition = parser.parse_supports()?;
+        parser.consume_whitespace();
+        parser.eof().then_some(condition)
+    }
+
+    fn parse_supports(&mut self) -> Option<SupportsCondition> {
+        self.consume_whitespace();
+        if self.consume_keyword("not") {
+            return Some(SupportsCondition::Not(Box::new(self.parse_supports_in_parens()?)));
+        }
+
+        let mut conditions = vec![self.parse_supports_in_parens()?];
+        let mut combinator = None;
+        loop {
+            self.consume_whitespace();
+            let keyword = if self.consume_keyword("and") {
+                "and"
+            } else if self.consume_keyword("or") {
+                "or"
+            } else {
+                break;
+            };
+            // `and` and `or` can't be mixed at the same level
+            if combinator.is_some_and(|c| c != keyword) {
+                return None;
+            }
+            combinator = Some(keyword);
+            conditions.push(self.parse_supports_in_parens()?);
+        }
+
+        Some(match combinator {
+            None => conditions.pop().unwrap(),
+            Some("and") => SupportsCondition::And(conditions),
+            Some(_) => SupportsCondition::Or(conditions),
+        })
+    }
+
+    fn parse_supports_in_parens(&mut self) -> Option<SupportsCondition> {
+        self.consume_whitespace();
+        let start = self.pos;
+        if self.next_char() != '(' {
+            let name = self.parse_identifier().to_ascii_lowercase();
+            if name.is_empty() || self.next_char() != '(' {
+                return None;
+            }
+            let argument_start = self.pos + 1;
+            self.skip_balanced();
+            let argument = self.input[argument_start..self.pos - 1].trim().to_string();
+            return Some(match &*name {
+                "selector" => SupportsCondition::Selector(argument),
+                "font-format" => SupportsCondition::FontFormat(argument),
+                _ => SupportsCondition::Unknown(self.input[start..self.pos].to_string()),
+            });
+        }
+
+        self.consume_char(); // consume '('
+        self.consume_whitespace();
+        let condition = if self.next_char() == '(' || self.peek_keyword("not") {
+            self.parse_supports()
+        } else {
+            self.parse_supports_declaration()
+        };
+        self.consume_whitespace();
+        if let Some(condition) = condition {
+            if self.next_char() == ')' {
+                self.consume_char();
+                return Some(condition);
+            }
+        }
+
+        self.pos = start;
+        self.skip_balanced();
+        Some(SupportsCondition::Unknown(self.input[start..self.pos].to_string()))
+    }
+
+    /// Parse `property: value` up to the closing parenthesis.
+    fn parse_supports_declaration(&mut self) -> Option<SupportsCondition> {
+        let name = self.parse_identifier();
+        self.consume_whitespace();
+        if name.is_empty() || self.consume_char() != ':' {
+            return None;
+        }
+        let start = self.pos;
+        while !self.eof() && self.next_char() != ')' {
+            match self.next_char() {
+                '(' | '[' | '{' | '"' | '\'' => self.skip_balanced(),
+                _ => {
+                    self.consume_char();
+                }
+            }
+        }
+        let value = self.input[start..self.pos].trim().to_string();
+        if value.is_empty() {
+            return None;
+        }
+        Some(SupportsCondition::Declaration(name, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supports(prelude: &str) -> Option<bool> {
+        Parser::parse_supports_condition(prelude).map(|condition| condition.evaluate())
+    }
+
+    #[test]
+    fn test_supports_conditions() {
+        assert_eq!(supports("(color: red)"), Some(true));
+        assert_eq!(supports("(font-size: 12furlongs)"), Some(false));
+        assert_eq!(supports("(backdrop-filter: blur(4px))"), Some(false));
+        assert_eq!(supports("not (backdrop-filter: blur(4px)) and (--x: 1)"), None);
+        assert_eq!(supports("not ((backdrop-filter: blur(4px)) or (width: calc(1px * 2px)))"), Some(true));
+        assert_eq!(supports("(display: block) and (--accent: { weird })"), Some(true));
+        assert_eq!(supports("selector(p.note) and font-format(woff2)"), Some(true));
+        assert_eq!(supports("selector(a:hover) or font-tech(color-COLRv1)"), Some(false));
+        assert_eq!(supports("selector(nav > a:is(.x, .y))"), Some(true));
+        // Unrecognised syntax in parentheses is false rather than an error
+        assert_eq!(supports("(color red) or (color: blue)"), Some(true));
+        assert_eq!(supports("color: red"), None);
+    }
+
+    #[test]
+    fn test_supports_declaration_values() {
+        // Values are checked against the property's keywords and value types
+        assert_eq!(supports("(color: banana)"), Some(false));
+        assert_eq!(supports("(display: frobnicate)"), Some(false));
+        assert_eq!(supports("(width: red)"), Some(false));
+        assert_eq!(supports("(width: 10px 20px)"), Some(false));
+        assert_eq!(supports("(padding-top: -1px)"), Some(false));
+        assert_eq!(supports("(z-index: 1.5)"), Some(false));
+        assert_eq!(supports("(transition-duration: 1s, fast)"), Some(false));
+        assert_eq!(supports("(margin: 1px auto blue)"), Some(false));
+        assert_eq!(supports("(cursor: pointer)"), Some(false));
+
+        assert_eq!(supports("(color: currentColor) and (background-color: rgb(0, 0, 0))"), Some(true));
+        assert_eq!(supports("(display: inline-grid) and (position: sticky)"), Some(true));
+        assert_eq!(supports("(width: calc(50% - 1em)) and (margin-left: -2px)"), Some(true));
+        assert_eq!(supports("(width: inherit) and (z-index: -3)"), Some(true));
+        assert_eq!(supports("(animation-name: spin, none) and (transition-timing-function: steps(2), ease-in)"), Some(true));
+        assert_eq!(supports("(font-family: \"Helvetica Neue\", sans-serif)"), Some(true));
+        assert_eq!(supports("(grid-template-columns: repeat(2, 1fr)) and (grid-area: a / b)"), Some(true));
+        assert_eq!(supports("(border: 1px solid red) and (flex: 1 1 0%)"), Some(true));
+        assert_eq!(supports("(width: var(--w))"), Some(true));
+        assert_eq!(supports("(color: red !important)"), Some(true));
+
+        // The parser drops the declarations `@supports` rejects
+        let stylesheet = crate::parse("p { color: red; color: banana; color: red blue; width: 10px 20px; margin: 1px auto blue; }");
+        let declarations = &stylesheet.rules[0].declarations;
+        assert_eq!(declarations.len(), 1);
+        assert_eq!(declarations[0].value, crate::Value::Keyword("red".to_string()));
+    }
+}
@@ -0,0 +1,200 @@
+// CSS tokens, used where a value has to be kept as a token stream rather than parsed
+use crate::{valid_identifier_char, Parser};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Ident(String),
+    /// A function name; the `(` is part of the token
+    Function(String),
+    AtKeyword(String),
+    Hash(String),
+    QuotedString(String),
+    Url(String),
+    Number(f32),
+    Percentage(f32),
+    Dimension(f32, String),
+    Whitespace,
+    Delim(char),
+    Comma,
+    Colon,
+    Semicolon,
+    OpenParen,
+    CloseParen,
+    OpenBracket,
+    CloseBracket,
+    OpenBrace,
+    CloseBrace,
+}
+
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut parser = Parser::new(source);
+    let mut tokens = Vec::new();
+    while let Some(token) = parser.next_token() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Serialize tokens back to source text.
+pub fn serialize_tokens(tokens: &[Token]) -> String {
+    tokens.iter().map(Token::to_string).collect()
+}
+
+/// Strip leading and trailing whitespace tokens.
+pub fn trim_tokens(tokens: &[Token]) -> &[Token] {
+    let start = tokens.iter().position(|t| *t != Token::Whitespace).unwrap_or(tokens.len());
+    let end = tokens.iter().rposition(|t| *t != Token::Whitespace).map_or(start, |i| i + 1);
+    &tokens[start..end]
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Ident(ref name) => write!(f, "{}", name),
+            Token::Function(ref name) => write!(f, "{}(", name),
+            Token::AtKeyword(ref name) => write!(f, "@{}", name),
+            Token::Hash(ref name) => write!(f, "#{}", name),
+            Token::QuotedString(ref s) => {
+                write!(f, "\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            Token::Url(ref url) => write!(f, "url({})", url),
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Percentage(n) => write!(f, "{}%", n),
+            Token::Dimension(n, ref unit) => write!(f, "{}{}", n, unit),
+            Token::Whitespace => write!(f, " "),
+            Token::Delim(c) => write!(f, "{}", c),
+            Token::Comma => write!(f, ","),
+            Token::Colon => write!(f, ":"),
+            Token::Semicolon => write!(f, ";"),
+            Token::OpenParen => write!(f, "("),
+            Token::CloseParen => write!(f, ")"),
+            Token::OpenBracket => write!(f, "["),
+            Token::CloseBracket => write!(f, "]"),
+            Token::OpenBrace => write!(f, "{{"),
+            Token::CloseBrace => write!(f, "}}"),
+        }
+    }
+}
+
+impl Parser {
+    fn next_token(&mut self) -> Option<Token> {
+        let start = self.pos;
+        self.consume_whitespace();
+        if self.pos != start && self.input[start..self.pos].chars().any(char::is_whitespace) {
+            return Some(Token::Whitespace);
+        }
+        if self.eof() {
+            return None;
+        }
+
+        let c = self.next_char();
+        if self.starts_number() {
+            let number = self.parse_number();
+            if self.next_char() == '%' {
+                self.consume_char();
+                return Some(Token::Percentage(number));
+            }
+            if self.starts_identifier() {
+                return Some(Token::Dimension(number, self.parse_identifier()));
+            }
+            return Some(Token::Number(number));
+        }
+        if self.starts_identifier() {
+            let name = self.parse_identifier();
+            if self.next_char() != '(' {
+                return Some(Token::Ident(name));
+            }
+            self.consume_char(); // consume '('
+            if name.eq_ignore_ascii_case("url") {
+                let start = self.pos;
+                self.consume_while(char::is_whitespace);
+                if !matches!(self.next_char(), '"' | '\'') {
+                    let url = self.consume_while(|c| c != ')').trim().to_string();
+                    self.consume_char(); // consume ')'
+                    return Some(Token::Url(url));
+                }
+                self.pos = start;
+            }
+            return Some(Token::Function(name));
+        }
+
+        self.consume_char();
+        Some(match c {
+            '"' | '\'' => {
+                self.pos -= 1;
+                Token::QuotedString(self.parse_string())
+            }
+            '#' if valid_identifier_char(self.next_char()) => Token::Hash(self.parse_identifier()),
+            '@' if self.starts_identifier() => Token::AtKeyword(self.parse_identifier()),
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            ';' => Token::Semicolon,
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            '{' => Token::OpenBrace,
+            '}' => Token::CloseBrace,
+            c => Token::Delim(c),
+        })
+    }
+
+    /// Whether the input starts with a number, optionally signed.
+    pub(crate) fn starts_number(&self) -> bool {
+        let mut chars = self.input[self.pos..].chars();
+        let mut c = chars.next();
+        if matches!(c, Some('+' | '-')) {
+            c = chars.next();
+        }
+        match c {
+            Some('0'..='9') => true,
+            Some('.') => matches!(chars.next(), Some('0'..='9')),
+            _ => false,
+        }
+    }
+
+    fn starts_identifier(&self) -> bool {
+        let mut chars = self.input[self.pos..].chars();
+        match chars.next() {
+            Some('-') => matches!(chars.next(), Some('a'..='z' | 'A'..='Z' | '_' | '-')),
+            Some(c) => c.is_ascii_alphabetic() || c == '_',
+            None => false,
+        }
+    }
+
+    /// Parse an optionally signed number with a fractional part.
+    pub(crate) fn parse_number(&mut self) -> f32 {
+        let start = self.pos;
+        if matches!(self.next_char(), '+' | '-') {
+            self.consume_char();
+        }
+        self.consume_while(|c| c.is_ascii_digit());
+        if self.next_char() == '.' && self.input[self.pos + 1..].starts_with(|c: char| c.is_ascii_digit()) {
+            self.consume_char();
+            self.consume_while(|c| c.is_ascii_digit());
+        }
+        self.input[start..self.pos].parse().unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("0 1px 3px rgba(0, 0, 0, .1), url(a.png) /* x */ -apple-system 50%");
+        assert_eq!(tokens[0], Token::Number(0.0));
+        assert_eq!(tokens[2], Token::Dimension(1.0, "px".to_string()));
+        assert_eq!(tokens[6], Token::Function("rgba".to_string()));
+        assert_eq!(tokens[16], Token::Number(0.1));
+        assert!(tokens.contains(&Token::Url("a.png".to_string())));
+        assert!(tokens.contains(&Token::Ident("-apple-system".to_string())));
+        assert_eq!(tokens.last(), Some(&Token::Percentage(50.0)));
+        assert_eq!(
+            serialize_tokens(&tokenize("'Stack Sans', system-ui")),
+            "\"Stack Sans\", system-ui"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+// Custom properties and var() substitution
+use crate::tokenizer::{trim_tokens, Token};
+use std::collections::{HashMap, HashSet};
+
+/// The computed custom properties of an element, by name (including the leading `--`).
+pub type CustomProperties = HashMap<String, Vec<Token>>;
+
+/// Compute an element's custom properties from those it inherits and those specified on it.
+/// References to other custom properties are substituted. Properties caught in a reference
+/// cycle, set to `initial`, or invalid at computed-value time because they refer to a
+/// missing property without a fallback, become guaranteed-invalid, which removes them.
+pub fn compute_custom_properties(
+    inherited: &CustomProperties,
+    specified: &HashMap<String, Vec<Token>>,
+) -> CustomProperties {
+    let mut computed = inherited.clone();
+    let mut resolver = Resolver {
+        inherited,
+        specified,
+        resolved: HashMap::new(),
+        stack: Vec::new(),
+        cyclic: HashSet::new(),
+    };
+    for name in specified.keys() {
+        match resolver.resolve(name) {
+            Some(value) => computed.insert(name.clone(), value),
+            None => computed.remove(name),
+        };
+    }
+    computed
+}
+
+/// Substitute every `var()` in `tokens`. Returns `None` if a referenced property is
+/// missing and has no fallback, making the declaration invalid at computed-value time.
+pub fn substitute_variables(tokens: &[Token], custom: &CustomProperties) -> Option<Vec<Token>> {
+    substitute(tokens, &mut |name| custom.get(name).cloned())
+}
+
+/// Whether `tokens` contain a `var()` reference.
+pub fn contains_variables(tokens: &[Token]) -> bool {
+    tokens
+        .iter()
+        .any(|token| matches!(token, Token::Function(name) if name.eq_ignore_ascii_case("var")))
+}
+
+struct Resolver<'a> {
+    inherited: &'a CustomProperties,
+    specified: &'a HashMap<String, Vec<Token>>,
+    resolved: HashMap<String, Option<Vec<Token>>>,
+    /// Properties currently being resolved, innermost last
+    stack: Vec<String>,
+    cyclic: HashSet<String>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, name: &str) -> Option<Vec<Token>> {
+        if let Some(value) = self.resolved.get(name) {
+            return value.clone();
+        }
+        let Some(specified) = self.specified.get(name) else {
+            return self.inherited.get(name).cloned();
+        };
+        if let Some(start) = self.stack.iter().position(|entry| entry == name) {
+            // Every property on the cycle is invalid
+            self.cyclic.extend(self.stack[start..].iter().cloned());
+            return None;
+        }
+
+        self.stack.push(name.to_string());
+        let value = match trim_tokens(specified) {
+            [Token::Ident(keyword)] if keyword.eq_ignore_ascii_case("initial") => None,
+            [Token::Ident(keyword)]
+                if keyword.eq_ignore_ascii_case("inherit") || keyword.eq_ignore_ascii_case("unset") =>
+            {
+                self.inherited.get(name).cloned()
+            }
+            _ => substitute(specified, &mut |reference| self.resolve(reference)),
+        };
+        self.stack.pop();
+
+        // Unregistered custom properties that are invalid at computed-value time don't
+        // inherit instead, per CSS Variables 1 §3.1
+        let value = if self.cyclic.contains(name) { None } else { value };
+        self.resolved.insert(name.to_string(), value.clone());
+        value
+    }
+}
+
+fn substitute(tokens: &[Token], lookup: &mut dyn FnMut(&str) -> Option<Vec<Token>>) -> Option<Vec<Token>> {
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i] {
+            Token::Function(ref name) if name.eq_ignore_ascii_case("var") => {
+                let end = matching_close_paren(tokens, i + 1);
+                let arguments = &tokens[i + 1..end];
+                result.extend(substitute_var(arguments, lookup)?);
+                i = end + 1;
+            }
+            ref token => {
+                result.push(token.clone());
+                i += 1;
+            }
+        }
+    }
+    Some(result)
+}
+
+/// Substitute a single `var(<name> [, <fallback>]?)` given its arguments.
+fn substitute_var(arguments: &[Token], lookup: &mut dyn FnMut(&str) -> Option<Vec<Token>>) -> Option<Vec<Token>> {
+    let arguments = trim_tokens(arguments);
+    let Some(Token::Ident(name)) = arguments.first() else {
+        return None;
+    };
+    if !name.starts_with("--") {
+        return None;
+    }
+    if let Some(value) = lookup(name) {
+        return Some(value);
+    }
+    match trim_tokens(&arguments[1..]) {
+        [Token::Comma, fallback @ ..] => substitute(trim_tokens(fallback), lookup),
+        _ => None,
+    }
+}
+
+/// Find the `)` closing the block that starts at `start`, or the end of input.
+fn matching_close_paren(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        match token {
+            Token::Function(_) | Token::OpenParen => depth += 1,
+            Token::CloseParen if depth == 0 => return i,
+            Token::CloseParen => depth -= 1,
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::{serialize_tokens, tokenize};
+
+    fn specified(declarations: &[(&str, &str)]) -> HashMap<String, Vec<Token>> {
+        declarations
+            .iter()
+            .map(|(name, value)| (name.to_string(), tokenize(value)))
+            .collect()
+    }
+
+    #[test]
+    fn test_substitution_and_fallbacks() {
+        let custom = compute_custom_properties(
+            &CustomProperties::new(),
+            &specified(&[("--gap", "4px"), ("--border", "1px solid var(--color, #d0d0d0)")]),
+        );
+        assert_eq!(serialize_tokens(&custom["--border"]), "1px solid #d0d0d0");
+
+        let tokens = tokenize("var(--gap) var(--missing, var(--also-missing, 2px))");
+        let result = substitute_variables(&tokens, &custom).unwrap();
+        assert_eq!(serialize_tokens(&result), "4px 2px");
+        assert_eq!(substitute_variables(&tokenize("var(--missing)"), &custom), None);
+    }
+
+    #[test]
+    fn test_cycles_and_inheritance() {
+        let parent = compute_custom_properties(
+            &CustomProperties::new(),
+            &specified(&[("--a", "red"), ("--b", "blue"), ("--e", "black")]),
+        );
+        let custom = compute_custom_properties(
+            &parent,
+            &specified(&[
+                ("--a", "var(--c)"),
+                ("--c", "var(--a)"),
+                ("--d", "var(--a, green)"),
+                ("--b", "initial"),
+                ("--e", "var(--missing)"),
+            ]),
+        );
+        assert!(!custom.contains_key("--a"));
+        assert!(!custom.contains_key("--c"));
+        // A reference to a cyclic property uses its fallback
+        assert_eq!(serialize_tokens(&custom["--d"]), "green");
+        assert!(!custom.contains_key("--b"));
+        // Invalid at computed-value time, which makes it guaranteed-invalid rather than
+        // inherited
+        assert!(!custom.contains_key("--e"));
+        assert_eq!(parent.len(), 3);
+    }
+}
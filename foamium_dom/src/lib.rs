@@ -200,9 +200,7 @@ pub fn parse_html_simple(html: &str) -> DomNode {
                 in_tag = false;
             }
             _ => {
-                if !in_tag && !in_script && !in_style {
-                    current_text.push(c);
-                } else if in_tag {
+                if in_tag || (!in_script && !in_style) {
                     current_text.push(c);
                 }
             }
@@ -0,0 +1,53 @@
+// Style resolution over a large synthetic document
+//
+// Run with `cargo bench -p foamium_layout`.
+use criterion::{criterion_group, criterion_main, Criterion};
+use foamium_dom::parse_html;
+use foamium_layout::build_layout_tree;
+
+/// Sections of articles of paragraphs, with a spread of ids and classes, about 9,000 elements in all.
+fn document() -> String {
+    let mut html = String::new();
+    for section in 0..50 {
+        html.push_str(&format!("<section id=\"s{section}\" class=\"section theme-{}\">", section % 5));
+        for article in 0..10 {
+            html.push_str(&format!("<article class=\"card card-{article}\"><h2 class=\"title\">Title</h2>"));
+            for paragraph in 0..8 {
+                html.push_str(&format!("<p class=\"text note-{paragraph}\"><a class=\"link\">Link</a></p>"));
+            }
+            html.push_str("</article>");
+        }
+        html.push_str("</section>");
+    }
+    html
+}
+
+/// About 1,000 rules, most of which match nothing, as is typical of real sites.
+fn stylesheet() -> String {
+    let mut css = String::new();
+    for i in 0..200 {
+        css.push_str(&format!(".unused-{i} {{ color: #010101; }}\n"));
+        css.push_str(&format!("#missing-{i} p {{ color: #020202; }}\n"));
+        css.push_str(&format!(".sidebar-{i} .link {{ color: #030303; }}\n"));
+        css.push_str(&format!("nav > .item-{i} {{ color: #040404; }}\n"));
+    }
+    for i in 0..50 {
+        css.push_str(&format!("#s{i} .title {{ font-size: 20px; }}\n"));
+        css.push_str(&format!(".theme-{} .card-{} p {{ color: #050505; }}\n", i % 5, i % 10));
+        css.push_str(&format!(".note-{} + p {{ background-color: #060606; }}\n", i % 8));
+        css.push_str(&format!("article > h2 ~ .note-{} a {{ color: #070707; }}\n", i % 8));
+    }
+    css.push_str("section { display: block; } p { display: block; } a { color: #0000ff; } * { font-size: 16px; }\n");
+    css
+}
+
+fn bench_style(c: &mut Criterion) {
+    let dom = parse_html(&document());
+    let stylesheet = foamium_css::parse(&stylesheet());
+    c.bench_function("style 9k elements, 1k rules", |b| {
+        b.iter(|| build_layout_tree(&dom, &stylesheet))
+    });
+}
+
+criterion_group!(benches, bench_style);
+criterion_main!(benches);
@@ -0,0 +1,643 @@
+// Transitions and CSS animations, driven by a caller-supplied clock
+use crate::{ComputedStyle, LayoutBox, LengthContext, PropertyMap, compute_style, resolve_variables};
+use foamium_css::{Color, CustomProperties, EasingFunction, Keyframes, MediaEnvironment, Stylesheet, Value};
+use foamium_dom::{DomNode, NodeType};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+
+/// A source of the current time in seconds. Animations only move when the clock does,
+/// so tests and offscreen rendering can step time deterministically.
+pub trait Clock {
+    fn now(&self) -> f32;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> f32 {
+        (**self).now()
+    }
+}
+
+/// A clock that only advances when told to.
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    time: Cell<f32>,
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock::default()
+    }
+
+    pub fn advance(&self, seconds: f32) {
+        self.time.set(self.time.get() + seconds);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> f32 {
+        self.time.get()
+    }
+}
+
+/// One transition from the `transition-*` properties.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    /// The property to transition, or `all`
+    pub property: String,
+    /// In seconds, as is the delay
+    pub duration: f32,
+    pub delay: f32,
+    pub timing_function: EasingFunction,
+}
+
+/// One animation from the `animation-*` properties.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    /// The name of its `@keyframes` rule
+    pub name: String,
+    /// In seconds, as is the delay
+    pub duration: f32,
+    pub delay: f32,
+    pub timing_function: EasingFunction,
+    /// Infinite for `infinite`
+    pub iteration_count: f32,
+    pub direction: AnimationDirection,
+    pub fill_mode: FillMode,
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationDirection {
+    Normal,
+    Reverse,
+    Alternate,
+    AlternateReverse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FillMode {
+    None,
+    Forwards,
+    Backwards,
+    Both,
+}
+
+/// The item at `index` of a list-valued property. Lists shorter than the number of
+/// transitions or animations repeat.
+fn list_item<'v>(values: &'v PropertyMap, name: &str, index: usize) -> Option<&'v Value> {
+    let items = values.get(name)?.items();
+    items.get(index % items.len())
+}
+
+fn keyword(value: Option<&Value>) -> Option<String> {
+    match value {
+        Some(Value::Keyword(keyword)) => Some(keyword.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+fn time(value: Option<&Value>) -> Option<f32> {
+    match value {
+        Some(&Value::Time(seconds)) if seconds >= 0.0 => Some(seconds),
+        Some(&Value::Number(0.0)) => Some(0.0),
+        _ => None,
+    }
+}
+
+/// A negative delay starts part way through.
+fn delay(value: Option<&Value>) -> f32 {
+    match value {
+        Some(&Value::Time(seconds)) => seconds,
+        _ => 0.0,
+    }
+}
+
+fn timing_function(value: Option<&Value>) -> EasingFunction {
+    value.and_then(EasingFunction::from_value).unwrap_or(EasingFunction::EASE)
+}
+
+/// The transitions described by an element's specified values. There is one for each
+/// item of `transition-property`, which defaults to `all`.
+pub(crate) fn compute_transitions(values: &PropertyMap) -> Vec<Transition> {
+    let properties: Vec<String> = match values.get("transition-property") {
+        Some(value) => value.items().iter().filter_map(|item| keyword(Some(item))).collect(),
+        None => vec!["all".to_string()],
+    };
+    if properties.iter().any(|property| property == "none") {
+        return Vec::new();
+    }
+    properties
+        .into_iter()
+        .enumerate()
+        .map(|(i, property)| Transition {
+            property,
+            duration: time(list_item(values, "transition-duration", i)).unwrap_or(0.0),
+            delay: delay(list_item(values, "transition-delay", i)),
+            timing_function: timing_function(list_item(values, "transition-timing-function", i)),
+        })
+        .collect()
+}
+
+/// The animations described by an element's specified values, one for each name in
+/// `animation-name` other than `none`.
+pub(crate) fn compute_animations(values: &PropertyMap) -> Vec<Animation> {
+    let Some(names) = values.get("animation-name") else {
+        return Vec::new();
+    };
+    names
+        .items()
+        .iter()
+        .enumerate()
+        .filter_map(|(i, name)| {
+            let Value::Keyword(name) = name else {
+                return None;
+            };
+            if name.is_empty() || name.eq_ignore_ascii_case("none") {
+                return None;
+            }
+            let iteration_count = match list_item(values, "animation-iteration-count", i) {
+                Some(&Value::Number(count)) if count >= 0.0 => count,
+                Some(Value::Keyword(keyword)) if keyword.eq_ignore_ascii_case("infinite") => f32::INFINITY,
+                _ => 1.0,
+            };
+            let direction = match keyword(list_item(values, "animation-direction", i)).as_deref() {
+                Some("reverse") => AnimationDirection::Reverse,
+                Some("alternate") => AnimationDirection::Alternate,
+                Some("alternate-reverse") => AnimationDirection::AlternateReverse,
+                _ => AnimationDirection::Normal,
+            };
+            let fill_mode = match keyword(list_item(values, "animation-fill-mode", i)).as_deref() {
+                Some("forwards") => FillMode::Forwards,
+                Some("backwards") => FillMode::Backwards,
+                Some("both") => FillMode::Both,
+                _ => FillMode::None,
+            };
+            Some(Animation {
+                name: name.clone(),
+                duration: time(list_item(values, "animation-duration", i)).unwrap_or(0.0),
+                delay: delay(list_item(values, "animation-delay", i)),
+                timing_function: timing_function(list_item(values, "animation-timing-function", i)),
+                iteration_count,
+                direction,
+                fill_mode,
+                paused: keyword(list_item(values, "animation-play-state", i)).as_deref() == Some("paused"),
+            })
+        })
+        .collect()
+}
+
+/// The properties that can be animated, in the order of `AnimatedValues`.
+const PROPERTIES: [&str; 3] = ["color", "background-color", "font-size"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AnimatedValue {
+    Color(Option<Color>),
+    Length(f32),
+}
+
+type AnimatedValues = [AnimatedValue; 3];
+
+fn animated_values(style: &ComputedStyle) -> AnimatedValues {
+    [
+        AnimatedValue::Color(style.color),
+        AnimatedValue::Color(style.background_color),
+        AnimatedValue::Length(style.font_size),
+    ]
+}
+
+fn set_animated_values(style: &mut ComputedStyle, values: AnimatedValues) {
+    for (property, value) in PROPERTIES.iter().zip(values) {
+        match (*property, value) {
+            ("color", AnimatedValue::Color(color)) => style.color = color,
+            ("background-color", AnimatedValue::Color(color)) => style.background_color = color,
+            ("font-size", AnimatedValue::Length(size)) => style.font_size = size.max(0.0),
+            _ => {}
+        }
+    }
+}
+
+fn interpolate(from: AnimatedValue, to: AnimatedValue, t: f32) -> AnimatedValue {
+    match (from, to) {
+        (AnimatedValue::Length(a), AnimatedValue::Length(b)) => AnimatedValue::Length(a + (b - a) * t),
+        (AnimatedValue::Color(Some(a)), AnimatedValue::Color(Some(b))) => {
+            AnimatedValue::Color(Some(interpolate_color(a, b, t)))
+        }
+        // Values that can't be interpolated flip halfway through
+        _ if t < 0.5 => from,
+        _ => to,
+    }
+}
+
+/// Interpolate with premultiplied alpha, so fading to `transparent` doesn't pass
+/// through black.
+fn interpolate_color(from: Color, to: Color, t: f32) -> Color {
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    let alpha = lerp(from.a as f32, to.a as f32).clamp(0.0, 255.0);
+    let channel = |a: u8, b: u8| {
+        if alpha == 0.0 {
+            return 0;
+        }
+        let premultiplied = lerp(a as f32 * from.a as f32, b as f32 * to.a as f32);
+        (premultiplied / alpha).round().clamp(0.0, 255.0) as u8
+    };
+    Color::new(
+        channel(from.r, to.r),
+        channel(from.g, to.g),
+        channel(from.b, to.b),
+        alpha.round() as u8,
+    )
+}
+
+/// Runs transitions and animations. Keep one engine per document, and pass each newly
+/// built layout tree to `tick`: it compares the styles with those at the last tick to
+/// start transitions, then writes the animated values into the tree.
+pub struct AnimationEngine<C> {
+    clock: C,
+    /// Keyed by DOM node, which outlives any one layout tree
+    elements: HashMap<*const DomNode, ElementState>,
+}
+
+#[derive(Debug, Default)]
+struct ElementState {
+    /// The values from the cascade at the last tick
+    before_change: Option<AnimatedValues>,
+    /// The values shown at the last tick, which new transitions start from
+    displayed: Option<AnimatedValues>,
+    transitions: Vec<RunningTransition>,
+    animations: Vec<RunningAnimation>,
+}
+
+#[derive(Debug)]
+struct RunningTransition {
+    /// Index into `PROPERTIES`
+    property: usize,
+    from: AnimatedValue,
+    to: AnimatedValue,
+    /// After the delay
+    start: f32,
+    duration: f32,
+    timing_function: EasingFunction,
+}
+
+#[derive(Debug)]
+struct RunningAnimation {
+    name: String,
+    start: f32,
+    /// When the animation was paused, if it is
+    paused_at: Option<f32>,
+}
+
+/// The inputs that stay the same for a whole tick.
+struct Tick<'a> {
+    stylesheet: &'a Stylesheet,
+    env: &'a MediaEnvironment,
+    now: f32,
+}
+
+impl<C: Clock> AnimationEngine<C> {
+    pub fn new(clock: C) -> AnimationEngine<C> {
+        AnimationEngine {
+            clock,
+            elements: HashMap::new(),
+        }
+    }
+
+    /// Apply transitions and animations at the clock's current time to `root`, which
+    /// must hold the styles straight from the cascade.
+    pub fn tick(&mut self, root: &mut LayoutBox, stylesheet: &Stylesheet, env: &MediaEnvironment) {
+        let tick = Tick {
+            stylesheet,
+            env,
+            now: self.clock.now(),
+        };
+        let mut seen = HashSet::new();
+        self.animate_box(root, &tick, &ComputedStyle::initial(), &LengthContext::new(env), true, &mut seen);
+        // Elements that are gone stop their transitions and animations
+        self.elements.retain(|node, _| seen.contains(node));
+    }
+
+    fn animate_box(
+        &mut self,
+        layout_box: &mut LayoutBox,
+        tick: &Tick,
+        parent_style: &ComputedStyle,
+        parent: &LengthContext,
+        is_root: bool,
+        seen: &mut HashSet<*const DomNode>,
+    ) {
+        match layout_box.node {
+            Some(node) if matches!(node.node_type, NodeType::Element(_)) => {
+                let node: *const DomNode = node;
+                seen.insert(node);
+                self.elements.entry(node).or_default().update(&mut layout_box.style, tick, parent_style, parent);
+            }
+            // Text and anonymous boxes take their colour and font size from their parent
+            _ => {
+                layout_box.style.color = parent_style.color;
+                layout_box.style.font_size = parent.font.font_size;
+            }
+        }
+
+        let style = &layout_box.style;
+        let mut context = parent.with_font_size(style.font_size).with_line_height(style.line_height.resolve(style.font_size));
+        if is_root {
+            context.root_font = context.font;
+        }
+        for child in &mut layout_box.children {
+            self.animate_box(child, tick, &layout_box.style, &context, false, seen);
+        }
+    }
+}
+
+impl ElementState {
+    fn update(&mut self, style: &mut ComputedStyle, tick: &Tick, parent_style: &ComputedStyle, parent: &LengthContext) {
+        let now = tick.now;
+        let after_change = animated_values(style);
+        if let Some(before_change) = self.before_change {
+            self.start_transitions(style, before_change, after_change, now);
+        }
+        self.before_change = Some(after_change);
+        self.transitions.retain(|transition| now < transition.start + transition.duration);
+        self.update_animations(style, now);
+
+        let mut values = after_change;
+        for (animation, running) in style.animations.iter().zip(&self.animations) {
+            let Some(keyframes) = tick.stylesheet.find_keyframes(&animation.name, tick.env) else {
+                continue;
+            };
+            let elapsed = running.paused_at.unwrap_or(now) - running.start;
+            let Some(progress) = iteration_progress(animation, elapsed) else {
+                continue;
+            };
+            let frames = resolve_keyframes(keyframes, style, parent_style, parent);
+            for (property, value) in values.iter_mut().enumerate() {
+                let underlying = after_change[property];
+                if let Some(animated) = keyframe_value(&frames, property, underlying, progress, &animation.timing_function) {
+                    *value = animated;
+                }
+            }
+        }
+        // Transitions override animations
+        for transition in &self.transitions {
+            let progress = ((now - transition.start) / transition.duration).clamp(0.0, 1.0);
+            let eased = transition.timing_function.apply(progress);
+            values[transition.property] = interpolate(transition.from, transition.to, eased);
+        }
+
+        self.displayed = Some(values);
+        set_animated_values(style, values);
+    }
+
+    /// Start a transition for each property whose value changed, from what was shown
+    /// before, replacing any transition that was running for it.
+    fn start_transitions(&mut self, style: &ComputedStyle, before: AnimatedValues, after: AnimatedValues, now: f32) {
+        for (property, name) in PROPERTIES.iter().enumerate() {
+            if before[property] == after[property] {
+                continue;
+            }
+            let from = self.displayed.map_or(before[property], |displayed| displayed[property]);
+            self.transitions.retain(|transition| transition.property != property);
+            let transition = style
+                .transitions
+                .iter()
+                .rev()
+                .find(|transition| transition.property == *name || transition.property == "all");
+            if let Some(transition) = transition.filter(|transition| transition.duration > 0.0) {
+                self.transitions.push(RunningTransition {
+                    property,
+                    from,
+                    to: after[property],
+                    start: now + transition.delay,
+                    duration: transition.duration,
+                    timing_function: transition.timing_function.clone(),
+                });
+            }
+        }
+    }
+
+    /// Match running animations up with `animation-name`. An animation keeps its start
+    /// time as long as its name stays, and pausing stops its clock.
+    fn update_animations(&mut self, style: &ComputedStyle, now: f32) {
+        let mut animations = Vec::with_capacity(style.animations.len());
+        for animation in &style.animations {
+            let mut running = match self.animations.iter().position(|running| running.name == animation.name) {
+                Some(i) => self.animations.remove(i),
+                None => RunningAnimation {
+                    name: animation.name.clone(),
+                    start: now,
+                    paused_at: None,
+                },
+            };
+            match (animation.paused, running.paused_at) {
+                (true, None) => running.paused_at = Some(now),
+                (false, Some(paused_at)) => {
+                    running.start += now - paused_at;
+                    running.paused_at = None;
+                }
+                _ => {}
+            }
+            animations.push(running);
+        }
+        self.animations = animations;
+    }
+}
+
+/// The progress through the current iteration, from 0 to 1 after applying the
+/// direction, or `None` when the animation has no effect `elapsed` seconds after it
+/// started.
+fn iteration_progress(animation: &Animation, elapsed: f32) -> Option<f32> {
+    let active = elapsed - animation.delay;
+    let count = animation.iteration_count;
+    let active_duration = if animation.duration > 0.0 { animation.duration * count } else { 0.0 };
+    let (iteration, progress) = if active < 0.0 {
+        if !matches!(animation.fill_mode, FillMode::Backwards | FillMode::Both) {
+            return None;
+        }
+        (0.0, 0.0)
+    } else if active >= active_duration {
+        if !matches!(animation.fill_mode, FillMode::Forwards | FillMode::Both) {
+            return None;
+        }
+        // Stay at the end of the last iteration
+        let end = if count.is_infinite() { 1.0 } else { count };
+        if end > 0.0 && end.fract() == 0.0 {
+            (end - 1.0, 1.0)
+        } else {
+            (end.floor(), end.fract())
+        }
+    } else {
+        let overall = active / animation.duration;
+        (overall.floor(), overall.fract())
+    };
+
+    let odd = iteration % 2.0 == 1.0;
+    let reversed = match animation.direction {
+        AnimationDirection::Normal => false,
+        AnimationDirection::Reverse => true,
+        AnimationDirection::Alternate => odd,
+        AnimationDirection::AlternateReverse => !odd,
+    };
+    Some(if reversed { 1.0 - progress } else { progress })
+}
+
+/// A keyframe with its values computed for the element.
+struct ResolvedKeyframe {
+    offset: f32,
+    values: [Option<AnimatedValue>; 3],
+    /// The keyframe's own `animation-timing-function`, for the interval it starts
+    timing_function: Option<EasingFunction>,
+}
+
+/// Compute the values in each keyframe, sorted by offset.
+fn resolve_keyframes(
+    keyframes: &Keyframes,
+    style: &ComputedStyle,
+    parent_style: &ComputedStyle,
+    parent: &LengthContext,
+) -> Vec<ResolvedKeyframe> {
+    let mut frames = Vec::new();
+    for frame in &keyframes.frames {
+        let mut declared: PropertyMap = frame
+            .declarations
+            .iter()
+            .map(|declaration| (declaration.name.clone(), declaration.value.clone()))
+            .collect();
+        resolve_variables(&mut declared, &style.custom_properties);
+        let computed = animated_values(&compute_style(&declared, CustomProperties::new(), parent_style, parent));
+        let mut values = [None; 3];
+        for (property, name) in PROPERTIES.iter().enumerate() {
+            if declared.contains_key(*name) {
+                values[property] = Some(computed[property]);
+            }
+        }
+        let timing_function = declared.get("animation-timing-function").and_then(EasingFunction::from_value);
+        for &offset in &frame.offsets {
+            frames.push(ResolvedKeyframe {
+                offset,
+                values,
+                timing_function: timing_function.clone(),
+            });
+        }
+    }
+    frames.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+    frames
+}
+
+/// The value of one property at `progress` through the keyframes, or `None` if no
+/// keyframe sets it. Missing `from` and `to` keyframes take the underlying value.
+fn keyframe_value(
+    frames: &[ResolvedKeyframe],
+    property: usize,
+    underlying: AnimatedValue,
+    progress: f32,
+    timing_function: &EasingFunction,
+) -> Option<AnimatedValue> {
+    let mut points: Vec<(f32, AnimatedValue, Option<&EasingFunction>)> = Vec::new();
+    for frame in frames {
+        let Some(value) = frame.values[property] else {
+            continue;
+        };
+        // Of several keyframes at the same offset, the last wins
+        if points.last().is_some_and(|point| point.0 == frame.offset) {
+            points.pop();
+        }
+        points.push((frame.offset, value, frame.timing_function.as_ref()));
+    }
+    if points.is_empty() {
+        return None;
+    }
+    if points[0].0 > 0.0 {
+        points.insert(0, (0.0, underlying, None));
+    }
+    if points[points.len() - 1].0 < 1.0 {
+        points.push((1.0, underlying, None));
+    }
+
+    let i = points[1..points.len() - 1].iter().take_while(|point| point.0 <= progress).count();
+    let ((start, from, easing), (end, to, _)) = (points[i], points[i + 1]);
+    let local = if end > start { (progress - start) / (end - start) } else { 1.0 };
+    Some(interpolate(from, to, easing.unwrap_or(timing_function).apply(local)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_layout_tree_with_environment;
+    use foamium_dom::parse_html;
+
+    #[test]
+    fn test_transitions() {
+        let dom = parse_html("<p>Text</p>");
+        let before = foamium_css::parse(
+            "p { color: #000000; font-size: 10px; transition-property: color, font-size;
+                 transition-duration: 1s, 2s; transition-timing-function: linear; transition-delay: 0s, 1s; }",
+        );
+        let after = foamium_css::parse(
+            "p { color: #ffffff; font-size: 30px; transition-property: color, font-size;
+                 transition-duration: 1s, 2s; transition-timing-function: linear; transition-delay: 0s, 1s; }",
+        );
+        let env = MediaEnvironment::default();
+        let clock = ManualClock::new();
+        let mut engine = AnimationEngine::new(&clock);
+        let mut frame = |stylesheet: &Stylesheet| {
+            let mut root = build_layout_tree_with_environment(&dom, stylesheet, &env);
+            engine.tick(&mut root, stylesheet, &env);
+            let p = &root.children[0];
+            (p.style.color.unwrap(), p.style.font_size)
+        };
+
+        assert_eq!(frame(&before), (Color::new(0, 0, 0, 255), 10.0));
+        clock.advance(1.0);
+        assert_eq!(frame(&after), (Color::new(0, 0, 0, 255), 10.0));
+        clock.advance(0.25);
+        assert_eq!(frame(&after), (Color::new(64, 64, 64, 255), 10.0));
+        clock.advance(1.25);
+        assert_eq!(frame(&after), (Color::new(255, 255, 255, 255), 15.0));
+        // Changing back part way reverses from where the transition had got to
+        assert_eq!(frame(&before).1, 15.0);
+        clock.advance(2.0);
+        assert_eq!(frame(&before).1, 12.5);
+    }
+
+    #[test]
+    fn test_animations() {
+        let dom = parse_html("<div><p>Text</p></div>");
+        let stylesheet = foamium_css::parse(
+            "@keyframes grow {
+                 from { font-size: 1em; background-color: #0000ff; }
+                 50% { font-size: 2em; animation-timing-function: steps(2); }
+                 to { font-size: 4em; background-color: #ff000000; }
+             }
+             div { font-size: 10px; }
+             p { animation-name: grow; animation-duration: 2s; animation-timing-function: linear;
+                 animation-iteration-count: 2; animation-direction: alternate; animation-delay: 1s; }",
+        );
+        let env = MediaEnvironment::default();
+        let clock = ManualClock::new();
+        let mut engine = AnimationEngine::new(&clock);
+        let mut frame = || {
+            let mut root = build_layout_tree_with_environment(&dom, &stylesheet, &env);
+            engine.tick(&mut root, &stylesheet, &env);
+            let p = &root.children[0].children[0];
+            (p.style.font_size, p.style.background_color)
+        };
+
+        // No effect during the delay, without `animation-fill-mode: backwards`
+        assert_eq!(frame(), (10.0, None));
+        clock.advance(1.5);
+        let (size, background) = frame();
+        assert_eq!(size, 15.0);
+        // Premultiplied interpolation keeps the blue while fading out
+        assert_eq!(background, Some(Color::new(0, 0, 255, 191)));
+        // The second half steps rather than growing smoothly
+        clock.advance(1.0);
+        assert_eq!(frame().0, 30.0);
+        // The second iteration runs backwards
+        clock.advance(1.0);
+        assert_eq!(frame().0, 30.0);
+        clock.advance(1.25);
+        assert_eq!(frame().0, 12.5);
+        clock.advance(1.0);
+        assert_eq!(frame(), (10.0, None));
+    }
+}
@@ -0,0 +1,724 @@
+// Block layout, per CSS 2.1: widths (§10.3.3), heights (§10.6.3) and collapsing
+// margins (§8.3.1)
+//
+// Boxes are first placed relative to their parent's content box, since where a parent
+// ends up can depend on margins that collapse through it from its children. A second
+// pass then turns the positions into page coordinates.
+use crate::{flex, grid, table};
+use crate::floats::{FloatContext, is_float, layout_float};
+use crate::inline::{is_inline, layout_inline};
+use crate::intrinsic::shrink_to_fit;
+use crate::overflow::{self, is_scroll_container};
+use crate::position::{self, is_out_of_flow, place_in_inline_run};
+use crate::{Clear, Display, EdgeSizes, FixedWidthMeasurer, LayoutBox, LengthPercentage, Rect, TextMeasurer};
+
+/// Lay out the tree in `viewport`, the initial containing block, measuring text with
+/// `FixedWidthMeasurer`.
+pub fn layout(root: &mut LayoutBox, viewport: Rect) {
+    layout_with_measurer(root, viewport, &FixedWidthMeasurer);
+}
+
+/// Lay out the tree in `viewport`, the initial containing block. The root is always
+/// laid out as a block, and its margins never collapse. Positioned boxes are placed
+/// once everything else is, and then the scroll containers' contents are scrolled.
+pub fn layout_with_measurer(root: &mut LayoutBox, viewport: Rect, measurer: &dyn TextMeasurer) {
+    let containing = ContainingBlock {
+        width: viewport.width,
+        height: Some(viewport.height),
+    };
+    let margins = layout_block(root, containing, true, &mut FloatContext::default(), (0.0, 0.0), measurer);
+    let dimensions = &mut root.dimensions;
+    dimensions.content.x = dimensions.margin.left + dimensions.border.left + dimensions.padding.left;
+    dimensions.content.y = margins.top.solve() + dimensions.border.top + dimensions.padding.top;
+    make_absolute(root, viewport.x, viewport.y);
+    position::layout_positioned(root, viewport, measurer);
+    overflow::layout_overflow(root, viewport);
+}
+
+/// The size of the box that percentages refer to. Heights aren't known until layout is
+/// done with the box's contents, unless the box's own height is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ContainingBlock {
+    pub(crate) width: f32,
+    pub(crate) height: Option<f32>,
+}
+
+/// Adjoining margins, which collapse to the largest positive one plus the most negative one.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct CollapsibleMargin {
+    positive: f32,
+    negative: f32,
+}
+
+impl CollapsibleMargin {
+    pub(crate) fn new(margin: f32) -> CollapsibleMargin {
+        CollapsibleMargin {
+            positive: margin.max(0.0),
+            negative: margin.min(0.0),
+        }
+    }
+
+    pub(crate) fn adjoin(self, other: CollapsibleMargin) -> CollapsibleMargin {
+        CollapsibleMargin {
+            positive: self.positive.max(other.positive),
+            negative: self.negative.min(other.negative),
+        }
+    }
+
+    pub(crate) fn solve(self) -> f32 {
+        self.positive + self.negative
+    }
+}
+
+/// The margins a block box presents to its parent and siblings, including any that
+/// collapsed into them from its children.
+#[derive(Debug, Clone, Copy, Default)]
+struct BlockMargins {
+    top: CollapsibleMargin,
+    bottom: CollapsibleMargin,
+    /// The box is empty, so its top and bottom margins are adjoining
+    collapses_through: bool,
+}
+
+/// What a block in normal flow was last laid out in and the margins that gave it. Laid
+/// out in the same containing block, with no floats around, a block with nothing changed
+/// inside comes out the same, so its layout is kept instead.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BlockLayout {
+    containing: ContainingBlock,
+    margins: BlockMargins,
+    /// Its contents have been moved into page coordinates since, so they aren't where
+    /// layout would put them
+    pub(crate) absolute: bool,
+    /// The layout was kept the last time the tree was laid out
+    pub(crate) kept: bool,
+}
+
+/// Lay out a block-level box and its contents. Sets its size, edges and the positions of
+/// its children; where the box itself goes is up to its parent. `origin` is where the
+/// containing block's left edge and the box's top border edge are expected to be, in the
+/// coordinates of the block formatting context's floats.
+fn layout_block(
+    layout_box: &mut LayoutBox,
+    containing: ContainingBlock,
+    is_root: bool,
+    floats: &mut FloatContext,
+    origin: (f32, f32),
+    measurer: &dyn TextMeasurer,
+) -> BlockMargins {
+    compute_width(layout_box, containing.width, measurer);
+    let independent = is_root || establishes_formatting_context(layout_box);
+    let style = &layout_box.style;
+    let resolve = |length: &LengthPercentage| length.resolve(containing.width);
+    let dimensions = &mut layout_box.dimensions;
+    dimensions.margin.top = style.margin.top.as_ref().map_or(0.0, resolve);
+    dimensions.margin.bottom = style.margin.bottom.as_ref().map_or(0.0, resolve);
+    dimensions.padding.top = resolve(&style.padding.top);
+    dimensions.padding.bottom = resolve(&style.padding.bottom);
+    dimensions.border.top = style.border_width.top;
+    dimensions.border.bottom = style.border_width.bottom;
+
+    let specified_height = style
+        .height
+        .as_ref()
+        .and_then(|height| height.resolve_against(containing.height))
+        .map(|height| {
+            let height = height - style.box_sizing_adjustment(false, containing.width);
+            style.clamp_height(height, containing.width, containing.height).max(0.0)
+        });
+    let children_containing = ContainingBlock {
+        width: dimensions.content.width,
+        height: specified_height,
+    };
+    let own_top = CollapsibleMargin::new(dimensions.margin.top);
+    let own_bottom = CollapsibleMargin::new(dimensions.margin.bottom);
+    let height_limits = (
+        style.clamp_height(0.0, containing.width, containing.height).max(0.0),
+        style.clamp_height(f32::INFINITY, containing.width, containing.height),
+    );
+    let is_table = layout_box.display == Display::TableGrid;
+    let clamp_height = |height: f32| match specified_height {
+        // A table's rows are never cut short, so its height is only a minimum
+        Some(specified) if is_table => height.max(specified),
+        Some(specified) => specified,
+        None => height.min(height_limits.1).max(height_limits.0),
+    };
+
+    // No margins collapse through the root or boxes that establish independent formatting
+    // contexts
+    if independent {
+        let content_height = layout_contents(layout_box, children_containing, height_limits, measurer);
+        layout_box.dimensions.content.height = clamp_height(content_height);
+        return BlockMargins {
+            top: own_top,
+            bottom: own_bottom,
+            collapses_through: false,
+        };
+    }
+
+    // Margins only collapse with the box's children when nothing separates them
+    let top_separated = dimensions.border.top > 0.0 || dimensions.padding.top > 0.0;
+    let bottom_separated =
+        dimensions.border.bottom > 0.0 || dimensions.padding.bottom > 0.0 || specified_height.is_some();
+    let content_origin = (
+        origin.0 + dimensions.margin.left + dimensions.border.left + dimensions.padding.left,
+        origin.1 + dimensions.border.top + dimensions.padding.top,
+    );
+    let flow = layout_flow(layout_box, children_containing, top_separated, bottom_separated, floats, content_origin, measurer);
+    let dimensions = &mut layout_box.dimensions;
+    dimensions.content.height = clamp_height(flow.height);
+
+    let (top, bottom) = (own_top.adjoin(flow.top), own_bottom.adjoin(flow.bottom));
+    let collapses_through = flow.empty
+        && dimensions.content.height == 0.0
+        && dimensions.border.top + dimensions.border.bottom + dimensions.padding.top + dimensions.padding.bottom == 0.0;
+    if collapses_through {
+        let through = top.adjoin(bottom);
+        return BlockMargins {
+            top: through,
+            bottom: through,
+            collapses_through,
+        };
+    }
+    BlockMargins {
+        top,
+        bottom,
+        collapses_through,
+    }
+}
+
+/// The children of a block container laid out in normal flow.
+struct Flow {
+    /// The height of the content, before the box's own height applies
+    height: f32,
+    /// Margins of children that collapse through the top and bottom of the box
+    top: CollapsibleMargin,
+    bottom: CollapsibleMargin,
+    /// Nothing separates the top of the content box from its bottom
+    empty: bool,
+}
+
+/// Lay out a block container's children one after another, collapsing their margins with
+/// each other and, unless separated, with the container's. `origin` is where the content
+/// box is expected to be in the coordinates of `floats`.
+fn layout_flow(
+    layout_box: &mut LayoutBox,
+    containing: ContainingBlock,
+    top_separated: bool,
+    bottom_separated: bool,
+    floats: &mut FloatContext,
+    origin: (f32, f32),
+    measurer: &dyn TextMeasurer,
+) -> Flow {
+    let mut cursor = 0.0;
+    // Margins between `cursor` and the next child that haven't been placed yet
+    let mut pending = CollapsibleMargin::default();
+    // Whether anything separates the top of the content box from the next child yet
+    let mut at_top = true;
+    let mut top = CollapsibleMargin::default();
+    let LayoutBox { style, children, lines, .. } = layout_box;
+    lines.clear();
+    let mut index = 0;
+    while index < children.len() {
+        let child = &mut children[index];
+        if child.display == Display::None {
+            index += 1;
+            continue;
+        }
+        if is_out_of_flow(child) {
+            // Left at its static position, where the next block would go
+            child.dimensions.content.x = 0.0;
+            child.dimensions.content.y = if at_top && !top_separated { 0.0 } else { cursor + pending.solve() };
+            index += 1;
+            continue;
+        }
+        if is_float(child) {
+            let top = if at_top && !top_separated { 0.0 } else { cursor + pending.solve() };
+            layout_float(child, containing.width, floats, origin, top, measurer);
+            index += 1;
+            continue;
+        }
+        if is_inline(child) {
+            // A run of inline-level boxes between blocks is laid out in lines
+            let end = index
+                + children[index..]
+                    .iter()
+                    .take_while(|child| {
+                        is_inline(child) || is_out_of_flow(child) || is_float(child) || child.display == Display::None
+                    })
+                    .count();
+            let position = if at_top && !top_separated { 0.0 } else { cursor + pending.solve() };
+            let (run_lines, height) =
+                layout_inline(&mut children[index..end], style, containing.width, position, floats, origin, measurer);
+            place_in_inline_run(&mut children[index..end], position);
+            index = end;
+            // Collapsed white space doesn't make any lines, or separate margins
+            if run_lines.is_empty() {
+                continue;
+            }
+            if at_top && !top_separated {
+                top = top.adjoin(pending);
+            }
+            lines.extend(run_lines);
+            cursor = position + height;
+            pending = CollapsibleMargin::default();
+            at_top = false;
+            continue;
+        }
+        index += 1;
+
+        // Where the top border edge goes if the margins collapse as the styles say, unless
+        // it has to go below floats
+        let mut border_top = if at_top && !top_separated {
+            0.0
+        } else {
+            cursor + pending.adjoin(estimate_top_margin(child, containing.width)).solve()
+        };
+        let cleared = floats.clearance(child.style.clear).map(|bottom| bottom - origin.1).filter(|&bottom| bottom > border_top);
+        if let Some(bottom) = cleared {
+            border_top = bottom;
+        }
+        let no_floats = floats.is_empty();
+        let kept = if no_floats { keep_layout(child, containing) } else { None };
+        let (margins, shift, lowered) = if let Some(margins) = kept {
+            (margins, 0.0, 0.0)
+        } else if establishes_formatting_context(child) {
+            layout_beside_floats(child, containing, floats, (origin.0, origin.1 + border_top), measurer)
+        } else {
+            (layout_block(child, containing, false, floats, (origin.0, origin.1 + border_top), measurer), 0.0, 0.0)
+        };
+        if kept.is_none() {
+            child.dirty.layout = true;
+            let independent = establishes_formatting_context(child);
+            child.cache = (no_floats && is_self_contained(child, independent)).then_some(BlockLayout {
+                containing,
+                margins,
+                absolute: false,
+                kept: false,
+            });
+        }
+        let has_clearance = cleared.is_some() || lowered > 0.0;
+        let child_dimensions = &mut child.dimensions;
+        let border_top = if has_clearance {
+            // Clearance separates the box's top margin from the margins above it
+            if at_top && !top_separated {
+                top = top.adjoin(pending);
+            }
+            pending = CollapsibleMargin::default();
+            at_top = false;
+            border_top + lowered
+        } else if margins.collapses_through {
+            // An empty box sits where its top margin would put it, and its margins join
+            // the ones around it
+            let position = if at_top && !top_separated {
+                0.0
+            } else {
+                cursor + pending.adjoin(margins.top).solve()
+            };
+            pending = pending.adjoin(margins.top).adjoin(margins.bottom);
+            position
+        } else if at_top && !top_separated {
+            top = top.adjoin(pending).adjoin(margins.top);
+            pending = CollapsibleMargin::default();
+            at_top = false;
+            0.0
+        } else {
+            let position = cursor + pending.adjoin(margins.top).solve();
+            pending = CollapsibleMargin::default();
+            at_top = false;
+            position
+        };
+        child_dimensions.content.x =
+            shift + child_dimensions.margin.left + child_dimensions.border.left + child_dimensions.padding.left;
+        child_dimensions.content.y = border_top + child_dimensions.border.top + child_dimensions.padding.top;
+        if has_clearance || !margins.collapses_through {
+            cursor = border_top + child_dimensions.border_box().height;
+            pending = margins.bottom;
+        }
+    }
+
+    let mut bottom = CollapsibleMargin::default();
+    let height = if at_top && !top_separated {
+        // Nothing but empty boxes: their margins all collapse into the top
+        top = top.adjoin(pending);
+        0.0
+    } else if bottom_separated {
+        cursor + pending.solve()
+    } else {
+        bottom = pending;
+        cursor
+    };
+    Flow {
+        height,
+        top,
+        bottom,
+        empty: at_top,
+    }
+}
+
+/// Lay out the contents of a box that establishes an independent formatting context and
+/// whose content width is already set, in its content box's coordinates. `containing`
+/// is the box's content box, with its height if that doesn't depend on the contents, and
+/// `height_limits` are what `min-height` and `max-height` allow. Returns the height of
+/// the contents.
+pub(crate) fn layout_contents(
+    layout_box: &mut LayoutBox,
+    containing: ContainingBlock,
+    height_limits: (f32, f32),
+    measurer: &dyn TextMeasurer,
+) -> f32 {
+    let height = match layout_box.display {
+        Display::Flex | Display::InlineFlex => flex::layout_flex(layout_box, containing, height_limits, measurer),
+        Display::Grid | Display::InlineGrid => grid::layout_grid(layout_box, containing, height_limits, measurer),
+        Display::TableGrid => table::layout_grid(layout_box, containing, measurer),
+        _ => {
+            // The box is the root of a block formatting context, which grows to contain its floats
+            let mut floats = FloatContext::default();
+            let flow = layout_flow(layout_box, containing, true, true, &mut floats, (0.0, 0.0), measurer);
+            return flow.height.max(floats.bottom());
+        }
+    };
+    // Absolutely positioned children of flex and grid containers aren't items, and their
+    // static position is the content box's corner
+    for child in layout_box.children.iter_mut().filter(|child| is_out_of_flow(child)) {
+        child.dimensions.content.x = 0.0;
+        child.dimensions.content.y = 0.0;
+    }
+    height
+}
+
+/// Lay out a flex or grid item's contents at a content width and, if it's known, a
+/// content height, in a containing block that's the container's content box or the
+/// item's grid area. Returns the height it ends up with.
+pub(crate) fn layout_item(
+    child: &mut LayoutBox,
+    width: f32,
+    height: Option<f32>,
+    containing: ContainingBlock,
+    measurer: &dyn TextMeasurer,
+) -> f32 {
+    let style = &child.style;
+    let height_limits = (
+        style.clamp_height(0.0, containing.width, containing.height),
+        style.clamp_height(f32::INFINITY, containing.width, containing.height),
+    );
+    child.dimensions.content.width = width;
+    let content_height = layout_contents(child, ContainingBlock { width, height }, height_limits, measurer);
+    let height = height.unwrap_or_else(|| content_height.min(height_limits.1).max(height_limits.0).max(0.0));
+    child.dimensions.content.height = height;
+    height
+}
+
+/// Lay out an atomic inline-level box, which is an inline flex, grid or table container, or
+/// a float, at its own width or its shrink-to-fit width (CSS 2.1 §10.3.9 and §10.3.5). Where
+/// it goes is up to line layout or the floats.
+pub(crate) fn layout_shrink_to_fit(layout_box: &mut LayoutBox, containing_width: f32, measurer: &dyn TextMeasurer) {
+    resolve_edges(layout_box, containing_width);
+    let style = &layout_box.style;
+    let dimensions = &layout_box.dimensions;
+    let edges = dimensions.margin_box().width - dimensions.content.width;
+    let width = match style.width {
+        Some(ref width) => width.resolve(containing_width) - style.box_sizing_adjustment(true, containing_width),
+        None if matches!(layout_box.display, Display::Table | Display::InlineTable) => {
+            table::wrapper_width(layout_box, containing_width, containing_width - edges, measurer)
+        }
+        None => shrink_to_fit(layout_box, containing_width - edges, measurer),
+    };
+    let width = style.clamp_width(width, containing_width).max(0.0);
+    // The containing block's height isn't known during line layout
+    let height_limits = (
+        style.clamp_height(0.0, containing_width, None).max(0.0),
+        style.clamp_height(f32::INFINITY, containing_width, None),
+    );
+    let height = style
+        .height
+        .as_ref()
+        .and_then(|height| height.resolve_against(None))
+        .map(|height| (height - style.box_sizing_adjustment(false, containing_width)).min(height_limits.1).max(height_limits.0));
+    layout_box.dimensions.content.width = width;
+    let content_height = layout_contents(layout_box, ContainingBlock { width, height }, height_limits, measurer);
+    layout_box.dimensions.content.height = height.unwrap_or_else(|| content_height.min(height_limits.1).max(height_limits.0));
+}
+
+/// Resolve a box's padding, borders and margins on every side, with `auto` margins as zero.
+pub(crate) fn resolve_edges(layout_box: &mut LayoutBox, containing_width: f32) {
+    let style = &layout_box.style;
+    let resolve = |length: &LengthPercentage| length.resolve(containing_width);
+    let dimensions = &mut layout_box.dimensions;
+    dimensions.padding = EdgeSizes {
+        left: resolve(&style.padding.left),
+        right: resolve(&style.padding.right),
+        top: resolve(&style.padding.top),
+        bottom: resolve(&style.padding.bottom),
+    };
+    dimensions.border = style.border_width.to_edge_sizes();
+    let margin = |margin: &Option<LengthPercentage>| margin.as_ref().map_or(0.0, resolve);
+    dimensions.margin = EdgeSizes {
+        left: margin(&style.margin.left),
+        right: margin(&style.margin.right),
+        top: margin(&style.margin.top),
+        bottom: margin(&style.margin.bottom),
+    };
+}
+
+/// The baseline of a laid out box's first line, relative to the top of its content box.
+/// Boxes without any lines inside have none.
+pub(crate) fn first_baseline(layout_box: &LayoutBox) -> Option<f32> {
+    if let Some(line) = layout_box.lines.first() {
+        return Some(line.baseline);
+    }
+    // A table's baseline is its first row's, wherever its captions are
+    layout_box
+        .children
+        .iter()
+        .filter(|child| !matches!(child.display, Display::None | Display::TableCaption))
+        .filter(|child| !is_inline(child) && !is_out_of_flow(child) && !is_float(child))
+        .find_map(|child| Some(child.dimensions.content.y + first_baseline(child)?))
+}
+
+/// Whether a block-level box in flow establishes an independent formatting context, which
+/// keeps its children's margins and floats inside and can't overlap the floats around it.
+fn establishes_formatting_context(layout_box: &LayoutBox) -> bool {
+    matches!(
+        layout_box.display,
+        Display::FlowRoot | Display::Flex | Display::Grid | Display::Table | Display::TableGrid | Display::TableCaption
+    ) || is_scroll_container(layout_box)
+}
+
+/// The top margin a block presents to the boxes above it, with those of its first children
+/// that collapse through its top, from the styles before layout. Empty children whose
+/// margins collapse through them are taken to separate the rest, so it's an estimate.
+fn estimate_top_margin(layout_box: &LayoutBox, containing_width: f32) -> CollapsibleMargin {
+    let style = &layout_box.style;
+    let own = CollapsibleMargin::new(style.margin.top.as_ref().map_or(0.0, |margin| margin.resolve(containing_width)));
+    let separated = style.border_width.top > 0.0
+        || style.padding.top.resolve(containing_width) > 0.0
+        || establishes_formatting_context(layout_box);
+    let first = layout_box
+        .children
+        .iter()
+        .find(|child| child.display != Display::None && !is_out_of_flow(child) && !is_float(child));
+    match first {
+        Some(child) if !separated && !is_inline(child) && child.style.clear == Clear::None => {
+            own.adjoin(estimate_top_margin(child, containing_width))
+        }
+        _ => own,
+    }
+}
+
+/// The margins of a block whose last layout can be kept in `containing`, after marking
+/// it kept.
+fn keep_layout(layout_box: &mut LayoutBox, containing: ContainingBlock) -> Option<BlockMargins> {
+    if layout_box.dirty.layout {
+        return None;
+    }
+    let cache = layout_box.cache.as_mut().filter(|cache| !cache.absolute && cache.containing == containing)?;
+    cache.kept = true;
+    Some(cache.margins)
+}
+
+/// Whether laying out a block placed everything inside it, so none of it depends on
+/// where the block goes: no absolutely positioned boxes, which are placed afterwards, and
+/// unless the block is a formatting context of its own, no floats, which affect the
+/// boxes beside it.
+fn is_self_contained(layout_box: &LayoutBox, independent: bool) -> bool {
+    layout_box.children.iter().all(|child| {
+        !is_out_of_flow(child)
+            && (independent || !is_float(child))
+            && is_self_contained(child, independent || establishes_formatting_context(child))
+    })
+}
+
+/// Lay out a box that establishes a formatting context, which can't overlap the floats
+/// around it: it goes beside them, narrowed to fit, or if it's too wide for that, down
+/// until it fits. `origin` is as for `layout_block`. Returns the margins, how far right of
+/// the containing block's left edge the box goes, and how far down it had to go.
+fn layout_beside_floats(
+    layout_box: &mut LayoutBox,
+    containing: ContainingBlock,
+    floats: &mut FloatContext,
+    origin: (f32, f32),
+    measurer: &dyn TextMeasurer,
+) -> (BlockMargins, f32, f32) {
+    let limits = (origin.0, origin.0 + containing.width);
+    let mut top = origin.1;
+    loop {
+        let band = floats.band(top, 0.0, limits);
+        let narrowed = ContainingBlock {
+            width: band.1 - band.0,
+            ..containing
+        };
+        let margins = layout_block(layout_box, narrowed, false, floats, (band.0, top), measurer);
+        // Its border box has to fit, as the end margin gives way when it's too wide
+        let dimensions = &layout_box.dimensions;
+        let beside = floats.band(top, dimensions.margin_box().height, limits);
+        let fits = dimensions.margin.left + dimensions.border_box().width <= beside.1 - beside.0 || beside == limits;
+        match floats.next_bottom(top) {
+            Some(bottom) if !fits => top = bottom,
+            _ => return (margins, beside.0 - origin.0, top - origin.1),
+        }
+    }
+}
+
+/// Resolve the width and horizontal margins of a block-level box in normal flow, so
+/// that the margin box exactly fills the containing block. A table's wrapper box is as
+/// wide as the table needs, and its table grid box fills it.
+fn compute_width(layout_box: &mut LayoutBox, containing_width: f32, measurer: &dyn TextMeasurer) {
+    let table_width = (layout_box.display == Display::Table).then(|| {
+        let margin = |margin: &Option<LengthPercentage>| margin.as_ref().map_or(0.0, |margin| margin.resolve(containing_width));
+        let margins = margin(&layout_box.style.margin.left) + margin(&layout_box.style.margin.right);
+        table::wrapper_width(layout_box, containing_width, containing_width - margins, measurer)
+    });
+    let style = &layout_box.style;
+    let resolve = |length: &LengthPercentage| length.resolve(containing_width);
+    let dimensions = &mut layout_box.dimensions;
+    dimensions.padding.left = resolve(&style.padding.left);
+    dimensions.padding.right = resolve(&style.padding.right);
+    dimensions.border.left = style.border_width.left;
+    dimensions.border.right = style.border_width.right;
+    let edges = dimensions.padding.left + dimensions.padding.right + dimensions.border.left + dimensions.border.right;
+    let margin_left = style.margin.left.as_ref().map(resolve);
+    let margin_right = style.margin.right.as_ref().map(resolve);
+
+    let solve = |width: Option<f32>| solve_width(width, margin_left, margin_right, edges, containing_width);
+    let width = match layout_box.display {
+        Display::Table => table_width,
+        Display::TableGrid => None,
+        _ => style.width.as_ref().map(|width| resolve(width) - style.box_sizing_adjustment(true, containing_width)),
+    };
+    let (mut width, mut left, mut right) = solve(width);
+    // The maximum and then the minimum width apply by solving again with them as the width
+    let clamped = style.clamp_width(width, containing_width).max(0.0);
+    if clamped != width && layout_box.display != Display::TableGrid {
+        (width, left, right) = solve(Some(clamped));
+    }
+    dimensions.content.width = width;
+    dimensions.margin.left = left;
+    dimensions.margin.right = right;
+}
+
+/// Solve the width constraint for a width and margins, any of which may be `auto`.
+fn solve_width(
+    width: Option<f32>,
+    margin_left: Option<f32>,
+    margin_right: Option<f32>,
+    edges: f32,
+    containing_width: f32,
+) -> (f32, f32, f32) {
+    match width {
+        None => {
+            let (left, right) = (margin_left.unwrap_or(0.0), margin_right.unwrap_or(0.0));
+            let width = containing_width - left - right - edges;
+            if width >= 0.0 {
+                (width, left, right)
+            } else {
+                (0.0, left, containing_width - left - edges)
+            }
+        }
+        Some(width) => {
+            let remaining = containing_width - width - edges;
+            match (margin_left, margin_right) {
+                // Auto margins share the space left over, which centres the box
+                (None, None) if remaining >= 0.0 => (width, remaining / 2.0, remaining / 2.0),
+                (None, Some(right)) if remaining - right >= 0.0 => (width, remaining - right, right),
+                // Too wide: auto margins are zero and the right margin gives way
+                (None, _) => (width, 0.0, remaining),
+                (Some(left), _) => (width, left, remaining - left),
+            }
+        }
+    }
+}
+
+/// Turn positions relative to the containing block's content box into page coordinates.
+/// Inline boxes, their fragments and lines are all relative to their block container.
+pub(crate) fn make_absolute(layout_box: &mut LayoutBox, x: f32, y: f32) {
+    let translate = |rect: &mut Rect, x: f32, y: f32| {
+        rect.x += x;
+        rect.y += y;
+    };
+    translate(&mut layout_box.dimensions.content, x, y);
+    if let Some(cache) = &mut layout_box.cache {
+        cache.absolute = true;
+    }
+    for fragment in &mut layout_box.fragments {
+        translate(&mut fragment.rect, x, y);
+        fragment.baseline += y;
+    }
+    let (x, y) = match layout_box.display {
+        Display::Inline => (x, y),
+        _ => (layout_box.dimensions.content.x, layout_box.dimensions.content.y),
+    };
+    for line in &mut layout_box.lines {
+        translate(&mut line.rect, x, y);
+        line.baseline += y;
+    }
+    for child in &mut layout_box.children {
+        make_absolute(child, x, y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{laid_out, rect};
+    use foamium_dom::parse_html;
+
+    #[test]
+    fn test_block_widths() {
+        let dom = parse_html("<main><p></p><p></p><p></p><p></p></main>");
+        let stylesheet = foamium_css::parse(
+            "main, p { display: block; height: 10px; }
+             main { margin: 5px; padding: 10px; border: 2px solid; height: auto; }
+             p:is(p) { width: 200px; margin: 0 auto; }
+             main > p + p { width: 50%; margin-left: auto; margin-right: 20px; }
+             main > p + p + p { width: auto; margin: 0 25% 0 4px; }
+             main > p + p + p + p { width: 900px; margin: 0 auto; box-sizing: border-box; padding: 0 10px; min-width: 0; max-width: 300px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let main = &root.children[0];
+        assert_eq!(main.dimensions.content, rect(17.0, 17.0, 766.0, 40.0));
+        assert_eq!(main.dimensions.border_box(), rect(5.0, 5.0, 790.0, 64.0));
+
+        let p = |index: usize| main.children[index].dimensions;
+        // Centred by auto margins
+        assert_eq!(p(0).content, rect(17.0 + 283.0, 17.0, 200.0, 10.0));
+        // The auto margin takes what's left
+        assert_eq!(p(1).content.x, 17.0 + 766.0 - 20.0 - 383.0);
+        assert_eq!(p(2).content, rect(17.0 + 4.0, 37.0, 766.0 - 4.0 - 191.5, 10.0));
+        // `max-width` caps the border box at 300px
+        assert_eq!(p(3).border_box().width, 300.0);
+        assert_eq!(p(3).margin.left, 233.0);
+        assert_eq!(root.dimensions.content.height, 74.0);
+    }
+
+    #[test]
+    fn test_collapsing_margins() {
+        let dom = parse_html(
+            "<div id=\"a\"><div id=\"b\"></div></div><div id=\"empty\"></div><div id=\"c\">Text<div id=\"d\"></div></div>",
+        );
+        let stylesheet = foamium_css::parse(
+            "div { display: block; }
+             #a { margin: 10px 0 20px; }
+             #b { margin: 30px 0 -5px; height: 50px; }
+             #empty { margin: 40px 0 25px; }
+             #c { margin-top: -10px; padding-bottom: 1px; line-height: 20px; }
+             #d { margin: 15px 0; height: 5px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let a = &root.children[0];
+        // `#b`'s top margin collapses through `#a` with its own, and the larger one wins
+        assert_eq!(a.dimensions.content.y, 30.0);
+        assert_eq!(a.children[0].dimensions.content.y, 30.0);
+        assert_eq!(a.dimensions.content.height, 50.0);
+
+        // `#a`'s bottom margin (20px, with -5px from `#b`), the empty box's margins and
+        // `#c`'s -10px all collapse together: 40px - 10px
+        let c = &root.children[2];
+        assert_eq!(c.dimensions.border_box().y, 80.0 + 30.0);
+        assert_eq!(root.children[1].dimensions.content.height, 0.0);
+
+        // Text separates `#c`'s top from `#d`, and the padding keeps `#d`'s bottom margin inside
+        let d = &c.children[1];
+        assert_eq!(d.dimensions.content.y, 110.0 + 20.0 + 15.0);
+        assert_eq!(c.dimensions.content.height, 20.0 + 15.0 + 5.0 + 15.0);
+    }
+}
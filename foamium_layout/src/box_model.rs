@@ -0,0 +1,371 @@
+// Box model properties: margins, borders, padding and sizes
+use crate::{Cascaded, ComputedStyle, Dimensions, EdgeSizes, LengthContext, PropertyMap, Rect, cascaded, to_color};
+use foamium_css::{CalcNode, Unit, Value};
+
+/// A computed `<length-percentage>`. Percentages are kept, since what they refer to is
+/// only known during layout.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LengthPercentage {
+    Px(f32),
+    Percent(f32),
+    /// A math function mixing the two, with every other unit already converted to px
+    Calc(CalcNode),
+}
+
+impl LengthPercentage {
+    pub const ZERO: LengthPercentage = LengthPercentage::Px(0.0);
+
+    /// Resolve to px, with percentages of `basis`.
+    pub fn resolve(&self, basis: f32) -> f32 {
+        self.resolve_against(Some(basis)).unwrap_or(0.0)
+    }
+
+    /// Resolve to px, or `None` if there's a percentage and `basis` isn't known.
+    pub fn resolve_against(&self, basis: Option<f32>) -> Option<f32> {
+        match *self {
+            LengthPercentage::Px(px) => Some(px),
+            LengthPercentage::Percent(percent) => Some(basis? * percent / 100.0),
+            LengthPercentage::Calc(ref node) => node.evaluate(&|v, unit| match unit {
+                Unit::Percent => Some(basis? * v / 100.0),
+                _ => Some(v),
+            }),
+        }
+    }
+
+    /// Compute a length or percentage, or `None` if `value` isn't one.
+    pub(crate) fn compute(value: &Value, context: &LengthContext) -> Option<LengthPercentage> {
+        match *value {
+            Value::Length(percent, Unit::Percent) => Some(LengthPercentage::Percent(percent)),
+            Value::Calc(ref node) => match context.resolve(value, None) {
+                Some(px) => Some(LengthPercentage::Px(px)),
+                None => absolutize(node, context).map(LengthPercentage::Calc),
+            },
+            _ => context.resolve(value, None).map(LengthPercentage::Px),
+        }
+    }
+}
+
+/// Convert every dimension but percentages to px.
+fn absolutize(node: &CalcNode, context: &LengthContext) -> Option<CalcNode> {
+    let pair = |a: &CalcNode, b: &CalcNode| Some((Box::new(absolutize(a, context)?), Box::new(absolutize(b, context)?)));
+    let list = |args: &[CalcNode]| args.iter().map(|arg| absolutize(arg, context)).collect::<Option<Vec<_>>>();
+    Some(match *node {
+        CalcNode::Number(n) => CalcNode::Number(n),
+        CalcNode::Dimension(v, Unit::Percent) => CalcNode::Dimension(v, Unit::Percent),
+        CalcNode::Dimension(v, unit) => CalcNode::Dimension(context.resolve(&Value::Length(v, unit), None)?, Unit::Px),
+        CalcNode::Add(ref a, ref b) => pair(a, b).map(|(a, b)| CalcNode::Add(a, b))?,
+        CalcNode::Sub(ref a, ref b) => pair(a, b).map(|(a, b)| CalcNode::Sub(a, b))?,
+        CalcNode::Mul(ref a, ref b) => pair(a, b).map(|(a, b)| CalcNode::Mul(a, b))?,
+        CalcNode::Div(ref a, ref b) => pair(a, b).map(|(a, b)| CalcNode::Div(a, b))?,
+        CalcNode::Min(ref args) => CalcNode::Min(list(args)?),
+        CalcNode::Max(ref args) => CalcNode::Max(list(args)?),
+        CalcNode::Clamp(ref min, ref value, ref max) => CalcNode::Clamp(
+            Box::new(absolutize(min, context)?),
+            Box::new(absolutize(value, context)?),
+            Box::new(absolutize(max, context)?),
+        ),
+    })
+}
+
+/// A value for each side of a box.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Sides<T> {
+    pub top: T,
+    pub right: T,
+    pub bottom: T,
+    pub left: T,
+}
+
+impl<T> Sides<T> {
+    pub fn all(value: T) -> Sides<T>
+    where
+        T: Clone,
+    {
+        Sides {
+            top: value.clone(),
+            right: value.clone(),
+            bottom: value.clone(),
+            left: value,
+        }
+    }
+
+    /// Build each side from its name and the parent's value for it.
+    pub(crate) fn compute<U>(parent: &Sides<U>, mut f: impl FnMut(&str, &U) -> T) -> Sides<T> {
+        Sides {
+            top: f("top", &parent.top),
+            right: f("right", &parent.right),
+            bottom: f("bottom", &parent.bottom),
+            left: f("left", &parent.left),
+        }
+    }
+}
+
+impl Sides<f32> {
+    pub(crate) fn to_edge_sizes(self) -> EdgeSizes {
+        EdgeSizes {
+            left: self.left,
+            right: self.right,
+            top: self.top,
+            bottom: self.bottom,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BorderStyle {
+    #[default]
+    None,
+    Hidden,
+    Dotted,
+    Dashed,
+    Solid,
+    Double,
+    Groove,
+    Ridge,
+    Inset,
+    Outset,
+}
+
+impl BorderStyle {
+    fn from_keyword(keyword: &str) -> Option<BorderStyle> {
+        Some(match &*keyword.to_ascii_lowercase() {
+            "none" => BorderStyle::None,
+            "hidden" => BorderStyle::Hidden,
+            "dotted" => BorderStyle::Dotted,
+            "dashed" => BorderStyle::Dashed,
+            "solid" => BorderStyle::Solid,
+            "double" => BorderStyle::Double,
+            "groove" => BorderStyle::Groove,
+            "ridge" => BorderStyle::Ridge,
+            "inset" => BorderStyle::Inset,
+            "outset" => BorderStyle::Outset,
+            _ => return None,
+        })
+    }
+}
+
+/// Which box `width` and `height` size.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BoxSizing {
+    #[default]
+    ContentBox,
+    BorderBox,
+}
+
+/// The computed value of a property that isn't inherited: the parent's for `inherit`, and
+/// `initial` for `initial`, `unset` and values `compute` rejects.
+pub(crate) fn computed<T: Clone>(
+    values: &PropertyMap,
+    name: &str,
+    parent: &T,
+    initial: T,
+    compute: impl Fn(&Value) -> Option<T>,
+) -> T {
+    match cascaded(values, name) {
+        Cascaded::Value(value) => compute(value).unwrap_or(initial),
+        Cascaded::Inherit => parent.clone(),
+        Cascaded::Initial => initial,
+    }
+}
+
+/// `auto`, or `none` for the maximum sizes, computes to `None`.
+pub(crate) fn length_or_keyword(value: &Value, keyword: &str, context: &LengthContext) -> Option<Option<LengthPercentage>> {
+    match value {
+        Value::Keyword(k) if k.eq_ignore_ascii_case(keyword) => Some(None),
+        _ => LengthPercentage::compute(value, context).map(Some),
+    }
+}
+
+/// Compute the box model properties into `style`, whose `color` must already be computed.
+pub(crate) fn compute_box_style(
+    style: &mut ComputedStyle,
+    values: &PropertyMap,
+    parent: &ComputedStyle,
+    context: &LengthContext,
+) {
+    let auto = |value: &Value| length_or_keyword(value, "auto", context);
+    style.margin = Sides::compute(&parent.margin, |side, parent| {
+        computed(values, &format!("margin-{side}"), parent, Some(LengthPercentage::ZERO), auto)
+    });
+    style.padding = Sides::compute(&parent.padding, |side, parent| {
+        let compute = |value: &Value| LengthPercentage::compute(value, context).filter(|length| !is_negative(length));
+        computed(values, &format!("padding-{side}"), parent, LengthPercentage::ZERO, compute)
+    });
+
+    style.border_style = Sides::compute(&parent.border_style, |side, parent| {
+        let compute = |value: &Value| match value {
+            Value::Keyword(keyword) => BorderStyle::from_keyword(keyword),
+            _ => None,
+        };
+        computed(values, &format!("border-{side}-style"), parent, BorderStyle::None, compute)
+    });
+    let color = style.color;
+    style.border_color = Sides::compute(&parent.border_color, |side, parent| {
+        let compute = |value: &Value| match value {
+            Value::Keyword(keyword) if keyword.eq_ignore_ascii_case("currentcolor") => Some(color),
+            _ => to_color(value).map(Some),
+        };
+        computed(values, &format!("border-{side}-color"), parent, color, compute)
+    });
+    let border_style = style.border_style;
+    style.border_width = Sides::compute(&parent.border_width, |side, parent| {
+        let compute = |value: &Value| match value {
+            Value::Keyword(keyword) => match &*keyword.to_ascii_lowercase() {
+                "thin" => Some(1.0),
+                "medium" => Some(3.0),
+                "thick" => Some(5.0),
+                _ => None,
+            },
+            _ => context.resolve(value, None).filter(|width| *width >= 0.0),
+        };
+        computed(values, &format!("border-{side}-width"), parent, 3.0, compute)
+    });
+    // A border with no style has no width
+    for (width, border_style) in [
+        (&mut style.border_width.top, border_style.top),
+        (&mut style.border_width.right, border_style.right),
+        (&mut style.border_width.bottom, border_style.bottom),
+        (&mut style.border_width.left, border_style.left),
+    ] {
+        if matches!(border_style, BorderStyle::None | BorderStyle::Hidden) {
+            *width = 0.0;
+        }
+    }
+
+    let size = |value: &Value| length_or_keyword(value, "auto", context).filter(|size| !size.as_ref().is_some_and(is_negative));
+    let max_size = |value: &Value| length_or_keyword(value, "none", context).filter(|size| !size.as_ref().is_some_and(is_negative));
+    style.width = computed(values, "width", &parent.width, None, size);
+    style.height = computed(values, "height", &parent.height, None, size);
+    style.min_width = computed(values, "min-width", &parent.min_width, None, size);
+    style.min_height = computed(values, "min-height", &parent.min_height, None, size);
+    style.max_width = computed(values, "max-width", &parent.max_width, None, max_size);
+    style.max_height = computed(values, "max-height", &parent.max_height, None, max_size);
+
+    style.box_sizing = computed(values, "box-sizing", &parent.box_sizing, BoxSizing::ContentBox, |value| match value {
+        Value::Keyword(keyword) if keyword.eq_ignore_ascii_case("content-box") => Some(BoxSizing::ContentBox),
+        Value::Keyword(keyword) if keyword.eq_ignore_ascii_case("border-box") => Some(BoxSizing::BorderBox),
+        _ => None,
+    });
+}
+
+/// Negative padding and sizes are invalid. Math functions can't be checked until they're
+/// resolved, so they're let through.
+pub(crate) fn is_negative(length: &LengthPercentage) -> bool {
+    match *length {
+        LengthPercentage::Px(v) | LengthPercentage::Percent(v) => v < 0.0,
+        LengthPercentage::Calc(_) => false,
+    }
+}
+
+impl Dimensions {
+    /// The box's edges and size from its style, before layout places it. Percentages
+    /// resolve against the containing block, whose height may not be known yet, and
+    /// `auto` margins are zero. An `auto` width fills the containing block; an `auto`
+    /// height stays zero until layout knows the contents.
+    pub fn from_style(style: &ComputedStyle, containing_width: f32, containing_height: Option<f32>) -> Dimensions {
+        let resolve = |length: &LengthPercentage| length.resolve(containing_width);
+        let margin = Sides::compute(&style.margin, |_, margin| margin.as_ref().map_or(0.0, resolve));
+        let padding = Sides::compute(&style.padding, |_, padding| resolve(padding));
+        let border = style.border_width;
+
+        let width = match style.width {
+            Some(ref width) => resolve(width) - style.box_sizing_adjustment(true, containing_width),
+            None => containing_width - margin.left - margin.right - padding.left - padding.right - border.left - border.right,
+        };
+        let height = style
+            .height
+            .as_ref()
+            .and_then(|height| height.resolve_against(containing_height))
+            .map_or(0.0, |height| height - style.box_sizing_adjustment(false, containing_width));
+
+        Dimensions {
+            content: Rect {
+                width: style.clamp_width(width, containing_width).max(0.0),
+                height: style.clamp_height(height, containing_width, containing_height).max(0.0),
+                ..Rect::default()
+            },
+            padding: padding.to_edge_sizes(),
+            border: border.to_edge_sizes(),
+            margin: margin.to_edge_sizes(),
+        }
+    }
+
+    /// The content area plus padding.
+    pub fn padding_box(&self) -> Rect {
+        self.content.expanded_by(self.padding)
+    }
+
+    /// The content area plus padding and borders.
+    pub fn border_box(&self) -> Rect {
+        self.padding_box().expanded_by(self.border)
+    }
+
+    /// The content area plus padding, borders and margins.
+    pub fn margin_box(&self) -> Rect {
+        self.border_box().expanded_by(self.margin)
+    }
+}
+
+impl Rect {
+    /// The smallest rectangle containing both.
+    pub fn union(self, other: Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+
+    /// Whether the point is inside: on its top or left edge, or between them and the others.
+    pub fn contains(self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    pub fn expanded_by(self, edge: EdgeSizes) -> Rect {
+        Rect {
+            x: self.x - edge.left,
+            y: self.y - edge.top,
+            width: self.width + edge.left + edge.right,
+            height: self.height + edge.top + edge.bottom,
+        }
+    }
+}
+
+impl ComputedStyle {
+    /// Apply `min-width` and `max-width` to a content width. The minimum wins.
+    pub fn clamp_width(&self, width: f32, containing_width: f32) -> f32 {
+        let adjustment = self.box_sizing_adjustment(true, containing_width);
+        let max = self.max_width.as_ref().map_or(f32::INFINITY, |max| max.resolve(containing_width) - adjustment);
+        let min = self.min_width.as_ref().map_or(0.0, |min| min.resolve(containing_width) - adjustment);
+        width.min(max).max(min)
+    }
+
+    /// Apply `min-height` and `max-height` to a content height. Percentages of an
+    /// unknown containing height are ignored. The minimum wins.
+    pub fn clamp_height(&self, height: f32, containing_width: f32, containing_height: Option<f32>) -> f32 {
+        let adjustment = self.box_sizing_adjustment(false, containing_width);
+        let resolve = |length: &Option<LengthPercentage>| {
+            length.as_ref().and_then(|length| length.resolve_against(containing_height)).map(|v| v - adjustment)
+        };
+        let max = resolve(&self.max_height).unwrap_or(f32::INFINITY);
+        let min = resolve(&self.min_height).unwrap_or(0.0);
+        height.min(max).max(min)
+    }
+
+    /// What to take off a `border-box` size to get the content size. Padding percentages
+    /// always refer to the containing block's width.
+    pub fn box_sizing_adjustment(&self, horizontal: bool, containing_width: f32) -> f32 {
+        if self.box_sizing == BoxSizing::ContentBox {
+            return 0.0;
+        }
+        let (padding, border) = if horizontal {
+            ((&self.padding.left, &self.padding.right), self.border_width.left + self.border_width.right)
+        } else {
+            ((&self.padding.top, &self.padding.bottom), self.border_width.top + self.border_width.bottom)
+        };
+        padding.0.resolve(containing_width) + padding.1.resolve(containing_width) + border
+    }
+}
@@ -0,0 +1,788 @@
+// Flex layout (CSS Flexbox 1 §9): a flex container lays its items out in lines along its
+// main axis, grows or shrinks them to fill each line, and aligns them along both axes
+//
+// Sizes along the main axis are worked out first, for every line, and then each item is
+// laid out at its main size to find its size across the lines. Items are block or flex
+// containers of their own, and like block layout's children, they're positioned relative
+// to the container's content box.
+use crate::block::{ContainingBlock, first_baseline, layout_item, resolve_edges};
+use crate::box_model::{computed, is_negative};
+use crate::intrinsic::{ContentSizes, content_sizes, shrink_to_fit};
+use crate::overflow::is_scroll_container;
+use crate::position::is_out_of_flow;
+use crate::{ComputedStyle, Display, LayoutBox, LengthContext, LengthPercentage, PropertyMap, TextMeasurer};
+use foamium_css::Value;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FlexDirection {
+    #[default]
+    Row,
+    RowReverse,
+    Column,
+    ColumnReverse,
+}
+
+impl FlexDirection {
+    fn from_keyword(keyword: &str) -> Option<FlexDirection> {
+        Some(match keyword {
+            "row" => FlexDirection::Row,
+            "row-reverse" => FlexDirection::RowReverse,
+            "column" => FlexDirection::Column,
+            "column-reverse" => FlexDirection::ColumnReverse,
+            _ => return None,
+        })
+    }
+
+    fn is_row(self) -> bool {
+        matches!(self, FlexDirection::Row | FlexDirection::RowReverse)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FlexWrap {
+    #[default]
+    NoWrap,
+    Wrap,
+    WrapReverse,
+}
+
+impl FlexWrap {
+    fn from_keyword(keyword: &str) -> Option<FlexWrap> {
+        Some(match keyword {
+            "nowrap" => FlexWrap::NoWrap,
+            "wrap" => FlexWrap::Wrap,
+            "wrap-reverse" => FlexWrap::WrapReverse,
+            _ => return None,
+        })
+    }
+}
+
+/// `flex-basis`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum FlexBasis {
+    /// The item's `width` or `height`, or the size of its content if that's `auto`
+    #[default]
+    Auto,
+    /// The size of the item's content
+    Content,
+    Length(LengthPercentage),
+}
+
+/// `justify-content` and `align-content`. Text always runs left to right and top to
+/// bottom, so `start` and `end` are the left or top and the right or bottom, while
+/// `flex-start` and `flex-end` follow the direction and wrapping of the container.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AlignContent {
+    #[default]
+    Normal,
+    Start,
+    End,
+    FlexStart,
+    FlexEnd,
+    Center,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly,
+    Stretch,
+}
+
+impl AlignContent {
+    fn from_keyword(keyword: &str) -> Option<AlignContent> {
+        Some(match keyword {
+            "normal" => AlignContent::Normal,
+            "start" | "left" => AlignContent::Start,
+            "end" | "right" => AlignContent::End,
+            "flex-start" => AlignContent::FlexStart,
+            "flex-end" => AlignContent::FlexEnd,
+            "center" => AlignContent::Center,
+            "space-between" => AlignContent::SpaceBetween,
+            "space-around" => AlignContent::SpaceAround,
+            "space-evenly" => AlignContent::SpaceEvenly,
+            "stretch" => AlignContent::Stretch,
+            _ => return None,
+        })
+    }
+}
+
+/// `align-items`, and `align-self` other than `auto`. `normal` stretches flex items.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AlignItems {
+    #[default]
+    Normal,
+    Start,
+    End,
+    FlexStart,
+    FlexEnd,
+    Center,
+    Baseline,
+    Stretch,
+}
+
+impl AlignItems {
+    pub(crate) fn from_keyword(keyword: &str) -> Option<AlignItems> {
+        Some(match keyword {
+            "normal" => AlignItems::Normal,
+            "start" | "self-start" => AlignItems::Start,
+            "end" | "self-end" => AlignItems::End,
+            "flex-start" => AlignItems::FlexStart,
+            "flex-end" => AlignItems::FlexEnd,
+            "center" => AlignItems::Center,
+            "baseline" => AlignItems::Baseline,
+            "stretch" => AlignItems::Stretch,
+            _ => return None,
+        })
+    }
+}
+
+/// Compute the flex container and flex item properties into `style`. None of them inherit.
+pub(crate) fn compute_flex_style(
+    style: &mut ComputedStyle,
+    values: &PropertyMap,
+    parent: &ComputedStyle,
+    context: &LengthContext,
+) {
+    let keyword = |value: &Value| match value {
+        Value::Keyword(keyword) => Some(keyword.to_ascii_lowercase()),
+        _ => None,
+    };
+    style.flex_direction = computed(values, "flex-direction", &parent.flex_direction, FlexDirection::Row, |value| {
+        FlexDirection::from_keyword(&keyword(value)?)
+    });
+    style.flex_wrap = computed(values, "flex-wrap", &parent.flex_wrap, FlexWrap::NoWrap, |value| {
+        FlexWrap::from_keyword(&keyword(value)?)
+    });
+
+    let factor = |value: &Value| match *value {
+        Value::Number(number) if number >= 0.0 => Some(number),
+        _ => None,
+    };
+    style.flex_grow = computed(values, "flex-grow", &parent.flex_grow, 0.0, factor);
+    style.flex_shrink = computed(values, "flex-shrink", &parent.flex_shrink, 1.0, factor);
+    style.flex_basis = computed(values, "flex-basis", &parent.flex_basis, FlexBasis::Auto, |value| {
+        match keyword(value).as_deref() {
+            Some("auto") => Some(FlexBasis::Auto),
+            Some("content") => Some(FlexBasis::Content),
+            _ => LengthPercentage::compute(value, context).filter(|basis| !is_negative(basis)).map(FlexBasis::Length),
+        }
+    });
+
+    let content = |value: &Value| AlignContent::from_keyword(&keyword(value)?);
+    style.justify_content = computed(values, "justify-content", &parent.justify_content, AlignContent::Normal, content);
+    style.align_content = computed(values, "align-content", &parent.align_content, AlignContent::Normal, content);
+    style.align_items = computed(values, "align-items", &parent.align_items, AlignItems::Normal, |value| {
+        AlignItems::from_keyword(&keyword(value)?)
+    });
+    style.align_self = computed(values, "align-self", &parent.align_self, None, |value| match &*keyword(value)? {
+        "auto" => Some(None),
+        keyword => AlignItems::from_keyword(keyword).map(Some),
+    });
+
+    let gap = |value: &Value| match keyword(value).as_deref() {
+        Some("normal") => Some(LengthPercentage::ZERO),
+        _ => LengthPercentage::compute(value, context).filter(|gap| !is_negative(gap)),
+    };
+    style.row_gap = computed(values, "row-gap", &parent.row_gap, LengthPercentage::ZERO, gap);
+    style.column_gap = computed(values, "column-gap", &parent.column_gap, LengthPercentage::ZERO, gap);
+    style.order = computed(values, "order", &parent.order, 0, |value| match *value {
+        Value::Number(number) if number.fract() == 0.0 => Some(number as i32),
+        _ => None,
+    });
+}
+
+/// A flex item, with its sizes along the main axis and across it. Sizes are of the
+/// content box; edges are margins, borders and padding.
+#[derive(Debug)]
+struct FlexItem {
+    /// Which of the container's children the item is
+    index: usize,
+    grow: f32,
+    shrink: f32,
+    /// The flex base size, and the hypothetical main size: the base size within the
+    /// minimum and maximum
+    base: f32,
+    hypothetical: f32,
+    min: f32,
+    max: f32,
+    main_edges: f32,
+    cross_edges: f32,
+    /// Which margins are `auto`, at the start and end of each axis
+    main_auto: (bool, bool),
+    cross_auto: (bool, bool),
+    /// The main size being resolved, and then the one the item gets
+    target: f32,
+    frozen: bool,
+    /// How far `min` or `max` moved the target in the last round of flexing
+    violation: f32,
+    /// The size across the main axis, once known
+    cross: f32,
+    /// The cross size from the item's own `height`, for items in rows
+    definite_cross: Option<f32>,
+    /// The distance from the top margin edge to the first baseline
+    baseline: Option<f32>,
+    align: AlignItems,
+    stretch: bool,
+}
+
+impl FlexItem {
+    fn outer_hypothetical(&self) -> f32 {
+        self.hypothetical + self.main_edges
+    }
+
+    fn outer_target(&self) -> f32 {
+        self.target + self.main_edges
+    }
+
+    fn outer_cross(&self) -> f32 {
+        self.cross + self.cross_edges
+    }
+
+    /// The distance from the cross-start margin edge to the baseline. Items without a
+    /// baseline use their bottom border edge.
+    fn ascent(&self) -> f32 {
+        self.baseline.unwrap_or(self.outer_cross())
+    }
+}
+
+/// A flex line: a range of the items, with its size and position across the main axis.
+#[derive(Debug)]
+struct FlexLine {
+    items: Range<usize>,
+    cross: f32,
+    position: f32,
+}
+
+/// Lay out a flex container's items, given the container's content width and height.
+/// `height_limits` are its minimum and maximum content heights, for when the height
+/// depends on the items. Returns the height of the contents.
+pub(crate) fn layout_flex(
+    container: &mut LayoutBox,
+    containing: ContainingBlock,
+    height_limits: (f32, f32),
+    measurer: &dyn TextMeasurer,
+) -> f32 {
+    let LayoutBox { style, children, .. } = container;
+    let row = style.flex_direction.is_row();
+    let reverse = matches!(style.flex_direction, FlexDirection::RowReverse | FlexDirection::ColumnReverse);
+    let wrap_reverse = style.flex_wrap == FlexWrap::WrapReverse;
+    let column_gap = style.column_gap.resolve(containing.width);
+    let row_gap = style.row_gap.resolve_against(containing.height).unwrap_or(0.0);
+    let (main_gap, cross_gap) = if row { (column_gap, row_gap) } else { (row_gap, column_gap) };
+    let clamp_height = |height: f32| height.min(height_limits.1).max(height_limits.0);
+
+    // Items in `order`, and then in document order
+    let mut order: Vec<usize> = (0..children.len())
+        .filter(|&index| children[index].display != Display::None && !is_out_of_flow(&children[index]))
+        .collect();
+    order.sort_by_key(|&index| children[index].style.order);
+    let mut items: Vec<FlexItem> = order
+        .into_iter()
+        .map(|index| flex_item(&mut children[index], index, style, containing, measurer))
+        .collect();
+
+    // Collect the items into lines, breaking before an item that doesn't fit if the
+    // container wraps
+    let main_limit = if row {
+        Some(containing.width)
+    } else {
+        containing.height.or(Some(height_limits.1).filter(|max| max.is_finite()))
+    };
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut line_main = 0.0;
+    for (index, item) in items.iter().enumerate() {
+        let outer = item.outer_hypothetical();
+        let breaks = style.flex_wrap != FlexWrap::NoWrap
+            && index > start
+            && main_limit.is_some_and(|limit| line_main + main_gap + outer > limit);
+        if breaks {
+            lines.push(start..index);
+            start = index;
+        }
+        line_main = if index == start { outer } else { line_main + main_gap + outer };
+    }
+    if start < items.len() {
+        lines.push(start..items.len());
+    }
+    let gaps = |range: &Range<usize>| main_gap * range.len().saturating_sub(1) as f32;
+
+    let main_size = match (row, containing.height) {
+        (true, _) => containing.width,
+        (false, Some(height)) => height,
+        (false, None) => clamp_height(
+            lines
+                .iter()
+                .map(|range| items[range.clone()].iter().map(FlexItem::outer_hypothetical).sum::<f32>() + gaps(range))
+                .fold(0.0, f32::max),
+        ),
+    };
+    for range in &lines {
+        resolve_flexible_lengths(&mut items[range.clone()], main_size, gaps(range));
+    }
+
+    // Lay the items out at their main sizes to find their cross sizes
+    for item in &mut items {
+        let child = &mut children[item.index];
+        if row {
+            item.cross = layout_item(child, item.target, item.definite_cross, containing, measurer);
+            let dimensions = &child.dimensions;
+            let top = dimensions.margin.top + dimensions.border.top + dimensions.padding.top;
+            item.baseline = first_baseline(child).map(|baseline| top + baseline);
+        } else {
+            layout_item(child, item.cross, Some(item.target), containing, measurer);
+        }
+    }
+
+    // The cross size of each line, and of the container
+    let single_line = style.flex_wrap == FlexWrap::NoWrap;
+    let inner_cross = if row { containing.height } else { Some(containing.width) };
+    let mut lines: Vec<FlexLine> = lines
+        .into_iter()
+        .map(|range| {
+            let line_items = &items[range.clone()];
+            let baseline_items = line_items.iter().filter(|item| row && item.align == AlignItems::Baseline);
+            let ascent = baseline_items.clone().map(FlexItem::ascent).fold(0.0, f32::max);
+            let descent = baseline_items.map(|item| item.outer_cross() - item.ascent()).fold(0.0, f32::max);
+            let cross = line_items.iter().map(FlexItem::outer_cross).fold(ascent + descent, f32::max);
+            FlexLine {
+                items: range,
+                cross,
+                position: 0.0,
+            }
+        })
+        .collect();
+    let cross_gaps = cross_gap * lines.len().saturating_sub(1) as f32;
+    let natural_cross = lines.iter().map(|line| line.cross).sum::<f32>() + cross_gaps;
+    let cross_size = inner_cross.unwrap_or_else(|| if row { clamp_height(natural_cross) } else { natural_cross });
+    if single_line && let Some(line) = lines.first_mut() {
+        line.cross = cross_size;
+    }
+
+    // Place the lines with `align-content`, which `stretch` and `normal` grow to fill
+    // the container
+    let free = cross_size - natural_cross;
+    if !single_line && free > 0.0 && matches!(style.align_content, AlignContent::Normal | AlignContent::Stretch) {
+        let extra = free / lines.len() as f32;
+        for line in &mut lines {
+            line.cross += extra;
+        }
+    }
+    let free = cross_size - lines.iter().map(|line| line.cross).sum::<f32>() - cross_gaps;
+    let (offset, between) = distribute(style.align_content, free, lines.len(), wrap_reverse);
+    let mut position = offset;
+    for line in &mut lines {
+        line.position = if wrap_reverse { cross_size - position - line.cross } else { position };
+        position += line.cross + cross_gap + between;
+    }
+
+    for line in &lines {
+        // Stretch items with an `auto` cross size to fill the line
+        for item in items[line.items.clone()].iter_mut().filter(|item| item.stretch) {
+            let child = &mut children[item.index];
+            let size = (line.cross - item.cross_edges).max(0.0);
+            if row {
+                item.cross = child.style.clamp_height(size, containing.width, containing.height).max(0.0);
+                layout_item(child, item.target, Some(item.cross), containing, measurer);
+            } else {
+                item.cross = child.style.clamp_width(size, containing.width).max(0.0);
+                layout_item(child, item.cross, Some(item.target), containing, measurer);
+            }
+        }
+
+        // Along the main axis, `auto` margins take the free space first, and then
+        // `justify-content` shares out what's left
+        let line_items = &mut items[line.items.clone()];
+        let used = line_items.iter().map(FlexItem::outer_target).sum::<f32>() + gaps(&line.items);
+        let mut free = main_size - used;
+        let auto_margins: usize = line_items.iter().map(|item| item.main_auto.0 as usize + item.main_auto.1 as usize).sum();
+        let auto_margin = if free > 0.0 && auto_margins > 0 { free / auto_margins as f32 } else { 0.0 };
+        if auto_margins > 0 {
+            free = free.min(0.0);
+        }
+        let (offset, between) = distribute(style.justify_content, free, line_items.len(), reverse);
+        let mut position = offset;
+        let ascent = line_items
+            .iter()
+            .filter(|item| row && item.align == AlignItems::Baseline)
+            .map(FlexItem::ascent)
+            .fold(0.0, f32::max);
+        for item in line_items {
+            let margin = &mut children[item.index].dimensions.margin;
+            let (main_start, main_end) = if row { (&mut margin.left, &mut margin.right) } else { (&mut margin.top, &mut margin.bottom) };
+            for (auto, margin) in [(item.main_auto.0, main_start), (item.main_auto.1, main_end)] {
+                if auto {
+                    *margin = auto_margin;
+                    item.main_edges += auto_margin;
+                }
+            }
+            let outer = item.outer_target();
+            let main_position = if reverse { main_size - position - outer } else { position };
+            position += outer + main_gap + between;
+
+            // Across it, `auto` margins take any free space, or else the item aligns itself
+            let free = line.cross - item.outer_cross();
+            let (cross_start, cross_end) = if row { (&mut margin.top, &mut margin.bottom) } else { (&mut margin.left, &mut margin.right) };
+            let cross_offset = if item.cross_auto.0 || item.cross_auto.1 {
+                let auto_margin = free.max(0.0) / (item.cross_auto.0 as usize + item.cross_auto.1 as usize) as f32;
+                for (auto, margin) in [(item.cross_auto.0, cross_start), (item.cross_auto.1, cross_end)] {
+                    if auto {
+                        *margin = auto_margin;
+                        item.cross_edges += auto_margin;
+                    }
+                }
+                0.0
+            } else {
+                match item.align {
+                    AlignItems::Baseline if row => ascent - item.ascent(),
+                    AlignItems::Center => free / 2.0,
+                    AlignItems::End => free,
+                    AlignItems::FlexEnd if !wrap_reverse => free,
+                    AlignItems::FlexStart if wrap_reverse => free,
+                    _ => 0.0,
+                }
+            };
+
+            let (x, y) = if row {
+                (main_position, line.position + cross_offset)
+            } else {
+                (line.position + cross_offset, main_position)
+            };
+            let dimensions = &mut children[item.index].dimensions;
+            dimensions.content.x = x + dimensions.margin.left + dimensions.border.left + dimensions.padding.left;
+            dimensions.content.y = y + dimensions.margin.top + dimensions.border.top + dimensions.padding.top;
+        }
+    }
+
+    if row { cross_size } else { main_size }
+}
+
+/// Resolve an item's edges and work out its flex base size, hypothetical main size and
+/// the limits on its main size (§9.2, §9.3), and for columns, its width.
+fn flex_item(
+    child: &mut LayoutBox,
+    index: usize,
+    container: &ComputedStyle,
+    containing: ContainingBlock,
+    measurer: &dyn TextMeasurer,
+) -> FlexItem {
+    let row = container.flex_direction.is_row();
+    resolve_edges(child, containing.width);
+    let style = &child.style;
+    let dimensions = &child.dimensions;
+    let horizontal = dimensions.margin.left + dimensions.margin.right + dimensions.border.left + dimensions.border.right
+        + dimensions.padding.left + dimensions.padding.right;
+    let vertical = dimensions.margin.top + dimensions.margin.bottom + dimensions.border.top + dimensions.border.bottom
+        + dimensions.padding.top + dimensions.padding.bottom;
+    let horizontal_auto = (style.margin.left.is_none(), style.margin.right.is_none());
+    let vertical_auto = (style.margin.top.is_none(), style.margin.bottom.is_none());
+    let (main_edges, cross_edges, main_auto, cross_auto) = if row {
+        (horizontal, vertical, horizontal_auto, vertical_auto)
+    } else {
+        (vertical, horizontal, vertical_auto, horizontal_auto)
+    };
+
+    // Sizes along each axis, as content sizes
+    let width_adjustment = style.box_sizing_adjustment(true, containing.width);
+    let height_adjustment = style.box_sizing_adjustment(false, containing.width);
+    let width = |length: &Option<LengthPercentage>| {
+        length.as_ref().map(|length| (length.resolve(containing.width) - width_adjustment).max(0.0))
+    };
+    let height = |length: &Option<LengthPercentage>| {
+        length.as_ref().and_then(|length| length.resolve_against(containing.height)).map(|length| (length - height_adjustment).max(0.0))
+    };
+    let (size, min, max) = if row {
+        (width(&style.width), width(&style.min_width), width(&style.max_width))
+    } else {
+        (height(&style.height), height(&style.min_height), height(&style.max_height))
+    };
+    let max = max.unwrap_or(f32::INFINITY);
+    let basis = match style.flex_basis {
+        FlexBasis::Length(ref basis) => {
+            let adjustment = if row { width_adjustment } else { height_adjustment };
+            basis.resolve_against(if row { Some(containing.width) } else { containing.height }).map(|basis| (basis - adjustment).max(0.0))
+        }
+        FlexBasis::Auto => size,
+        FlexBasis::Content => None,
+    };
+
+    let align = match style.align_self.unwrap_or(container.align_items) {
+        AlignItems::Baseline if !row => AlignItems::FlexStart,
+        align => align,
+    };
+    let auto_cross_size = if row { height(&style.height).is_none() } else { style.width.is_none() };
+    let stretch = matches!(align, AlignItems::Normal | AlignItems::Stretch)
+        && auto_cross_size
+        && !cross_auto.0
+        && !cross_auto.1;
+    let definite_cross = if row {
+        height(&style.height).map(|height| style.clamp_height(height, containing.width, containing.height).max(0.0))
+    } else {
+        None
+    };
+    // A column's items are as wide as they'll be across it, stretched or fitting their content
+    let cross = if row {
+        0.0
+    } else {
+        let width = match width(&style.width) {
+            Some(width) => width,
+            None if stretch => containing.width - cross_edges,
+            None => shrink_to_fit(child, containing.width - cross_edges, measurer),
+        };
+        style.clamp_width(width, containing.width).max(0.0)
+    };
+
+    // The content's size along the main axis: its widths for a row, or its height at
+    // its width for a column. It's only needed without a definite basis or minimum.
+    let content = if basis.is_some() && min.is_some() {
+        ContentSizes::default()
+    } else if row {
+        content_sizes(child, measurer)
+    } else {
+        let height = layout_item(child, cross, None, containing, measurer);
+        ContentSizes { min: height, max: height }
+    };
+    let base = basis.unwrap_or(content.max);
+    // The automatic minimum size keeps items from shrinking below their content, or
+    // their own size if that's smaller (§4.5). Scroll containers can shrink to nothing.
+    let automatic = if is_scroll_container(child) { 0.0 } else { size.map_or(content.min, |size| size.min(content.min)) };
+    let min = min.unwrap_or_else(|| automatic.min(max));
+    let hypothetical = base.min(max).max(min);
+    let style = &child.style;
+    FlexItem {
+        index,
+        grow: style.flex_grow,
+        shrink: style.flex_shrink,
+        base,
+        hypothetical,
+        min,
+        max,
+        main_edges,
+        cross_edges,
+        main_auto,
+        cross_auto,
+        target: hypothetical,
+        frozen: false,
+        violation: 0.0,
+        cross,
+        definite_cross,
+        baseline: None,
+        align,
+        stretch,
+    }
+}
+
+/// Grow or shrink the items on a line to fill `main_size` (§9.7). Items that would go
+/// past their minimum or maximum are frozen there, and the rest flex again.
+fn resolve_flexible_lengths(items: &mut [FlexItem], main_size: f32, gaps: f32) {
+    let hypothetical = items.iter().map(FlexItem::outer_hypothetical).sum::<f32>() + gaps;
+    let growing = hypothetical < main_size;
+    for item in items.iter_mut() {
+        let factor = if growing { item.grow } else { item.shrink };
+        item.target = item.hypothetical;
+        item.frozen = factor == 0.0
+            || (growing && item.base > item.hypothetical)
+            || (!growing && item.base < item.hypothetical);
+    }
+    let free_space = |items: &[FlexItem]| {
+        let sizes: f32 = items
+            .iter()
+            .map(|item| item.main_edges + if item.frozen { item.target } else { item.base })
+            .sum();
+        main_size - gaps - sizes
+    };
+    let initial_free_space = free_space(items);
+
+    while items.iter().any(|item| !item.frozen) {
+        let mut free = free_space(items);
+        let factors: f32 = items
+            .iter()
+            .filter(|item| !item.frozen)
+            .map(|item| if growing { item.grow } else { item.shrink })
+            .sum();
+        // Factors adding up to less than one only take that fraction of the space
+        if factors < 1.0 && (initial_free_space * factors).abs() < free.abs() {
+            free = initial_free_space * factors;
+        }
+        let scaled_shrink: f32 = items.iter().filter(|item| !item.frozen).map(|item| item.shrink * item.base).sum();
+
+        let mut total_violation = 0.0;
+        for item in items.iter_mut().filter(|item| !item.frozen) {
+            let target = if growing {
+                item.base + free * item.grow / factors
+            } else if scaled_shrink > 0.0 {
+                // Shrinking is in proportion to the base size too, so small items don't
+                // vanish first
+                item.base + free * item.shrink * item.base / scaled_shrink
+            } else {
+                item.base
+            };
+            let clamped = target.min(item.max).max(item.min);
+            item.violation = clamped - target;
+            item.target = clamped;
+            total_violation += item.violation;
+        }
+        for item in items.iter_mut().filter(|item| !item.frozen) {
+            item.frozen = total_violation == 0.0
+                || (total_violation > 0.0 && item.violation > 0.0)
+                || (total_violation < 0.0 && item.violation < 0.0);
+        }
+    }
+}
+
+/// Where the first of `count` things goes, and the extra space between them, to share
+/// out `free` space as `align` says. `reversed` is whether the axis's flex-start is at
+/// its physical end, which is where the things are placed from.
+pub(crate) fn distribute(align: AlignContent, free: f32, count: usize, reversed: bool) -> (f32, f32) {
+    let count = count.max(1) as f32;
+    let align = match align {
+        AlignContent::Start if reversed => AlignContent::FlexEnd,
+        AlignContent::End if reversed => AlignContent::FlexStart,
+        align => align,
+    };
+    match align {
+        AlignContent::End | AlignContent::FlexEnd => (free, 0.0),
+        AlignContent::Center => (free / 2.0, 0.0),
+        // Without space to share, `space-between` acts like `flex-start` and the others like `center`
+        AlignContent::SpaceBetween if free > 0.0 && count > 1.0 => (0.0, free / (count - 1.0)),
+        AlignContent::SpaceAround if free > 0.0 => (free / count / 2.0, free / count),
+        AlignContent::SpaceEvenly if free > 0.0 => (free / (count + 1.0), free / (count + 1.0)),
+        AlignContent::SpaceAround | AlignContent::SpaceEvenly => (free / 2.0, 0.0),
+        AlignContent::Normal
+        | AlignContent::Start
+        | AlignContent::FlexStart
+        | AlignContent::SpaceBetween
+        | AlignContent::Stretch => (0.0, 0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::test_support::{contents, laid_out, rect};
+    use foamium_dom::parse_html;
+
+    #[test]
+    fn test_grow_shrink_and_gap() {
+        let dom = parse_html(
+            "<div><p id=\"a\"></p><p id=\"b\"></p><p id=\"c\"></p></div><div><p></p><p id=\"d\"></p></div>",
+        );
+        let stylesheet = foamium_css::parse(
+            "div { display: flex; width: 310px; gap: 10px; }
+             div + div { width: 100px; gap: 0; }
+             #a { width: 50px; flex-grow: 1; height: 10px; }
+             #b { width: 50px; flex-grow: 3; }
+             #c { flex: none; width: 70px; height: 30px; }
+             div + div p { flex: 0 1 100px; }
+             div + div #d { flex: 0 2 50px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        // 120px to share out, a quarter and three quarters; the auto height stretches
+        let row = &root.children[0];
+        assert_eq!(
+            contents(row),
+            [rect(0.0, 0.0, 80.0, 10.0), rect(90.0, 0.0, 140.0, 30.0), rect(240.0, 0.0, 70.0, 30.0)]
+        );
+        assert_eq!(row.dimensions.content.height, 30.0);
+        // 50px too much, taken in proportion to shrink factor times base size
+        let widths: Vec<f32> = contents(&root.children[1]).iter().map(|content| content.width).collect();
+        assert_eq!(widths, [75.0, 25.0]);
+    }
+
+    #[test]
+    fn test_alignment() {
+        let dom = parse_html("<div><p></p><p></p><p></p></div><nav><p></p></nav><ul><p></p><p></p><p></p></ul>");
+        let stylesheet = foamium_css::parse(
+            "div, nav, ul { display: flex; width: 200px; }
+             p { width: 20px; }
+             div { height: 50px; justify-content: space-between; align-items: center; }
+             div p { height: 10px; }
+             div p + p { height: 20px; }
+             div p + p + p { height: auto; align-self: flex-end; }
+             nav p { margin: 5px 0 5px auto; height: 10px; }
+             ul { flex-wrap: wrap; width: 50px; height: 100px; column-gap: 10px; align-content: space-between; }
+             ul p { height: 20px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        assert_eq!(
+            contents(&root.children[0]),
+            [rect(0.0, 20.0, 20.0, 10.0), rect(90.0, 15.0, 20.0, 20.0), rect(180.0, 50.0, 20.0, 0.0)]
+        );
+        // An auto margin takes all the free space
+        let nav = &root.children[1];
+        assert_eq!(contents(nav), [rect(180.0, 55.0, 20.0, 10.0)]);
+        assert_eq!(nav.children[0].dimensions.margin.left, 180.0);
+        assert_eq!(nav.dimensions.content.height, 20.0);
+        // Two lines, pushed apart by `align-content`
+        assert_eq!(
+            contents(&root.children[2]),
+            [rect(0.0, 70.0, 20.0, 20.0), rect(30.0, 70.0, 20.0, 20.0), rect(0.0, 150.0, 20.0, 20.0)]
+        );
+    }
+
+    #[test]
+    fn test_column_and_order() {
+        let dom = parse_html("<div><p>a</p><p class=\"grow\"></p><p class=\"fixed\"></p></div>");
+        let stylesheet = foamium_css::parse(
+            "div { display: flex; flex-direction: column-reverse; width: 100px; height: 90px; font-size: 10px; line-height: 10px; }
+             p { order: 2; }
+             .grow { flex: 1; order: 1; }
+             .fixed { height: 20px; order: 1; align-self: center; width: 30px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        // In order 2, 3, 1 from the bottom up; the first grows into what the others leave
+        assert_eq!(
+            contents(&root.children[0]),
+            [rect(0.0, 0.0, 100.0, 10.0), rect(0.0, 30.0, 100.0, 60.0), rect(35.0, 10.0, 30.0, 20.0)]
+        );
+    }
+
+    #[test]
+    fn test_automatic_minimum_size() {
+        let dom = parse_html("<div><p>aaaa bbbbbbbb</p><p></p></div>");
+        let stylesheet = foamium_css::parse(
+            "div { display: flex; width: 50px; font-size: 10px; }
+             p + p { width: 40px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        // The text would shrink to 31px, but can't go below its longest word
+        let widths: Vec<f32> = contents(&root.children[0]).iter().map(|content| content.width).collect();
+        assert_eq!(widths, [40.0, 10.0]);
+        assert_eq!(root.children[0].children[0].lines.len(), 2);
+    }
+
+    #[test]
+    fn test_history_item_row() {
+        let dom = parse_html(
+            "<a class=\"history-item\"><span class=\"icon\">G</span><span class=\"title\">Example page</span><span>12:00</span></a>",
+        );
+        let stylesheet = foamium_css::parse(
+            ".history-item { display: flex; align-items: center; gap: 16px; padding: 12px 16px; font-size: 10px; }
+             .icon { width: 24px; height: 24px; display: flex; align-items: center; justify-content: center; flex-shrink: 0; }
+             .title { flex: 1; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let item = &root.children[0];
+        assert_eq!(item.display, Display::Flex);
+        assert_eq!(
+            contents(item),
+            [rect(16.0, 12.0, 24.0, 24.0), rect(56.0, 18.0, 687.0, 12.0), rect(759.0, 18.0, 25.0, 12.0)]
+        );
+        // The text in the icon is in an anonymous item, centred both ways
+        assert_eq!(item.children[0].children[0].dimensions.content, rect(25.5, 18.0, 5.0, 12.0));
+    }
+
+    #[test]
+    fn test_inline_flex() {
+        let dom = parse_html("<p>ab <span>cd</span> ef</p>");
+        let stylesheet = foamium_css::parse(
+            "p { display: block; font-size: 10px; }
+             span { display: inline-flex; padding: 0 5px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let p = &root.children[0];
+        // An atomic inline, shrunk to fit its text and sitting on the line's baseline
+        assert_eq!(p.children[1].dimensions.content, rect(20.0, 0.0, 10.0, 12.0));
+        assert_eq!(p.children[2].fragments[0].rect.x, 35.0);
+        assert_eq!(p.lines.len(), 1);
+    }
+}
@@ -0,0 +1,282 @@
+// Floats (CSS 2.1 §9.5): boxes shifted to the left or right of where they'd be, with the
+// content after them flowing around them
+//
+// The floats in a block formatting context are placed in the coordinates of its root's
+// content box, since they shorten the line boxes of every block inside it. Block layout
+// tells each block where its content box is in those coordinates before laying out its
+// contents. When margins collapse through the top of a block that's an estimate, as how
+// far they go depends on what's inside, so floats can end up slightly off from the blocks
+// around them then.
+use crate::block::layout_shrink_to_fit;
+use crate::box_model::computed;
+use crate::{ComputedStyle, LayoutBox, PropertyMap, Rect, TextMeasurer};
+use foamium_css::Value;
+
+/// `float`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Float {
+    #[default]
+    None,
+    Left,
+    Right,
+}
+
+/// `clear`: which sides' floats a box goes below.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Clear {
+    #[default]
+    None,
+    Left,
+    Right,
+    Both,
+}
+
+/// Compute `float` and `clear` into `style`. Neither inherits.
+pub(crate) fn compute_float_style(style: &mut ComputedStyle, values: &PropertyMap, parent: &ComputedStyle) {
+    let keyword = |value: &Value| match value {
+        Value::Keyword(keyword) => Some(keyword.to_ascii_lowercase()),
+        _ => None,
+    };
+    style.float = computed(values, "float", &parent.float, Float::None, |value| {
+        Some(match &*keyword(value)? {
+            "none" => Float::None,
+            "left" => Float::Left,
+            "right" => Float::Right,
+            _ => return None,
+        })
+    });
+    style.clear = computed(values, "clear", &parent.clear, Clear::None, |value| {
+        Some(match &*keyword(value)? {
+            "none" => Clear::None,
+            "left" => Clear::Left,
+            "right" => Clear::Right,
+            "both" => Clear::Both,
+            _ => return None,
+        })
+    });
+}
+
+/// Whether a box is floated, which takes it out of flow.
+pub(crate) fn is_float(layout_box: &LayoutBox) -> bool {
+    layout_box.style.float != Float::None
+}
+
+/// A float's margin box, once it's placed.
+#[derive(Debug, Clone, Copy)]
+struct PlacedFloat {
+    side: Float,
+    rect: Rect,
+}
+
+impl PlacedFloat {
+    /// Whether the float is beside any of the band from `top` down by `height`. A band
+    /// with no height is beside the floats that start at its top.
+    fn is_beside(&self, top: f32, height: f32) -> bool {
+        (self.rect.y < top + height || self.rect.y <= top) && self.rect.y + self.rect.height > top
+    }
+}
+
+/// The floats placed so far in a block formatting context.
+#[derive(Debug, Default)]
+pub(crate) struct FloatContext {
+    floats: Vec<PlacedFloat>,
+    /// The top of the last float placed, which later ones can't go above
+    top: f32,
+}
+
+impl FloatContext {
+    /// Whether no floats have been placed.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.floats.is_empty()
+    }
+
+    /// The left and right edges of the room between the floats beside the band from `top`
+    /// down by `height`, within `limits`.
+    pub(crate) fn band(&self, top: f32, height: f32, limits: (f32, f32)) -> (f32, f32) {
+        self.floats
+            .iter()
+            .filter(|float| float.is_beside(top, height))
+            .fold(limits, |(left, right), float| match float.side {
+                Float::Left => (left.max(float.rect.x + float.rect.width), right),
+                _ => (left, right.min(float.rect.x)),
+            })
+    }
+
+    /// The highest bottom edge of a float below `top`, where the room beside the floats
+    /// next changes.
+    pub(crate) fn next_bottom(&self, top: f32) -> Option<f32> {
+        self.floats
+            .iter()
+            .map(|float| float.rect.y + float.rect.height)
+            .filter(|&bottom| bottom > top)
+            .reduce(f32::min)
+    }
+
+    /// How far down a box that clears `clear` has to go: below the floats on those sides.
+    pub(crate) fn clearance(&self, clear: Clear) -> Option<f32> {
+        self.floats
+            .iter()
+            .filter(|float| match clear {
+                Clear::None => false,
+                Clear::Left => float.side == Float::Left,
+                Clear::Right => float.side == Float::Right,
+                Clear::Both => true,
+            })
+            .map(|float| float.rect.y + float.rect.height)
+            .reduce(f32::max)
+    }
+
+    /// The bottom of the lowest float, which a formatting context's root grows to contain.
+    pub(crate) fn bottom(&self) -> f32 {
+        self.floats.iter().map(|float| float.rect.y + float.rect.height).fold(0.0, f32::max)
+    }
+
+    /// Place a float's margin box of `size`, no higher than `top` or the floats before it,
+    /// and as far to its side of `limits` as it goes. If it's too wide to fit beside the
+    /// floats already there, it goes down until it does or there are none. Returns where
+    /// its top left corner goes.
+    fn place(&mut self, side: Float, clear: Clear, size: (f32, f32), top: f32, limits: (f32, f32)) -> (f32, f32) {
+        let mut top = top.max(self.top).max(self.clearance(clear).unwrap_or(f32::NEG_INFINITY));
+        let (left, right) = loop {
+            let band = self.band(top, size.1, limits);
+            if band.1 - band.0 >= size.0 || band == limits {
+                break band;
+            }
+            match self.next_bottom(top) {
+                Some(bottom) => top = bottom,
+                None => break band,
+            }
+        };
+        let x = match side {
+            Float::Right => right - size.0,
+            _ => left,
+        };
+        let rect = Rect {
+            x,
+            y: top,
+            width: size.0,
+            height: size.1,
+        };
+        self.floats.push(PlacedFloat { side, rect });
+        self.top = top;
+        (x, top)
+    }
+
+    /// Place a float whose margin box is `size`, in a line or between blocks starting
+    /// `top` down a containing block whose content box starts at `origin` in the floats'
+    /// coordinates. Returns where its margin box goes relative to the containing block.
+    pub(crate) fn place_in(
+        &mut self,
+        style: &ComputedStyle,
+        size: (f32, f32),
+        top: f32,
+        origin: (f32, f32),
+        containing_width: f32,
+    ) -> (f32, f32) {
+        let limits = (origin.0, origin.0 + containing_width);
+        let (x, y) = self.place(style.float, style.clear, size, origin.1 + top, limits);
+        (x - origin.0, y - origin.1)
+    }
+}
+
+/// Lay out a float between blocks, at its shrink-to-fit width, and place it in `floats`.
+/// Like other blocks, it's positioned relative to its containing block's content box,
+/// which starts at `origin` in the floats' coordinates.
+pub(crate) fn layout_float(
+    layout_box: &mut LayoutBox,
+    containing_width: f32,
+    floats: &mut FloatContext,
+    origin: (f32, f32),
+    top: f32,
+    measurer: &dyn TextMeasurer,
+) {
+    layout_shrink_to_fit(layout_box, containing_width, measurer);
+    let margin_box = layout_box.dimensions.margin_box();
+    let position = floats.place_in(&layout_box.style, (margin_box.width, margin_box.height), top, origin, containing_width);
+    set_position(layout_box, position);
+}
+
+/// Move a laid out float so its margin box's top left corner is at `position`.
+pub(crate) fn set_position(layout_box: &mut LayoutBox, position: (f32, f32)) {
+    let dimensions = &mut layout_box.dimensions;
+    dimensions.content.x = position.0 + dimensions.margin.left + dimensions.border.left + dimensions.padding.left;
+    dimensions.content.y = position.1 + dimensions.margin.top + dimensions.border.top + dimensions.padding.top;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::test_support::{laid_out, rect};
+    use foamium_dom::parse_html;
+
+    #[test]
+    fn test_floats_shorten_lines() {
+        let dom = parse_html(
+            "<div><span id=\"l\"></span><span id=\"r\"></span>aaaa bbbb cccc dddd eeee ffff gggg<p></p></div>\
+             <div>aaaa <em></em>bbbb</div>",
+        );
+        let stylesheet = foamium_css::parse(
+            "div { display: block; width: 100px; font-size: 10px; line-height: 10px; }
+             p { display: block; }
+             #l { float: left; width: 30px; height: 25px; }
+             #r { float: right; width: 20px; height: 15px; }
+             em { float: left; width: 20px; height: 10px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let div = &root.children[0];
+        assert_eq!(div.children[0].dimensions.content, rect(0.0, 0.0, 30.0, 25.0));
+        assert_eq!(div.children[1].dimensions.content, rect(80.0, 0.0, 20.0, 15.0));
+        // The text is wrapped in an anonymous block, which shares the div's floats
+        let run = &div.children[2];
+        let lines: Vec<Rect> = run.lines.iter().map(|line| line.rect).collect();
+        // The third line is only beside the left float
+        assert_eq!(lines, [rect(30.0, 0.0, 50.0, 10.0), rect(30.0, 10.0, 50.0, 10.0), rect(30.0, 20.0, 70.0, 10.0)]);
+        let text = &run.children[0];
+        let texts: Vec<&str> = text.fragments.iter().map(|fragment| &*fragment.text).collect();
+        assert_eq!(texts, ["aaaa bbbb", "cccc dddd", "eeee ffff gggg"]);
+        assert_eq!(text.fragments[2].rect.x, 30.0);
+
+        // A float in the middle of a line that it fits beside goes at its top, and moves
+        // what's before it over
+        let div = &root.children[1];
+        assert_eq!(div.children[1].dimensions.content, rect(0.0, 30.0, 20.0, 10.0));
+        assert_eq!(div.children[0].fragments[0].rect.x, 20.0);
+        assert_eq!(div.children[2].fragments[0].rect.x, 20.0 + 25.0);
+    }
+
+    #[test]
+    fn test_clear_and_formatting_context_roots() {
+        let dom = parse_html(
+            "<div id=\"a\"><div id=\"f\"></div></div><div id=\"c\"><div id=\"h\"></div></div>\
+             <div id=\"b\"></div><div id=\"e\"></div><div id=\"d\"><div id=\"g\"></div></div>",
+        );
+        let stylesheet = foamium_css::parse(
+            "div { display: block; }
+             #f { float: left; width: 50px; height: 40px; }
+             #c { clear: left; margin-top: 5px; height: 10px; }
+             #h { float: right; width: 100px; height: 30px; }
+             #b, #e, #d { display: flow-root; }
+             #b, #e { height: 10px; }
+             #e { width: 780px; }
+             #g { float: left; width: 20px; height: 30px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let contents: Vec<Rect> = root.children.iter().map(|child| child.dimensions.content).collect();
+        assert_eq!(
+            contents,
+            [
+                // Floats take no room in their parent
+                rect(0.0, 0.0, 800.0, 0.0),
+                // Cleared below the left float, but not the right one inside it
+                rect(0.0, 40.0, 800.0, 10.0),
+                // Formatting context roots go beside floats, or below them if they're too wide
+                rect(0.0, 50.0, 700.0, 10.0),
+                rect(0.0, 70.0, 780.0, 10.0),
+                // and grow to contain the floats inside them
+                rect(0.0, 80.0, 800.0, 30.0),
+            ]
+        );
+        assert_eq!(root.children[0].children[0].dimensions.content, rect(0.0, 0.0, 50.0, 40.0));
+        assert_eq!(root.children[1].children[0].dimensions.content, rect(700.0, 40.0, 100.0, 30.0));
+    }
+}
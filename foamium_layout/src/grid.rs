@@ -0,0 +1,1255 @@
+// Grid layout (CSS Grid 1): a grid container places its items in the areas of a grid of
+// rows and columns (§8), sizes the tracks to fit them (§11), and aligns each item in its
+// area
+//
+// Columns are sized first, from the items' intrinsic widths, and then rows, from the
+// heights the items take at the widths of their columns. Like flex items, grid items are
+// positioned relative to the container's content box.
+use crate::block::{ContainingBlock, layout_item, resolve_edges};
+use crate::box_model::{computed, is_negative};
+use crate::flex::distribute;
+use crate::intrinsic::{ContentSizes, outer_sizes, shrink_to_fit};
+use crate::position::is_out_of_flow;
+use crate::{
+    AlignContent, AlignItems, ComputedStyle, Display, LayoutBox, LengthContext, LengthPercentage, PropertyMap,
+    TextMeasurer,
+};
+use foamium_css::{GridLine, GridValue, RepeatCount, Value};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// One end of a track sizing function.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrackBreadth {
+    Length(LengthPercentage),
+    /// In `fr`; only ever a maximum
+    Flex(f32),
+    MinContent,
+    MaxContent,
+    Auto,
+    /// The limit of `fit-content()`; only ever a maximum
+    FitContent(LengthPercentage),
+}
+
+/// A track sizing function, as the minimum and maximum of `minmax()` (§7.2.3). A single
+/// size is both, except that a flexible size's minimum is `auto`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackSize {
+    pub min: TrackBreadth,
+    pub max: TrackBreadth,
+}
+
+impl TrackSize {
+    pub const AUTO: TrackSize = TrackSize {
+        min: TrackBreadth::Auto,
+        max: TrackBreadth::Auto,
+    };
+}
+
+/// A computed `grid-template-rows` or `grid-template-columns`, with repeats of a fixed
+/// count written out.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrackListEntry {
+    /// Names for the line between two tracks
+    LineNames(Vec<String>),
+    Track(TrackSize),
+    /// `repeat(auto-fill, ...)`, or `repeat(auto-fit, ...)` if `fit`, which repeats as
+    /// many times as fit once the container's size is known
+    AutoRepeat { fit: bool, entries: Vec<TrackListEntry> },
+}
+
+/// `grid-auto-flow`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GridAutoFlow {
+    /// Whether items fill each column before the next, rather than each row
+    pub column: bool,
+    /// Whether items go in the first space that fits rather than after the last item
+    pub dense: bool,
+}
+
+/// Compute the grid container and grid item properties into `style`. None of them inherit.
+pub(crate) fn compute_grid_style(
+    style: &mut ComputedStyle,
+    values: &PropertyMap,
+    parent: &ComputedStyle,
+    context: &LengthContext,
+) {
+    let is_none = |value: &Value| matches!(value, Value::Keyword(keyword) if keyword.eq_ignore_ascii_case("none"));
+    let template = |value: &Value| match value {
+        _ if is_none(value) => Some(Vec::new()),
+        Value::Grid(GridValue::Tracks(items)) => track_list(items, context),
+        _ => None,
+    };
+    style.grid_template_columns =
+        computed(values, "grid-template-columns", &parent.grid_template_columns, Vec::new(), template);
+    style.grid_template_rows = computed(values, "grid-template-rows", &parent.grid_template_rows, Vec::new(), template);
+    style.grid_template_areas = computed(values, "grid-template-areas", &parent.grid_template_areas, None, |value| {
+        match value {
+            _ if is_none(value) => Some(None),
+            Value::Grid(GridValue::Areas(areas)) => Some(Some(areas.clone())),
+            _ => None,
+        }
+    });
+
+    let auto_tracks = |value: &Value| match value {
+        Value::Grid(GridValue::Tracks(items)) => items
+            .iter()
+            .map(|item| match item {
+                foamium_css::TrackListItem::Track(size) => track_size(size, context),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .filter(|tracks| !tracks.is_empty()),
+        _ => None,
+    };
+    style.grid_auto_columns =
+        computed(values, "grid-auto-columns", &parent.grid_auto_columns, vec![TrackSize::AUTO], auto_tracks);
+    style.grid_auto_rows = computed(values, "grid-auto-rows", &parent.grid_auto_rows, vec![TrackSize::AUTO], auto_tracks);
+    style.grid_auto_flow = computed(values, "grid-auto-flow", &parent.grid_auto_flow, GridAutoFlow::default(), |value| {
+        match *value {
+            Value::Grid(GridValue::AutoFlow { column, dense }) => Some(GridAutoFlow { column, dense }),
+            _ => None,
+        }
+    });
+
+    let line = |value: &Value| match value {
+        Value::Grid(GridValue::Line(line)) => Some(line.clone()),
+        _ => None,
+    };
+    style.grid_row_start = computed(values, "grid-row-start", &parent.grid_row_start, GridLine::Auto, line);
+    style.grid_row_end = computed(values, "grid-row-end", &parent.grid_row_end, GridLine::Auto, line);
+    style.grid_column_start = computed(values, "grid-column-start", &parent.grid_column_start, GridLine::Auto, line);
+    style.grid_column_end = computed(values, "grid-column-end", &parent.grid_column_end, GridLine::Auto, line);
+
+    // Text runs left to right, so `left` and `right` are `start` and `end`
+    let justify = |keyword: &str| match keyword {
+        "left" => Some(AlignItems::Start),
+        "right" => Some(AlignItems::End),
+        keyword => AlignItems::from_keyword(keyword),
+    };
+    let keyword = |value: &Value| match value {
+        Value::Keyword(keyword) => Some(keyword.to_ascii_lowercase()),
+        _ => None,
+    };
+    style.justify_items = computed(values, "justify-items", &parent.justify_items, AlignItems::Normal, |value| {
+        justify(&keyword(value)?)
+    });
+    style.justify_self = computed(values, "justify-self", &parent.justify_self, None, |value| match &*keyword(value)? {
+        "auto" => Some(None),
+        keyword => justify(keyword).map(Some),
+    });
+}
+
+fn track_list(items: &[foamium_css::TrackListItem], context: &LengthContext) -> Option<Vec<TrackListEntry>> {
+    let mut entries = Vec::new();
+    for item in items {
+        match *item {
+            foamium_css::TrackListItem::LineNames(ref names) => entries.push(TrackListEntry::LineNames(names.clone())),
+            foamium_css::TrackListItem::Track(ref size) => entries.push(TrackListEntry::Track(track_size(size, context)?)),
+            foamium_css::TrackListItem::Repeat(RepeatCount::Count(count), ref repeated) => {
+                let repeated = track_list(repeated, context)?;
+                for _ in 0..count {
+                    entries.extend(repeated.iter().cloned());
+                }
+            }
+            foamium_css::TrackListItem::Repeat(count, ref repeated) => entries.push(TrackListEntry::AutoRepeat {
+                fit: count == RepeatCount::AutoFit,
+                entries: track_list(repeated, context)?,
+            }),
+        }
+    }
+    Some(entries)
+}
+
+fn track_size(size: &foamium_css::TrackSize, context: &LengthContext) -> Option<TrackSize> {
+    let length = |value: &Value| LengthPercentage::compute(value, context).filter(|length| !is_negative(length));
+    let breadth = |breadth: &foamium_css::TrackBreadth| {
+        Some(match *breadth {
+            foamium_css::TrackBreadth::Length(ref value) => TrackBreadth::Length(length(value)?),
+            foamium_css::TrackBreadth::Flex(fr) => TrackBreadth::Flex(fr),
+            foamium_css::TrackBreadth::MinContent => TrackBreadth::MinContent,
+            foamium_css::TrackBreadth::MaxContent => TrackBreadth::MaxContent,
+            foamium_css::TrackBreadth::Auto => TrackBreadth::Auto,
+        })
+    };
+    Some(match *size {
+        foamium_css::TrackSize::Breadth(foamium_css::TrackBreadth::Flex(fr)) => TrackSize {
+            min: TrackBreadth::Auto,
+            max: TrackBreadth::Flex(fr),
+        },
+        foamium_css::TrackSize::Breadth(ref size) => {
+            let size = breadth(size)?;
+            TrackSize {
+                min: size.clone(),
+                max: size,
+            }
+        }
+        foamium_css::TrackSize::MinMax(ref min, ref max) => TrackSize {
+            min: breadth(min)?,
+            max: breadth(max)?,
+        },
+        foamium_css::TrackSize::FitContent(ref limit) => TrackSize {
+            min: TrackBreadth::Auto,
+            max: TrackBreadth::FitContent(length(limit)?),
+        },
+    })
+}
+
+/// The explicit tracks along one axis, with the names of the lines between them.
+#[derive(Debug)]
+struct ExplicitTracks {
+    sizes: Vec<TrackSize>,
+    /// The names of each line, of which there is one more than there are tracks. Areas
+    /// can add lines past the template's tracks.
+    line_names: Vec<Vec<String>>,
+    /// The tracks repeated by `auto-fit`, which collapse if no item is in them
+    auto_fit: Range<usize>,
+}
+
+impl ExplicitTracks {
+    /// Write out a template, repeating any `auto-fill` or `auto-fit` as many times as
+    /// fits in `available`, or once if that's not known (§7.2.3.2).
+    fn new(entries: &[TrackListEntry], available: Option<f32>, gap: f32) -> ExplicitTracks {
+        let repeated = entries.iter().find_map(|entry| match *entry {
+            TrackListEntry::AutoRepeat { ref entries, .. } => Some(entries),
+            _ => None,
+        });
+        let repetitions = match (repeated, available) {
+            (None, _) => 0,
+            (Some(_), None) => 1,
+            (Some(repeated), Some(available)) => {
+                // Every track has a fixed minimum or maximum, and the maximum counts if
+                // it's fixed
+                let fixed = |entries: &[TrackListEntry]| {
+                    let sizes = entries.iter().filter_map(|entry| match *entry {
+                        TrackListEntry::Track(ref size) => Some(match (&size.max, &size.min) {
+                            (TrackBreadth::Length(length), _) | (_, TrackBreadth::Length(length)) => length.resolve(available),
+                            _ => 0.0,
+                        }),
+                        _ => None,
+                    });
+                    sizes.fold((0.0, 0.0), |(total, count), size| (total + size, count + 1.0))
+                };
+                let (outside, outside_count) = fixed(entries);
+                let (inside, inside_count) = fixed(repeated);
+                // A repetition less than 1px would repeat forever
+                let repetition = (inside + gap * inside_count).max(1.0);
+                ((available - outside - gap * outside_count + gap) / repetition).floor().max(1.0) as usize
+            }
+        };
+        let mut tracks = ExplicitTracks {
+            sizes: Vec::new(),
+            line_names: vec![Vec::new()],
+            auto_fit: 0..0,
+        };
+        tracks.push(entries, repetitions);
+        tracks
+    }
+
+    fn push(&mut self, entries: &[TrackListEntry], repetitions: usize) {
+        for entry in entries {
+            match *entry {
+                TrackListEntry::LineNames(ref names) => self.line_names.last_mut().unwrap().extend(names.iter().cloned()),
+                TrackListEntry::Track(ref size) => {
+                    self.sizes.push(size.clone());
+                    self.line_names.push(Vec::new());
+                }
+                TrackListEntry::AutoRepeat { fit, ref entries } => {
+                    let start = self.sizes.len();
+                    for _ in 0..repetitions {
+                        self.push(entries, 0);
+                    }
+                    if fit {
+                        self.auto_fit = start..self.sizes.len();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Name the lines at the edges of each area `<name>-start` and `<name>-end`.
+    fn add_area_names(&mut self, names: impl Iterator<Item = (String, Range<usize>)>) {
+        for (name, range) in names {
+            if self.line_names.len() <= range.end {
+                self.line_names.resize(range.end + 1, Vec::new());
+            }
+            self.line_names[range.start].push(format!("{}-start", name));
+            self.line_names[range.end].push(format!("{}-end", name));
+        }
+    }
+
+    /// The number of explicit tracks, including any that only areas make.
+    fn count(&self) -> i32 {
+        self.line_names.len() as i32 - 1
+    }
+
+    /// The explicit lines with `name`, in order.
+    fn named(&self, name: &str) -> Vec<i32> {
+        (0..self.line_names.len())
+            .filter(|&line| self.line_names[line].iter().any(|line_name| line_name == name))
+            .map(|line| line as i32)
+            .collect()
+    }
+
+    /// The nth line with `name`, counting back from the end if `n` is negative. Lines
+    /// past the explicit grid all count as having every name.
+    fn nth_named(&self, name: &str, n: i32) -> i32 {
+        let named = self.named(name);
+        let found = named.len() as i32;
+        if n > 0 {
+            if n <= found { named[n as usize - 1] } else { self.count() + n - found }
+        } else if -n <= found {
+            named[(found + n) as usize]
+        } else {
+            n + found
+        }
+    }
+
+    /// The line a placement property names, or `None` for `auto` and spans (§8.3).
+    /// Lines are numbered from zero at the start of the explicit grid.
+    fn line(&self, line: &GridLine, end: bool) -> Option<i32> {
+        Some(match *line {
+            GridLine::Auto | GridLine::Span(..) => return None,
+            GridLine::Line(n, None) if n > 0 => n - 1,
+            GridLine::Line(n, None) => self.count() + 1 + n,
+            GridLine::Line(n, Some(ref name)) => self.nth_named(name, n),
+            // An area's edge, or else a line with the name
+            GridLine::Named(ref name) => {
+                let edge = format!("{}-{}", name, if end { "end" } else { "start" });
+                match self.named(&edge).first().or(self.named(name).first()) {
+                    Some(&line) => line,
+                    None => self.nth_named(name, 1),
+                }
+            }
+        })
+    }
+
+    /// The line a span from `line` reaches, forwards or backwards.
+    fn span(&self, line: i32, span: u32, name: Option<&str>, forwards: bool) -> i32 {
+        let span = span as i32;
+        let Some(name) = name else {
+            return if forwards { line + span } else { line - span };
+        };
+        let named = self.named(name);
+        if forwards {
+            let after: Vec<i32> = named.into_iter().filter(|&named| named > line).collect();
+            match after.get(span as usize - 1) {
+                Some(&named) => named,
+                None => self.count().max(line) + span - after.len() as i32,
+            }
+        } else {
+            let before: Vec<i32> = named.into_iter().rev().filter(|&named| named < line).collect();
+            match before.get(span as usize - 1) {
+                Some(&named) => named,
+                None => line.min(0) - (span - before.len() as i32),
+            }
+        }
+    }
+
+    /// Where an item's start and end lines put it (§8.3.1).
+    fn placement(&self, start: &GridLine, end: &GridLine) -> Placement {
+        match (self.line(start, false), self.line(end, true)) {
+            (Some(start), Some(end)) if start == end => Placement::Definite(start..start + 1),
+            (Some(start), Some(end)) => Placement::Definite(start.min(end)..start.max(end)),
+            (Some(start), None) => match line_span(end) {
+                Some((span, name)) => Placement::Definite(start..self.span(start, span, name, true)),
+                None => Placement::Definite(start..start + 1),
+            },
+            (None, Some(end)) => match line_span(start) {
+                Some((span, name)) => Placement::Definite(self.span(end, span, name, false)..end),
+                None => Placement::Definite(end - 1..end),
+            },
+            // A span to a named line is just one track without a line to count from
+            (None, None) => match line_span(start).or(line_span(end)) {
+                Some((span, None)) => Placement::Auto(span as i32),
+                _ => Placement::Auto(1),
+            },
+        }
+    }
+}
+
+/// The count and name of a `span` line.
+fn line_span(line: &GridLine) -> Option<(u32, Option<&str>)> {
+    match *line {
+        GridLine::Span(span, ref name) => Some((span, name.as_deref())),
+        _ => None,
+    }
+}
+
+/// Where an item goes along one axis before auto-placement: between two lines, or
+/// anywhere it fits with a span.
+#[derive(Debug, Clone)]
+enum Placement {
+    Definite(Range<i32>),
+    Auto(i32),
+}
+
+/// Place the items whose positions are left `auto` (§8.5), given each item's placement
+/// across the tracks auto-placement fills one after another ("major": rows when filling
+/// rows) and along them ("minor"), and the number of explicit minor tracks. Returns the
+/// major and minor lines of every item.
+fn auto_place(placements: &[(Placement, Placement)], minor_count: i32, dense: bool) -> Vec<(Range<i32>, Range<i32>)> {
+    let mut areas: Vec<Option<(Range<i32>, Range<i32>)>> = vec![None; placements.len()];
+    let overlaps = |areas: &[Option<(Range<i32>, Range<i32>)>], major: &Range<i32>, minor: &Range<i32>| {
+        areas.iter().flatten().any(|(placed_major, placed_minor)| {
+            placed_major.start < major.end
+                && major.start < placed_major.end
+                && placed_minor.start < minor.end
+                && minor.start < placed_minor.end
+        })
+    };
+
+    // The implicit grid's minor tracks take in every definite position and are enough
+    // for the widest item
+    let minor_start = placements
+        .iter()
+        .filter_map(|(_, minor)| match minor {
+            Placement::Definite(minor) => Some(minor.start),
+            Placement::Auto(_) => None,
+        })
+        .fold(0, i32::min);
+    let mut minor_end = placements
+        .iter()
+        .map(|(_, minor)| match *minor {
+            Placement::Definite(ref minor) => minor.end,
+            Placement::Auto(span) => minor_start + span,
+        })
+        .fold(minor_count, i32::max);
+
+    // Items placed in both axes first, and then those locked to a major track, after
+    // any earlier ones in it unless packing densely
+    for (i, placement) in placements.iter().enumerate() {
+        if let (Placement::Definite(major), Placement::Definite(minor)) = placement {
+            areas[i] = Some((major.clone(), minor.clone()));
+        }
+    }
+    let mut cursors: HashMap<i32, i32> = HashMap::new();
+    for (i, placement) in placements.iter().enumerate() {
+        if let (Placement::Definite(major), Placement::Auto(span)) = placement {
+            let span = *span;
+            let mut minor = if dense { minor_start } else { *cursors.get(&major.start).unwrap_or(&minor_start) };
+            while overlaps(&areas, major, &(minor..minor + span)) {
+                minor += 1;
+            }
+            cursors.insert(major.start, minor + span);
+            minor_end = minor_end.max(minor + span);
+            areas[i] = Some((major.clone(), minor..minor + span));
+        }
+    }
+
+    // Then the rest, moving a cursor through the grid
+    let major_start = areas.iter().flatten().map(|(major, _)| major.start).fold(0, i32::min);
+    let mut cursor = (major_start, minor_start);
+    for (i, placement) in placements.iter().enumerate() {
+        let &(Placement::Auto(span), ref minor) = placement else {
+            continue;
+        };
+        if dense {
+            cursor = (major_start, minor_start);
+        }
+        let area = match *minor {
+            Placement::Definite(ref minor) => {
+                if !dense && minor.start < cursor.1 {
+                    cursor.0 += 1;
+                }
+                cursor.1 = minor.start;
+                while overlaps(&areas, &(cursor.0..cursor.0 + span), minor) {
+                    cursor.0 += 1;
+                }
+                (cursor.0..cursor.0 + span, minor.clone())
+            }
+            Placement::Auto(minor_span) => loop {
+                if cursor.1 + minor_span > minor_end {
+                    cursor = (cursor.0 + 1, minor_start);
+                    continue;
+                }
+                let area = (cursor.0..cursor.0 + span, cursor.1..cursor.1 + minor_span);
+                if !overlaps(&areas, &area.0, &area.1) {
+                    break area;
+                }
+                cursor.1 += 1;
+            },
+        };
+        areas[i] = Some(area);
+    }
+    areas.into_iter().map(Option::unwrap).collect()
+}
+
+/// A track's sizing function with percentages resolved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Breadth {
+    Fixed(f32),
+    Flex(f32),
+    MinContent,
+    MaxContent,
+    Auto,
+    FitContent(f32),
+}
+
+impl Breadth {
+    /// Percentages of an unknown size act as `auto`, or for `fit-content()`, as no limit.
+    fn new(breadth: &TrackBreadth, basis: Option<f32>) -> Breadth {
+        match *breadth {
+            TrackBreadth::Length(ref length) => length.resolve_against(basis).map_or(Breadth::Auto, Breadth::Fixed),
+            TrackBreadth::Flex(fr) => Breadth::Flex(fr),
+            TrackBreadth::MinContent => Breadth::MinContent,
+            TrackBreadth::MaxContent => Breadth::MaxContent,
+            TrackBreadth::Auto => Breadth::Auto,
+            TrackBreadth::FitContent(ref limit) => Breadth::FitContent(limit.resolve_against(basis).unwrap_or(f32::INFINITY)),
+        }
+    }
+
+    fn is_intrinsic(self) -> bool {
+        matches!(self, Breadth::MinContent | Breadth::MaxContent | Breadth::Auto | Breadth::FitContent(_))
+    }
+
+    /// Whether the breadth is sized from the items' max-content contributions.
+    fn is_max_content(self) -> bool {
+        matches!(self, Breadth::MaxContent | Breadth::Auto | Breadth::FitContent(_))
+    }
+}
+
+/// A row or column being sized (§11.4).
+#[derive(Debug, Clone)]
+struct Track {
+    min: Breadth,
+    max: Breadth,
+    base: f32,
+    /// Infinite until an item sets it
+    growth_limit: f32,
+    /// An empty `auto-fit` track, which takes no space and no gaps
+    collapsed: bool,
+}
+
+impl Track {
+    fn new(size: &TrackSize, basis: Option<f32>) -> Track {
+        let min = Breadth::new(&size.min, basis);
+        let max = Breadth::new(&size.max, basis);
+        let base = match min {
+            Breadth::Fixed(size) => size,
+            _ => 0.0,
+        };
+        let growth_limit = match max {
+            Breadth::Fixed(size) => size.max(base),
+            _ => f32::INFINITY,
+        };
+        Track {
+            min,
+            max,
+            base,
+            growth_limit,
+            collapsed: false,
+        }
+    }
+
+    fn is_flexible(&self) -> bool {
+        matches!(self.max, Breadth::Flex(_))
+    }
+
+    fn flex_factor(&self) -> f32 {
+        match self.max {
+            Breadth::Flex(fr) => fr,
+            _ => 0.0,
+        }
+    }
+
+    /// The growth limit, or the base size while there isn't one.
+    fn limit_or_base(&self) -> f32 {
+        if self.growth_limit.is_finite() { self.growth_limit } else { self.base }
+    }
+}
+
+/// The space tracks are sized in: a definite size, or for the grid's intrinsic sizes
+/// and a height that depends on the rows, a min-content or max-content constraint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AvailableSpace {
+    Definite(f32),
+    MinContent,
+    MaxContent,
+}
+
+/// A grid item, with the tracks it's in once it's placed.
+#[derive(Debug)]
+struct GridItem {
+    /// Which of the container's children the item is
+    index: usize,
+    rows: Range<usize>,
+    columns: Range<usize>,
+    /// The width of its content box
+    width: f32,
+}
+
+/// The size a track gets from an item spanning it.
+#[derive(Debug, Clone, Copy)]
+struct Contribution {
+    tracks: (usize, usize),
+    sizes: ContentSizes,
+}
+
+/// Place the items and set up the tracks along both axes, with the size of the
+/// container's content box if it's known.
+fn place_items(
+    style: &ComputedStyle,
+    children: &[LayoutBox],
+    width: Option<f32>,
+    height: Option<f32>,
+    gaps: (f32, f32),
+) -> (Vec<Track>, Vec<Track>, Vec<GridItem>) {
+    let mut columns = ExplicitTracks::new(&style.grid_template_columns, width, gaps.0);
+    let mut rows = ExplicitTracks::new(&style.grid_template_rows, height, gaps.1);
+    if let Some(ref areas) = style.grid_template_areas {
+        let bounds: Vec<_> =
+            areas.names().into_iter().map(|name| (name.to_string(), areas.bounds(name).unwrap())).collect();
+        rows.add_area_names(bounds.iter().map(|(name, (area_rows, _))| (name.clone(), area_rows.clone())));
+        columns.add_area_names(bounds.iter().map(|(name, (_, area_columns))| (name.clone(), area_columns.clone())));
+    }
+
+    // Items in `order`, and then in document order
+    let mut order: Vec<usize> = (0..children.len())
+        .filter(|&index| children[index].display != Display::None && !is_out_of_flow(&children[index]))
+        .collect();
+    order.sort_by_key(|&index| children[index].style.order);
+    let flow = style.grid_auto_flow;
+    let placements: Vec<(Placement, Placement)> = order
+        .iter()
+        .map(|&index| {
+            let style = &children[index].style;
+            let row = rows.placement(&style.grid_row_start, &style.grid_row_end);
+            let column = columns.placement(&style.grid_column_start, &style.grid_column_end);
+            if flow.column { (column, row) } else { (row, column) }
+        })
+        .collect();
+    let minor_count = if flow.column { rows.count() } else { columns.count() };
+    let areas: Vec<(Range<i32>, Range<i32>)> = auto_place(&placements, minor_count, flow.dense)
+        .into_iter()
+        .map(|(major, minor)| if flow.column { (minor, major) } else { (major, minor) })
+        .collect();
+
+    // The implicit grid covers the explicit grid and every item, and its lines are
+    // numbered from zero at its start
+    let bounds = |explicit: &ExplicitTracks, ranges: &mut dyn Iterator<Item = &Range<i32>>| {
+        ranges.fold(0..explicit.count(), |bounds, range| bounds.start.min(range.start)..bounds.end.max(range.end))
+    };
+    let row_bounds = bounds(&rows, &mut areas.iter().map(|(rows, _)| rows));
+    let column_bounds = bounds(&columns, &mut areas.iter().map(|(_, columns)| columns));
+    let to_tracks = |range: &Range<i32>, bounds: &Range<i32>| (range.start - bounds.start) as usize..(range.end - bounds.start) as usize;
+    let items: Vec<GridItem> = order
+        .into_iter()
+        .zip(&areas)
+        .map(|(index, (rows, columns))| GridItem {
+            index,
+            rows: to_tracks(rows, &row_bounds),
+            columns: to_tracks(columns, &column_bounds),
+            width: 0.0,
+        })
+        .collect();
+
+    let tracks = |explicit: &ExplicitTracks, auto: &[TrackSize], bounds: Range<i32>, basis: Option<f32>, occupied: &dyn Fn(usize) -> bool| {
+        // Implicit tracks repeat the `grid-auto-*` sizes, the first after the explicit
+        // grid and the last before it
+        let template_tracks = explicit.sizes.len() as i32;
+        let repeat = auto.len() as i32;
+        bounds
+            .clone()
+            .map(|line| {
+                let size = match line {
+                    0.. if line < template_tracks => &explicit.sizes[line as usize],
+                    0.. => &auto[((line - template_tracks) % repeat) as usize],
+                    _ => &auto[line.rem_euclid(repeat) as usize],
+                };
+                let mut track = Track::new(size, basis);
+                let explicit_index = line as usize;
+                if line >= 0 && explicit.auto_fit.contains(&explicit_index) {
+                    track.collapsed = !occupied((line - bounds.start) as usize);
+                }
+                track
+            })
+            .collect::<Vec<Track>>()
+    };
+    let row_tracks = tracks(&rows, &style.grid_auto_rows, row_bounds, height, &|track| {
+        items.iter().any(|item| item.rows.contains(&track))
+    });
+    let column_tracks = tracks(&columns, &style.grid_auto_columns, column_bounds, width, &|track| {
+        items.iter().any(|item| item.columns.contains(&track))
+    });
+    (column_tracks, row_tracks, items)
+}
+
+/// Size the tracks along one axis from the sizes the items spanning them contribute
+/// (§11.3), with `gap` between each pair. `stretch` is whether `auto` tracks grow to
+/// fill any space left.
+fn size_tracks(tracks: &mut [Track], contributions: &[Contribution], space: AvailableSpace, gap: f32, stretch: bool) {
+    for track in tracks.iter_mut().filter(|track| track.collapsed) {
+        track.min = Breadth::Fixed(0.0);
+        track.max = Breadth::Fixed(0.0);
+        track.base = 0.0;
+        track.growth_limit = 0.0;
+    }
+    let spans_flexible = |contribution: &Contribution| {
+        tracks[contribution.tracks.0..contribution.tracks.1].iter().any(Track::is_flexible)
+    };
+    let (flexible, inflexible): (Vec<Contribution>, Vec<Contribution>) =
+        contributions.iter().copied().partition(|contribution| spans_flexible(contribution));
+    // `auto` minimums take the min-content contribution, or under a max-content
+    // constraint, the max-content one
+    let auto_minimum = |sizes: &ContentSizes| if space == AvailableSpace::MaxContent { sizes.max } else { sizes.min };
+
+    // Resolve intrinsic sizes (§11.5): items spanning one track first, then by how many
+    // tracks they span
+    for contribution in inflexible.iter().filter(|contribution| contribution.tracks.1 - contribution.tracks.0 == 1) {
+        let track = &mut tracks[contribution.tracks.0];
+        let sizes = contribution.sizes;
+        match track.min {
+            Breadth::MinContent => track.base = track.base.max(sizes.min),
+            Breadth::MaxContent => track.base = track.base.max(sizes.max),
+            Breadth::Auto => track.base = track.base.max(auto_minimum(&sizes)),
+            _ => {}
+        }
+        let growth = match track.max {
+            Breadth::MinContent => sizes.min,
+            Breadth::MaxContent | Breadth::Auto => sizes.max,
+            Breadth::FitContent(limit) => sizes.max.min(limit),
+            _ => continue,
+        };
+        track.growth_limit = if track.growth_limit.is_finite() { track.growth_limit.max(growth) } else { growth };
+    }
+    for track in tracks.iter_mut() {
+        track.growth_limit = track.growth_limit.max(track.base);
+    }
+    let mut spans: Vec<usize> = inflexible.iter().map(|contribution| contribution.tracks.1 - contribution.tracks.0).filter(|&span| span > 1).collect();
+    spans.sort_unstable();
+    spans.dedup();
+    for span in spans {
+        let group: Vec<Contribution> =
+            inflexible.iter().filter(|contribution| contribution.tracks.1 - contribution.tracks.0 == span).copied().collect();
+        distribute_extra_space(tracks, &group, gap, Breadth::is_intrinsic, Target::IntrinsicBase, auto_minimum);
+        distribute_extra_space(
+            tracks,
+            &group,
+            gap,
+            |breadth| breadth == Breadth::MaxContent || breadth == Breadth::Auto && space == AvailableSpace::MaxContent,
+            Target::MaxContentBase,
+            |sizes| sizes.max,
+        );
+        distribute_extra_space(tracks, &group, gap, Breadth::is_intrinsic, Target::GrowthLimit, |sizes| sizes.min);
+        distribute_extra_space(tracks, &group, gap, Breadth::is_max_content, Target::GrowthLimit, |sizes| sizes.max);
+    }
+    // Items spanning flexible tracks only grow those tracks' base sizes
+    distribute_extra_space(tracks, &flexible, gap, |breadth| breadth == Breadth::Auto, Target::FlexibleBase, auto_minimum);
+    for track in tracks.iter_mut() {
+        if track.growth_limit.is_infinite() {
+            track.growth_limit = track.base;
+        }
+    }
+
+    // Maximize the tracks (§11.6), growing them to their limits if there's room
+    let open = tracks.iter().filter(|track| !track.collapsed).count();
+    let gaps = gap * open.saturating_sub(1) as f32;
+    let free = match space {
+        AvailableSpace::Definite(available) => available - gaps - tracks.iter().map(|track| track.base).sum::<f32>(),
+        AvailableSpace::MinContent => 0.0,
+        AvailableSpace::MaxContent => f32::INFINITY,
+    };
+    if free.is_infinite() {
+        for track in tracks.iter_mut() {
+            track.base = track.growth_limit;
+        }
+    } else if free > 0.0 {
+        let mut growing: Vec<usize> = (0..tracks.len()).collect();
+        let mut free = free;
+        while free > 0.001 && !growing.is_empty() {
+            let share = free / growing.len() as f32;
+            growing.retain(|&index| {
+                let track = &mut tracks[index];
+                let grow = share.min(track.growth_limit - track.base);
+                track.base += grow;
+                free -= grow;
+                track.growth_limit - track.base > 0.001
+            });
+        }
+    }
+
+    // Expand flexible tracks (§11.7) to the size of one `fr` times their factors
+    if tracks.iter().any(Track::is_flexible) {
+        let fr = match space {
+            AvailableSpace::MinContent => 0.0,
+            AvailableSpace::Definite(available) => fr_size(tracks, 0..tracks.len(), available - gaps),
+            AvailableSpace::MaxContent => {
+                let from_tracks = tracks
+                    .iter()
+                    .filter(|track| track.is_flexible())
+                    .map(|track| track.base / track.flex_factor().max(1.0))
+                    .fold(0.0, f32::max);
+                flexible
+                    .iter()
+                    .map(|contribution| {
+                        let range = contribution.tracks.0..contribution.tracks.1;
+                        let gaps = gap * (range.len() - 1) as f32;
+                        fr_size(tracks, range, contribution.sizes.max - gaps)
+                    })
+                    .fold(from_tracks, f32::max)
+            }
+        };
+        for track in tracks.iter_mut().filter(|track| track.is_flexible()) {
+            track.base = track.base.max(fr * track.flex_factor());
+        }
+    }
+
+    // Stretch `auto` tracks (§11.8) into whatever's left
+    if stretch && let AvailableSpace::Definite(available) = space {
+        let free = available - gaps - tracks.iter().map(|track| track.base).sum::<f32>();
+        stretch_auto_tracks(tracks, free);
+    }
+}
+
+/// Which size `distribute_extra_space` grows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    /// Base sizes, of tracks whose minimum matches, for a minimum or min-content
+    /// contribution
+    IntrinsicBase,
+    /// Base sizes, of tracks whose minimum matches, for a max-content contribution
+    MaxContentBase,
+    /// Growth limits, of tracks whose maximum matches
+    GrowthLimit,
+    /// Base sizes of flexible tracks, whose minimum matches
+    FlexibleBase,
+}
+
+/// Grow the tracks that items span so the items fit (§11.5.1). Each item's extra space
+/// is shared equally between the tracks it affects, first up to their limits and then
+/// past them, and each track grows by the most any item asks of it.
+fn distribute_extra_space(
+    tracks: &mut [Track],
+    contributions: &[Contribution],
+    gap: f32,
+    affects: impl Fn(Breadth) -> bool,
+    target: Target,
+    size: impl Fn(&ContentSizes) -> f32,
+) {
+    let mut planned = vec![0.0f32; tracks.len()];
+    for contribution in contributions {
+        let range = contribution.tracks.0..contribution.tracks.1;
+        let current = |track: &Track| if target == Target::GrowthLimit { track.limit_or_base() } else { track.base };
+        let spanned = tracks[range.clone()].iter().map(current).sum::<f32>() + gap * (range.len() - 1) as f32;
+        let mut space = (size(&contribution.sizes) - spanned).max(0.0);
+        let affected: Vec<usize> = range
+            .filter(|&index| {
+                let track = &tracks[index];
+                match target {
+                    Target::IntrinsicBase | Target::MaxContentBase => affects(track.min),
+                    Target::GrowthLimit => affects(track.max),
+                    Target::FlexibleBase => track.is_flexible() && affects(track.min),
+                }
+            })
+            .collect();
+        if space <= 0.0 || affected.is_empty() {
+            continue;
+        }
+        let limit = |track: &Track| match (target, track.max) {
+            (Target::IntrinsicBase | Target::MaxContentBase, _) => track.growth_limit,
+            (Target::GrowthLimit, Breadth::FitContent(limit)) => limit.max(track.base),
+            _ => f32::INFINITY,
+        };
+        let mut increases = vec![0.0f32; affected.len()];
+        let mut growing: Vec<usize> = (0..affected.len()).collect();
+        while space > 0.001 && !growing.is_empty() {
+            let share = space / growing.len() as f32;
+            growing.retain(|&i| {
+                let track = &tracks[affected[i]];
+                let grow = share.min(limit(track) - current(track) - increases[i]).max(0.0);
+                increases[i] += grow;
+                space -= grow;
+                grow == share
+            });
+        }
+        // What's left goes past the limits, to tracks whose maximum depends on the
+        // content in the same way if there are any
+        if space > 0.001 {
+            let beyond: Vec<usize> = (0..affected.len())
+                .filter(|&i| match target {
+                    Target::IntrinsicBase => tracks[affected[i]].max.is_intrinsic(),
+                    Target::MaxContentBase => tracks[affected[i]].max.is_max_content(),
+                    Target::GrowthLimit | Target::FlexibleBase => true,
+                })
+                .collect();
+            let beyond = if beyond.is_empty() { (0..affected.len()).collect() } else { beyond };
+            for &i in &beyond {
+                increases[i] += space / beyond.len() as f32;
+            }
+        }
+        for (i, &index) in affected.iter().enumerate() {
+            planned[index] = planned[index].max(increases[i]);
+        }
+    }
+    for (track, increase) in tracks.iter_mut().zip(planned) {
+        if increase == 0.0 {
+            continue;
+        }
+        match target {
+            Target::IntrinsicBase | Target::MaxContentBase | Target::FlexibleBase => track.base += increase,
+            Target::GrowthLimit => track.growth_limit = track.limit_or_base() + increase,
+        }
+        if track.growth_limit < track.base {
+            track.growth_limit = track.base;
+        }
+    }
+}
+
+/// The size of one `fr` that fills `space` with the tracks in `range` (§11.7.1).
+/// Flexible tracks whose base size is more than their share are treated as inflexible.
+fn fr_size(tracks: &[Track], range: Range<usize>, space: f32) -> f32 {
+    let mut inflexible: Vec<bool> = tracks.iter().map(|track| !track.is_flexible()).collect();
+    loop {
+        let used: f32 = range.clone().filter(|&index| inflexible[index]).map(|index| tracks[index].base).sum();
+        let factors: f32 = range.clone().filter(|&index| !inflexible[index]).map(|index| tracks[index].flex_factor()).sum();
+        let fr = (space - used).max(0.0) / factors.max(1.0);
+        let too_big: Vec<usize> = range
+            .clone()
+            .filter(|&index| !inflexible[index] && fr * tracks[index].flex_factor() < tracks[index].base)
+            .collect();
+        if too_big.is_empty() {
+            return fr;
+        }
+        for index in too_big {
+            inflexible[index] = true;
+        }
+    }
+}
+
+fn stretch_auto_tracks(tracks: &mut [Track], free: f32) {
+    let auto = tracks.iter().filter(|track| track.max == Breadth::Auto && !track.collapsed).count();
+    if free > 0.0 && auto > 0 {
+        for track in tracks.iter_mut().filter(|track| track.max == Breadth::Auto && !track.collapsed) {
+            track.base += free / auto as f32;
+        }
+    }
+}
+
+/// Where each track starts, given the free space to share out with `align`. Collapsed
+/// tracks take no gap of their own.
+fn track_positions(tracks: &[Track], gap: f32, align: AlignContent, size: f32) -> Vec<f32> {
+    let open = tracks.iter().filter(|track| !track.collapsed).count();
+    let used = tracks.iter().map(|track| track.base).sum::<f32>() + gap * open.saturating_sub(1) as f32;
+    let align = match align {
+        AlignContent::FlexStart => AlignContent::Start,
+        AlignContent::FlexEnd => AlignContent::End,
+        align => align,
+    };
+    let (mut position, between) = distribute(align, size - used, open, false);
+    let mut remaining = open;
+    tracks
+        .iter()
+        .map(|track| {
+            let start = position;
+            position += track.base;
+            if !track.collapsed {
+                remaining -= 1;
+                if remaining > 0 {
+                    position += gap + between;
+                }
+            }
+            start
+        })
+        .collect()
+}
+
+/// Lay out a grid container's items, given the container's content width and height.
+/// `height_limits` are its minimum and maximum content heights, for when the height
+/// depends on the rows. Returns the height of the contents.
+pub(crate) fn layout_grid(
+    container: &mut LayoutBox,
+    containing: ContainingBlock,
+    height_limits: (f32, f32),
+    measurer: &dyn TextMeasurer,
+) -> f32 {
+    let LayoutBox { style, children, .. } = container;
+    let column_gap = style.column_gap.resolve(containing.width);
+    let row_gap = style.row_gap.resolve_against(containing.height).unwrap_or(0.0);
+    let clamp_height = |height: f32| height.min(height_limits.1).max(height_limits.0);
+    let max_height = containing.height.or(Some(height_limits.1).filter(|max| max.is_finite()));
+    let (mut columns, mut rows, mut items) =
+        place_items(style, children, Some(containing.width), max_height, (column_gap, row_gap));
+    let stretches = |align: AlignContent| matches!(align, AlignContent::Normal | AlignContent::Stretch);
+
+    // Columns, from the items' intrinsic widths
+    let contributions: Vec<Contribution> = items
+        .iter()
+        .map(|item| Contribution {
+            tracks: (item.columns.start, item.columns.end),
+            sizes: outer_sizes(&children[item.index], measurer),
+        })
+        .collect();
+    size_tracks(
+        &mut columns,
+        &contributions,
+        AvailableSpace::Definite(containing.width),
+        column_gap,
+        stretches(style.justify_content),
+    );
+    let column_positions = track_positions(&columns, column_gap, style.justify_content, containing.width);
+    let area = |tracks: &[Track], positions: &[f32], range: &Range<usize>| {
+        let last = range.end - 1;
+        (positions[range.start], positions[last] + tracks[last].base - positions[range.start])
+    };
+
+    // Rows, from the items' heights at the widths of their areas
+    let mut contributions = Vec::new();
+    for item in &mut items {
+        let (_, area_width) = area(&columns, &column_positions, &item.columns);
+        let child = &mut children[item.index];
+        resolve_edges(child, area_width);
+        item.width = item_width(child, area_width, style, measurer);
+        let height = specified_height(child, None, area_width);
+        let height = layout_item(child, item.width, height, ContainingBlock { width: area_width, height: None }, measurer);
+        let dimensions = &child.dimensions;
+        let outer = height + dimensions.margin_box().height - dimensions.content.height;
+        contributions.push(Contribution {
+            tracks: (item.rows.start, item.rows.end),
+            sizes: ContentSizes { min: outer, max: outer },
+        });
+    }
+    let space = containing.height.map_or(AvailableSpace::MaxContent, AvailableSpace::Definite);
+    size_tracks(&mut rows, &contributions, space, row_gap, stretches(style.align_content));
+    let open_rows = rows.iter().filter(|track| !track.collapsed).count();
+    let used = rows.iter().map(|track| track.base).sum::<f32>() + row_gap * open_rows.saturating_sub(1) as f32;
+    let height = containing.height.unwrap_or_else(|| clamp_height(used));
+    // A `min-height` leaves room for the `auto` rows to grow into
+    if containing.height.is_none() && stretches(style.align_content) {
+        stretch_auto_tracks(&mut rows, height - used);
+    }
+    let row_positions = track_positions(&rows, row_gap, style.align_content, height);
+
+    // Each item in its area
+    for item in &items {
+        let (x, area_width) = area(&columns, &column_positions, &item.columns);
+        let (y, area_height) = area(&rows, &row_positions, &item.rows);
+        let child = &mut children[item.index];
+        let child_style = &child.style;
+        let justify = child_style.justify_self.unwrap_or(style.justify_items);
+        let align = child_style.align_self.unwrap_or(style.align_items);
+        let dimensions = &child.dimensions;
+        let vertical_edges = dimensions.margin.top
+            + dimensions.margin.bottom
+            + dimensions.border.top
+            + dimensions.border.bottom
+            + dimensions.padding.top
+            + dimensions.padding.bottom;
+        let vertical_auto = (child_style.margin.top.is_none(), child_style.margin.bottom.is_none());
+        let height = match specified_height(child, Some(area_height), area_width) {
+            Some(height) => Some(height),
+            None if stretches_item(align, vertical_auto) => {
+                Some(child_style.clamp_height(area_height - vertical_edges, area_width, Some(area_height)).max(0.0))
+            }
+            None => None,
+        };
+        let containing = ContainingBlock {
+            width: area_width,
+            height: Some(area_height),
+        };
+        let height = layout_item(child, item.width, height, containing, measurer);
+
+        let horizontal_auto = (child.style.margin.left.is_none(), child.style.margin.right.is_none());
+        let dimensions = &mut child.dimensions;
+        let free_width = area_width - item.width - (dimensions.margin_box().width - dimensions.content.width);
+        let free_height = area_height - height - vertical_edges;
+        let margin = &mut dimensions.margin;
+        let x = x + align_in_area(justify, free_width, horizontal_auto, (&mut margin.left, &mut margin.right));
+        let y = y + align_in_area(align, free_height, vertical_auto, (&mut margin.top, &mut margin.bottom));
+        dimensions.content.x = x + dimensions.margin.left + dimensions.border.left + dimensions.padding.left;
+        dimensions.content.y = y + dimensions.margin.top + dimensions.border.top + dimensions.padding.top;
+    }
+
+    height
+}
+
+/// Whether `normal` or `stretch` stretches an item without `auto` margins.
+fn stretches_item(align: AlignItems, auto_margins: (bool, bool)) -> bool {
+    matches!(align, AlignItems::Normal | AlignItems::Stretch) && !auto_margins.0 && !auto_margins.1
+}
+
+/// An item's content width in an area `area_width` wide: its own width, or the area's
+/// if it stretches, or else its shrink-to-fit width.
+fn item_width(child: &LayoutBox, area_width: f32, container: &ComputedStyle, measurer: &dyn TextMeasurer) -> f32 {
+    let style = &child.style;
+    let edges = child.dimensions.margin_box().width - child.dimensions.content.width;
+    let justify = style.justify_self.unwrap_or(container.justify_items);
+    let width = match style.width {
+        Some(ref width) => width.resolve(area_width) - style.box_sizing_adjustment(true, area_width),
+        None if stretches_item(justify, (style.margin.left.is_none(), style.margin.right.is_none())) => area_width - edges,
+        None => shrink_to_fit(child, area_width - edges, measurer),
+    };
+    style.clamp_width(width, area_width).max(0.0)
+}
+
+/// An item's own content height, if it has one, in an area `area_height` tall.
+fn specified_height(child: &LayoutBox, area_height: Option<f32>, area_width: f32) -> Option<f32> {
+    let style = &child.style;
+    let height = style.height.as_ref()?.resolve_against(area_height)?;
+    let height = height - style.box_sizing_adjustment(false, area_width);
+    Some(style.clamp_height(height, area_width, area_height).max(0.0))
+}
+
+/// The offset of an item's margin box in its area, with `free` space left over, and the
+/// margins any of it goes to. `auto` margins take the free space before `align` does,
+/// and a baseline acts as `start`.
+fn align_in_area(align: AlignItems, free: f32, auto_margins: (bool, bool), margins: (&mut f32, &mut f32)) -> f32 {
+    if auto_margins.0 || auto_margins.1 {
+        let share = free.max(0.0) / (auto_margins.0 as usize + auto_margins.1 as usize) as f32;
+        if auto_margins.0 {
+            *margins.0 = share;
+        }
+        if auto_margins.1 {
+            *margins.1 = share;
+        }
+        return 0.0;
+    }
+    match align {
+        AlignItems::Center => free / 2.0,
+        AlignItems::End | AlignItems::FlexEnd => free,
+        _ => 0.0,
+    }
+}
+
+/// The min-content and max-content widths of a grid container's content box: its
+/// columns sized under each constraint.
+pub(crate) fn content_sizes(layout_box: &LayoutBox, measurer: &dyn TextMeasurer) -> ContentSizes {
+    let style = &layout_box.style;
+    let gap = style.column_gap.resolve(0.0);
+    let (columns, _, items) = place_items(style, &layout_box.children, None, None, (gap, 0.0));
+    let contributions: Vec<Contribution> = items
+        .iter()
+        .map(|item| Contribution {
+            tracks: (item.columns.start, item.columns.end),
+            sizes: outer_sizes(&layout_box.children[item.index], measurer),
+        })
+        .collect();
+    let size = |space: AvailableSpace| {
+        let mut columns = columns.clone();
+        size_tracks(&mut columns, &contributions, space, gap, false);
+        let open = columns.iter().filter(|track| !track.collapsed).count();
+        columns.iter().map(|track| track.base).sum::<f32>() + gap * open.saturating_sub(1) as f32
+    };
+    ContentSizes {
+        min: size(AvailableSpace::MinContent),
+        max: size(AvailableSpace::MaxContent),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{contents, laid_out, rect};
+    use foamium_dom::parse_html;
+
+    #[test]
+    fn test_flexible_tracks_and_gaps() {
+        let dom = parse_html("<div><p></p><p></p><p></p><p></p><p></p></div>");
+        let stylesheet = foamium_css::parse(
+            "div { display: grid; width: 420px; grid-template-columns: 100px 1fr 2fr; gap: 5px 10px; grid-auto-rows: 20px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let grid = &root.children[0];
+        // 300px left over after the fixed track and the gaps, a third of it per `fr`
+        assert_eq!(
+            contents(grid),
+            [
+                rect(0.0, 0.0, 100.0, 20.0),
+                rect(110.0, 0.0, 100.0, 20.0),
+                rect(220.0, 0.0, 200.0, 20.0),
+                rect(0.0, 25.0, 100.0, 20.0),
+                rect(110.0, 25.0, 100.0, 20.0),
+            ]
+        );
+        assert_eq!(grid.dimensions.content.height, 45.0);
+    }
+
+    #[test]
+    fn test_areas_and_named_lines() {
+        let dom = parse_html("<div><p id=\"h\"></p><p id=\"n\"></p><p id=\"m\"></p></div>");
+        let stylesheet = foamium_css::parse(
+            "div {
+                display: grid; width: 300px; height: 200px;
+                grid-template: \"head head\" auto \"nav main\" 1fr / [side] 100px [main-start] minmax(50px, 1fr) [main-end];
+             }
+             #h { grid-area: head; height: 30px; }
+             #n { grid-area: nav; }
+             #m { grid-column: main; grid-row: -2; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        // The `auto` row fits the header, and the `1fr` row takes the rest
+        assert_eq!(
+            contents(&root.children[0]),
+            [rect(0.0, 0.0, 300.0, 30.0), rect(0.0, 30.0, 100.0, 170.0), rect(100.0, 30.0, 200.0, 170.0)]
+        );
+    }
+
+    #[test]
+    fn test_auto_placement() {
+        let dom = parse_html(
+            "<div><p class=\"wide\"></p><p class=\"wide\"></p><p></p></div>\
+             <div class=\"dense\"><p class=\"wide\"></p><p class=\"wide\"></p><p></p></div>",
+        );
+        let stylesheet = foamium_css::parse(
+            "div { display: grid; grid-template-columns: repeat(3, 100px); grid-auto-rows: 10px; }
+             .dense { grid-auto-flow: row dense; }
+             .wide { grid-column: span 2; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        // The last item goes after the second, or back in the hole the second left
+        assert_eq!(
+            contents(&root.children[0]),
+            [rect(0.0, 0.0, 200.0, 10.0), rect(0.0, 10.0, 200.0, 10.0), rect(200.0, 10.0, 100.0, 10.0)]
+        );
+        assert_eq!(
+            contents(&root.children[1]),
+            [rect(0.0, 20.0, 200.0, 10.0), rect(0.0, 30.0, 200.0, 10.0), rect(200.0, 20.0, 100.0, 10.0)]
+        );
+    }
+
+    #[test]
+    fn test_auto_repeat_and_alignment() {
+        let dom = parse_html("<div><p></p><p></p><p></p><p></p></div><div class=\"fit\"><p></p><p></p></div>");
+        let stylesheet = foamium_css::parse(
+            "div { display: grid; width: 250px; grid-template-columns: repeat(auto-fill, 60px); column-gap: 10px; grid-auto-rows: 10px; }
+             .fit { grid-template-columns: repeat(auto-fit, 60px); justify-content: center; }
+             .fit p + p { justify-self: center; width: 20px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        // Three 60px columns fit with their gaps
+        assert_eq!(
+            contents(&root.children[0]),
+            [
+                rect(0.0, 0.0, 60.0, 10.0),
+                rect(70.0, 0.0, 60.0, 10.0),
+                rect(140.0, 0.0, 60.0, 10.0),
+                rect(0.0, 10.0, 60.0, 10.0),
+            ]
+        );
+        // The empty third column collapses, so the other two are centred
+        assert_eq!(contents(&root.children[1]), [rect(60.0, 20.0, 60.0, 10.0), rect(150.0, 20.0, 20.0, 10.0)]);
+    }
+
+    #[test]
+    fn test_intrinsic_tracks() {
+        let dom = parse_html(
+            "<div><p>aa bbbb</p><p>aa bbbb</p><p>cc</p><p class=\"wide\">xxxxxxxxxxxxxxxxxxxx</p></div>\
+             <p><span><b>ab</b><b>cde</b></span></p>",
+        );
+        let stylesheet = foamium_css::parse(
+            "div { display: grid; width: 400px; grid-template-columns: min-content max-content auto; }
+             * { font-size: 10px; }
+             .wide { grid-column: 1 / 3; }
+             span { display: inline-grid; grid-template-columns: auto auto; column-gap: 4px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let widths: Vec<f32> = root.children[0].children.iter().map(|item| item.dimensions.content.width).collect();
+        // 20px and 35px from the words, then the 100px item spanning both shares its
+        // extra 45px between them, and the `auto` column stretches
+        assert_eq!(widths, [42.5, 57.5, 300.0, 100.0]);
+        assert_eq!(root.children[0].children[2].dimensions.content.x, 100.0);
+        // An inline grid is as wide as its columns' max-content sizes
+        let span = &root.children[1].children[0].children[0];
+        assert_eq!(span.dimensions.content.width, 29.0);
+        assert_eq!(span.children[1].dimensions.content.x, 14.0);
+    }
+}
@@ -0,0 +1,188 @@
+// Presentational hints: legacy HTML attributes mapped to CSS, per the HTML rendering spec
+use foamium_css::{Color, Declaration, Image, Unit, Value};
+use foamium_dom::ElementData;
+
+/// The declarations implied by an element's presentational attributes. These are
+/// author-level with zero specificity, so any matching rule overrides them.
+pub fn presentational_hints(elem: &ElementData) -> Vec<Declaration> {
+    let mut hints = Vec::new();
+    let mut hint = |name: &str, value: Value| {
+        hints.push(Declaration {
+            name: name.to_string(),
+            value,
+            important: false,
+        })
+    };
+    let attr = |name: &str| elem.attributes.get(name).map(String::as_str);
+    let tag = elem.tag_name.to_ascii_lowercase();
+
+    if attr("hidden").is_some() {
+        hint("display", Value::Keyword("none".to_string()));
+    }
+
+    if matches!(&*tag, "body" | "table" | "thead" | "tbody" | "tfoot" | "tr" | "td" | "th")
+        && let Some(color) = attr("bgcolor").and_then(parse_legacy_color)
+    {
+        hint("background-color", Value::ColorValue(color));
+    }
+    if matches!(&*tag, "body" | "table" | "thead" | "tbody" | "tfoot" | "tr" | "td" | "th")
+        && let Some(url) = attr("background").map(str::trim).filter(|url| !url.is_empty())
+    {
+        hint("background-image", Value::Image(Image::Url(url.to_string())));
+    }
+    if matches!(&*tag, "td" | "th") && attr("nowrap").is_some() {
+        hint("white-space", Value::Keyword("nowrap".to_string()));
+    }
+    if tag == "body"
+        && let Some(color) = attr("text").and_then(parse_legacy_color)
+    {
+        hint("color", Value::ColorValue(color));
+    }
+
+    if tag == "font" {
+        if let Some(color) = attr("color").and_then(parse_legacy_color) {
+            hint("color", Value::ColorValue(color));
+        }
+        if let Some(face) = attr("face") {
+            let families = face.split(',').map(str::trim).filter(|name| !name.is_empty());
+            hint("font-family", Value::List(families.map(|name| Value::String(name.to_string())).collect()));
+        }
+        if let Some(size) = attr("size").and_then(legacy_font_size) {
+            hint("font-size", Value::Length(size, Unit::Px));
+        }
+    }
+
+    if matches!(&*tag, "img" | "table" | "td" | "th" | "col" | "hr" | "iframe" | "video" | "canvas")
+        && let Some(width) = attr("width").and_then(parse_dimension)
+    {
+        hint("width", width);
+    }
+    if matches!(&*tag, "img" | "tr" | "td" | "th" | "iframe" | "video" | "canvas")
+        && let Some(height) = attr("height").and_then(parse_dimension)
+    {
+        hint("height", height);
+    }
+    if tag == "table"
+        && let Some(Value::Length(spacing, Unit::Px)) = attr("cellspacing").and_then(parse_dimension)
+    {
+        hint("-webkit-border-horizontal-spacing", Value::Length(spacing, Unit::Px));
+        hint("-webkit-border-vertical-spacing", Value::Length(spacing, Unit::Px));
+    }
+
+    if let Some(align) = attr("align").map(str::to_ascii_lowercase) {
+        match &*tag {
+            "table" if align == "center" => {
+                hint("margin-left", Value::Keyword("auto".to_string()));
+                hint("margin-right", Value::Keyword("auto".to_string()));
+            }
+            "table" | "img" if align == "left" || align == "right" => hint("float", Value::Keyword(align)),
+            "div" | "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "caption" | "thead" | "tbody" | "tfoot"
+            | "tr" | "td" | "th"
+                if matches!(&*align, "left" | "right" | "center" | "justify") =>
+            {
+                hint("text-align", Value::Keyword(align));
+            }
+            _ => {}
+        }
+    }
+
+    hints
+}
+
+/// The rules for parsing a legacy colour value, which accept almost anything.
+pub fn parse_legacy_color(input: &str) -> Option<Color> {
+    let input = input.trim();
+    if input.is_empty() || input.eq_ignore_ascii_case("transparent") {
+        return None;
+    }
+    if let Some(color) = Color::from_name(input) {
+        return Some(color);
+    }
+    if input.len() == 4
+        && input.starts_with('#')
+        && let Some(color) = Color::from_hex(&input[1..])
+    {
+        return Some(color);
+    }
+
+    // Anything else: replace non-hex characters with zeros and split into three components
+    let digits: String = input
+        .strip_prefix('#')
+        .unwrap_or(input)
+        .chars()
+        .take(128)
+        .map(|c| if c.is_ascii_hexdigit() { c } else { '0' })
+        .collect();
+    let mut digits = if digits.is_empty() { "0".to_string() } else { digits };
+    while digits.len() % 3 != 0 {
+        digits.push('0');
+    }
+    let length = digits.len() / 3;
+    let mut components: Vec<&str> = (0..3).map(|i| &digits[i * length..(i + 1) * length]).collect();
+    if length > 8 {
+        components = components.iter().map(|c| &c[c.len() - 8..]).collect();
+    }
+    while components[0].len() > 2 && components.iter().all(|c| c.starts_with('0')) {
+        components = components.iter().map(|c| &c[1..]).collect();
+    }
+    let channel = |c: &str| u8::from_str_radix(&c[..c.len().min(2)], 16).ok();
+    Some(Color::new(
+        channel(components[0])?,
+        channel(components[1])?,
+        channel(components[2])?,
+        255,
+    ))
+}
+
+/// The rules for parsing dimension values: a number of pixels or a percentage.
+fn parse_dimension(input: &str) -> Option<Value> {
+    let input = input.trim_start();
+    let end = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let number: f32 = input[..end].trim_end_matches('.').parse().ok()?;
+    if input[end..].starts_with('%') {
+        Some(Value::Length(number, Unit::Percent))
+    } else {
+        Some(Value::Length(number, Unit::Px))
+    }
+}
+
+/// Map `<font size>`, which may be relative to the default size 3, to px.
+fn legacy_font_size(input: &str) -> Option<f32> {
+    let input = input.trim();
+    let size: i32 = input.trim_start_matches(['+', '-']).parse().ok()?;
+    let size = match input.chars().next()? {
+        '+' => 3 + size,
+        '-' => 3 - size,
+        _ => size,
+    };
+    const SIZES: [f32; 7] = [10.0, 13.0, 16.0, 18.0, 24.0, 32.0, 48.0];
+    Some(SIZES[size.clamp(1, 7) as usize - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_colors() {
+        assert_eq!(parse_legacy_color("red"), Some(Color::new(255, 0, 0, 255)));
+        assert_eq!(parse_legacy_color("#0f0"), Some(Color::new(0, 255, 0, 255)));
+        assert_eq!(parse_legacy_color("#3584e4"), Some(Color::new(0x35, 0x84, 0xe4, 255)));
+        // The infamous "chucknorris" is red
+        assert_eq!(parse_legacy_color("chucknorris"), Some(Color::new(0xc0, 0, 0, 255)));
+        assert_eq!(parse_legacy_color("transparent"), None);
+    }
+
+    #[test]
+    fn test_dimensions_and_font_sizes() {
+        assert_eq!(parse_dimension("100"), Some(Value::Length(100.0, Unit::Px)));
+        assert_eq!(parse_dimension("50%"), Some(Value::Length(50.0, Unit::Percent)));
+        assert_eq!(parse_dimension("12.5px"), Some(Value::Length(12.5, Unit::Px)));
+        assert_eq!(parse_dimension("wide"), None);
+        assert_eq!(legacy_font_size("+2"), Some(24.0));
+        assert_eq!(legacy_font_size("1"), Some(10.0));
+        assert_eq!(legacy_font_size("9"), Some(48.0));
+    }
+}
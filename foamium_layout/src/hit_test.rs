@@ -0,0 +1,227 @@
+// Hit testing: mapping a point back to the content under it
+//
+// What's under a point is what was painted there last, so boxes are tested in reverse
+// paint order. Each box is hit where it paints itself, inside its `clip`: its border box,
+// or for an inline box the content areas of its fragments. The tree holds where boxes
+// are painted once everything is scrolled, so points are in the viewport's coordinates.
+// An anonymous box belongs to the element it's inside.
+use crate::box_model::computed;
+use crate::inline::TAB_SIZE;
+use crate::{ComputedStyle, Display, FixedWidthMeasurer, Fragment, LayoutBox, PropertyMap, StackingContext, TextMeasurer};
+use foamium_css::Value;
+use foamium_dom::{DomNode, NodeType};
+use std::ptr;
+
+/// `pointer-events`. The values for SVG act like `auto` on other boxes.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PointerEvents {
+    #[default]
+    Auto,
+    /// The box is never hit, though the boxes inside it can be
+    None,
+}
+
+/// Compute `pointer-events` into `style`. It inherits.
+pub(crate) fn compute_hit_test_style(style: &mut ComputedStyle, values: &PropertyMap, parent: &ComputedStyle) {
+    style.pointer_events = computed(values, "pointer-events", &parent.pointer_events, PointerEvents::Auto, |value| {
+        let Value::Keyword(keyword) = value else {
+            return None;
+        };
+        match &*keyword.to_ascii_lowercase() {
+            "none" => Some(PointerEvents::None),
+            "auto" | "bounding-box" | "visiblepainted" | "visiblefill" | "visiblestroke" | "visible" | "painted" | "fill"
+            | "stroke" | "all" => Some(PointerEvents::Auto),
+            _ => None,
+        }
+    });
+}
+
+/// The content under a point.
+#[derive(Debug, Clone, Copy)]
+pub struct Hit<'a> {
+    /// The text or element
+    pub node: &'a DomNode,
+    /// For text, the caret position nearest the point, as a byte offset in the text
+    pub offset: Option<usize>,
+}
+
+/// The topmost content at `(x, y)` in a laid out tree, measuring text with
+/// `FixedWidthMeasurer`.
+pub fn hit_test<'a>(root: &LayoutBox<'a>, x: f32, y: f32) -> Option<Hit<'a>> {
+    hit_test_with_measurer(root, x, y, &FixedWidthMeasurer)
+}
+
+/// The topmost content at `(x, y)` in a laid out tree, measuring text with the measurer
+/// it was laid out with. Points in the viewport that nothing else is at hit the root.
+pub fn hit_test_with_measurer<'a>(root: &LayoutBox<'a>, x: f32, y: f32, measurer: &dyn TextMeasurer) -> Option<Hit<'a>> {
+    let context = StackingContext::new(root);
+    let hit = context.paint_order().into_iter().rev().find_map(|layout_box| hit_box(layout_box, x, y, measurer));
+    if let Some((layout_box, offset)) = hit {
+        let node = owner(root, layout_box, None).flatten()?;
+        return Some(Hit { node, offset });
+    }
+    let in_viewport = root.scroll.is_some_and(|scroll| scroll.scrollport.contains(x, y));
+    match root.node {
+        Some(node) if in_viewport && root.style.pointer_events != PointerEvents::None => Some(Hit { node, offset: None }),
+        _ => None,
+    }
+}
+
+/// Whether a box paints itself at `(x, y)` and can be hit there, and if it's text, the
+/// caret position there.
+fn hit_box<'t, 'a>(
+    layout_box: &'t LayoutBox<'a>,
+    x: f32,
+    y: f32,
+    measurer: &dyn TextMeasurer,
+) -> Option<(&'t LayoutBox<'a>, Option<usize>)> {
+    if layout_box.style.pointer_events == PointerEvents::None || layout_box.clip.is_some_and(|clip| !clip.contains(x, y)) {
+        return None;
+    }
+    if layout_box.display != Display::Inline {
+        return layout_box.dimensions.border_box().contains(x, y).then_some((layout_box, None));
+    }
+    let index = layout_box.fragments.iter().position(|fragment| fragment.rect.contains(x, y))?;
+    let offset = match layout_box.node.map(|node| &node.node_type) {
+        Some(NodeType::Text(text)) => {
+            let fragment = &layout_box.fragments[index];
+            let caret = caret(fragment, x, &layout_box.style, measurer);
+            Some(source_offset(text, &layout_box.fragments[..=index], caret, layout_box.style.white_space.collapses_spaces()))
+        }
+        _ => None,
+    };
+    Some((layout_box, offset))
+}
+
+/// The node of `target` or the nearest box around it that has one, looking inside
+/// `layout_box`, whose nearest node is `node`. `None` if `target` isn't inside.
+fn owner<'a>(layout_box: &LayoutBox<'a>, target: &LayoutBox, node: Option<&'a DomNode>) -> Option<Option<&'a DomNode>> {
+    let node = layout_box.node.or(node);
+    if ptr::eq(layout_box, target) {
+        return Some(node);
+    }
+    layout_box.children.iter().find_map(|child| owner(child, target, node))
+}
+
+/// The caret position in a fragment's text nearest `x`, in characters: before the
+/// character `x` is in the first half of, or after the last one.
+fn caret(fragment: &Fragment, x: f32, style: &ComputedStyle, measurer: &dyn TextMeasurer) -> usize {
+    let mut start = fragment.rect.x;
+    for (index, (at, c)) in fragment.text.char_indices().enumerate() {
+        let mut width = measurer.advance(&fragment.text[at..at + c.len_utf8()], style);
+        if c == ' ' {
+            width += fragment.justification;
+        }
+        if x < start + width / 2.0 {
+            return index;
+        }
+        start += width;
+    }
+    fragment.text.chars().count()
+}
+
+/// Where the caret position `caret` in the last of a text box's fragments is in the text
+/// they were made from, as a byte offset. Collapsing white space and breaking lines drop
+/// characters, and a tab that doesn't collapse becomes several spaces, so each character
+/// in the fragments is matched to the next one it could have come from.
+fn source_offset(text: &str, fragments: &[Fragment], caret: usize, collapses_spaces: bool) -> usize {
+    let mut chars = text.char_indices();
+    // The start of the tab the spaces are coming from, and how many spaces it has left
+    let mut tab = (0, 0);
+    let mut end = 0;
+    let last = fragments.len() - 1;
+    for (index, fragment) in fragments.iter().enumerate() {
+        for (position, c) in fragment.text.chars().enumerate() {
+            let start = if c == ' ' && tab.1 > 0 {
+                tab.1 -= 1;
+                tab.0
+            } else {
+                let is_space = |s: char| match s {
+                    '\t' => true,
+                    '\n' | '\r' | '\u{0C}' => collapses_spaces,
+                    _ => false,
+                };
+                let Some((start, s)) = chars.by_ref().find(|&(_, s)| s == c || c == ' ' && is_space(s)) else {
+                    return text.len();
+                };
+                if s == '\t' && !collapses_spaces {
+                    tab = (start, TAB_SIZE - 1);
+                }
+                end = start + s.len_utf8();
+                start
+            };
+            if index == last && position == caret {
+                return start;
+            }
+        }
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::test_support::laid_out;
+    use foamium_dom::parse_html;
+
+    #[test]
+    fn test_hit_test_text() {
+        let dom = parse_html("<p>ab  cd\n ef</p><pre>a\tb</pre>");
+        let stylesheet = foamium_css::parse(
+            "p, pre { display: block; width: 30px; font-size: 10px; line-height: 10px; }
+             pre { white-space: pre; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let text = root.children[0].children[0].node.unwrap();
+        // Lines of "ab cd" and "ef", with each character 5px wide
+        let hit = |x: f32, y: f32| hit_test(&root, x, y).map(|hit| (hit.node as *const DomNode, hit.offset));
+        assert_eq!(hit(1.0, 5.0), Some((text as *const DomNode, Some(0))));
+        assert_eq!(hit(3.0, 5.0), Some((text as *const DomNode, Some(1))));
+        // The collapsed space starts where the spaces do, and what's after it is past them
+        assert_eq!(hit(11.0, 5.0), Some((text as *const DomNode, Some(2))));
+        assert_eq!(hit(16.0, 5.0), Some((text as *const DomNode, Some(4))));
+        assert_eq!(hit(9.0, 15.0), Some((text as *const DomNode, Some(10))));
+        // Past the end of a line is the block's
+        let p = root.children[0].node.unwrap();
+        assert_eq!(hit(29.0, 5.0), Some((p as *const DomNode, None)));
+
+        // A tab is eight spaces, which all come from it
+        let text = root.children[1].children[0].node.unwrap();
+        assert_eq!(hit(24.0, 25.0), Some((text as *const DomNode, Some(1))));
+        assert_eq!(hit(44.0, 25.0), Some((text as *const DomNode, Some(2))));
+        assert_eq!(hit(49.0, 25.0), Some((text as *const DomNode, Some(3))));
+    }
+
+    #[test]
+    fn test_hit_test_order_clipping_and_scrolling() {
+        let dom = parse_html(
+            "<div id=\"s\"><p id=\"a\"></p><p id=\"b\"></p></div><em id=\"o\"></em><em id=\"n\"></em>",
+        );
+        let stylesheet = foamium_css::parse(
+            "div, p { display: block; }
+             div { height: 100px; overflow: auto; }
+             p { height: 80px; }
+             em { display: block; position: absolute; top: 0; left: 0; width: 50px; height: 50px; }
+             #o { z-index: -1; }
+             #n { pointer-events: none; }",
+        );
+        let mut root = laid_out(&dom, &stylesheet);
+        let id = |root: &LayoutBox, x: f32, y: f32| {
+            hit_test(root, x, y).and_then(|hit| match &hit.node.node_type {
+                foamium_dom::NodeType::Element(element) => element.attributes.get("id").cloned(),
+                _ => None,
+            })
+        };
+        // `#n` is on top but can't be hit, and `#o` is under the div's content
+        assert_eq!(id(&root, 10.0, 10.0).as_deref(), Some("a"));
+        assert_eq!(id(&root, 10.0, 90.0).as_deref(), Some("b"));
+        // `#b` overflows the div, but it's clipped
+        assert_eq!(id(&root, 10.0, 150.0), None);
+        assert_eq!(hit_test(&root, 10.0, 150.0).map(|hit| hit.node as *const DomNode), Some(root.node.unwrap() as *const _));
+        // Scrolling moves what's hit
+        let div = root.children[0].node.unwrap();
+        assert!(scroll_to(&mut root, div, (0.0, 50.0)));
+        assert_eq!(id(&root, 10.0, 40.0).as_deref(), Some("b"));
+        assert!(hit_test(&root, 10.0, 700.0).is_none());
+    }
+}
@@ -0,0 +1,1212 @@
+// Inline layout: white space processing, line breaking, `text-align` and vertical
+// alignment in line boxes (CSS 2.1 §10.8, CSS Text 3)
+//
+// The inline content of a block container is flattened into items: runs of text with
+// their white space already processed, the start and end edges of inline boxes, and
+// forced breaks. Break opportunities are found in the text of the whole paragraph,
+// since a word can run across elements, then the items between them are packed into
+// lines greedily. Positions are worked out first and written back to the boxes after.
+//
+// Floats among the inline content are placed as the line they're in is filled, if they
+// fit beside what's on it already, or else below it. Each line is as wide as the room
+// between the floats beside it, which is found for the height of the container's strut.
+use crate::block::{first_baseline, layout_shrink_to_fit};
+use crate::floats::{self, FloatContext, is_float};
+use crate::box_model::computed;
+use crate::intrinsic::{self, ContentSizes};
+use crate::line_break::{Break, break_opportunities};
+use crate::{ComputedStyle, Display, EdgeSizes, LayoutBox, LengthContext, LengthPercentage, PropertyMap, Rect};
+use foamium_css::Value;
+use foamium_dom::NodeType;
+
+/// `line-height: normal`, as a multiple of the font size.
+const NORMAL_LINE_HEIGHT: f32 = 1.2;
+
+/// How far `sub` lowers and `super` raises a box, as a fraction of the parent's font size.
+const SUB_SHIFT: f32 = 0.2;
+const SUPER_SHIFT: f32 = 0.34;
+
+/// A tab is as wide as this many spaces. Tab stops aren't tracked.
+pub(crate) const TAB_SIZE: usize = 8;
+
+/// Stands in for an atomic inline in the paragraph's text, for finding break opportunities.
+const OBJECT_REPLACEMENT: char = '\u{FFFC}';
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WhiteSpace {
+    #[default]
+    Normal,
+    Nowrap,
+    Pre,
+    PreWrap,
+    PreLine,
+}
+
+impl WhiteSpace {
+    fn from_keyword(keyword: &str) -> Option<WhiteSpace> {
+        Some(match &*keyword.to_ascii_lowercase() {
+            "normal" => WhiteSpace::Normal,
+            "nowrap" => WhiteSpace::Nowrap,
+            "pre" => WhiteSpace::Pre,
+            "pre-wrap" => WhiteSpace::PreWrap,
+            "pre-line" => WhiteSpace::PreLine,
+            _ => return None,
+        })
+    }
+
+    /// Whether runs of spaces and tabs collapse to one space.
+    pub(crate) fn collapses_spaces(self) -> bool {
+        matches!(self, WhiteSpace::Normal | WhiteSpace::Nowrap | WhiteSpace::PreLine)
+    }
+
+    /// Whether newlines force a line break rather than counting as spaces.
+    fn preserves_newlines(self) -> bool {
+        matches!(self, WhiteSpace::Pre | WhiteSpace::PreWrap | WhiteSpace::PreLine)
+    }
+
+    /// Whether lines may wrap at break opportunities.
+    fn wraps(self) -> bool {
+        matches!(self, WhiteSpace::Normal | WhiteSpace::PreWrap | WhiteSpace::PreLine)
+    }
+}
+
+/// `text-align`. Text is always left to right, so `start` and `end` are `Left` and `Right`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Right,
+    Center,
+    Justify,
+}
+
+impl TextAlign {
+    fn from_keyword(keyword: &str) -> Option<TextAlign> {
+        Some(match &*keyword.to_ascii_lowercase() {
+            "left" | "start" => TextAlign::Left,
+            "right" | "end" => TextAlign::Right,
+            "center" => TextAlign::Center,
+            "justify" => TextAlign::Justify,
+            _ => return None,
+        })
+    }
+}
+
+/// `line-height`. Numbers are inherited as numbers, so they scale with each element's font.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineHeight {
+    Normal,
+    Number(f32),
+    Px(f32),
+}
+
+impl LineHeight {
+    /// The line height in px for a font size.
+    pub fn resolve(self, font_size: f32) -> f32 {
+        match self {
+            LineHeight::Normal => font_size * NORMAL_LINE_HEIGHT,
+            LineHeight::Number(number) => font_size * number,
+            LineHeight::Px(px) => px,
+        }
+    }
+}
+
+/// `vertical-align`. Lengths, and percentages of the line height, compute to px raising the box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerticalAlign {
+    Baseline,
+    Sub,
+    Super,
+    TextTop,
+    TextBottom,
+    Middle,
+    Top,
+    Bottom,
+    Length(f32),
+}
+
+impl VerticalAlign {
+    fn from_keyword(keyword: &str) -> Option<VerticalAlign> {
+        Some(match &*keyword.to_ascii_lowercase() {
+            "baseline" => VerticalAlign::Baseline,
+            "sub" => VerticalAlign::Sub,
+            "super" => VerticalAlign::Super,
+            "text-top" => VerticalAlign::TextTop,
+            "text-bottom" => VerticalAlign::TextBottom,
+            "middle" => VerticalAlign::Middle,
+            "top" => VerticalAlign::Top,
+            "bottom" => VerticalAlign::Bottom,
+            _ => return None,
+        })
+    }
+}
+
+/// Compute the properties inline layout uses into `style`, whose `font_size` and
+/// `line_height` must already be computed.
+pub(crate) fn compute_text_style(
+    style: &mut ComputedStyle,
+    values: &PropertyMap,
+    parent: &ComputedStyle,
+    context: &LengthContext,
+) {
+    let keyword = |value: &Value| match value {
+        Value::Keyword(keyword) => Some(keyword.clone()),
+        _ => None,
+    };
+    style.white_space = computed(values, "white-space", &parent.white_space, WhiteSpace::Normal, |value| {
+        keyword(value).and_then(|keyword| WhiteSpace::from_keyword(&keyword))
+    });
+    style.text_align = computed(values, "text-align", &parent.text_align, TextAlign::Left, |value| {
+        keyword(value).and_then(|keyword| TextAlign::from_keyword(&keyword))
+    });
+
+    let line_height = style.line_height.resolve(style.font_size);
+    style.vertical_align = computed(values, "vertical-align", &parent.vertical_align, VerticalAlign::Baseline, |value| {
+        match value {
+            Value::Keyword(keyword) => VerticalAlign::from_keyword(keyword),
+            _ => context.resolve(value, Some(line_height)).map(VerticalAlign::Length),
+        }
+    });
+}
+
+/// Compute `line-height` into `style`, which needs its font size. It's computed before
+/// the other properties, whose `lh` lengths refer to it.
+pub(crate) fn compute_line_height(
+    style: &mut ComputedStyle,
+    values: &PropertyMap,
+    parent: &ComputedStyle,
+    context: &LengthContext,
+) {
+    let font_size = style.font_size;
+    style.line_height = computed(values, "line-height", &parent.line_height, LineHeight::Normal, |value| match *value {
+        Value::Keyword(ref keyword) if keyword.eq_ignore_ascii_case("normal") => Some(LineHeight::Normal),
+        Value::Number(number) if number >= 0.0 => Some(LineHeight::Number(number)),
+        _ => context.resolve(value, Some(font_size)).filter(|height| *height >= 0.0).map(LineHeight::Px),
+    });
+}
+
+/// Measures text for inline layout. Renderers implement this with their font backend.
+pub trait TextMeasurer {
+    /// The advance width of `text` set in `style`'s font.
+    fn advance(&self, text: &str, style: &ComputedStyle) -> f32;
+
+    /// The measurements of `style`'s font.
+    fn metrics(&self, style: &ComputedStyle) -> TextMetrics;
+}
+
+/// The vertical measurements of a font at a particular size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub x_height: f32,
+}
+
+/// A monospace stand-in for a real font: every character is half an em wide, with the
+/// ascent and descent making up one em.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FixedWidthMeasurer;
+
+impl TextMeasurer for FixedWidthMeasurer {
+    fn advance(&self, text: &str, style: &ComputedStyle) -> f32 {
+        text.chars().count() as f32 * style.font_size * 0.5
+    }
+
+    fn metrics(&self, style: &ComputedStyle) -> TextMetrics {
+        TextMetrics {
+            ascent: style.font_size * 0.8,
+            descent: style.font_size * 0.2,
+            x_height: style.font_size * 0.5,
+        }
+    }
+}
+
+/// Where an inline box is on one line. Text boxes have one for each line their text is
+/// on, and inline elements one for each line they take part in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fragment {
+    /// The content area: the advance of the text, and the font's ascent plus descent
+    pub rect: Rect,
+    pub baseline: f32,
+    /// The text on this line, or empty for an element
+    pub text: String,
+    /// The space `text-align: justify` adds to each space in the text
+    pub justification: f32,
+}
+
+/// One line of inline content in a block container.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineBox {
+    pub rect: Rect,
+    pub baseline: f32,
+}
+
+/// Whether a box takes part in its parent's inline formatting context.
+pub(crate) fn is_inline(layout_box: &LayoutBox) -> bool {
+    layout_box.display == Display::Inline || is_atomic(layout_box)
+}
+
+/// Whether a box is an atomic inline: an inline flex, grid or table container.
+pub(crate) fn is_atomic(layout_box: &LayoutBox) -> bool {
+    matches!(layout_box.display, Display::InlineFlex | Display::InlineGrid | Display::InlineTable)
+}
+
+/// Lay out a run of inline-level boxes, and the floats among them, in a block container
+/// into lines starting at `y`, in the container's content box coordinates. The content
+/// box starts at `origin` in the coordinates of `floats`. Returns the lines and their
+/// total height; there are no lines if the run was nothing but collapsible white space.
+pub(crate) fn layout_inline(
+    boxes: &mut [LayoutBox],
+    container: &ComputedStyle,
+    width: f32,
+    y: f32,
+    floats: &mut FloatContext,
+    origin: (f32, f32),
+    measurer: &dyn TextMeasurer,
+) -> (Vec<LineBox>, f32) {
+    for layout_box in boxes.iter_mut() {
+        layout_atomics(layout_box, width, measurer);
+    }
+    let mut collector = Collector::new(container, width, false, measurer);
+    for layout_box in boxes.iter() {
+        if is_inline(layout_box) || is_float(layout_box) {
+            collector.collect(layout_box, 0);
+        }
+    }
+    let Collector { boxes: inline_boxes, items, paragraph, floats: run_floats, .. } = collector;
+
+    let segments = segments(&items, &paragraph, &inline_boxes, measurer);
+    let mut breaker = LineBreaker::new(&inline_boxes, &run_floats, floats, origin, width, y, measurer);
+    for segment in segments {
+        breaker.push(segment);
+    }
+    let LineBreaker { lines, fragments, positions, top, .. } = breaker.finish();
+
+    let mut fragments = fragments.into_iter().skip(1);
+    let mut positions = positions.into_iter();
+    for layout_box in boxes.iter_mut() {
+        if is_inline(layout_box) {
+            assign(layout_box, &mut fragments, width);
+        }
+        place_floats(layout_box, &mut positions);
+    }
+    (lines, top - y)
+}
+
+/// The min-content and max-content widths of a run of inline-level boxes: the widest
+/// part that can't be broken, and the widest line with only the forced breaks.
+pub(crate) fn content_sizes(boxes: &[LayoutBox], container: &ComputedStyle, measurer: &dyn TextMeasurer) -> ContentSizes {
+    let mut collector = Collector::new(container, 0.0, true, measurer);
+    for layout_box in boxes.iter().filter(|layout_box| is_inline(layout_box)) {
+        collector.collect(layout_box, 0);
+    }
+    let Collector { boxes: inline_boxes, items, paragraph, .. } = collector;
+
+    let mut sizes = ContentSizes::default();
+    let (mut unbreakable, mut line) = (0.0, 0.0);
+    for segment in segments(&items, &paragraph, &inline_boxes, measurer) {
+        let trailing = segment.trailing_spaces(&inline_boxes, measurer);
+        unbreakable += segment.min_width();
+        line += segment.width();
+        sizes.min = sizes.min.max(unbreakable - trailing);
+        sizes.max = sizes.max.max(line - trailing);
+        if segment.wraps || segment.forced {
+            unbreakable = 0.0;
+        }
+        if segment.forced {
+            line = 0.0;
+        }
+    }
+    sizes
+}
+
+/// Lay out the atomic inlines and floats among a run of inline boxes, so their sizes are
+/// known before they're put in lines.
+fn layout_atomics(layout_box: &mut LayoutBox, width: f32, measurer: &dyn TextMeasurer) {
+    match layout_box.display {
+        _ if is_float(layout_box) || is_atomic(layout_box) => layout_shrink_to_fit(layout_box, width, measurer),
+        Display::Inline => {
+            for child in &mut layout_box.children {
+                layout_atomics(child, width, measurer);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// An inline box taking part in layout, or the container's own root inline box.
+struct InlineBox<'s> {
+    style: &'s ComputedStyle,
+    parent: Option<usize>,
+    is_text: bool,
+    /// An atomic inline, whose metrics are its margin box above and below its baseline
+    is_atomic: bool,
+    metrics: TextMetrics,
+}
+
+impl<'s> InlineBox<'s> {
+    fn new(style: &'s ComputedStyle, parent: Option<usize>, measurer: &dyn TextMeasurer) -> InlineBox<'s> {
+        InlineBox {
+            style,
+            parent,
+            is_text: false,
+            is_atomic: false,
+            metrics: measurer.metrics(style),
+        }
+    }
+
+    /// Half the difference between the line height and the content area, added above and
+    /// below. Atomic inlines have none.
+    fn half_leading(&self) -> f32 {
+        if self.is_atomic {
+            return 0.0;
+        }
+        (self.style.line_height.resolve(self.style.font_size) - self.metrics.ascent - self.metrics.descent) / 2.0
+    }
+}
+
+/// The flattened inline content. Boxes are numbered in tree order, after the root inline box.
+#[derive(Debug, Clone)]
+enum Item {
+    /// Text whose white space has been processed, starting at `offset` in the paragraph
+    Text { id: usize, offset: usize, text: String },
+    Start { id: usize, edge: f32 },
+    End { id: usize, edge: f32 },
+    Break { id: usize },
+    /// An atomic inline, with the widths of its margin box
+    Atomic { id: usize, offset: usize, sizes: ContentSizes },
+    /// A float, numbered in tree order among the run's floats
+    Float { index: usize },
+}
+
+/// A float among the inline content, laid out and waiting to be placed.
+struct RunFloat<'s> {
+    style: &'s ComputedStyle,
+    /// The size of its margin box
+    size: (f32, f32),
+}
+
+struct Collector<'s, 'm> {
+    measurer: &'m dyn TextMeasurer,
+    /// The container's width, which horizontal edges resolve against
+    width: f32,
+    /// Whether the content is being measured rather than laid out, so atomic inlines
+    /// haven't been laid out yet
+    intrinsic: bool,
+    boxes: Vec<InlineBox<'s>>,
+    items: Vec<Item>,
+    floats: Vec<RunFloat<'s>>,
+    /// All the text, for finding break opportunities
+    paragraph: String,
+    /// Whether a collapsible space would be dropped, because one came just before
+    collapse_space: bool,
+}
+
+impl<'s, 'm> Collector<'s, 'm> {
+    fn new(container: &'s ComputedStyle, width: f32, intrinsic: bool, measurer: &'m dyn TextMeasurer) -> Collector<'s, 'm> {
+        Collector {
+            measurer,
+            width,
+            intrinsic,
+            boxes: vec![InlineBox::new(container, None, measurer)],
+            items: Vec::new(),
+            floats: Vec::new(),
+            paragraph: String::new(),
+            collapse_space: true,
+        }
+    }
+
+    fn collect(&mut self, layout_box: &'s LayoutBox, parent: usize) {
+        if is_float(layout_box) {
+            self.push_float(layout_box);
+            return;
+        }
+        let id = self.boxes.len();
+        let mut inline_box = InlineBox::new(&layout_box.style, Some(parent), self.measurer);
+        if is_atomic(layout_box) {
+            inline_box.is_atomic = true;
+            let sizes = if self.intrinsic {
+                intrinsic::outer_sizes(layout_box, self.measurer)
+            } else {
+                // The baseline is the first line's inside, or else the bottom margin edge
+                let dimensions = &layout_box.dimensions;
+                let margin_box = dimensions.margin_box();
+                let ascent = first_baseline(layout_box).map_or(margin_box.height, |baseline| {
+                    dimensions.margin.top + dimensions.border.top + dimensions.padding.top + baseline
+                });
+                inline_box.metrics = TextMetrics {
+                    ascent,
+                    descent: margin_box.height - ascent,
+                    x_height: 0.0,
+                };
+                ContentSizes {
+                    min: margin_box.width,
+                    max: margin_box.width,
+                }
+            };
+            self.boxes.push(inline_box);
+            let offset = self.paragraph.len();
+            self.paragraph.push(OBJECT_REPLACEMENT);
+            self.items.push(Item::Atomic { id, offset, sizes });
+            self.collapse_space = false;
+            return;
+        }
+        match layout_box.node.map(|node| &node.node_type) {
+            Some(NodeType::Text(text)) => {
+                inline_box.is_text = true;
+                self.boxes.push(inline_box);
+                self.push_text(id, text, layout_box.style.white_space);
+            }
+            node_type => {
+                self.boxes.push(inline_box);
+                if let Some(NodeType::Element(element)) = node_type
+                    && element.tag_name.eq_ignore_ascii_case("br")
+                {
+                    self.push_break(id);
+                    return;
+                }
+                let (start, end) = horizontal_edges(&layout_box.style, self.width);
+                self.items.push(Item::Start { id, edge: start });
+                // Box tree construction moves block-level boxes out of inline boxes
+                for child in layout_box.children.iter().filter(|child| is_inline(child) || is_float(child)) {
+                    self.collect(child, id);
+                }
+                self.items.push(Item::End { id, edge: end });
+            }
+        }
+    }
+
+    /// Add text, collapsing and preserving white space as `white_space` says.
+    fn push_text(&mut self, id: usize, text: &str, white_space: WhiteSpace) {
+        let mut run = String::new();
+        for c in text.chars() {
+            match c {
+                '\n' if white_space.preserves_newlines() => {
+                    // Spaces around a preserved newline still collapse away
+                    if white_space.collapses_spaces() {
+                        run.truncate(run.trim_end_matches(' ').len());
+                    }
+                    self.push_run(id, std::mem::take(&mut run));
+                    self.push_break(id);
+                }
+                ' ' | '\t' | '\n' | '\r' | '\u{0C}' if white_space.collapses_spaces() => {
+                    if !self.collapse_space {
+                        run.push(' ');
+                        self.collapse_space = true;
+                    }
+                }
+                '\r' => {}
+                '\t' => {
+                    run.push_str(&" ".repeat(TAB_SIZE));
+                    self.collapse_space = false;
+                }
+                c => {
+                    run.push(c);
+                    self.collapse_space = false;
+                }
+            }
+        }
+        self.push_run(id, run);
+    }
+
+    fn push_run(&mut self, id: usize, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        let offset = self.paragraph.len();
+        self.paragraph.push_str(&text);
+        self.items.push(Item::Text { id, offset, text });
+    }
+
+    fn push_break(&mut self, id: usize) {
+        self.paragraph.push('\n');
+        self.items.push(Item::Break { id });
+        self.collapse_space = true;
+    }
+
+    /// Add a float, which takes no room in the text. Measuring leaves floats to the
+    /// container.
+    fn push_float(&mut self, layout_box: &'s LayoutBox) {
+        if self.intrinsic {
+            return;
+        }
+        let margin_box = layout_box.dimensions.margin_box();
+        self.items.push(Item::Float { index: self.floats.len() });
+        self.floats.push(RunFloat {
+            style: &layout_box.style,
+            size: (margin_box.width, margin_box.height),
+        });
+    }
+}
+
+/// The start and end edges of an inline box: margin, border and padding. `auto` margins are zero.
+fn horizontal_edges(style: &ComputedStyle, width: f32) -> (f32, f32) {
+    let margin = |margin: &Option<LengthPercentage>| margin.as_ref().map_or(0.0, |margin| margin.resolve(width));
+    let start = margin(&style.margin.left) + style.border_width.left + style.padding.left.resolve(width);
+    let end = margin(&style.margin.right) + style.border_width.right + style.padding.right.resolve(width);
+    (start, end)
+}
+
+/// A measured item.
+#[derive(Debug, Clone)]
+enum Piece {
+    Text { id: usize, text: String, width: f32 },
+    Start { id: usize, edge: f32 },
+    End { id: usize, edge: f32 },
+    Atomic { id: usize, sizes: ContentSizes },
+}
+
+impl Piece {
+    fn width(&self) -> f32 {
+        match *self {
+            Piece::Text { width, .. } => width,
+            Piece::Start { edge, .. } | Piece::End { edge, .. } => edge,
+            Piece::Atomic { sizes, .. } => sizes.max,
+        }
+    }
+}
+
+/// The pieces between two break opportunities, which always go on the same line.
+#[derive(Debug, Default)]
+struct Segment {
+    pieces: Vec<Piece>,
+    /// The line may wrap after this segment
+    wraps: bool,
+    /// The line must end after this segment
+    forced: bool,
+    /// The floats that come in this segment, by their numbers in the run
+    floats: Vec<usize>,
+}
+
+impl Segment {
+    fn width(&self) -> f32 {
+        self.pieces.iter().map(Piece::width).sum()
+    }
+
+    /// The width with atomic inlines at their min-content widths.
+    fn min_width(&self) -> f32 {
+        self.pieces
+            .iter()
+            .map(|piece| match *piece {
+                Piece::Atomic { sizes, .. } => sizes.min,
+                ref piece => piece.width(),
+            })
+            .sum()
+    }
+
+    /// The width of the spaces at the end, which don't need to fit on the line.
+    fn trailing_spaces(&self, boxes: &[InlineBox], measurer: &dyn TextMeasurer) -> f32 {
+        let mut width = 0.0;
+        for piece in self.pieces.iter().rev() {
+            if let Piece::Text { id, ref text, .. } = *piece {
+                let trimmed = text.trim_end_matches(' ');
+                width += measurer.advance(&text[trimmed.len()..], boxes[id].style);
+                if !trimmed.is_empty() {
+                    break;
+                }
+            }
+        }
+        width
+    }
+}
+
+/// Split the items into segments at the paragraph's break opportunities.
+fn segments(items: &[Item], paragraph: &str, boxes: &[InlineBox], measurer: &dyn TextMeasurer) -> Vec<Segment> {
+    let mut opportunities = break_opportunities(paragraph).into_iter().peekable();
+    let mut segments = Vec::new();
+    let mut current = Segment::default();
+    // Where the run of `Start` pieces at the end of `current` begins: they belong after
+    // a break that comes before the next text
+    let mut starts = 0;
+    let mut wraps = true;
+    let measure = |id: usize, text: &str| Piece::Text {
+        id,
+        text: text.to_string(),
+        width: measurer.advance(text, boxes[id].style),
+    };
+
+    for item in items {
+        match *item {
+            Item::Start { id, edge } => current.pieces.push(Piece::Start { id, edge }),
+            Item::End { id, edge } => {
+                current.pieces.push(Piece::End { id, edge });
+                starts = current.pieces.len();
+            }
+            Item::Break { id } => {
+                // Breaks are items of their own, so the mandatory opportunity after one is skipped
+                while opportunities.next_if(|&(_, kind)| kind == Break::Mandatory).is_some() {}
+                current.pieces.insert(starts, measure(id, ""));
+                current.forced = true;
+                segments.push(std::mem::take(&mut current));
+                starts = 0;
+                wraps = true;
+            }
+            Item::Float { index } => current.floats.push(index),
+            Item::Atomic { id, offset, sizes } => {
+                if opportunities.next_if(|&(at, _)| at == offset).is_some() {
+                    let next = current.pieces.split_off(starts);
+                    current.wraps = wraps;
+                    segments.push(std::mem::replace(&mut current, Segment { pieces: next, ..Segment::default() }));
+                }
+                current.pieces.push(Piece::Atomic { id, sizes });
+                starts = current.pieces.len();
+                wraps = boxes[id].style.white_space.wraps();
+            }
+            Item::Text { id, offset, ref text } => {
+                let mut start = 0;
+                while let Some(&(at, kind)) = opportunities.peek() {
+                    if at >= offset + text.len() {
+                        break;
+                    }
+                    opportunities.next();
+                    if kind == Break::Mandatory {
+                        continue;
+                    }
+                    let at = at - offset;
+                    if at > 0 {
+                        current.pieces.push(measure(id, &text[start..at]));
+                        wraps = boxes[id].style.white_space.wraps();
+                        starts = current.pieces.len();
+                    }
+                    // A break at the very start of the text comes before the boxes it starts
+                    let next = current.pieces.split_off(starts);
+                    current.wraps = wraps;
+                    segments.push(std::mem::replace(&mut current, Segment { pieces: next, ..Segment::default() }));
+                    start = at;
+                    starts = 0;
+                }
+                if start < text.len() {
+                    current.pieces.push(measure(id, &text[start..]));
+                    starts = current.pieces.len();
+                    wraps = boxes[id].style.white_space.wraps();
+                }
+            }
+        }
+    }
+    if !current.pieces.is_empty() || !current.floats.is_empty() {
+        segments.push(current);
+    }
+    segments
+}
+
+/// The contents of one line.
+#[derive(Debug, Default)]
+struct Line {
+    pieces: Vec<Piece>,
+    /// The line ends in a forced break
+    forced: bool,
+}
+
+impl Line {
+    /// Remove collapsible spaces from the end of the line.
+    fn trim_end(&mut self, boxes: &[InlineBox], measurer: &dyn TextMeasurer) {
+        for piece in self.pieces.iter_mut().rev() {
+            match *piece {
+                Piece::Text { id, ref mut text, ref mut width } => {
+                    if !boxes[id].style.white_space.collapses_spaces() {
+                        break;
+                    }
+                    text.truncate(text.trim_end_matches(' ').len());
+                    *width = measurer.advance(text, boxes[id].style);
+                    if !text.is_empty() {
+                        break;
+                    }
+                }
+                Piece::Atomic { .. } => break,
+                Piece::Start { .. } | Piece::End { .. } => {}
+            }
+        }
+    }
+
+    /// Whether the line has anything in it. Lines with only collapsed white space and
+    /// empty inline boxes are left out entirely.
+    fn has_content(&self) -> bool {
+        self.forced
+            || self.pieces.iter().any(|piece| match piece {
+                Piece::Text { text, .. } => !text.is_empty(),
+                Piece::Start { edge, .. } | Piece::End { edge, .. } => *edge != 0.0,
+                Piece::Atomic { .. } => true,
+            })
+    }
+}
+
+/// Packs segments into lines greedily, wrapping before a segment that doesn't fit, and
+/// places each line when it's done, since the floats placed by then decide how wide the
+/// next one is. Spaces at the end of a segment don't need to fit, since they'd hang or
+/// collapse.
+struct LineBreaker<'b, 's> {
+    boxes: &'b [InlineBox<'s>],
+    run_floats: &'b [RunFloat<'s>],
+    floats: &'b mut FloatContext,
+    measurer: &'b dyn TextMeasurer,
+    /// Where the container's content box starts in the floats' coordinates
+    origin: (f32, f32),
+    width: f32,
+    /// The height the room beside the floats is found for
+    strut: f32,
+    line: Line,
+    line_width: f32,
+    may_wrap: bool,
+    /// The top of the current line
+    top: f32,
+    /// The left and right edges of the current line, in the container's coordinates
+    band: (f32, f32),
+    /// Floats that didn't fit on the current line, to go below it
+    deferred: Vec<usize>,
+    lines: Vec<LineBox>,
+    fragments: Vec<Vec<Fragment>>,
+    /// Where each float's margin box goes, in the container's coordinates
+    positions: Vec<(f32, f32)>,
+}
+
+impl<'b, 's> LineBreaker<'b, 's> {
+    fn new(
+        boxes: &'b [InlineBox<'s>],
+        run_floats: &'b [RunFloat<'s>],
+        floats: &'b mut FloatContext,
+        origin: (f32, f32),
+        width: f32,
+        top: f32,
+        measurer: &'b dyn TextMeasurer,
+    ) -> LineBreaker<'b, 's> {
+        let container = boxes[0].style;
+        let mut breaker = LineBreaker {
+            boxes,
+            run_floats,
+            floats,
+            measurer,
+            origin,
+            width,
+            strut: container.line_height.resolve(container.font_size),
+            line: Line::default(),
+            line_width: 0.0,
+            may_wrap: false,
+            top,
+            band: (0.0, width),
+            deferred: Vec::new(),
+            lines: Vec::new(),
+            fragments: vec![Vec::new(); boxes.len()],
+            positions: vec![(0.0, 0.0); run_floats.len()],
+        };
+        breaker.update_band();
+        breaker
+    }
+
+    fn update_band(&mut self) {
+        let limits = (self.origin.0, self.origin.0 + self.width);
+        let (left, right) = self.floats.band(self.origin.1 + self.top, self.strut, limits);
+        self.band = (left - self.origin.0, right - self.origin.0);
+    }
+
+    fn push(&mut self, segment: Segment) {
+        let segment_width = segment.width();
+        let needed = segment_width - segment.trailing_spaces(self.boxes, self.measurer);
+        if self.may_wrap && !self.line.pieces.is_empty() && self.line_width + needed > self.band.1 - self.band.0 {
+            self.end_line(false);
+        }
+        // A line that's too narrow beside floats for anything to fit moves down below them
+        while self.line.pieces.is_empty()
+            && needed > self.band.1 - self.band.0
+            && let Some(bottom) = self.floats.next_bottom(self.origin.1 + self.top)
+        {
+            self.top = bottom - self.origin.1;
+            self.update_band();
+        }
+        self.line_width += segment_width;
+        self.line.pieces.extend(segment.pieces);
+        self.may_wrap = segment.wraps;
+        for index in segment.floats {
+            // A float goes beside the line it's in if there's room, or else below it, after
+            // any floats before it that had to
+            let fits = self.line_width + self.run_floats[index].size.0 <= self.band.1 - self.band.0;
+            if self.deferred.is_empty() && (fits || self.line.pieces.is_empty()) {
+                self.place_float(index);
+            } else {
+                self.deferred.push(index);
+            }
+        }
+        if segment.forced {
+            self.line.forced = true;
+            self.end_line(false);
+            self.may_wrap = false;
+        }
+    }
+
+    fn place_float(&mut self, index: usize) {
+        let float = &self.run_floats[index];
+        self.positions[index] = self.floats.place_in(float.style, float.size, self.top, self.origin, self.width);
+        self.update_band();
+    }
+
+    /// Place the current line, then the floats that had to wait for it, and start the next.
+    fn end_line(&mut self, last: bool) {
+        let mut line = std::mem::take(&mut self.line);
+        line.trim_end(self.boxes, self.measurer);
+        if line.has_content() {
+            let align = self.boxes[0].style.text_align;
+            let justify = align == TextAlign::Justify && !line.forced && !last;
+            let line_box = place_line(&line, self.boxes, align, justify, self.band, self.top, &mut self.fragments);
+            self.top += line_box.rect.height;
+            self.lines.push(line_box);
+        }
+        self.line_width = 0.0;
+        for index in std::mem::take(&mut self.deferred) {
+            self.place_float(index);
+        }
+        self.update_band();
+    }
+
+    fn finish(mut self) -> LineBreaker<'b, 's> {
+        if !self.line.pieces.is_empty() {
+            self.end_line(true);
+        }
+        for index in std::mem::take(&mut self.deferred) {
+            self.place_float(index);
+        }
+        self
+    }
+}
+
+/// Position the contents of a line whose top is at `top`, between the left and right
+/// edges of `band`, adding each box's fragment.
+fn place_line(
+    line: &Line,
+    boxes: &[InlineBox],
+    align: TextAlign,
+    justify: bool,
+    band: (f32, f32),
+    top: f32,
+    fragments: &mut [Vec<Fragment>],
+) -> LineBox {
+    let ancestors = |id: usize| std::iter::successors(Some(id), |&id| boxes[id].parent);
+    let mut present = vec![false; boxes.len()];
+    for piece in &line.pieces {
+        let (Piece::Text { id, .. } | Piece::Start { id, .. } | Piece::End { id, .. } | Piece::Atomic { id, .. }) = *piece;
+        for id in ancestors(id) {
+            present[id] = true;
+        }
+    }
+
+    // Horizontal: alignment, with justification spreading the space left over between spaces
+    let content_width: f32 = line.pieces.iter().map(Piece::width).sum();
+    let spaces: usize = line
+        .pieces
+        .iter()
+        .map(|piece| match piece {
+            Piece::Text { text, .. } => text.matches(' ').count(),
+            _ => 0,
+        })
+        .sum();
+    let width = band.1 - band.0;
+    let free = (width - content_width).max(0.0);
+    let justification = if justify && spaces > 0 { free / spaces as f32 } else { 0.0 };
+    let mut x = band.0
+        + match align {
+            TextAlign::Left | TextAlign::Justify => 0.0,
+            TextAlign::Right => free,
+            TextAlign::Center => free / 2.0,
+        };
+    // The horizontal extent of each inline element's content on this line
+    let mut spans: Vec<Option<(f32, f32)>> = vec![None; boxes.len()];
+    let mut extend = |id: Option<usize>, start: f32, end: f32| {
+        for id in std::iter::successors(id, |&id| boxes[id].parent).filter(|&id| id != 0) {
+            let span = spans[id].get_or_insert((start, end));
+            *span = (span.0.min(start), span.1.max(end));
+        }
+    };
+    let mut texts: Vec<(usize, String, f32, f32)> = Vec::new();
+    let mut atomics = Vec::new();
+    for piece in &line.pieces {
+        match *piece {
+            Piece::Start { id, edge } => {
+                extend(boxes[id].parent, x, x + edge);
+                x += edge;
+                extend(Some(id), x, x);
+            }
+            Piece::End { id, edge } => {
+                extend(Some(id), x, x);
+                extend(boxes[id].parent, x, x + edge);
+                x += edge;
+            }
+            Piece::Text { id, ref text, width } => {
+                let width = width + text.matches(' ').count() as f32 * justification;
+                let owner = if boxes[id].is_text { boxes[id].parent } else { Some(id) };
+                extend(owner, x, x + width);
+                if boxes[id].is_text && !text.is_empty() {
+                    // A text box has one fragment per line, however many segments it's in
+                    match texts.last_mut() {
+                        Some((last, last_text, _, last_width)) if *last == id => {
+                            last_text.push_str(text);
+                            *last_width += width;
+                        }
+                        _ => texts.push((id, text.clone(), x, width)),
+                    }
+                }
+                x += width;
+            }
+            Piece::Atomic { id, sizes } => {
+                extend(boxes[id].parent, x, x + sizes.max);
+                atomics.push((id, x, sizes.max));
+                x += sizes.max;
+            }
+        }
+    }
+
+    // Vertical: each box's baseline relative to its parent's, then the line is made tall
+    // enough for every box. Boxes aligned to the top or bottom of the line, and their
+    // descendants, are positioned relative to that box until the line's height is known.
+    let mut offsets = vec![0.0; boxes.len()];
+    let mut anchors: Vec<Option<usize>> = vec![None; boxes.len()];
+    let mut extents: Vec<Option<(f32, f32)>> = vec![None; boxes.len()];
+    let mut root_extent = (f32::INFINITY, f32::NEG_INFINITY);
+    for id in (0..boxes.len()).filter(|&id| present[id]) {
+        let inline_box = &boxes[id];
+        let metrics = inline_box.metrics;
+        if let Some(parent) = inline_box.parent {
+            if inline_box.is_text {
+                offsets[id] = offsets[parent];
+                anchors[id] = anchors[parent];
+                continue;
+            }
+            let parent_box = &boxes[parent];
+            let parent_metrics = parent_box.metrics;
+            let shift = match inline_box.style.vertical_align {
+                VerticalAlign::Baseline | VerticalAlign::Top | VerticalAlign::Bottom => 0.0,
+                VerticalAlign::Sub => parent_box.style.font_size * SUB_SHIFT,
+                VerticalAlign::Super => -parent_box.style.font_size * SUPER_SHIFT,
+                VerticalAlign::Length(raise) => -raise,
+                VerticalAlign::Middle => -parent_metrics.x_height / 2.0 - (metrics.descent - metrics.ascent) / 2.0,
+                VerticalAlign::TextTop => -parent_metrics.ascent + metrics.ascent + inline_box.half_leading(),
+                VerticalAlign::TextBottom => parent_metrics.descent - metrics.descent - inline_box.half_leading(),
+            };
+            if matches!(inline_box.style.vertical_align, VerticalAlign::Top | VerticalAlign::Bottom) {
+                anchors[id] = Some(id);
+            } else {
+                anchors[id] = anchors[parent];
+                offsets[id] = offsets[parent] + shift;
+            }
+        }
+        let half_leading = inline_box.half_leading();
+        let box_top = offsets[id] - metrics.ascent - half_leading;
+        let box_bottom = offsets[id] + metrics.descent + half_leading;
+        let extent = match anchors[id] {
+            Some(anchor) => extents[anchor].get_or_insert((box_top, box_bottom)),
+            None => &mut root_extent,
+        };
+        *extent = (extent.0.min(box_top), extent.1.max(box_bottom));
+    }
+    let height = extents
+        .iter()
+        .flatten()
+        .map(|(top, bottom)| bottom - top)
+        .fold(root_extent.1 - root_extent.0, f32::max);
+    // Where each anchor's baseline is, relative to the root inline box's baseline
+    let anchor_offset = |anchor: Option<usize>| match anchor {
+        None => 0.0,
+        Some(anchor) => {
+            let (anchor_top, anchor_bottom) = extents[anchor].unwrap_or_default();
+            match boxes[anchor].style.vertical_align {
+                VerticalAlign::Bottom => root_extent.0 + height - anchor_bottom,
+                _ => root_extent.0 - anchor_top,
+            }
+        }
+    };
+    let baseline = |id: usize| top - root_extent.0 + anchor_offset(anchors[id]) + offsets[id];
+
+    for (id, text, x, width) in texts {
+        let metrics = boxes[id].metrics;
+        let baseline = baseline(id);
+        fragments[id].push(Fragment {
+            rect: Rect {
+                x,
+                y: baseline - metrics.ascent,
+                width,
+                height: metrics.ascent + metrics.descent,
+            },
+            baseline,
+            text,
+            justification,
+        });
+    }
+    for (id, x, width) in atomics {
+        let metrics = boxes[id].metrics;
+        let baseline = baseline(id);
+        fragments[id].push(Fragment {
+            rect: Rect {
+                x,
+                y: baseline - metrics.ascent,
+                width,
+                height: metrics.ascent + metrics.descent,
+            },
+            baseline,
+            text: String::new(),
+            justification: 0.0,
+        });
+    }
+    for (id, span) in spans.into_iter().enumerate() {
+        let Some((start, end)) = span else { continue };
+        let metrics = boxes[id].metrics;
+        let baseline = baseline(id);
+        fragments[id].push(Fragment {
+            rect: Rect {
+                x: start,
+                y: baseline - metrics.ascent,
+                width: end - start,
+                height: metrics.ascent + metrics.descent,
+            },
+            baseline,
+            text: String::new(),
+            justification: 0.0,
+        });
+    }
+
+    LineBox {
+        rect: Rect {
+            x: band.0,
+            y: top,
+            width,
+            height,
+        },
+        baseline: top - root_extent.0,
+    }
+}
+
+/// Give a box, and the inline boxes inside it, their fragments in tree order. The box
+/// covers its fragments, with its horizontal edges from its style. An atomic inline's
+/// fragment is its margin box, and it's already laid out inside.
+fn assign(layout_box: &mut LayoutBox, fragments: &mut impl Iterator<Item = Vec<Fragment>>, width: f32) {
+    let own = fragments.next().unwrap_or_default();
+    if is_atomic(layout_box) {
+        if let Some(fragment) = own.first() {
+            let dimensions = &mut layout_box.dimensions;
+            dimensions.content.x = fragment.rect.x + dimensions.margin.left + dimensions.border.left + dimensions.padding.left;
+            dimensions.content.y = fragment.rect.y + dimensions.margin.top + dimensions.border.top + dimensions.padding.top;
+        }
+        layout_box.fragments = own;
+        return;
+    }
+    let style = &layout_box.style;
+    let dimensions = &mut layout_box.dimensions;
+    dimensions.content = own.iter().map(|fragment| fragment.rect).reduce(Rect::union).unwrap_or_default();
+    // Vertical padding and borders are drawn around the fragments, but don't take up space
+    dimensions.padding = EdgeSizes {
+        left: style.padding.left.resolve(width),
+        right: style.padding.right.resolve(width),
+        top: style.padding.top.resolve(width),
+        bottom: style.padding.bottom.resolve(width),
+    };
+    dimensions.border = style.border_width.to_edge_sizes();
+    let margin = |margin: &Option<LengthPercentage>| margin.as_ref().map_or(0.0, |margin| margin.resolve(width));
+    dimensions.margin = EdgeSizes {
+        left: margin(&style.margin.left),
+        right: margin(&style.margin.right),
+        top: 0.0,
+        bottom: 0.0,
+    };
+    layout_box.fragments = own;
+    for child in layout_box.children.iter_mut().filter(|child| is_inline(child)) {
+        assign(child, fragments, width);
+    }
+}
+
+/// Move the floats in a box of a run, or the box itself if it's a float, to the next of
+/// `positions`, in tree order.
+fn place_floats(layout_box: &mut LayoutBox, positions: &mut impl Iterator<Item = (f32, f32)>) {
+    if is_float(layout_box) {
+        if let Some(position) = positions.next() {
+            floats::set_position(layout_box, position);
+        }
+    } else if layout_box.display == Display::Inline {
+        for child in &mut layout_box.children {
+            place_floats(child, positions);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::test_support::{laid_out, rect};
+    use foamium_dom::parse_html;
+
+    fn texts(layout_box: &LayoutBox) -> Vec<String> {
+        layout_box.fragments.iter().map(|fragment| fragment.text.clone()).collect()
+    }
+
+    #[test]
+    fn test_line_breaking_and_white_space() {
+        let dom = parse_html(
+            "<p>aaa  bbb\nccc dddd</p><pre>a  b\n  c</pre><nobr>aaa bbb ccc dddd</nobr><div>one \n two  three</div>",
+        );
+        let stylesheet = foamium_css::parse(
+            "p, pre, div { display: block; }
+             html { width: 40px; font-size: 10px; line-height: 20px; }
+             pre { white-space: pre; }
+             nobr { white-space: nowrap; }
+             div { white-space: pre-line; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let p = &root.children[0];
+        // Eight characters fit on a line, and spaces at the end don't need to
+        assert_eq!(texts(&p.children[0]), ["aaa bbb", "ccc dddd"]);
+        assert_eq!(p.lines.len(), 2);
+        assert_eq!(p.dimensions.content.height, 40.0);
+        // The half-leading is 5px above and below the 10px content area
+        assert_eq!(p.children[0].fragments[0].rect, rect(0.0, 5.0, 35.0, 10.0));
+        assert_eq!(p.lines[1].baseline, 20.0 + 13.0);
+
+        let pre = &root.children[1];
+        assert_eq!(texts(&pre.children[0]), ["a  b", "  c"]);
+        // `nowrap` text overflows rather than wrapping
+        assert_eq!(texts(&root.children[2].children[0].children[0]), ["aaa bbb ccc dddd"]);
+        assert_eq!(texts(&root.children[3].children[0]), ["one", "two", "three"]);
+    }
+
+    #[test]
+    fn test_text_align_and_inline_edges() {
+        let dom = parse_html("<p>aa bb cc dd</p><div>ab</div><h1>a<br></br>b <em>cd</em></h1>");
+        let stylesheet = foamium_css::parse(
+            "p, div, h1 { display: block; width: 50px; font-size: 10px; line-height: 10px; }
+             p { text-align: justify; }
+             div { text-align: center; }
+             em { margin-left: 3px; padding: 2px 5px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let text = &root.children[0].children[0];
+        assert_eq!(texts(text), ["aa bb cc", "dd"]);
+        // Ten spare px shared between two spaces
+        assert_eq!(text.fragments[0].justification, 5.0);
+        assert_eq!(text.fragments[0].rect.width, 50.0);
+        assert_eq!(text.fragments[1].justification, 0.0);
+        assert_eq!(root.children[1].children[0].fragments[0].rect.x, 20.0);
+
+        let h1 = &root.children[2];
+        assert_eq!(h1.lines.len(), 2);
+        let em = &h1.children[3];
+        // Below the 20px paragraph and the 10px div, on the second line
+        assert_eq!(em.dimensions.content, rect(10.0 + 3.0 + 5.0, 30.0 + 10.0, 10.0, 10.0));
+        assert_eq!(em.dimensions.padding_box().height, 14.0);
+        assert_eq!(h1.dimensions.content.height, 20.0);
+    }
+
+    #[test]
+    fn test_vertical_align() {
+        let dom = parse_html("<p>x<span>Y</span><b>z</b><i>w</i></p>");
+        let stylesheet = foamium_css::parse(
+            "p { display: block; font-size: 10px; line-height: 1; }
+             span { font-size: 20px; }
+             b { vertical-align: -5px; }
+             i { vertical-align: top; line-height: 40px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let p = &root.children[0];
+        // The 20px span reaches 16px above the baseline and `b` 7px below it, but the
+        // 40px `i` makes the line taller, and sits at its top
+        assert_eq!(p.lines[0].rect.height, 40.0);
+        assert_eq!(p.lines[0].baseline, 16.0);
+        let span = &p.children[1];
+        assert_eq!(span.fragments[0].rect, rect(5.0, 0.0, 10.0, 20.0));
+        assert_eq!(p.children[2].fragments[0].baseline, 21.0);
+        assert_eq!(p.children[3].fragments[0].baseline, 15.0 + 8.0);
+    }
+}
@@ -0,0 +1,96 @@
+// Intrinsic sizes: the widths boxes take with as much or as little room as they can use
+// (CSS Sizing 3 §5)
+//
+// Percentages refer to a containing block whose size may depend on these, so they're
+// treated as zero, or as `auto` for sizes.
+use crate::{grid, table};
+use crate::floats::is_float;
+use crate::inline::{self, is_inline};
+use crate::position::is_out_of_flow;
+use crate::{Display, FlexDirection, FlexWrap, LayoutBox, LengthPercentage, TextMeasurer};
+
+/// A box's min-content and max-content widths.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct ContentSizes {
+    pub(crate) min: f32,
+    pub(crate) max: f32,
+}
+
+/// The intrinsic widths of a box's content box.
+pub(crate) fn content_sizes(layout_box: &LayoutBox, measurer: &dyn TextMeasurer) -> ContentSizes {
+    let style = &layout_box.style;
+    let children = layout_box.children.iter().filter(|child| child.display != Display::None && !is_out_of_flow(child));
+    match layout_box.display {
+        Display::Flex | Display::InlineFlex
+            if matches!(style.flex_direction, FlexDirection::Row | FlexDirection::RowReverse) =>
+        {
+            let items: Vec<ContentSizes> = children.map(|child| outer_sizes(child, measurer)).collect();
+            let gaps = style.column_gap.resolve(0.0) * items.len().saturating_sub(1) as f32;
+            let max = items.iter().map(|sizes| sizes.max).sum::<f32>() + gaps;
+            let min = match style.flex_wrap {
+                FlexWrap::NoWrap => items.iter().map(|sizes| sizes.min).sum::<f32>() + gaps,
+                FlexWrap::Wrap | FlexWrap::WrapReverse => items.iter().map(|sizes| sizes.min).fold(0.0, f32::max),
+            };
+            ContentSizes { min, max }
+        }
+        Display::Grid | Display::InlineGrid => grid::content_sizes(layout_box, measurer),
+        Display::Table | Display::InlineTable => table::wrapper_content_sizes(layout_box, measurer),
+        Display::TableGrid => table::content_sizes(layout_box, measurer),
+        Display::Block | Display::FlowRoot | Display::Inline | Display::TableCell | Display::TableCaption if layout_box.children.iter().any(is_inline) => {
+            // Floats go beside the lines, so they add to the longest one
+            let floats = children.filter(|child| is_float(child)).map(|child| outer_sizes(child, measurer)).fold(
+                ContentSizes::default(),
+                |sizes, float| ContentSizes {
+                    min: sizes.min.max(float.min),
+                    max: sizes.max + float.max,
+                },
+            );
+            let lines = inline::content_sizes(&layout_box.children, style, measurer);
+            ContentSizes {
+                min: lines.min.max(floats.min),
+                max: lines.max + floats.max,
+            }
+        }
+        _ => children.map(|child| outer_sizes(child, measurer)).fold(ContentSizes::default(), |sizes, child| {
+            ContentSizes {
+                min: sizes.min.max(child.min),
+                max: sizes.max.max(child.max),
+            }
+        }),
+    }
+}
+
+/// The intrinsic widths of a box's margin box, which is what it contributes to its
+/// parent's: its `width` if that's a length, or its content's, within `min-width` and
+/// `max-width`.
+pub(crate) fn outer_sizes(layout_box: &LayoutBox, measurer: &dyn TextMeasurer) -> ContentSizes {
+    let style = &layout_box.style;
+    let margin = |margin: &Option<LengthPercentage>| margin.as_ref().map_or(0.0, |margin| margin.resolve(0.0));
+    let edges = margin(&style.margin.left)
+        + margin(&style.margin.right)
+        + style.padding.left.resolve(0.0)
+        + style.padding.right.resolve(0.0)
+        + style.border_width.left
+        + style.border_width.right;
+    let adjustment = style.box_sizing_adjustment(true, 0.0);
+    let length = |length: &Option<LengthPercentage>| {
+        length.as_ref().and_then(|length| length.resolve_against(None)).map(|length| length - adjustment)
+    };
+    let sizes = match length(&style.width) {
+        Some(width) => ContentSizes { min: width, max: width },
+        None => content_sizes(layout_box, measurer),
+    };
+    let (min, max) = (length(&style.min_width).unwrap_or(0.0), length(&style.max_width).unwrap_or(f32::INFINITY));
+    let clamp = |width: f32| width.min(max).max(min).max(0.0) + edges;
+    ContentSizes {
+        min: clamp(sizes.min),
+        max: clamp(sizes.max),
+    }
+}
+
+/// The width of a box's content box that shrinks to fit `available`: no narrower than
+/// its min-content width and no wider than its max-content width (CSS 2.1 §10.3.5).
+pub(crate) fn shrink_to_fit(layout_box: &LayoutBox, available: f32, measurer: &dyn TextMeasurer) -> f32 {
+    let sizes = content_sizes(layout_box, measurer);
+    sizes.max.min(sizes.min.max(available))
+}
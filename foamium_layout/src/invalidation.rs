@@ -0,0 +1,777 @@
+// Incremental updates: styling and laying out again only what changes affect
+//
+// A laid out tree borrows the DOM, so it can't be kept while the DOM changes. Instead it's
+// handed back to an `IncrementalLayout`, which keeps it with each box's node replaced by
+// the node's address. Addresses are only ever compared with those of the nodes in the DOM
+// the next tree is built from, never followed. Changes are reported as they're made, and
+// the next update builds a new tree, taking from the kept one whatever can't have changed:
+//
+// - A node that hasn't changed, with nothing changed inside it, keeps its boxes as they
+//   were, unless something around it changed in a way that could restyle it.
+// - One with changes inside it keeps its style, and has its children built again.
+// - A changed node is styled again along with everything inside it, since selectors can
+//   refer to its attributes. A box whose style comes out the same where layout is
+//   concerned, with the same boxes inside, keeps its layout.
+//
+// Layout then runs over the whole tree, but keeps the layout of each block in normal flow
+// that isn't dirty and is laid out in the same containing block as before, without going
+// inside. For that, the kept tree is first put back in the coordinates layout works in.
+// Positions that go there and back can come out different from a fresh layout's in the
+// last bit, which nothing painted can show.
+use crate::overflow;
+use crate::position::relative_offset;
+use crate::{
+    build_with_retained, layout_with_measurer, ComputedStyle, Dimensions, Display, LayoutBox, Position, Rect, TextMeasurer,
+};
+use foamium_css::{parse_value, tokenize, Combinator, Condition, MediaEnvironment, Selector, SimpleSelector, Stylesheet, Token, Value};
+use foamium_dom::{DomNode, NodeType};
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+/// What an update had to redo for a box, rather than keep from the last tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Dirty {
+    /// Its style was computed
+    pub style: bool,
+    /// It was laid out
+    pub layout: bool,
+    /// It has to be painted again, since it was restyled or laid out, or ended up
+    /// somewhere else or clipped differently
+    pub paint: bool,
+}
+
+impl Dirty {
+    /// Everything, as for a new box.
+    pub const ALL: Dirty = Dirty {
+        style: true,
+        layout: true,
+        paint: true,
+    };
+}
+
+/// How many boxes a tree has, and how many of them were restyled, laid out and need
+/// painting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UpdateCounts {
+    pub boxes: usize,
+    pub styled: usize,
+    pub laid_out: usize,
+    pub painted: usize,
+}
+
+impl UpdateCounts {
+    /// Count a tree's boxes by their `dirty` flags.
+    pub fn of(root: &LayoutBox) -> UpdateCounts {
+        let mut counts = UpdateCounts {
+            boxes: 1,
+            styled: root.dirty.style as usize,
+            laid_out: root.dirty.layout as usize,
+            painted: root.dirty.paint as usize,
+        };
+        for child in &root.children {
+            let inner = UpdateCounts::of(child);
+            counts.boxes += inner.boxes;
+            counts.styled += inner.styled;
+            counts.laid_out += inner.laid_out;
+            counts.painted += inner.painted;
+        }
+        counts
+    }
+}
+
+/// How a node changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Change {
+    /// Its text, for a text node
+    Text,
+    /// Its attributes, which selectors and presentational hints can refer to
+    Attributes,
+    /// Children were added, removed or moved
+    Children,
+}
+
+/// What a node's boxes can keep from the retained tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Reuse {
+    /// Nothing: the node may not be the one a retained box with its address was for
+    None,
+    /// The scroll offsets, and the layout if the style comes out the same where it matters
+    Layout,
+    /// Whatever can't have changed
+    All,
+}
+
+impl Reuse {
+    /// What the children of a node that can keep this much and changed by `change` can
+    /// keep. A node with new children may have them where removed ones were, so nothing
+    /// inside it is looked up.
+    pub(crate) fn for_children(self, change: Option<Change>) -> Reuse {
+        match (self, change) {
+            (Reuse::None, _) | (_, Some(Change::Children)) => Reuse::None,
+            (Reuse::All, None) => Reuse::All,
+            _ => Reuse::Layout,
+        }
+    }
+}
+
+/// The addresses of a retained tree's nodes, in the same shape as the tree.
+#[derive(Debug, Default)]
+struct Nodes {
+    node: Option<usize>,
+    children: Vec<Nodes>,
+}
+
+fn address(node: &DomNode) -> usize {
+    node as *const DomNode as usize
+}
+
+/// The last tree, as it's being taken apart for the next one.
+pub(crate) struct Retained {
+    /// With the boxes that have been kept taken out
+    root: LayoutBox<'static>,
+    nodes: Nodes,
+    /// Where in `root` each node's box is, by the node's address, or `None` if it has
+    /// several
+    paths: HashMap<usize, Option<Vec<usize>>>,
+    changes: HashMap<usize, Change>,
+    /// The nodes that changed or have changes inside them
+    changed: HashSet<usize>,
+}
+
+impl Retained {
+    /// Get ready to take boxes for the nodes of `dom` from `root`. With sibling
+    /// combinators about, a change to an element's attributes can restyle the elements
+    /// after it, so they're taken to have changed too.
+    fn new(
+        root: LayoutBox<'static>,
+        nodes: Nodes,
+        mut changes: HashMap<usize, Change>,
+        dom: &DomNode,
+        sibling_selectors: bool,
+    ) -> Retained {
+        fn find_paths(nodes: &Nodes, path: &mut Vec<usize>, outer: Option<usize>, paths: &mut HashMap<usize, Option<Vec<usize>>>) {
+            // Only the outermost box of a node that has boxes inside its own counts
+            if let Some(node) = nodes.node.filter(|&node| Some(node) != outer) {
+                paths.entry(node).and_modify(|path| *path = None).or_insert_with(|| Some(path.clone()));
+            }
+            for (index, child) in nodes.children.iter().enumerate() {
+                path.push(index);
+                find_paths(child, path, nodes.node.or(outer), paths);
+                path.pop();
+            }
+        }
+        fn spread_to_siblings(node: &DomNode, changes: &mut HashMap<usize, Change>) {
+            let mut after_change = false;
+            for child in &node.children {
+                if let NodeType::Element(_) = child.node_type {
+                    if after_change {
+                        changes.entry(address(child)).or_insert(Change::Attributes);
+                    }
+                    after_change |= changes.get(&address(child)) == Some(&Change::Attributes);
+                }
+                spread_to_siblings(child, changes);
+            }
+        }
+        fn find_changed(node: &DomNode, changes: &HashMap<usize, Change>, changed: &mut HashSet<usize>) -> bool {
+            let mut inside = changes.contains_key(&address(node));
+            for child in &node.children {
+                inside |= find_changed(child, changes, changed);
+            }
+            if inside {
+                changed.insert(address(node));
+            }
+            inside
+        }
+
+        let mut paths = HashMap::new();
+        find_paths(&nodes, &mut Vec::new(), None, &mut paths);
+        if sibling_selectors {
+            spread_to_siblings(dom, &mut changes);
+        }
+        let mut changed = HashSet::new();
+        find_changed(dom, &changes, &mut changed);
+        Retained {
+            root,
+            nodes,
+            paths,
+            changes,
+            changed,
+        }
+    }
+
+    pub(crate) fn change(&self, node: &DomNode) -> Option<Change> {
+        self.changes.get(&address(node)).copied()
+    }
+
+    fn path(&self, node: &DomNode) -> Option<&[usize]> {
+        self.paths.get(&address(node))?.as_deref()
+    }
+
+    /// Take the box at `path` out of the tree, leaving an empty one for the same node.
+    fn take(&mut self, path: &[usize]) -> (LayoutBox<'static>, Nodes) {
+        let (mut layout_box, mut nodes) = (&mut self.root, &mut self.nodes);
+        for &index in path {
+            layout_box = &mut layout_box.children[index];
+            nodes = &mut nodes.children[index];
+        }
+        let empty = LayoutBox::new(None, ComputedStyle::initial(), Dimensions::default(), Vec::new());
+        let node = nodes.node;
+        (mem::replace(layout_box, empty), mem::replace(nodes, Nodes { node, children: Vec::new() }))
+    }
+
+    /// The box of a node with nothing changed inside it, with everything inside it kept.
+    pub(crate) fn keep<'a>(&mut self, node: &'a DomNode) -> Option<LayoutBox<'a>> {
+        fn find_nodes<'a>(node: &'a DomNode, found: &mut HashMap<usize, &'a DomNode>) {
+            found.insert(address(node), node);
+            for child in &node.children {
+                find_nodes(child, found);
+            }
+        }
+        fn attach<'a>(layout_box: &mut LayoutBox<'a>, nodes: &Nodes, dom: &HashMap<usize, &'a DomNode>) {
+            layout_box.node = nodes.node.and_then(|node| dom.get(&node).copied());
+            for (child, nodes) in layout_box.children.iter_mut().zip(&nodes.children) {
+                attach(child, nodes, dom);
+            }
+        }
+
+        if self.changed.contains(&address(node)) {
+            return None;
+        }
+        let path = self.path(node)?.to_vec();
+        let (layout_box, nodes) = self.take(&path);
+        let mut dom = HashMap::new();
+        find_nodes(node, &mut dom);
+        let mut layout_box: LayoutBox<'a> = layout_box;
+        attach(&mut layout_box, &nodes, &dom);
+        Some(layout_box)
+    }
+
+    /// The style a node's box had.
+    pub(crate) fn style(&self, node: &DomNode) -> Option<ComputedStyle> {
+        let mut layout_box = &self.root;
+        for &index in self.path(node)? {
+            layout_box = &layout_box.children[index];
+        }
+        Some(layout_box.style.clone())
+    }
+
+    /// Give a node's new box what it can keep from its old one: its scroll offsets, and
+    /// unless its text or children changed, its layout where that comes out the same.
+    /// `kept_style` is whether it kept its style.
+    pub(crate) fn restore<'a>(&mut self, node: &'a DomNode, boxes: &mut [LayoutBox<'a>], change: Option<Change>, kept_style: bool) {
+        let [layout_box] = boxes else {
+            return;
+        };
+        if layout_box.node.is_none_or(|own| address(own) != address(node)) {
+            return;
+        }
+        let Some(path) = self.path(node).map(<[usize]>::to_vec) else {
+            return;
+        };
+        let (mut old, nodes) = self.take(&path);
+        layout_box.scroll = old.scroll;
+        if kept_style {
+            layout_box.dirty.style = false;
+        }
+        if !matches!(change, Some(Change::Text | Change::Children)) {
+            transplant(layout_box, &mut old, &nodes);
+        }
+    }
+}
+
+/// Give a rebuilt box the layout of its old one, if laying it out would come out the same:
+/// its style is the same where layout is concerned, and the boxes inside are the same
+/// ones, already laid out. Anonymous boxes inside are matched up with the old ones in the
+/// same places.
+fn transplant(layout_box: &mut LayoutBox, old: &mut LayoutBox<'static>, nodes: &Nodes) -> bool {
+    if layout_box.node.map(address) != nodes.node
+        || layout_box.display != old.display
+        || layout_box.children.len() != old.children.len()
+        || !lays_out_the_same(&layout_box.style, &old.style)
+    {
+        return false;
+    }
+    for ((child, old_child), nodes) in layout_box.children.iter_mut().zip(&mut old.children).zip(&nodes.children) {
+        let same = child.node.map(address) == nodes.node
+            && (!child.dirty.layout || child.node.is_none() && transplant(child, old_child, nodes));
+        if !same {
+            return false;
+        }
+    }
+    layout_box.dimensions = old.dimensions;
+    layout_box.fragments = mem::take(&mut old.fragments);
+    layout_box.lines = mem::take(&mut old.lines);
+    layout_box.cache = old.cache;
+    layout_box.painted = old.painted;
+    layout_box.dirty.layout = false;
+    true
+}
+
+/// Whether two styles differ at most in properties that are only painted.
+fn lays_out_the_same(style: &ComputedStyle, old: &ComputedStyle) -> bool {
+    let mut old = old.clone();
+    old.color = style.color;
+    old.background_color = style.background_color;
+    old.background_image.clone_from(&style.background_image);
+    old.border_color = style.border_color;
+    old.z_index = style.z_index;
+    old.pointer_events = style.pointer_events;
+    old.transitions.clone_from(&style.transitions);
+    old.animations.clone_from(&style.animations);
+    *style == old
+}
+
+/// Keeps the last layout tree of a document, so that after the DOM or the environment
+/// changes only the boxes the changes affect are styled and laid out again.
+///
+/// Hand each tree back with `retain` before changing the DOM, report the changes as
+/// they're made, and call `update` for the next tree. Each box's `dirty` flags say what
+/// the update redid for it, and `counts` adds them up. Without a tree handed back,
+/// `update` builds everything.
+#[derive(Debug)]
+pub struct IncrementalLayout {
+    env: MediaEnvironment,
+    /// What the retained tree was built in
+    built_in: Option<MediaEnvironment>,
+    retained: Option<(LayoutBox<'static>, Nodes)>,
+    /// The changed nodes, by address
+    changes: HashMap<usize, Change>,
+    /// Everything's style has to be computed again
+    restyle: bool,
+    counts: UpdateCounts,
+}
+
+impl IncrementalLayout {
+    /// An engine for a document shown in `env`, with its viewport as the initial
+    /// containing block.
+    pub fn new(env: MediaEnvironment) -> IncrementalLayout {
+        IncrementalLayout {
+            env,
+            built_in: None,
+            retained: None,
+            changes: HashMap::new(),
+            restyle: false,
+            counts: UpdateCounts::default(),
+        }
+    }
+
+    pub fn environment(&self) -> &MediaEnvironment {
+        &self.env
+    }
+
+    /// Change the environment, such as when the viewport is resized. Only a change that
+    /// flips a media query, or resizes the viewport when lengths are relative to it,
+    /// restyles anything.
+    pub fn set_environment(&mut self, env: MediaEnvironment) {
+        self.env = env;
+    }
+
+    /// Keep a tree `update` built, for the next update to take what it can from. Scroll
+    /// offsets set on it since are kept too.
+    pub fn retain(&mut self, root: LayoutBox) {
+        let Some(env) = &self.built_in else {
+            return;
+        };
+        let (mut root, nodes) = detach(root);
+        let viewport = viewport(env);
+        make_relative(&mut root, (viewport.x, viewport.y), viewport);
+        self.retained = Some((root, nodes));
+    }
+
+    /// Note that an element's attributes changed.
+    pub fn attributes_changed(&mut self, node: &DomNode) {
+        self.record(node, Change::Attributes);
+    }
+
+    /// Note that a text node's text changed.
+    pub fn text_changed(&mut self, node: &DomNode) {
+        self.record(node, Change::Text);
+    }
+
+    /// Note that children were added to or removed from a node, or moved within it.
+    pub fn children_changed(&mut self, node: &DomNode) {
+        self.record(node, Change::Children);
+    }
+
+    /// Note that the stylesheet changed, which restyles everything.
+    pub fn stylesheet_changed(&mut self) {
+        self.restyle = true;
+    }
+
+    fn record(&mut self, node: &DomNode, change: Change) {
+        let recorded = self.changes.entry(address(node)).or_insert(change);
+        *recorded = (*recorded).max(change);
+    }
+
+    /// Build and lay out the tree for `root`, keeping what the changes since the retained
+    /// tree allow, and measuring text with `measurer`.
+    pub fn update<'a>(&mut self, root: &'a DomNode, stylesheet: &Stylesheet, measurer: &dyn TextMeasurer) -> LayoutBox<'a> {
+        // Container queries depend on sizes from the last layout, so with any of them about,
+        // everything is styled again
+        let restyle = self.restyle
+            || self.built_in.as_ref().is_some_and(|built_in| restyles(stylesheet, built_in, &self.env))
+            || has_container_queries(stylesheet);
+        let changes = mem::take(&mut self.changes);
+        let retained = self
+            .retained
+            .take()
+            .map(|(retained, nodes)| Retained::new(retained, nodes, changes, root, has_sibling_combinators(stylesheet)));
+        let reuse = match retained {
+            None => Reuse::None,
+            Some(_) if restyle => Reuse::Layout,
+            Some(_) => Reuse::All,
+        };
+        let mut tree = build_with_retained(root, stylesheet, &self.env, retained, reuse);
+        spread_layout(&mut tree);
+        layout_with_measurer(&mut tree, viewport(&self.env), measurer);
+        mark_laid_out(&mut tree);
+        mark_painted(&mut tree);
+        self.counts = UpdateCounts::of(&tree);
+        self.restyle = false;
+        self.built_in = Some(self.env.clone());
+        tree
+    }
+
+    /// What the last update redid.
+    pub fn counts(&self) -> UpdateCounts {
+        self.counts
+    }
+}
+
+fn viewport(env: &MediaEnvironment) -> Rect {
+    Rect {
+        x: 0.0,
+        y: 0.0,
+        width: env.viewport_width,
+        height: env.viewport_height,
+    }
+}
+
+/// Replace the nodes of a tree's boxes with their addresses, so it no longer borrows them.
+fn detach(layout_box: LayoutBox) -> (LayoutBox<'static>, Nodes) {
+    let LayoutBox {
+        node,
+        display,
+        dimensions,
+        style,
+        children,
+        fragments,
+        lines,
+        scroll,
+        clip,
+        translation,
+        dirty,
+        cache,
+        painted,
+    } = layout_box;
+    let (children, nodes) = children.into_iter().map(detach).unzip();
+    let detached = LayoutBox {
+        node: None,
+        display,
+        dimensions,
+        style,
+        children,
+        fragments,
+        lines,
+        scroll,
+        clip,
+        translation,
+        dirty,
+        cache,
+        painted,
+    };
+    (detached, Nodes { node: node.map(address), children: nodes })
+}
+
+/// Undo what happens to a laid out box and everything inside it once layout has placed
+/// them relative to their parents: scrolling, relative positioning and the move into
+/// page coordinates. `origin` is the point it was placed relative to, in page coordinates,
+/// and `flow` is its block container's content box. Notes where each box was painted, and
+/// clears the dirty flags for the next update.
+fn make_relative(layout_box: &mut LayoutBox, origin: (f32, f32), flow: Rect) {
+    layout_box.painted = Some(painted_area(layout_box));
+    layout_box.dirty = Dirty::default();
+    if let Some(cache) = &mut layout_box.cache {
+        cache.absolute = false;
+        cache.kept = false;
+    }
+    let (dx, dy) = layout_box.translation;
+    overflow::shift(layout_box, -dx, -dy);
+    layout_box.translation = (0.0, 0.0);
+    let (dx, dy) = match layout_box.style.position {
+        Position::Relative => relative_offset(&layout_box.style.inset, flow),
+        _ => (0.0, 0.0),
+    };
+    let (x, y) = (origin.0 + dx, origin.1 + dy);
+    let content = layout_box.dimensions.content;
+    // Inline boxes are placed relative to their block container, like what's inside them
+    let (inner, inner_flow) = match layout_box.display {
+        Display::Inline => ((x, y), flow),
+        _ => ((content.x, content.y), content),
+    };
+    layout_box.dimensions.content.x -= x;
+    layout_box.dimensions.content.y -= y;
+    for fragment in &mut layout_box.fragments {
+        fragment.rect.x -= x;
+        fragment.rect.y -= y;
+        fragment.baseline -= y;
+    }
+    for line in &mut layout_box.lines {
+        line.rect.x -= inner.0;
+        line.rect.y -= inner.1;
+        line.baseline -= inner.1;
+    }
+    for child in &mut layout_box.children {
+        make_relative(child, inner, inner_flow);
+    }
+}
+
+/// Where a box paints itself, and what clips it.
+fn painted_area(layout_box: &LayoutBox) -> (Rect, Option<Rect>) {
+    let area = match layout_box.display {
+        Display::Inline => layout_box.fragments.iter().map(|fragment| fragment.rect).reduce(Rect::union).unwrap_or_default(),
+        _ => layout_box.dimensions.border_box(),
+    };
+    (area, layout_box.clip)
+}
+
+/// Make every box with a box inside it that has to be laid out, have to be laid out too.
+fn spread_layout(layout_box: &mut LayoutBox) -> bool {
+    let mut dirty = layout_box.dirty.layout;
+    for child in &mut layout_box.children {
+        dirty |= spread_layout(child);
+    }
+    layout_box.dirty.layout = dirty;
+    dirty
+}
+
+/// Mark every box laid out except those in blocks whose layout was kept.
+fn mark_laid_out(layout_box: &mut LayoutBox) {
+    if layout_box.cache.is_some_and(|cache| cache.kept) {
+        return;
+    }
+    layout_box.dirty.layout = true;
+    for child in &mut layout_box.children {
+        mark_laid_out(child);
+    }
+}
+
+fn mark_painted(layout_box: &mut LayoutBox) {
+    let dirty = layout_box.dirty;
+    layout_box.dirty.paint = dirty.style || dirty.layout || layout_box.painted != Some(painted_area(layout_box));
+    for child in &mut layout_box.children {
+        mark_painted(child);
+    }
+}
+
+/// Whether going from one environment to another can change styles: a media query's
+/// result flips, or the viewport is resized and some length is relative to it.
+fn restyles(stylesheet: &Stylesheet, previous: &MediaEnvironment, env: &MediaEnvironment) -> bool {
+    let flips = stylesheet
+        .rules
+        .iter()
+        .flat_map(|rule| &rule.conditions)
+        .filter(|condition| matches!(condition, Condition::Media(_)))
+        .any(|condition| condition.matches(previous, &[]) != condition.matches(env, &[]));
+    let resized = (previous.viewport_width, previous.viewport_height) != (env.viewport_width, env.viewport_height);
+    flips || resized && has_viewport_units(stylesheet)
+}
+
+fn has_viewport_units(stylesheet: &Stylesheet) -> bool {
+    let is_viewport_unit = |unit: &str| match parse_value(&format!("1{unit}")) {
+        Some(Value::Length(_, unit)) => unit.viewport_px(1.0, 1.0).is_some(),
+        _ => false,
+    };
+    stylesheet.rules.iter().flat_map(|rule| &rule.declarations).any(|declaration| {
+        tokenize(&declaration.value.to_string()).iter().any(|token| match token {
+            Token::Dimension(_, unit) => is_viewport_unit(unit),
+            _ => false,
+        })
+    })
+}
+
+fn has_container_queries(stylesheet: &Stylesheet) -> bool {
+    stylesheet
+        .rules
+        .iter()
+        .flat_map(|rule| &rule.conditions)
+        .any(|condition| matches!(condition, Condition::Container(_)))
+}
+
+fn has_sibling_combinators(stylesheet: &Stylesheet) -> bool {
+    fn in_selector(selector: &Selector) -> bool {
+        match selector {
+            Selector::Simple(simple) => in_simple(simple),
+            Selector::Complex(left, combinator, simple) => {
+                matches!(combinator, Combinator::NextSibling | Combinator::SubsequentSibling) || in_selector(left) || in_simple(simple)
+            }
+        }
+    }
+    fn in_simple(simple: &SimpleSelector) -> bool {
+        simple.is.iter().flatten().any(in_selector)
+    }
+    stylesheet.rules.iter().flat_map(|rule| &rule.selectors).any(in_selector)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use foamium_css::MediaEnvironment;
+    use foamium_dom::{parse_html, DomNode, NodeType};
+
+    /// Check that an updated tree is laid out like one built from scratch, give or take
+    /// rounding.
+    fn assert_same_layout(tree: &LayoutBox, fresh: &LayoutBox) {
+        let rects = |layout_box: &LayoutBox| {
+            let fragments = layout_box.fragments.iter().map(|fragment| fragment.rect);
+            let lines = layout_box.lines.iter().map(|line| line.rect);
+            std::iter::once(layout_box.dimensions.border_box()).chain(fragments).chain(lines).collect::<Vec<_>>()
+        };
+        let (rects, fresh_rects) = (rects(tree), rects(fresh));
+        assert_eq!(rects.len(), fresh_rects.len());
+        for (rect, fresh) in rects.iter().zip(&fresh_rects) {
+            let close = |a: f32, b: f32| (a - b).abs() < 1e-3;
+            let same = close(rect.x, fresh.x) && close(rect.y, fresh.y);
+            assert!(same && close(rect.width, fresh.width) && close(rect.height, fresh.height), "{rect:?} != {fresh:?}");
+        }
+        assert_eq!(tree.children.len(), fresh.children.len());
+        for (child, fresh) in tree.children.iter().zip(&fresh.children) {
+            assert_same_layout(child, fresh);
+        }
+    }
+
+    fn fresh<'a>(dom: &'a DomNode, stylesheet: &'a foamium_css::Stylesheet, env: &MediaEnvironment) -> LayoutBox<'a> {
+        let mut root = build_layout_tree_with_environment(dom, stylesheet, env);
+        layout(&mut root, Rect { x: 0.0, y: 0.0, width: env.viewport_width, height: env.viewport_height });
+        root
+    }
+
+    fn counts(boxes: usize, styled: usize, laid_out: usize, painted: usize) -> UpdateCounts {
+        UpdateCounts { boxes, styled, laid_out, painted }
+    }
+
+    #[test]
+    fn test_dom_changes() {
+        let mut dom = parse_html("<div><p>one</p><p>two</p></div><div class=\"moved\"><p>three</p></div>");
+        let stylesheet = foamium_css::parse(
+            "div, p { display: block; width: 50px; }
+             .moved { position: relative; left: 5px; top: 5px; }
+             .red { color: red; }
+             .wide { padding-left: 10px; }",
+        );
+        let env = MediaEnvironment::default();
+        let mut engine = IncrementalLayout::new(env.clone());
+        let tree = engine.update(&dom, &stylesheet, &FixedWidthMeasurer);
+        assert_eq!(engine.counts(), counts(9, 9, 9, 9));
+
+        // With nothing changed, only the root is laid out again, around the blocks it had, which
+        // is all that needs painting
+        engine.retain(tree);
+        let tree = engine.update(&dom, &stylesheet, &FixedWidthMeasurer);
+        assert_eq!(engine.counts(), counts(9, 0, 1, 1));
+        assert_same_layout(&tree, &fresh(&dom, &stylesheet, &env));
+
+        // Longer text wraps onto more lines, which moves the second div down
+        engine.retain(tree);
+        let text = &mut dom.children[0].children[1].children[0];
+        text.node_type = NodeType::Text("two two two".to_string());
+        engine.text_changed(text);
+        let tree = engine.update(&dom, &stylesheet, &FixedWidthMeasurer);
+        assert_eq!(engine.counts(), counts(9, 1, 4, 7));
+        assert!(!tree.children[0].children[0].dirty.layout && tree.children[1].dirty.paint);
+        assert_same_layout(&tree, &fresh(&dom, &stylesheet, &env));
+
+        // A new colour only needs painting
+        engine.retain(tree);
+        let set_class = |node: &mut DomNode, class: &str| {
+            if let NodeType::Element(element) = &mut node.node_type {
+                element.attributes.insert("class".to_string(), class.to_string());
+            }
+        };
+        set_class(&mut dom.children[0].children[0], "red");
+        engine.attributes_changed(&dom.children[0].children[0]);
+        let tree = engine.update(&dom, &stylesheet, &FixedWidthMeasurer);
+        assert_eq!(engine.counts(), counts(9, 2, 1, 3));
+
+        // Padding lays out the paragraph and the div around it, but not the next paragraph
+        engine.retain(tree);
+        set_class(&mut dom.children[0].children[0], "wide");
+        engine.attributes_changed(&dom.children[0].children[0]);
+        let tree = engine.update(&dom, &stylesheet, &FixedWidthMeasurer);
+        assert_eq!(engine.counts(), counts(9, 2, 4, 4));
+        assert_same_layout(&tree, &fresh(&dom, &stylesheet, &env));
+
+        // New children are styled and laid out along with the ones already there
+        engine.retain(tree);
+        let four = DomNode::elem("p".to_string(), Default::default(), vec![DomNode::text("four".to_string())]);
+        dom.children[1].children.push(four);
+        engine.children_changed(&dom.children[1]);
+        let tree = engine.update(&dom, &stylesheet, &FixedWidthMeasurer);
+        assert_eq!(engine.counts(), counts(11, 5, 6, 6));
+        assert_same_layout(&tree, &fresh(&dom, &stylesheet, &env));
+    }
+
+    #[test]
+    fn test_environment_changes() {
+        let dom = parse_html("<div><p>one</p></div>");
+        let stylesheet = foamium_css::parse(
+            "div, p { display: block; }
+             @media (max-width: 600px) { p { margin: 10px; } }",
+        );
+        let mut env = MediaEnvironment::default();
+        let mut engine = IncrementalLayout::new(env.clone());
+        let tree = engine.update(&dom, &stylesheet, &FixedWidthMeasurer);
+
+        // A resize lays everything out in the new width, but restyles nothing
+        engine.retain(tree);
+        env.viewport_width = 1000.0;
+        engine.set_environment(env.clone());
+        let tree = engine.update(&dom, &stylesheet, &FixedWidthMeasurer);
+        assert_eq!(engine.counts(), counts(4, 0, 4, 4));
+        assert_same_layout(&tree, &fresh(&dom, &stylesheet, &env));
+
+        // Unless it flips a media query
+        engine.retain(tree);
+        env.viewport_width = 500.0;
+        engine.set_environment(env.clone());
+        let tree = engine.update(&dom, &stylesheet, &FixedWidthMeasurer);
+        assert_eq!(engine.counts(), counts(4, 4, 4, 4));
+        assert_same_layout(&tree, &fresh(&dom, &stylesheet, &env));
+
+        // Or lengths depend on the viewport
+        let stylesheet = foamium_css::parse("div, p { display: block; } p { width: 50vw; }");
+        engine.stylesheet_changed();
+        engine.retain(tree);
+        let tree = engine.update(&dom, &stylesheet, &FixedWidthMeasurer);
+        engine.retain(tree);
+        env.viewport_height = 400.0;
+        engine.set_environment(env.clone());
+        engine.update(&dom, &stylesheet, &FixedWidthMeasurer);
+        assert_eq!(engine.counts().styled, 4);
+    }
+
+    #[test]
+    fn test_scroll_offsets_are_kept() {
+        let mut dom = parse_html("<div><p></p></div><em></em>");
+        let stylesheet = foamium_css::parse(
+            "div, p, em { display: block; }
+             div { height: 50px; overflow: auto; }
+             p { height: 200px; }
+             .tall { height: 20px; }",
+        );
+        let mut engine = IncrementalLayout::new(MediaEnvironment::default());
+        let mut tree = engine.update(&dom, &stylesheet, &FixedWidthMeasurer);
+        let div = tree.children[0].node.unwrap();
+        assert!(scroll_to(&mut tree, div, (0.0, 30.0)));
+        engine.retain(tree);
+
+        if let NodeType::Element(element) = &mut dom.children[1].node_type {
+            element.attributes.insert("class".to_string(), "tall".to_string());
+        }
+        engine.attributes_changed(&dom.children[1]);
+        let tree = engine.update(&dom, &stylesheet, &FixedWidthMeasurer);
+        assert_eq!(tree.children[0].scroll.map(|scroll| scroll.offset), Some((0.0, 30.0)));
+        assert_eq!(tree.children[0].children[0].dimensions.content.y, -30.0);
+        assert_eq!(tree.children[1].dimensions.content, Rect { x: 0.0, y: 50.0, width: 1280.0, height: 20.0 });
+    }
+}
@@ -0,0 +1,171 @@
+// Resolving lengths, percentages and math functions to px
+use foamium_css::{CalcType, MediaEnvironment, Unit, Value};
+
+/// The initial value of `font-size`, `medium`.
+pub const INITIAL_FONT_SIZE: f32 = 16.0;
+
+/// The font measurements font-relative units refer to. Without access to the font's
+/// tables we use the fallbacks CSS Values 4 suggests, scaled by the font size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontMetrics {
+    /// `em`
+    pub font_size: f32,
+    /// `ex`
+    pub x_height: f32,
+    /// `cap`
+    pub cap_height: f32,
+    /// `ch`, the advance of "0"
+    pub zero_advance: f32,
+    /// `ic`, the advance of "水"
+    pub ideographic_advance: f32,
+    /// `lh`, the computed `line-height`
+    pub line_height: f32,
+}
+
+impl FontMetrics {
+    /// The metrics of a font size, with the line height of `line-height: normal`.
+    pub fn for_font_size(font_size: f32) -> FontMetrics {
+        FontMetrics {
+            font_size,
+            x_height: font_size * 0.5,
+            cap_height: font_size * 0.7,
+            zero_advance: font_size * 0.5,
+            ideographic_advance: font_size,
+            line_height: font_size * 1.2,
+        }
+    }
+}
+
+/// Everything relative lengths resolve against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LengthContext {
+    /// The font `em`, `ex` and friends refer to
+    pub font: FontMetrics,
+    /// The root element's font, for `rem`, `rex` and friends
+    pub root_font: FontMetrics,
+    pub viewport_width: f32,
+    pub viewport_height: f32,
+}
+
+impl LengthContext {
+    pub fn new(env: &MediaEnvironment) -> LengthContext {
+        let font = FontMetrics::for_font_size(INITIAL_FONT_SIZE);
+        LengthContext {
+            font,
+            root_font: font,
+            viewport_width: env.viewport_width,
+            viewport_height: env.viewport_height,
+        }
+    }
+
+    /// The context for an element whose computed font size is `font_size`. `lh` still
+    /// refers to the parent's line height, as it does in `line-height` itself, until
+    /// `with_line_height` gives the element's.
+    pub fn with_font_size(&self, font_size: f32) -> LengthContext {
+        LengthContext {
+            font: FontMetrics {
+                line_height: self.font.line_height,
+                ..FontMetrics::for_font_size(font_size)
+            },
+            ..*self
+        }
+    }
+
+    /// The context for an element whose computed `line-height` is `line_height` px.
+    pub fn with_line_height(&self, line_height: f32) -> LengthContext {
+        LengthContext {
+            font: FontMetrics {
+                line_height,
+                ..self.font
+            },
+            ..*self
+        }
+    }
+
+    /// Resolve a length, percentage or math function to px. Percentages resolve against
+    /// `percentage_basis`, and can't be resolved while it is unknown.
+    pub fn resolve(&self, value: &Value, percentage_basis: Option<f32>) -> Option<f32> {
+        match *value {
+            Value::Length(v, unit) => self.resolve_unit(v, unit, percentage_basis),
+            // A bare number is only a valid length when it is zero
+            Value::Number(0.0) => Some(0.0),
+            Value::Calc(ref node) => match node.calc_type()? {
+                CalcType::Number => None,
+                _ => node.evaluate(&|v, unit| self.resolve_unit(v, unit, percentage_basis)),
+            },
+            _ => None,
+        }
+    }
+
+    fn resolve_unit(&self, v: f32, unit: Unit, percentage_basis: Option<f32>) -> Option<f32> {
+        if let Some(px) = unit
+            .absolute_px()
+            .or_else(|| unit.viewport_px(self.viewport_width, self.viewport_height))
+        {
+            return Some(v * px);
+        }
+        let (font, root) = (&self.font, &self.root_font);
+        Some(
+            v * match unit {
+                Unit::Em => font.font_size,
+                Unit::Rem => root.font_size,
+                Unit::Ex => font.x_height,
+                Unit::Rex => root.x_height,
+                Unit::Cap => font.cap_height,
+                Unit::Rcap => root.cap_height,
+                Unit::Ch => font.zero_advance,
+                Unit::Rch => root.zero_advance,
+                Unit::Ic => font.ideographic_advance,
+                Unit::Ric => root.ideographic_advance,
+                Unit::Lh => font.line_height,
+                Unit::Rlh => root.line_height,
+                Unit::Percent => percentage_basis? / 100.0,
+                _ => return None,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> LengthContext {
+        LengthContext {
+            font: FontMetrics::for_font_size(20.0),
+            root_font: FontMetrics::for_font_size(10.0),
+            viewport_width: 1000.0,
+            viewport_height: 500.0,
+        }
+    }
+
+    #[test]
+    fn test_resolve_mixed_units() {
+        let context = context();
+        let value = foamium_css::parse_value("calc(50% - 1em + 2rem + 1vw)").unwrap();
+        assert_eq!(context.resolve(&value, Some(200.0)), Some(110.0));
+        assert_eq!(context.resolve(&value, None), None);
+
+        let value = foamium_css::parse_value("max(10vmin, 1em)").unwrap();
+        assert_eq!(context.resolve(&value, None), Some(50.0));
+        let value = foamium_css::parse_value("calc(3 * 2)").unwrap();
+        assert_eq!(context.resolve(&value, None), None);
+    }
+
+    #[test]
+    fn test_resolve_units() {
+        let context = context();
+        let resolve = |source: &str| context.resolve(&foamium_css::parse_value(source).unwrap(), None);
+        assert_eq!(resolve("1in"), Some(96.0));
+        assert_eq!(resolve("2.54cm"), Some(96.0));
+        assert_eq!(resolve("40q"), resolve("1cm"));
+        assert_eq!(resolve("6pc"), Some(96.0));
+        assert_eq!(resolve("2ex"), Some(20.0));
+        assert_eq!(resolve("2rch"), Some(10.0));
+        assert_eq!(resolve("1lh"), Some(24.0));
+        assert_eq!(resolve("10dvh"), Some(50.0));
+        assert_eq!(resolve("10vi"), Some(100.0));
+        assert_eq!(resolve("0"), Some(0.0));
+        assert_eq!(resolve("12"), None);
+    }
+}
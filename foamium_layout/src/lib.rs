@@ -1,17 +1,85 @@
 // Layout tree - combines StyledNode with box model
-use foamium_css::{Stylesheet, Rule, Selector, SimpleSelector, Value, Specificity, Unit, Color};
+mod animation;
+mod block;
+mod box_model;
+mod flex;
+mod floats;
+mod grid;
+mod hints;
+mod hit_test;
+mod inline;
+mod intrinsic;
+mod invalidation;
+mod length;
+mod line_break;
+mod overflow;
+mod position;
+mod selector_map;
+mod stacking;
+mod table;
+#[cfg(test)]
+mod test_support;
+
+pub use animation::{Animation, AnimationDirection, AnimationEngine, Clock, FillMode, ManualClock, Transition};
+pub use block::{layout, layout_with_measurer};
+pub use box_model::{BorderStyle, BoxSizing, LengthPercentage, Sides};
+pub use flex::{AlignContent, AlignItems, FlexBasis, FlexDirection, FlexWrap};
+pub use floats::{Clear, Float};
+pub use grid::{GridAutoFlow, TrackBreadth, TrackListEntry, TrackSize};
+pub use hints::{parse_legacy_color, presentational_hints};
+pub use hit_test::{hit_test, hit_test_with_measurer, Hit, PointerEvents};
+pub use invalidation::{Dirty, IncrementalLayout, UpdateCounts};
+pub use inline::{
+    FixedWidthMeasurer, Fragment, LineBox, LineHeight, TextAlign, TextMeasurer, TextMetrics, VerticalAlign, WhiteSpace,
+};
+pub use length::{FontMetrics, LengthContext, INITIAL_FONT_SIZE};
+pub use overflow::{scroll_into_view, scroll_to, Overflow, Scroll, ScrollIntoViewOptions, ScrollLogicalPosition};
+pub use position::Position;
+pub use stacking::StackingContext;
+pub use table::{BorderCollapse, CaptionSide, TableLayout};
+
+use foamium_css::{Stylesheet, Rule, Declaration, Selector, SimpleSelector, Combinator, Value, Specificity, Color, MediaEnvironment, Image};
+use foamium_css::{ContainerType, GridLine, QueryContainer, TemplateAreas};
+use foamium_css::{compute_custom_properties, is_inherited, serialize_tokens, substitute_variables, CustomProperties};
 use foamium_dom::{DomNode, NodeType, ElementData};
+use block::BlockLayout;
+use floats::is_float;
+use inline::is_inline;
+use invalidation::{Retained, Reuse};
+use position::is_out_of_flow;
+use selector_map::{AncestorFilter, SelectorMap};
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 
 pub type PropertyMap = HashMap<String, Value>;
 
 #[derive(Debug)]
 pub struct LayoutBox<'a> {
-    pub node: &'a DomNode,
+    /// The element or text that generated the box, or `None` for an anonymous box
+    pub node: Option<&'a DomNode>,
     pub display: Display,
     pub dimensions: Dimensions,
     pub style: ComputedStyle,
     pub children: Vec<LayoutBox<'a>>,
+    /// Where an inline box is on each line it's on
+    pub fragments: Vec<Fragment>,
+    /// The line boxes of a block container's inline content
+    pub lines: Vec<LineBox>,
+    /// A scroll container's scrolling, or the viewport's for the root
+    pub scroll: Option<Scroll>,
+    /// What the overflow of the boxes around it clips it to, in page coordinates, or
+    /// `None` if nothing does
+    pub clip: Option<Rect>,
+    /// How far scrolling and sticky positioning have moved it from where layout put it
+    pub translation: (f32, f32),
+    /// What building and laying out the tree had to redo for the box, rather than keep
+    /// from the last tree an `IncrementalLayout` built
+    pub dirty: Dirty,
+    /// Its last layout as a block in normal flow, if that can be kept when it's laid out
+    /// the same way again
+    pub(crate) cache: Option<BlockLayout>,
+    /// Where it was painted in the last tree, and what that clipped it to
+    pub(crate) painted: Option<(Rect, Option<Rect>)>,
 }
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -22,7 +90,7 @@ pub struct Dimensions {
     pub margin: EdgeSizes,
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct Rect {
     pub x: f32,
     pub y: f32,
@@ -30,7 +98,7 @@ pub struct Rect {
     pub height: f32,
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct EdgeSizes {
     pub left: f32,
     pub right: f32,
@@ -38,117 +106,882 @@ pub struct EdgeSizes {
     pub bottom: f32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ComputedStyle {
+    /// `None` until some ancestor sets it, for the renderer's default text colour
     pub color: Option<Color>,
     pub background_color: Option<Color>,
     pub font_size: f32,
+    /// Family names and generic families in order of preference. Empty for the
+    /// renderer's default font.
+    pub font_family: Vec<String>,
     pub display: Display,
+    /// `None` for `auto`
+    pub margin: Sides<Option<LengthPercentage>>,
+    pub padding: Sides<LengthPercentage>,
+    /// In px, and zero where the border style is `none` or `hidden`
+    pub border_width: Sides<f32>,
+    pub border_style: Sides<BorderStyle>,
+    /// `currentcolor` computes to `color`
+    pub border_color: Sides<Option<Color>>,
+    /// `None` for `auto`
+    pub width: Option<LengthPercentage>,
+    pub height: Option<LengthPercentage>,
+    pub min_width: Option<LengthPercentage>,
+    pub min_height: Option<LengthPercentage>,
+    /// `None` for `none`
+    pub max_width: Option<LengthPercentage>,
+    pub max_height: Option<LengthPercentage>,
+    pub box_sizing: BoxSizing,
+    pub position: Position,
+    /// `top`, `right`, `bottom` and `left`, with `None` for `auto`
+    pub inset: Sides<Option<LengthPercentage>>,
+    /// `None` for `auto`
+    pub z_index: Option<i32>,
+    /// `none` for absolutely positioned boxes and flex and grid items, which can't float
+    pub float: Float,
+    pub clear: Clear,
+    pub overflow_x: Overflow,
+    pub overflow_y: Overflow,
+    pub pointer_events: PointerEvents,
+    pub white_space: WhiteSpace,
+    pub text_align: TextAlign,
+    pub line_height: LineHeight,
+    pub vertical_align: VerticalAlign,
+    pub flex_direction: FlexDirection,
+    pub flex_wrap: FlexWrap,
+    pub flex_grow: f32,
+    pub flex_shrink: f32,
+    pub flex_basis: FlexBasis,
+    pub justify_content: AlignContent,
+    pub align_content: AlignContent,
+    pub align_items: AlignItems,
+    /// `None` for `auto`, which takes the parent's `align-items`
+    pub align_self: Option<AlignItems>,
+    /// `normal` computes to zero, since only flex containers have gaps
+    pub row_gap: LengthPercentage,
+    pub column_gap: LengthPercentage,
+    pub order: i32,
+    /// Empty for `none`
+    pub grid_template_columns: Vec<TrackListEntry>,
+    pub grid_template_rows: Vec<TrackListEntry>,
+    /// `None` for `none`
+    pub grid_template_areas: Option<TemplateAreas>,
+    pub grid_auto_columns: Vec<TrackSize>,
+    pub grid_auto_rows: Vec<TrackSize>,
+    pub grid_auto_flow: GridAutoFlow,
+    pub grid_row_start: GridLine,
+    pub grid_row_end: GridLine,
+    pub grid_column_start: GridLine,
+    pub grid_column_end: GridLine,
+    pub justify_items: AlignItems,
+    /// `None` for `auto`, which takes the parent's `justify-items`
+    pub justify_self: Option<AlignItems>,
+    /// The `background-image` layers, topmost first
+    pub background_image: Vec<Image>,
+    pub border_collapse: BorderCollapse,
+    /// `border-spacing` in px, horizontal then vertical
+    pub border_spacing: (f32, f32),
+    pub caption_side: CaptionSide,
+    pub table_layout: TableLayout,
+    /// Inherited by children, for their `var()` references
+    pub custom_properties: CustomProperties,
+    pub transitions: Vec<Transition>,
+    pub animations: Vec<Animation>,
+}
+
+impl ComputedStyle {
+    /// The initial values of every property, which the root element inherits from.
+    pub fn initial() -> ComputedStyle {
+        ComputedStyle {
+            color: None,
+            background_color: None,
+            font_size: INITIAL_FONT_SIZE,
+            font_family: Vec::new(),
+            display: Display::Inline,
+            margin: Sides::all(Some(LengthPercentage::ZERO)),
+            padding: Sides::all(LengthPercentage::ZERO),
+            border_width: Sides::all(0.0),
+            border_style: Sides::all(BorderStyle::None),
+            border_color: Sides::all(None),
+            width: None,
+            height: None,
+            min_width: None,
+            min_height: None,
+            max_width: None,
+            max_height: None,
+            box_sizing: BoxSizing::ContentBox,
+            position: Position::Static,
+            inset: Sides::all(None),
+            z_index: None,
+            float: Float::None,
+            clear: Clear::None,
+            overflow_x: Overflow::Visible,
+            overflow_y: Overflow::Visible,
+            pointer_events: PointerEvents::Auto,
+            white_space: WhiteSpace::Normal,
+            text_align: TextAlign::Left,
+            line_height: LineHeight::Normal,
+            vertical_align: VerticalAlign::Baseline,
+            flex_direction: FlexDirection::Row,
+            flex_wrap: FlexWrap::NoWrap,
+            flex_grow: 0.0,
+            flex_shrink: 1.0,
+            flex_basis: FlexBasis::Auto,
+            justify_content: AlignContent::Normal,
+            align_content: AlignContent::Normal,
+            align_items: AlignItems::Normal,
+            align_self: None,
+            row_gap: LengthPercentage::ZERO,
+            column_gap: LengthPercentage::ZERO,
+            order: 0,
+            grid_template_columns: Vec::new(),
+            grid_template_rows: Vec::new(),
+            grid_template_areas: None,
+            grid_auto_columns: vec![TrackSize::AUTO],
+            grid_auto_rows: vec![TrackSize::AUTO],
+            grid_auto_flow: GridAutoFlow::default(),
+            grid_row_start: GridLine::Auto,
+            grid_row_end: GridLine::Auto,
+            grid_column_start: GridLine::Auto,
+            grid_column_end: GridLine::Auto,
+            justify_items: AlignItems::Normal,
+            justify_self: None,
+            background_image: Vec::new(),
+            border_collapse: BorderCollapse::Separate,
+            border_spacing: (0.0, 0.0),
+            caption_side: CaptionSide::Top,
+            table_layout: TableLayout::Auto,
+            custom_properties: CustomProperties::new(),
+            transitions: Vec::new(),
+            animations: Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Display {
     Inline,
     Block,
+    /// A block container that establishes a new block formatting context
+    FlowRoot,
     None,
+    /// The element generates no box of its own, and its children take its place
+    Contents,
+    /// A block-level flex container
+    Flex,
+    /// An inline-level flex container, laid out as an atomic inline
+    InlineFlex,
+    /// A block-level grid container
+    Grid,
+    /// An inline-level grid container, laid out as an atomic inline
+    InlineGrid,
+    /// A block-level table's wrapper box, which holds its captions and its table grid box
+    Table,
+    /// An inline-level table's wrapper box, laid out as an atomic inline
+    InlineTable,
+    TableCaption,
+    TableRowGroup,
+    TableHeaderGroup,
+    TableFooterGroup,
+    TableRow,
+    TableColumnGroup,
+    TableColumn,
+    TableCell,
+    /// The box inside a table wrapper box that holds the rows and columns. No element
+    /// has it; a table generates it along with its wrapper.
+    TableGrid,
 }
 
 pub fn build_layout_tree<'a>(root: &'a DomNode, stylesheet: &'a Stylesheet) -> LayoutBox<'a> {
-    let specified_values = match root.node_type {
-        NodeType::Element(ref elem) => specified_values(elem, stylesheet),
-        NodeType::Text(_) => HashMap::new(),
-    };
-    
-    let display = get_display(&specified_values);
-    let style = compute_style(&specified_values);
-    
+    build_layout_tree_with_environment(root, stylesheet, &MediaEnvironment::default())
+}
+
+/// Build the layout tree, applying only the rules whose media queries match `env`.
+pub fn build_layout_tree_with_environment<'a>(
+    root: &'a DomNode,
+    stylesheet: &'a Stylesheet,
+    env: &MediaEnvironment,
+) -> LayoutBox<'a> {
+    build_with_retained(root, stylesheet, env, None, Reuse::None)
+}
+
+/// Build the layout tree, keeping what `reuse` allows of a retained one.
+fn build_with_retained<'a>(
+    root: &'a DomNode,
+    stylesheet: &Stylesheet,
+    env: &MediaEnvironment,
+    retained: Option<Retained>,
+    reuse: Reuse,
+) -> LayoutBox<'a> {
+    let mut cascade = Cascade::new(stylesheet, env);
+    cascade.retained = retained.map(RefCell::new);
+    let parent = Parent {
+        context: LengthContext::new(env),
+        style: &ComputedStyle::initial(),
+        containers: &[],
+        available_width: env.viewport_width,
+        element: None,
+        is_root: true,
+        blockify: true,
+        reuse,
+    };
+    build_box(root, &cascade, &parent, &[]).pop().expect("the root always generates a box")
+}
+
+/// The inputs that stay the same while the whole tree is built.
+struct Cascade<'a> {
+    stylesheet: &'a Stylesheet,
+    env: &'a MediaEnvironment,
+    /// Cascade layers from lowest to highest priority
+    layer_order: Vec<&'a str>,
+    selector_map: SelectorMap,
+    /// The ancestors of the element being styled
+    ancestor_filter: RefCell<AncestorFilter>,
+    /// The last tree built, for the boxes that can be kept from it
+    retained: Option<RefCell<Retained>>,
+}
+
+impl<'a> Cascade<'a> {
+    fn new(stylesheet: &'a Stylesheet, env: &'a MediaEnvironment) -> Cascade<'a> {
+        Cascade {
+            stylesheet,
+            env,
+            layer_order: stylesheet.layer_order(),
+            selector_map: SelectorMap::new(stylesheet),
+            ancestor_filter: RefCell::new(AncestorFilter::new()),
+            retained: None,
+        }
+    }
+}
+
+/// What an element's style depends on from its parent.
+struct Parent<'p> {
+    context: LengthContext,
+    style: &'p ComputedStyle,
+    /// The element's query containers, outermost first
+    containers: &'p [QueryContainer],
+    /// The width the element's box can fill, used to size query containers
+    available_width: f32,
+    /// The parent element, for selector matching
+    element: Option<&'p ElementContext<'p>>,
+    is_root: bool,
+    /// Whether the parent makes its children block-level, as the root's parent and flex
+    /// containers do
+    blockify: bool,
+    /// What the element's boxes can keep from the retained tree
+    reuse: Reuse,
+}
+
+/// An element with the parts of the tree around it that selectors can refer to.
+struct ElementContext<'e> {
+    element: &'e ElementData,
+    parent: Option<&'e ElementContext<'e>>,
+    /// The element siblings before this one, in document order
+    previous_siblings: &'e [&'e ElementData],
+}
+
+impl<'e> ElementContext<'e> {
+    fn ancestors(&self) -> impl Iterator<Item = &ElementContext<'e>> {
+        std::iter::successors(self.parent, |elem| elem.parent)
+    }
+
+    /// The context of the sibling at `index` in `previous_siblings`.
+    fn sibling(&self, index: usize) -> ElementContext<'e> {
+        ElementContext {
+            element: self.previous_siblings[index],
+            parent: self.parent,
+            previous_siblings: &self.previous_siblings[..index],
+        }
+    }
+}
+
+/// Build the boxes a node generates, per CSS Display 3: none for `display: none`, its
+/// children's for `display: contents`, and otherwise its own box. An inline box with
+/// block-level boxes inside is split around them, so it can generate several.
+fn build_box<'a>(
+    root: &'a DomNode,
+    cascade: &Cascade,
+    parent: &Parent,
+    previous_siblings: &[&ElementData],
+) -> Vec<LayoutBox<'a>> {
+    // Nothing about a node that hasn't changed, with nothing changed inside it, can have
+    // changed, unless something around it did
+    let retained = cascade.retained.as_ref();
+    let change = retained.and_then(|retained| retained.borrow().change(root));
+    let unchanged = parent.reuse == Reuse::All && change.is_none();
+    if let Some(kept) = retained.filter(|_| unchanged).and_then(|retained| retained.borrow_mut().keep(root)) {
+        return vec![kept];
+    }
+    let element = match root.node_type {
+        NodeType::Element(ref elem) => Some(ElementContext {
+            element: elem,
+            parent: parent.element,
+            previous_siblings,
+        }),
+        NodeType::Text(_) => None,
+    };
+    let kept_style = retained.filter(|_| unchanged).and_then(|retained| retained.borrow().style(root));
+    let keeps_style = kept_style.is_some();
+    let (style, specified_values) = match kept_style {
+        Some(style) => (style, HashMap::new()),
+        None => match element_style(element.as_ref(), cascade, parent) {
+            Some(computed) => computed,
+            None => return Vec::new(),
+        },
+    };
+
+    let mut context = parent.context.with_font_size(style.font_size).with_line_height(style.line_height.resolve(style.font_size));
+    if parent.is_root {
+        context.root_font = context.font;
+    }
+    // Until layout runs, assume boxes fill the width available to them unless given one
+    let dimensions = Dimensions::from_style(&style, parent.available_width, None);
+    let available_width = match style.display {
+        Display::Contents => parent.available_width,
+        _ => dimensions.content.width,
+    };
+    let containers = match query_container(&specified_values, &context, available_width) {
+        Some(container) => [parent.containers, &[container]].concat(),
+        None => parent.containers.to_vec(),
+    };
+
+    let child_parent = Parent {
+        context,
+        style: &style,
+        containers: &containers,
+        available_width,
+        element: element.as_ref(),
+        is_root: false,
+        blockify: match style.display {
+            Display::Flex | Display::InlineFlex | Display::Grid | Display::InlineGrid => true,
+            Display::Contents => parent.blockify,
+            _ => false,
+        },
+        reuse: parent.reuse.for_children(change),
+    };
+    if let Some(ref elem) = element {
+        cascade.ancestor_filter.borrow_mut().push(elem.element);
+    }
     let mut children = Vec::new();
-    for child in &root.children {
-        children.push(build_layout_tree(child, stylesheet));
+    let mut siblings = Vec::new();
+    if style.display != Display::None {
+        for child in &root.children {
+            children.extend(build_box(child, cascade, &child_parent, &siblings));
+            if let NodeType::Element(ref elem) = child.node_type {
+                siblings.push(elem);
+            }
+        }
     }
-    
-    LayoutBox {
-        node: root,
-        display,
-        dimensions: Dimensions::default(), // Layout calculation happens later
-        style,
-        children,
+    if let Some(ref elem) = element {
+        cascade.ancestor_filter.borrow_mut().pop(elem.element);
+    }
+
+    let children = table::fix_children(children, &style, &child_parent.context);
+    let mut boxes = match style.display {
+        Display::Contents => children,
+        Display::Block
+        | Display::FlowRoot
+        | Display::Flex
+        | Display::InlineFlex
+        | Display::Grid
+        | Display::InlineGrid
+        | Display::TableCaption
+        | Display::TableCell => {
+            let children = wrap_inline_runs(children, &style, &child_parent.context);
+            vec![LayoutBox::new(Some(root), style, dimensions, children)]
+        }
+        Display::Table | Display::InlineTable => vec![table::wrap_table(Some(root), style, dimensions, children)],
+        Display::Inline if children.iter().any(|child| !is_inline(child) && !is_out_of_flow(child) && !is_float(child)) => {
+            split_inline(root, style, dimensions, children)
+        }
+        Display::Inline
+        | Display::None
+        | Display::TableRowGroup
+        | Display::TableHeaderGroup
+        | Display::TableFooterGroup
+        | Display::TableRow
+        | Display::TableColumnGroup
+        | Display::TableColumn
+        | Display::TableGrid => vec![LayoutBox::new(Some(root), style, dimensions, children)],
+    };
+    if let Some(retained) = retained.filter(|_| parent.reuse != Reuse::None) {
+        retained.borrow_mut().restore(root, &mut boxes, change, keeps_style);
+    }
+    boxes
+}
+
+/// Cascade and compute the style of an element, or of text when `element` is `None`,
+/// adjusted for where its box goes, along with the values it was computed from. `None`
+/// if it doesn't generate a box.
+fn element_style(element: Option<&ElementContext>, cascade: &Cascade, parent: &Parent) -> Option<(ComputedStyle, PropertyMap)> {
+    let mut specified_values = match element {
+        Some(elem) => specified_values(elem, cascade, parent.containers),
+        None => HashMap::new(),
+    };
+    let custom_properties = resolve_variables(&mut specified_values, &parent.style.custom_properties);
+    let mut style = compute_style(&specified_values, custom_properties, parent.style, &parent.context);
+    // The root element, flex and grid items, and absolutely positioned and floated boxes are
+    // always block-level, and the root always generates a box. Only boxes in a block
+    // container's flow can float. The internal table parts become plain blocks then.
+    if parent.blockify || style.position.is_absolute() {
+        style.float = Float::None;
+    }
+    if (parent.blockify || style.position.is_absolute() || style.float != Float::None) && element.is_some() {
+        style.display = match style.display {
+            Display::Inline => Display::Block,
+            Display::InlineFlex => Display::Flex,
+            Display::InlineGrid => Display::Grid,
+            Display::InlineTable => Display::Table,
+            Display::Contents if parent.is_root => Display::Block,
+            Display::TableCaption
+            | Display::TableRowGroup
+            | Display::TableHeaderGroup
+            | Display::TableFooterGroup
+            | Display::TableRow
+            | Display::TableColumnGroup
+            | Display::TableColumn
+            | Display::TableCell => Display::Block,
+            display => display,
+        };
+    }
+    if style.display == Display::None && !parent.is_root {
+        return None;
+    }
+    table::adjust_style(&mut style);
+    Some((style, specified_values))
+}
+
+impl<'a> LayoutBox<'a> {
+    fn new(
+        node: Option<&'a DomNode>,
+        style: ComputedStyle,
+        dimensions: Dimensions,
+        children: Vec<LayoutBox<'a>>,
+    ) -> LayoutBox<'a> {
+        LayoutBox {
+            node,
+            display: style.display,
+            dimensions, // Positions are left for layout
+            style,
+            children,
+            fragments: Vec::new(),
+            lines: Vec::new(),
+            scroll: None,
+            clip: None,
+            translation: (0.0, 0.0),
+            dirty: Dirty::ALL,
+            cache: None,
+            painted: None,
+        }
+    }
+
+    /// Whether this box was generated by the box tree rather than by an element or text.
+    pub fn is_anonymous(&self) -> bool {
+        self.node.is_none()
+    }
+}
+
+/// Wrap each run of inline-level children of a block container that also has block-level
+/// children in an anonymous block box, so every block container holds only one kind.
+/// Text directly in a flex or grid container is always wrapped, since each item is a block.
+/// Runs of white space that would collapse away are dropped instead. Absolutely positioned
+/// and floated boxes don't break runs in a block container.
+fn wrap_inline_runs<'a>(children: Vec<LayoutBox<'a>>, parent: &ComputedStyle, context: &LengthContext) -> Vec<LayoutBox<'a>> {
+    let wraps_all = matches!(parent.display, Display::Flex | Display::InlineFlex | Display::Grid | Display::InlineGrid);
+    let in_run = |child: &LayoutBox| is_inline(child) || !wraps_all && (is_out_of_flow(child) || is_float(child));
+    if !wraps_all && children.iter().all(in_run) || !children.iter().any(is_inline) {
+        return children;
+    }
+    let mut wrapped = Vec::new();
+    let mut run = Vec::new();
+    let flush = |run: &mut Vec<LayoutBox<'a>>, wrapped: &mut Vec<LayoutBox<'a>>| {
+        if run.iter().all(is_collapsible_white_space) {
+            run.clear();
+            return;
+        }
+        let mut style = compute_style(&PropertyMap::new(), parent.custom_properties.clone(), parent, context);
+        style.display = Display::Block;
+        wrapped.push(LayoutBox::new(None, style, Dimensions::default(), std::mem::take(run)));
+    };
+    for child in children {
+        if is_inline(&child) || in_run(&child) && !run.is_empty() {
+            run.push(child);
+        } else {
+            flush(&mut run, &mut wrapped);
+            wrapped.push(child);
+        }
     }
+    flush(&mut run, &mut wrapped);
+    wrapped
 }
 
-fn get_display(values: &PropertyMap) -> Display {
-    match values.get("display") {
-        Some(Value::Keyword(s)) => match s.as_str() {
+/// Whether a box is text that's all white space which collapses away.
+fn is_collapsible_white_space(layout_box: &LayoutBox) -> bool {
+    match layout_box.node.map(|node| &node.node_type) {
+        Some(NodeType::Text(text)) => {
+            layout_box.style.white_space == WhiteSpace::Normal && text.chars().all(|c| matches!(c, ' ' | '\t' | '\n' | '\r' | '\u{0C}'))
+        }
+        _ => false,
+    }
+}
+
+/// Break an inline box around the block-level boxes in flow inside it. The block-level
+/// boxes become siblings of the pieces, with the start edges on the first piece and the
+/// end edges on the last.
+fn split_inline<'a>(
+    node: &'a DomNode,
+    style: ComputedStyle,
+    dimensions: Dimensions,
+    children: Vec<LayoutBox<'a>>,
+) -> Vec<LayoutBox<'a>> {
+    let mut boxes = Vec::new();
+    let mut run = Vec::new();
+    for child in children {
+        if is_inline(&child) || is_out_of_flow(&child) || is_float(&child) {
+            run.push(child);
+        } else {
+            boxes.push(LayoutBox::new(Some(node), style.clone(), dimensions, std::mem::take(&mut run)));
+            boxes.push(child);
+        }
+    }
+    boxes.push(LayoutBox::new(Some(node), style, dimensions, run));
+
+    let pieces: Vec<usize> = (0..boxes.len()).filter(|&index| is_inline(&boxes[index])).collect();
+    let (first, last) = (pieces[0], pieces[pieces.len() - 1]);
+    for index in pieces {
+        let style = &mut boxes[index].style;
+        if index != first {
+            style.margin.left = Some(LengthPercentage::ZERO);
+            style.padding.left = LengthPercentage::ZERO;
+            style.border_width.left = 0.0;
+        }
+        if index != last {
+            style.margin.right = Some(LengthPercentage::ZERO);
+            style.padding.right = LengthPercentage::ZERO;
+            style.border_width.right = 0.0;
+        }
+    }
+    boxes
+}
+
+/// The query container an element establishes through `container-type`, if any.
+fn query_container(values: &PropertyMap, context: &LengthContext, width: f32) -> Option<QueryContainer> {
+    let container_type = match values.get("container-type") {
+        Some(Value::Keyword(keyword)) => match &*keyword.to_ascii_lowercase() {
+            "size" => ContainerType::Size,
+            "inline-size" => ContainerType::InlineSize,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let names = match values.get("container-name") {
+        Some(Value::Keyword(name)) if !name.is_empty() && !name.eq_ignore_ascii_case("none") => vec![name.clone()],
+        _ => Vec::new(),
+    };
+    // Size containment ignores the contents, so an auto height is zero
+    let height = match container_type {
+        ContainerType::Size => Some(values.get("height").and_then(|h| context.resolve(h, None)).unwrap_or(0.0)),
+        ContainerType::InlineSize => None,
+    };
+    Some(QueryContainer {
+        names,
+        container_type,
+        width,
+        height,
+        font_size: context.font.font_size,
+    })
+}
+
+fn get_display(value: &Value) -> Display {
+    match value {
+        Value::Keyword(s) => match s.as_str() {
             "block" => Display::Block,
+            "flow-root" => Display::FlowRoot,
             "none" => Display::None,
+            "contents" => Display::Contents,
+            "flex" => Display::Flex,
+            "inline-flex" => Display::InlineFlex,
+            "grid" => Display::Grid,
+            "inline-grid" => Display::InlineGrid,
+            "table" => Display::Table,
+            "inline-table" => Display::InlineTable,
+            "table-caption" => Display::TableCaption,
+            "table-row-group" => Display::TableRowGroup,
+            "table-header-group" => Display::TableHeaderGroup,
+            "table-footer-group" => Display::TableFooterGroup,
+            "table-row" => Display::TableRow,
+            "table-column-group" => Display::TableColumnGroup,
+            "table-column" => Display::TableColumn,
+            "table-cell" => Display::TableCell,
             _ => Display::Inline,
         },
         _ => Display::Inline,
     }
 }
 
-fn compute_style(values: &PropertyMap) -> ComputedStyle {
-    let color = match values.get("color") {
-        Some(Value::ColorValue(c)) => Some(*c),
-        _ => None,
+/// Compute the element's custom properties and substitute them into any values using `var()`.
+/// A value whose substitution fails is invalid at computed-value time and becomes `unset`.
+fn resolve_variables(values: &mut PropertyMap, inherited: &CustomProperties) -> CustomProperties {
+    let specified = values
+        .iter()
+        .filter_map(|(name, value)| match value {
+            Value::Custom(tokens) => Some((name.clone(), tokens.clone())),
+            _ => None,
+        })
+        .collect();
+    let custom_properties = compute_custom_properties(inherited, &specified);
+
+    for (name, value) in values.iter_mut() {
+        let substituted = match value {
+            Value::WithVariables(tokens) => substitute_variables(tokens, &custom_properties)
+                .and_then(|tokens| foamium_css::parse_value(&serialize_tokens(&tokens))),
+            // Expand the shorthand and take this longhand's part
+            Value::ShorthandWithVariables(shorthand, tokens) => substitute_variables(tokens, &custom_properties)
+                .and_then(|tokens| foamium_css::parse_shorthand(shorthand, &serialize_tokens(&tokens)))
+                .and_then(|longhands| longhands.into_iter().find(|longhand| longhand.name == *name))
+                .map(|longhand| longhand.value),
+            _ => continue,
+        };
+        *value = substituted.unwrap_or_else(|| Value::Keyword("unset".to_string()));
+    }
+    custom_properties
+}
+
+/// Compute the element's style from its specified values and its parent's computed
+/// style. `context` is the parent's length context, which `font-size` resolves against.
+fn compute_style(
+    values: &PropertyMap,
+    custom_properties: CustomProperties,
+    parent: &ComputedStyle,
+    context: &LengthContext,
+) -> ComputedStyle {
+    // Values that are invalid at computed-value time act like `unset`
+    let color = match cascaded(values, "color") {
+        Cascaded::Value(value) => to_color(value).or(parent.color),
+        Cascaded::Inherit => parent.color,
+        Cascaded::Initial => None,
     };
-    
-    let background_color = match values.get("background-color") {
-        Some(Value::ColorValue(c)) => Some(*c),
-        _ => None,
+    let background_color = match cascaded(values, "background-color") {
+        Cascaded::Value(Value::Keyword(keyword)) if keyword.eq_ignore_ascii_case("currentcolor") => color,
+        Cascaded::Value(value) => to_color(value),
+        Cascaded::Inherit => parent.background_color,
+        Cascaded::Initial => None,
+    };
+
+    // Relative font sizes resolve against the parent's font size
+    let font_size = match cascaded(values, "font-size") {
+        Cascaded::Value(Value::Keyword(keyword)) => font_size_keyword(keyword, parent.font_size),
+        Cascaded::Value(value) => context.resolve(value, Some(parent.font_size)).filter(|size| *size >= 0.0),
+        Cascaded::Inherit => None,
+        Cascaded::Initial => Some(INITIAL_FONT_SIZE),
+    }
+    .unwrap_or(parent.font_size);
+    let font_family = match cascaded(values, "font-family") {
+        Cascaded::Value(value) => get_font_family(value).unwrap_or_else(|| parent.font_family.clone()),
+        Cascaded::Inherit => parent.font_family.clone(),
+        Cascaded::Initial => Vec::new(),
+    };
+
+    let display = match cascaded(values, "display") {
+        Cascaded::Value(value) => get_display(value),
+        Cascaded::Inherit => parent.display,
+        Cascaded::Initial => Display::Inline,
     };
-    
-    let font_size = match values.get("font-size") {
-        Some(Value::Length(v, Unit::Px)) => *v,
-        Some(Value::Length(v, Unit::Pt)) => *v * 1.33, // Approx pt to px
-        _ => 16.0, // Default
+    let background_image = match cascaded(values, "background-image") {
+        Cascaded::Value(value) => get_background_image(value),
+        Cascaded::Inherit => parent.background_image.clone(),
+        Cascaded::Initial => Vec::new(),
     };
-    
-    ComputedStyle {
+
+    let mut style = ComputedStyle {
         color,
         background_color,
         font_size,
-        display: get_display(values),
+        font_family,
+        display,
+        background_image,
+        custom_properties,
+        transitions: animation::compute_transitions(values),
+        animations: animation::compute_animations(values),
+        ..ComputedStyle::initial()
+    };
+    // Lengths other than `font-size` are relative to the element's own font, and once it's
+    // computed, its own line height
+    let context = context.with_font_size(font_size);
+    inline::compute_line_height(&mut style, values, parent, &context);
+    let context = context.with_line_height(style.line_height.resolve(font_size));
+    box_model::compute_box_style(&mut style, values, parent, &context);
+    position::compute_position_style(&mut style, values, parent, &context);
+    floats::compute_float_style(&mut style, values, parent);
+    overflow::compute_overflow_style(&mut style, values, parent);
+    hit_test::compute_hit_test_style(&mut style, values, parent);
+    inline::compute_text_style(&mut style, values, parent, &context);
+    flex::compute_flex_style(&mut style, values, parent, &context);
+    grid::compute_grid_style(&mut style, values, parent, &context);
+    table::compute_table_style(&mut style, values, parent, &context);
+    style
+}
+
+/// A property's value once the CSS-wide keywords are accounted for.
+enum Cascaded<'v> {
+    Value(&'v Value),
+    Inherit,
+    Initial,
+}
+
+/// Look up a property. Without a value, or with `unset`, inherited properties take the
+/// parent's value and the rest their initial value. The cascade only has author styles,
+/// so `revert` rolls back to nothing and acts like `unset` too.
+fn cascaded<'v>(values: &'v PropertyMap, name: &str) -> Cascaded<'v> {
+    let keyword = match values.get(name) {
+        Some(Value::Keyword(keyword)) => keyword.to_ascii_lowercase(),
+        Some(value) => return Cascaded::Value(value),
+        None => "unset".to_string(),
+    };
+    match &*keyword {
+        "inherit" => Cascaded::Inherit,
+        "initial" => Cascaded::Initial,
+        "unset" | "revert" if is_inherited(name) => Cascaded::Inherit,
+        "unset" | "revert" => Cascaded::Initial,
+        _ => Cascaded::Value(&values[name]),
+    }
+}
+
+/// The family names in a `font-family` list, or `None` if it holds anything else.
+fn get_font_family(value: &Value) -> Option<Vec<String>> {
+    value
+        .items()
+        .iter()
+        .map(|item| match item {
+            Value::String(name) => Some(name.clone()),
+            Value::Keyword(name) if !name.is_empty() => Some(name.to_ascii_lowercase()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The layers of `background-image`. `none` layers paint nothing, so they're dropped.
+fn get_background_image(value: &Value) -> Vec<Image> {
+    value
+        .items()
+        .iter()
+        .filter_map(|item| match item {
+            Value::Image(image) => Some(image.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Resolve the absolute size keywords, and `larger` and `smaller`, which scale the parent's size.
+fn font_size_keyword(keyword: &str, parent_font_size: f32) -> Option<f32> {
+    Some(match &*keyword.to_ascii_lowercase() {
+        "xx-small" => 9.0,
+        "x-small" => 10.0,
+        "small" => 13.0,
+        "medium" => INITIAL_FONT_SIZE,
+        "large" => 18.0,
+        "x-large" => 24.0,
+        "xx-large" => 32.0,
+        "xxx-large" => 48.0,
+        "larger" => parent_font_size * 1.2,
+        "smaller" => parent_font_size / 1.2,
+        _ => return None,
+    })
+}
+
+fn to_color(value: &Value) -> Option<Color> {
+    match value {
+        Value::ColorValue(c) => Some(*c),
+        Value::Keyword(name) => Color::from_name(name),
+        _ => None,
     }
 }
 
-fn specified_values(elem: &ElementData, stylesheet: &Stylesheet) -> PropertyMap {
+fn specified_values(elem: &ElementContext, cascade: &Cascade, containers: &[QueryContainer]) -> PropertyMap {
     let mut values = HashMap::new();
-    let mut rules = matching_rules(elem, stylesheet);
+    for declaration in presentational_hints(elem.element) {
+        values.insert(declaration.name, declaration.value);
+    }
 
-    rules.sort_by(|&(a, _), &(b, _)| a.cmp(&b));
-    for (_, rule) in rules {
-        for declaration in &rule.declarations {
+    // Later layers win, then more specific selectors; the sort is stable, so source order breaks ties
+    let mut rules = matching_rules(elem, cascade, containers);
+    rules.sort_by_key(|&(layer, specificity, _)| (layer, specificity));
+    let normal = |declaration: &&Declaration| !declaration.important;
+    for (_, _, rule) in &rules {
+        for declaration in rule.declarations.iter().filter(normal) {
             values.insert(declaration.name.clone(), declaration.value.clone());
         }
     }
+
+    // Inline style wins over any selector
+    let inline = elem.element.attributes.get("style").map(|style| foamium_css::parse_inline_style(style)).unwrap_or_default();
+    for declaration in inline.iter().filter(normal) {
+        values.insert(declaration.name.clone(), declaration.value.clone());
+    }
+
+    // Important declarations win over normal ones. Among them earlier layers win, and
+    // unlayered rules lose to every layer
+    rules.sort_by_key(|&(layer, specificity, _)| (std::cmp::Reverse(layer), specificity));
+    for (_, _, rule) in &rules {
+        for declaration in rule.declarations.iter().filter(|declaration| declaration.important) {
+            values.insert(declaration.name.clone(), declaration.value.clone());
+        }
+    }
+    for declaration in inline.into_iter().filter(|declaration| declaration.important) {
+        values.insert(declaration.name, declaration.value);
+    }
     values
 }
 
-type MatchedRule<'a> = (Specificity, &'a Rule);
+/// A matching rule with the rank of its cascade layer and the specificity of its
+/// most specific matching selector.
+type MatchedRule<'a> = (usize, Specificity, &'a Rule);
 
-fn matching_rules<'a>(elem: &ElementData, stylesheet: &'a Stylesheet) -> Vec<MatchedRule<'a>> {
-    stylesheet
-        .rules
-        .iter()
-        .filter_map(|rule| match_rule(elem, rule))
+fn matching_rules<'a>(elem: &ElementContext, cascade: &Cascade<'a>, containers: &[QueryContainer]) -> Vec<MatchedRule<'a>> {
+    let filter = cascade.ancestor_filter.borrow();
+    cascade
+        .selector_map
+        .matching_rules(elem, &filter)
+        .into_iter()
+        .map(|(index, specificity)| (&cascade.stylesheet.rules[index], specificity))
+        .filter(|(rule, _)| rule.applies_to(cascade.env, containers))
+        .map(|(rule, specificity)| (layer_rank(rule, &cascade.layer_order), specificity, rule))
         .collect()
 }
 
-fn match_rule<'a>(elem: &ElementData, rule: &'a Rule) -> Option<MatchedRule<'a>> {
-    rule.selectors
-        .iter()
-        .find(|selector| matches(elem, *selector))
-        .map(|selector| (selector.specificity(), rule))
+/// Where a rule's layer comes in the cascade. Unlayered rules beat every layer.
+fn layer_rank(rule: &Rule, layer_order: &[&str]) -> usize {
+    match rule.layer() {
+        Some(layer) => layer_order.iter().position(|l| *l == layer).unwrap_or(0),
+        None => layer_order.len(),
+    }
 }
 
-fn matches(elem: &ElementData, selector: &Selector) -> bool {
+fn matches(elem: &ElementContext, selector: &Selector) -> bool {
     match *selector {
         Selector::Simple(ref simple_selector) => matches_simple_selector(elem, simple_selector),
+        Selector::Complex(ref left, combinator, ref simple_selector) => {
+            if !matches_simple_selector(elem, simple_selector) {
+                return false;
+            }
+            match combinator {
+                Combinator::Descendant => elem.ancestors().any(|ancestor| matches(ancestor, left)),
+                Combinator::Child => elem.parent.is_some_and(|parent| matches(parent, left)),
+                Combinator::NextSibling => match elem.previous_siblings.len() {
+                    0 => false,
+                    len => matches(&elem.sibling(len - 1), left),
+                },
+                Combinator::SubsequentSibling => {
+                    (0..elem.previous_siblings.len()).any(|index| matches(&elem.sibling(index), left))
+                }
+            }
+        }
     }
 }
 
-fn matches_simple_selector(elem: &ElementData, selector: &SimpleSelector) -> bool {
+fn matches_simple_selector(context: &ElementContext, selector: &SimpleSelector) -> bool {
+    let elem = context.element;
     if selector.tag_name.iter().any(|name| elem.tag_name != *name) {
         return false;
     }
@@ -166,7 +999,10 @@ fn matches_simple_selector(elem: &ElementData, selector: &SimpleSelector) -> boo
         return false;
     }
 
-    true
+    selector
+        .is
+        .iter()
+        .all(|list| list.iter().any(|selector| matches(context, selector)))
 }
 
 // Helper functions for ElementData
@@ -181,3 +1017,369 @@ fn elem_classes(elem: &ElementData) -> HashSet<&str> {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foamium_css::{ColorScheme, Unit};
+    use foamium_dom::parse_html;
+
+    #[test]
+    fn test_media_rules_follow_environment() {
+        let dom = parse_html("<p>Hello</p>");
+        let stylesheet = foamium_css::parse(
+            "p { color: #000000; } @media (prefers-color-scheme: dark) { p { color: #ffffff; } }",
+        );
+
+        let light = build_layout_tree(&dom, &stylesheet);
+        assert_eq!(light.children[0].style.color, Some(Color::new(0, 0, 0, 255)));
+
+        let env = MediaEnvironment {
+            color_scheme: ColorScheme::Dark,
+            ..MediaEnvironment::default()
+        };
+        let dark = build_layout_tree_with_environment(&dom, &stylesheet, &env);
+        assert_eq!(dark.children[0].style.color, Some(Color::new(255, 255, 255, 255)));
+    }
+
+    #[test]
+    fn test_var_substitution() {
+        let dom = parse_html("<div><p>One</p><h1>Two</h1></div>");
+        let stylesheet = foamium_css::parse(
+            "html { --text: #1c1c1c; --size: 20px; }
+             div { --bg: var(--text); background-color: var(--bg); }
+             p { color: var(--text); font-size: var(--size); }
+             h1 { color: #ff0000; color: var(--missing); }",
+        );
+        let root = build_layout_tree(&dom, &stylesheet);
+        let div = &root.children[0];
+        assert_eq!(div.style.background_color, Some(Color::new(0x1c, 0x1c, 0x1c, 255)));
+
+        let p = &div.children[0];
+        assert_eq!(p.style.color, Some(Color::new(0x1c, 0x1c, 0x1c, 255)));
+        assert_eq!(p.style.font_size, 20.0);
+
+        // Invalid at computed-value time: the earlier declaration doesn't come back
+        let h1 = &div.children[1];
+        assert_eq!(h1.style.color, None);
+    }
+
+    #[test]
+    fn test_inline_style_and_hints() {
+        let dom = parse_html(
+            "<div><p style=\"color: red; font-size: 20px\">One</p><font color=\"#00ff00\">Two</font><table bgcolor=\"blue\" width=\"50%\"></table></div>",
+        );
+        let stylesheet = foamium_css::parse("#x, p { color: #000000; } font { color: #0000ff; }");
+        let root = build_layout_tree(&dom, &stylesheet);
+        let div = &root.children[0];
+
+        // Inline style beats the stylesheet
+        assert_eq!(div.children[0].style.color, Some(Color::new(255, 0, 0, 255)));
+        assert_eq!(div.children[0].style.font_size, 20.0);
+        // But the stylesheet beats presentational hints
+        assert_eq!(div.children[1].style.color, Some(Color::new(0, 0, 255, 255)));
+        assert_eq!(div.children[2].style.background_color, Some(Color::new(0, 0, 255, 255)));
+
+        let NodeType::Element(ref table) = div.children[2].node.unwrap().node_type else {
+            panic!("expected an element");
+        };
+        let env = MediaEnvironment::default();
+        let cascade = Cascade::new(&stylesheet, &env);
+        let table = ElementContext {
+            element: table,
+            parent: None,
+            previous_siblings: &[],
+        };
+        let values = specified_values(&table, &cascade, &[]);
+        assert_eq!(values.get("width"), Some(&Value::Length(50.0, Unit::Percent)));
+    }
+
+    #[test]
+    fn test_calc_font_size() {
+        let dom = parse_html("<div><p>One</p></div>");
+        let stylesheet = foamium_css::parse(
+            "div { font-size: calc(1em + 2px); } p { font-size: clamp(12px, 2vw, 2em); }",
+        );
+        let env = MediaEnvironment {
+            viewport_width: 800.0,
+            ..MediaEnvironment::default()
+        };
+        let root = build_layout_tree_with_environment(&dom, &stylesheet, &env);
+        assert_eq!(root.children[0].style.font_size, 18.0);
+        assert_eq!(root.children[0].children[0].style.font_size, 16.0);
+    }
+
+    #[test]
+    fn test_font_relative_units() {
+        let dom = parse_html("<div><p>One<span>Two</span></p></div>");
+        let stylesheet = foamium_css::parse(
+            "div { font-size: 0.25in; } p { font-size: 2ex; } span { font-size: larger; }",
+        );
+        let root = build_layout_tree(&dom, &stylesheet);
+        let div = &root.children[0];
+        assert_eq!(div.style.font_size, 24.0);
+        assert_eq!(div.children[0].style.font_size, 24.0);
+        assert!((div.children[0].children[1].style.font_size - 28.8).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_line_height_units() {
+        let dom = parse_html("<div><p>One</p></div>");
+        let stylesheet = foamium_css::parse(
+            "html { line-height: 10px; }
+             div { line-height: 2lh; padding-top: 1lh; padding-left: 1rlh; }
+             p { font-size: 10px; line-height: 3; padding-top: 1lh; margin-left: 2rlh; }",
+        );
+        let root = build_layout_tree(&dom, &stylesheet);
+        // `lh` in `line-height` is the parent's, and elsewhere the element's own
+        let div = &root.children[0];
+        assert_eq!(div.style.line_height, LineHeight::Px(20.0));
+        assert_eq!(div.style.padding.top.resolve(0.0), 20.0);
+        assert_eq!(div.style.padding.left.resolve(0.0), 10.0);
+        let p = &div.children[0];
+        assert_eq!(p.style.padding.top.resolve(0.0), 30.0);
+        assert_eq!(p.style.margin.left.as_ref().map(|margin| margin.resolve(0.0)), Some(20.0));
+    }
+
+    #[test]
+    fn test_inheritance_and_css_wide_keywords() {
+        let dom = parse_html(
+            "<div><p>One<em>Two</em></p><h1>Three</h1><h2>Four</h2><font face=\"Arial, Helvetica\">Five</font></div>",
+        );
+        let stylesheet = foamium_css::parse(
+            "div { color: #ff0000; background-color: #00ff00; font-size: 20px; font-family: \"Open Sans\", serif; }
+             div, p, h1, font { display: block; }
+             p { font-size: 150%; background-color: inherit; }
+             em { font-size: 2em; color: initial; }
+             h1 { color: unset; background-color: unset; font-family: initial; }
+             h2 { display: block; color: revert; font-size: 12px; }
+             html { font-size: 10px; }
+             h2 { font-size: 2rem; background-color: currentcolor; }",
+        );
+        let root = build_layout_tree(&dom, &stylesheet);
+        let div = &root.children[0];
+        let red = Some(Color::new(255, 0, 0, 255));
+        assert_eq!(div.style.font_family, ["Open Sans", "serif"]);
+
+        let p = &div.children[0];
+        assert_eq!(p.style.color, red);
+        assert_eq!(p.style.font_size, 30.0);
+        assert_eq!(p.style.font_family, div.style.font_family);
+        assert_eq!(p.style.background_color, div.style.background_color);
+        // Text inherits from its element
+        assert_eq!(p.children[0].style.color, red);
+        assert_eq!(p.children[0].style.font_size, 30.0);
+
+        let em = &p.children[1];
+        assert_eq!(em.style.color, None);
+        assert_eq!(em.style.font_size, 60.0);
+        assert_eq!(em.style.background_color, None);
+
+        let h1 = &div.children[1];
+        assert_eq!(h1.style.color, red);
+        assert_eq!(h1.style.background_color, None);
+        assert!(h1.style.font_family.is_empty());
+
+        let h2 = &div.children[2];
+        assert_eq!(h2.display, Display::Block);
+        assert_eq!(h2.style.color, red);
+        assert_eq!(h2.style.font_size, 20.0);
+        assert_eq!(h2.style.background_color, red);
+
+        assert_eq!(div.children[3].style.font_family, ["Arial", "Helvetica"]);
+    }
+
+    #[test]
+    fn test_cascade_layers() {
+        let dom = parse_html("<div><p>One</p></div>");
+        let stylesheet = foamium_css::parse(
+            "@layer base, theme;
+             p { font-size: 10px; }
+             @layer theme { #x, p { color: #0000ff; font-size: 30px; } }
+             @layer base { div p { color: #ff0000; } p { color: #00ff00; } }",
+        );
+        let root = build_layout_tree(&dom, &stylesheet);
+        let p = &root.children[0].children[0];
+        // The later layer wins regardless of order in the sheet, and unlayered rules beat both
+        assert_eq!(p.style.color, Some(Color::new(0, 0, 255, 255)));
+        assert_eq!(p.style.font_size, 10.0);
+    }
+
+    #[test]
+    fn test_important_declarations() {
+        let dom = parse_html("<div><p style=\"color: #00ff00; width: 5px !important\">One</p></div>");
+        let stylesheet = foamium_css::parse(
+            "@layer base, theme;
+             p { color: #ff0000 !important; font-size: 30px !important; width: 10px !important; }
+             div p { color: #0000ff; }
+             @layer base { p { font-size: 10px !important; } }
+             @layer theme { p { font-size: 20px !important; } }",
+        );
+        let root = build_layout_tree(&dom, &stylesheet);
+        let p = &root.children[0].children[0];
+        // Important beats normal, even inline; among important declarations earlier layers
+        // win, and inline style wins over any of them
+        assert_eq!(p.style.color, Some(Color::new(255, 0, 0, 255)));
+        assert_eq!(p.style.font_size, 10.0);
+        assert_eq!(p.style.width, Some(LengthPercentage::Px(5.0)));
+    }
+
+    #[test]
+    fn test_container_queries() {
+        let dom = parse_html("<div class=\"card\"><p>One</p></div>");
+        let stylesheet = foamium_css::parse(
+            ".card { container-type: inline-size; container-name: card; width: 50%; }
+             p { color: #000000; }
+             @container card (width > 500px) { p { color: #ff0000; } }
+             @container (height > 0px) { p { font-size: 30px; } }",
+        );
+        let wide = MediaEnvironment {
+            viewport_width: 1200.0,
+            ..MediaEnvironment::default()
+        };
+        let root = build_layout_tree_with_environment(&dom, &stylesheet, &wide);
+        let p = &root.children[0].children[0];
+        assert_eq!(p.style.color, Some(Color::new(255, 0, 0, 255)));
+        // An inline-size container has no height to query
+        assert_eq!(p.style.font_size, 16.0);
+
+        let narrow = MediaEnvironment {
+            viewport_width: 800.0,
+            ..MediaEnvironment::default()
+        };
+        let root = build_layout_tree_with_environment(&dom, &stylesheet, &narrow);
+        assert_eq!(root.children[0].children[0].style.color, Some(Color::new(0, 0, 0, 255)));
+    }
+
+    #[test]
+    fn test_nested_rules_and_combinators() {
+        let dom = parse_html(
+            "<nav class=\"menu\"><a>One</a><p><a>Two</a></p><b>Three</b><i>Four</i></nav><a>Five</a>",
+        );
+        let stylesheet = foamium_css::parse(
+            ".menu {
+                color: #000000;
+                > a { color: #ff0000; }
+                & a { font-size: 20px; }
+                a + b, p ~ i { color: #00ff00; }
+                @media (width > 600px) { background-color: #0000ff; }
+            }
+            a { color: #111111; }",
+        );
+        let root = build_layout_tree(&dom, &stylesheet);
+        let nav = &root.children[0];
+        assert_eq!(nav.style.background_color, Some(Color::new(0, 0, 255, 255)));
+
+        // `.menu > a` is more specific than `a`, so it wins despite coming first
+        assert_eq!(nav.children[0].style.color, Some(Color::new(255, 0, 0, 255)));
+        assert_eq!(nav.children[0].style.font_size, 20.0);
+        let nested_a = &nav.children[1].children[0];
+        assert_eq!(nested_a.style.color, Some(Color::new(17, 17, 17, 255)));
+        assert_eq!(nested_a.style.font_size, 20.0);
+        // `<b>` follows the `<p>`, not the first `<a>`, so it only inherits the menu's colour
+        assert_eq!(nav.children[2].style.color, Some(Color::new(0, 0, 0, 255)));
+        assert_eq!(nav.children[3].style.color, Some(Color::new(0, 255, 0, 255)));
+        assert_eq!(root.children[1].style.font_size, 16.0);
+    }
+
+    #[test]
+    fn test_box_model() {
+        let dom = parse_html("<div><p>One</p><span>Two</span></div>");
+        let stylesheet = foamium_css::parse(
+            "div { color: #ff0000; font-size: 20px; margin: 10px 5%; padding: 1em 2px; border: 3px solid; }
+             p { --pad: 10px; padding: var(--pad) 0; width: 50%; box-sizing: border-box; min-width: 600px; max-width: 400px; }
+             span { border-top: 2px dotted; border-left-width: 4px; margin: auto; height: 2em; max-height: 10%; }",
+        );
+        let root = build_layout_tree(&dom, &stylesheet);
+        let div = &root.children[0];
+        let red = Some(Color::new(255, 0, 0, 255));
+        assert_eq!(div.style.margin.right, Some(LengthPercentage::Percent(5.0)));
+        assert_eq!(div.style.padding.top, LengthPercentage::Px(20.0));
+        assert_eq!(div.style.border_style, Sides::all(BorderStyle::Solid));
+        assert_eq!(div.style.border_color, Sides::all(red));
+        assert_eq!(div.dimensions.margin.left, 64.0);
+        assert_eq!(div.dimensions.border.bottom, 3.0);
+        assert_eq!(div.dimensions.content.width, 1280.0 - 128.0 - 4.0 - 6.0);
+
+        // The minimum width wins over the maximum
+        let p = &div.children[0];
+        assert_eq!(p.style.box_sizing, BoxSizing::BorderBox);
+        assert_eq!(p.style.padding.top, LengthPercentage::Px(10.0));
+        assert_eq!(p.style.padding.left, LengthPercentage::ZERO);
+        assert_eq!(p.dimensions.content.width, 600.0);
+        assert_eq!(p.style.margin.top, Some(LengthPercentage::ZERO));
+
+        // A border without a style has no width; percentage heights wait for layout
+        let span = &div.children[1];
+        assert_eq!(span.style.border_width.top, 2.0);
+        assert_eq!(span.style.border_width.left, 0.0);
+        assert_eq!(span.style.margin, Sides::all(None));
+        assert_eq!(span.style.height, Some(LengthPercentage::Px(40.0)));
+        assert_eq!(span.dimensions.content.height, 40.0);
+        assert_eq!(span.style.max_height, Some(LengthPercentage::Percent(10.0)));
+
+        let calc = LengthPercentage::Calc(match foamium_css::parse_value("calc(50% - 1em)").unwrap() {
+            Value::Calc(node) => node,
+            _ => unreachable!(),
+        });
+        assert_eq!(calc.resolve_against(None), None);
+    }
+
+    #[test]
+    fn test_background_image() {
+        let dom = parse_html("<div><p>One</p><table background=\"tile.png\"></table></div>");
+        let stylesheet = foamium_css::parse(
+            "div { background-image: none; }
+             p { background-image: url(top.png), none, linear-gradient(red, blue); }",
+        );
+        let root = build_layout_tree(&dom, &stylesheet);
+        let div = &root.children[0];
+        assert!(div.style.background_image.is_empty());
+
+        let p = &div.children[0].style.background_image;
+        assert_eq!(p.len(), 2);
+        assert_eq!(p[0], Image::Url("top.png".to_string()));
+        assert!(matches!(p[1], Image::Gradient(_)));
+
+        let table = &div.children[1].style.background_image;
+        assert_eq!(table, &[Image::Url("tile.png".to_string())]);
+    }
+
+    #[test]
+    fn test_anonymous_boxes() {
+        let dom = parse_html(
+            "<div>One<p>Two</p> <span>Three</span><i>Hidden</i></div><section><b>Four<p>Five</p>Six</b></section><ul><li>Seven</li></ul>",
+        );
+        let stylesheet = foamium_css::parse(
+            "div, p, section { display: block; }
+             i { display: none; }
+             ul { display: contents; color: red; }
+             b { padding: 0 4px; }",
+        );
+        let root = build_layout_tree(&dom, &stylesheet);
+        let node_name = |layout_box: &LayoutBox| match layout_box.node.map(|node| &node.node_type) {
+            Some(NodeType::Element(element)) => element.tag_name.clone(),
+            Some(NodeType::Text(text)) => text.clone(),
+            None => "(anonymous)".to_string(),
+        };
+        let names = |layout_box: &LayoutBox| layout_box.children.iter().map(node_name).collect::<Vec<_>>();
+
+        // The root is a block, holding the block, the section and the `contents` list's child
+        assert_eq!(root.display, Display::Block);
+        assert_eq!(names(&root), ["div", "section", "(anonymous)"]);
+        assert_eq!(root.children[2].children[0].style.color, Some(Color::new(255, 0, 0, 255)));
+
+        // Inline content beside blocks is wrapped, and `display: none` generates nothing
+        let div = &root.children[0];
+        assert_eq!(names(div), ["(anonymous)", "p", "(anonymous)"]);
+        assert_eq!(names(&div.children[2]), ["span"]);
+        assert_eq!(div.children[0].display, Display::Block);
+
+        // The inline box is split around the block inside it
+        let section = &root.children[1];
+        assert_eq!(names(section), ["(anonymous)", "p", "(anonymous)"]);
+        let (first, last) = (&section.children[0].children[0], &section.children[2].children[0]);
+        assert_eq!(node_name(first), "b");
+        assert_eq!((first.style.padding.left.resolve(0.0), first.style.padding.right.resolve(0.0)), (4.0, 0.0));
+        assert_eq!((last.style.padding.left.resolve(0.0), last.style.padding.right.resolve(0.0)), (0.0, 4.0));
+    }
+}
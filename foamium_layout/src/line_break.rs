@@ -0,0 +1,188 @@
+// Line break opportunities, per the Unicode line breaking algorithm (UAX #14)
+//
+// This covers the classes and rules that matter for the text we lay out: spaces,
+// hyphens, punctuation, quotes, numbers and ideographs. Characters we don't classify
+// are treated as alphabetic, so words stay together.
+
+/// Whether a line may or must break at an opportunity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Break {
+    Allowed,
+    Mandatory,
+}
+
+/// The line breaking classes we distinguish.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Class {
+    /// Mandatory break: line feed, carriage return and other line ends
+    BK,
+    CR,
+    SP,
+    /// Zero width space
+    ZW,
+    /// Word joiner and non-breaking glue
+    GL,
+    /// Break after: tab, soft hyphen, dashes
+    BA,
+    /// Hyphen-minus
+    HY,
+    /// Opening punctuation
+    OP,
+    /// Closing punctuation and parentheses
+    CL,
+    /// Exclamation and interrogation
+    EX,
+    /// Infix separators: `,` `.` `:` `;`
+    IS,
+    /// Ambiguous quotes
+    QU,
+    /// Non-starters, like small kana and iteration marks
+    NS,
+    /// Ideographs and other characters that break on either side
+    ID,
+    NU,
+    /// Prefix and postfix numeric
+    PR,
+    PO,
+    /// Combining marks, which take the class of the character they attach to
+    CM,
+    AL,
+}
+
+fn class(c: char) -> Class {
+    match c {
+        '\n' | '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{2028}' | '\u{2029}' => Class::BK,
+        '\r' => Class::CR,
+        ' ' => Class::SP,
+        '\u{200B}' => Class::ZW,
+        '\u{A0}' | '\u{202F}' | '\u{2007}' | '\u{2060}' | '\u{FEFF}' | '\u{34F}' => Class::GL,
+        '\t' | '\u{AD}' | '\u{2010}' | '\u{2012}' | '\u{2013}' | '|' => Class::BA,
+        '-' => Class::HY,
+        '(' | '[' | '{' | '\u{A1}' | '\u{BF}' | '\u{3008}' | '\u{300A}' | '\u{300C}' | '\u{300E}' | '\u{3010}'
+        | '\u{FF08}' => Class::OP,
+        ')' | ']' | '}' | '\u{3001}' | '\u{3002}' | '\u{3009}' | '\u{300B}' | '\u{300D}' | '\u{300F}' | '\u{3011}'
+        | '\u{FF09}' | '\u{FF0C}' | '\u{FF0E}' => Class::CL,
+        '!' | '?' | '\u{FF01}' | '\u{FF1F}' => Class::EX,
+        ',' | '.' | ':' | ';' => Class::IS,
+        '"' | '\'' | '\u{AB}' | '\u{BB}' | '\u{2018}' | '\u{2019}' | '\u{201C}' | '\u{201D}' => Class::QU,
+        '\u{3005}' | '\u{303B}' | '\u{309D}' | '\u{309E}' | '\u{30FD}' | '\u{30FE}' | '\u{30FC}' | '\u{3041}'
+        | '\u{3043}' | '\u{3045}' | '\u{3047}' | '\u{3049}' | '\u{3063}' | '\u{30A1}' | '\u{30A3}' | '\u{30A5}'
+        | '\u{30A7}' | '\u{30A9}' | '\u{30C3}' => Class::NS,
+        '0'..='9' => Class::NU,
+        '$' | '+' | '\\' | '\u{A3}' | '\u{A5}' | '\u{20AC}' => Class::PR,
+        '%' | '\u{A2}' | '\u{B0}' | '\u{2030}' => Class::PO,
+        '\u{300}'..='\u{36F}' | '\u{200C}' | '\u{200D}' | '\u{FE00}'..='\u{FE0F}' => Class::CM,
+        '\u{2E80}'..='\u{2FFF}'
+        | '\u{3040}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7A3}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF00}'..='\u{FFEF}'
+        // The object replacement character, which stands in for atomic inlines
+        | '\u{FFFC}'
+        | '\u{1F000}'..='\u{1FAFF}'
+        | '\u{20000}'..='\u{3FFFD}' => Class::ID,
+        _ => Class::AL,
+    }
+}
+
+/// The break opportunities in `text`, as byte offsets of the character after the break.
+/// The end of the text is always a break, so it isn't included.
+pub(crate) fn break_opportunities(text: &str) -> Vec<(usize, Break)> {
+    use Class::*;
+
+    let mut opportunities = Vec::new();
+    let mut chars = text.char_indices();
+    let Some((_, first)) = chars.next() else {
+        return opportunities;
+    };
+    // The class of the previous character, with combining marks resolved (LB9, LB10)
+    let mut before = match class(first) {
+        CM => AL,
+        class => class,
+    };
+    // The class before any run of spaces ending at `before`, for LB14 to LB17
+    let mut before_spaces = before;
+    for (offset, c) in chars {
+        let after = class(c);
+        let opportunity = match (before, after) {
+            // LB4, LB5: always break after line ends, but not between CR and LF
+            (CR, BK) if c == '\n' => None,
+            (BK | CR, _) => Some(Break::Mandatory),
+            // LB6, LB7: never break before line ends, spaces or zero width spaces
+            (_, BK | CR | SP | ZW) => None,
+            // LB8: break after zero width spaces, even with spaces between
+            (ZW, _) => Some(Break::Allowed),
+            _ if before == SP && before_spaces == ZW => Some(Break::Allowed),
+            // LB9: combining marks stay with their base
+            (_, CM) => None,
+            // LB11, LB12: glue binds on both sides
+            (GL, _) => None,
+            (SP | BA | HY, GL) => Some(Break::Allowed),
+            (_, GL) => None,
+            // LB13: no break before closing punctuation or separators
+            (_, CL | EX | IS) => None,
+            // LB14: no break after opening punctuation, even with spaces between
+            (OP, _) => None,
+            (SP, _) if before_spaces == OP => None,
+            // LB15, LB16
+            (QU, OP) => None,
+            (SP, OP) if before_spaces == QU => None,
+            (CL, NS) => None,
+            (SP, NS) if before_spaces == CL => None,
+            // LB18: break after spaces
+            (SP, _) => Some(Break::Allowed),
+            // LB19: quotes bind on both sides
+            (_, QU) | (QU, _) => None,
+            // LB21: no break before hyphens, dashes and non-starters
+            (_, BA | HY | NS) => None,
+            // LB25: numbers and what goes with them
+            (PR | HY | IS | NU | AL, NU) | (NU, PO | AL) | (PR, OP | AL) | (CL, PO) => None,
+            // LB28, LB30: letters and numbers stay together, and with the punctuation around them
+            (AL, AL) | (AL | NU, OP) | (CL, AL | NU) => None,
+            // LB31: break everywhere else
+            _ => Some(Break::Allowed),
+        };
+        if let Some(opportunity) = opportunity {
+            opportunities.push((offset, opportunity));
+        }
+        match after {
+            CM => {}
+            SP => before = SP,
+            _ => {
+                before = after;
+                before_spaces = after;
+            }
+        }
+    }
+    opportunities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(text: &str) -> Vec<&str> {
+        let mut start = 0;
+        let mut segments = Vec::new();
+        for (offset, _) in break_opportunities(text) {
+            segments.push(&text[start..offset]);
+            start = offset;
+        }
+        segments.push(&text[start..]);
+        segments
+    }
+
+    #[test]
+    fn test_break_opportunities() {
+        assert_eq!(segments("The quick  brown fox."), ["The ", "quick  ", "brown ", "fox."]);
+        assert_eq!(segments("well-known (see: 3.5%)"), ["well-", "known ", "(see: ", "3.5%)"]);
+        assert_eq!(segments("10\u{A0}km \"quoted\" -5"), ["10\u{A0}km ", "\"quoted\" ", "-5"]);
+        assert_eq!(segments("日本語です。"), ["日", "本", "語", "で", "す。"]);
+        assert_eq!(
+            break_opportunities("a\r\nb\nc"),
+            [(3, Break::Mandatory), (5, Break::Mandatory)]
+        );
+    }
+}
@@ -0,0 +1,529 @@
+// Overflow (CSS Overflow 3): content that doesn't fit in its box, and scrolling it
+//
+// A box whose `overflow` isn't `visible` clips its content to its padding box, and unless
+// it's `clip` it's a scroll container, which the content scrolls in. The root's `overflow`
+// applies to the viewport instead, which always scrolls the document.
+//
+// Layout puts every box where it'd be if nothing was scrolled. A last pass then moves the
+// contents of each scroll container by its scroll offset, and sticky boxes to stay in view
+// in their scrollports, and works out what clips each box, so the tree holds where boxes
+// are painted. What a scroll container scrolls and clips is what it contains: absolutely
+// positioned boxes whose containing block is outside it escape it, and fixed ones escape
+// every scroll container. Scrolling runs the pass again, after moving everything back.
+use crate::box_model::computed;
+use crate::position::sticky_offset;
+use crate::{ComputedStyle, Display, LayoutBox, PropertyMap, Position, Rect};
+use foamium_css::Value;
+use foamium_dom::DomNode;
+
+/// `overflow-x` and `overflow-y`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Overflow {
+    #[default]
+    Visible,
+    Hidden,
+    Clip,
+    Scroll,
+    Auto,
+}
+
+impl Overflow {
+    fn from_keyword(keyword: &str) -> Option<Overflow> {
+        Some(match &*keyword.to_ascii_lowercase() {
+            "visible" => Overflow::Visible,
+            "hidden" => Overflow::Hidden,
+            "clip" => Overflow::Clip,
+            "scroll" => Overflow::Scroll,
+            "auto" => Overflow::Auto,
+            _ => return None,
+        })
+    }
+
+    /// Whether content overflowing the box along this axis is clipped.
+    pub fn clips(self) -> bool {
+        self != Overflow::Visible
+    }
+
+    /// Whether a box with this overflow is a scroll container.
+    pub fn scrolls(self) -> bool {
+        matches!(self, Overflow::Hidden | Overflow::Scroll | Overflow::Auto)
+    }
+}
+
+/// Compute `overflow-x` and `overflow-y` into `style`. Neither inherits. A box that
+/// scrolls along one axis can't let its content overflow along the other, so `visible`
+/// becomes `auto` and `clip` becomes `hidden` then (§3.1).
+pub(crate) fn compute_overflow_style(style: &mut ComputedStyle, values: &PropertyMap, parent: &ComputedStyle) {
+    let overflow = |name: &str, parent: &Overflow| {
+        computed(values, name, parent, Overflow::Visible, |value| match value {
+            Value::Keyword(keyword) => Overflow::from_keyword(keyword),
+            _ => None,
+        })
+    };
+    let (x, y) = (overflow("overflow-x", &parent.overflow_x), overflow("overflow-y", &parent.overflow_y));
+    let adjust = |overflow: Overflow, other: Overflow| match overflow {
+        Overflow::Visible if other.scrolls() => Overflow::Auto,
+        Overflow::Clip if other.scrolls() => Overflow::Hidden,
+        overflow => overflow,
+    };
+    style.overflow_x = adjust(x, y);
+    style.overflow_y = adjust(y, x);
+}
+
+/// Whether `overflow` applies to a box: block containers, and flex and grid containers.
+fn has_overflow(layout_box: &LayoutBox) -> bool {
+    matches!(
+        layout_box.display,
+        Display::Block
+            | Display::FlowRoot
+            | Display::Flex
+            | Display::InlineFlex
+            | Display::Grid
+            | Display::InlineGrid
+            | Display::TableCell
+            | Display::TableCaption
+    )
+}
+
+/// Whether a box clips its content along either axis.
+fn clips(layout_box: &LayoutBox) -> bool {
+    has_overflow(layout_box) && (layout_box.style.overflow_x.clips() || layout_box.style.overflow_y.clips())
+}
+
+/// Whether a box is a scroll container, which also establishes an independent formatting
+/// context. The root's `overflow` is the viewport's, but it scrolls the document anyway.
+pub(crate) fn is_scroll_container(layout_box: &LayoutBox) -> bool {
+    has_overflow(layout_box) && layout_box.style.overflow_x.scrolls()
+}
+
+/// A scroll container's scrolling, or the viewport's for the root.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scroll {
+    /// The padding box, or the viewport for the root, in page coordinates
+    pub scrollport: Rect,
+    /// The scrollable overflow rectangle: the scrollport and the content that overflows
+    /// it, relative to the scrollport's top left corner as if nothing was scrolled
+    pub overflow: Rect,
+    /// How far the content is scrolled right and down
+    pub offset: (f32, f32),
+}
+
+impl Scroll {
+    /// The scrolling of a scrollport with `overflow`, scrolled as far as `previous` was
+    /// and it can go.
+    fn clamped(scrollport: Rect, overflow: Rect, previous: Option<Scroll>) -> Scroll {
+        let mut scroll = Scroll {
+            scrollport,
+            overflow,
+            offset: (0.0, 0.0),
+        };
+        let (offset, max) = (previous.map_or((0.0, 0.0), |previous| previous.offset), scroll.max_offset());
+        scroll.offset = (offset.0.clamp(0.0, max.0), offset.1.clamp(0.0, max.1));
+        scroll
+    }
+
+    /// The largest offset along each axis, which scrolls the overflow's far edges into view.
+    pub fn max_offset(&self) -> (f32, f32) {
+        let overflow = &self.overflow;
+        (
+            (overflow.x + overflow.width - self.scrollport.width).max(0.0),
+            (overflow.y + overflow.height - self.scrollport.height).max(0.0),
+        )
+    }
+}
+
+/// What moves and clips the boxes inside a box.
+#[derive(Debug, Clone, Copy, Default)]
+struct Context {
+    translation: (f32, f32),
+    clip: Option<Rect>,
+}
+
+/// What the boxes inside a box are scrolled, stuck and clipped against.
+#[derive(Debug, Clone, Copy)]
+struct Containing {
+    /// For boxes in flow, and absolutely positioned ones whose containing block is the box
+    flow: Context,
+    /// For absolutely positioned boxes, whose containing block may be further out
+    absolute: Context,
+    /// The content box of the nearest block container, for sticky boxes
+    block: Rect,
+    /// The nearest scroll container's scrollport, which sticky boxes stay inside
+    scrollport: Rect,
+}
+
+/// Scroll the contents of the scroll containers in a tree that layout just put in page
+/// coordinates, stick sticky boxes, and set what clips each box. `viewport` is the root's
+/// scrollport, and it scrolls the root along with everything else.
+pub(crate) fn layout_overflow(root: &mut LayoutBox, viewport: Rect) {
+    let overflow = scrollable_overflow(contribution(root, true), (viewport.x, viewport.y), viewport, (0.0, 0.0));
+    let scroll = Scroll::clamped(viewport, overflow, root.scroll);
+    let context = Context {
+        translation: (-scroll.offset.0, -scroll.offset.1),
+        clip: None,
+    };
+    let containing = Containing {
+        flow: context,
+        absolute: context,
+        block: viewport,
+        scrollport: viewport,
+    };
+    scroll_box(root, containing, true);
+    root.scroll = Some(scroll);
+}
+
+/// Move every box back to where layout put it, and scroll the tree again.
+fn rescroll(root: &mut LayoutBox) {
+    fn restore(layout_box: &mut LayoutBox) {
+        let (dx, dy) = layout_box.translation;
+        shift(layout_box, -dx, -dy);
+        for child in &mut layout_box.children {
+            restore(child);
+        }
+    }
+    let Some(scroll) = root.scroll else {
+        return;
+    };
+    restore(root);
+    layout_overflow(root, scroll.scrollport);
+}
+
+fn scroll_box(layout_box: &mut LayoutBox, containing: Containing, is_root: bool) {
+    let context = match layout_box.style.position {
+        Position::Fixed => Context::default(),
+        Position::Absolute => containing.absolute,
+        _ => containing.flow,
+    };
+    let mut translation = context.translation;
+    shift(layout_box, translation.0, translation.1);
+    if layout_box.style.position == Position::Sticky {
+        let (dx, dy) = sticky_offset(&layout_box.style.inset, &layout_box.dimensions, containing.block, containing.scrollport);
+        shift(layout_box, dx, dy);
+        translation = (translation.0 + dx, translation.1 + dy);
+    }
+    layout_box.translation = translation;
+    layout_box.clip = context.clip;
+    let previous = layout_box.scroll.take();
+
+    let mut inner = containing;
+    if layout_box.display != Display::Inline {
+        inner.block = layout_box.dimensions.content;
+    }
+    let mut flow = Context {
+        translation,
+        clip: context.clip,
+    };
+    if !is_root && is_scroll_container(layout_box) {
+        let scrollport = layout_box.dimensions.padding_box();
+        let padding = layout_box.dimensions.padding;
+        // The contents are still where layout put them, which the box has moved from
+        let origin = (scrollport.x - translation.0, scrollport.y - translation.1);
+        let content = layout_box.children.iter().filter_map(|child| contribution(child, layout_box.style.position.is_positioned()));
+        let overflow = scrollable_overflow(content.reduce(Rect::union), origin, scrollport, (padding.right, padding.bottom));
+        let scroll = Scroll::clamped(scrollport, overflow, previous);
+        flow.translation = (translation.0 - scroll.offset.0, translation.1 - scroll.offset.1);
+        // Sticky boxes stay inside the whole of what scrolls, not just what's in view
+        inner.block = Rect {
+            x: scrollport.x + overflow.x + padding.left - scroll.offset.0,
+            y: scrollport.y + overflow.y + padding.top - scroll.offset.1,
+            width: (overflow.width - padding.left - padding.right).max(0.0),
+            height: (overflow.height - padding.top - padding.bottom).max(0.0),
+        };
+        inner.scrollport = scrollport;
+        layout_box.scroll = Some(scroll);
+    }
+    if !is_root && clips(layout_box) {
+        let clip = clip_rect(layout_box);
+        flow.clip = Some(flow.clip.map_or(clip, |outer| intersection(outer, clip)));
+    }
+    inner.flow = flow;
+    if layout_box.style.position.is_positioned() {
+        inner.absolute = flow;
+    }
+    for child in &mut layout_box.children {
+        scroll_box(child, inner, false);
+    }
+}
+
+/// Move a box's own rectangles, but not its children's.
+pub(crate) fn shift(layout_box: &mut LayoutBox, dx: f32, dy: f32) {
+    let move_rect = |rect: &mut Rect| {
+        rect.x += dx;
+        rect.y += dy;
+    };
+    move_rect(&mut layout_box.dimensions.content);
+    for fragment in &mut layout_box.fragments {
+        move_rect(&mut fragment.rect);
+        fragment.baseline += dy;
+    }
+    for line in &mut layout_box.lines {
+        move_rect(&mut line.rect);
+        line.baseline += dy;
+    }
+}
+
+/// What a box clips its content to: its padding box, along the axes it clips.
+fn clip_rect(layout_box: &LayoutBox) -> Rect {
+    // Far enough out to never clip, and not so far that adding them up overflows
+    const UNBOUNDED: f32 = f32::MAX / 4.0;
+    let padding = layout_box.dimensions.padding_box();
+    let style = &layout_box.style;
+    let (x, width) = if style.overflow_x.clips() { (padding.x, padding.width) } else { (-UNBOUNDED, 2.0 * UNBOUNDED) };
+    let (y, height) = if style.overflow_y.clips() { (padding.y, padding.height) } else { (-UNBOUNDED, 2.0 * UNBOUNDED) };
+    Rect { x, y, width, height }
+}
+
+fn intersection(a: Rect, b: Rect) -> Rect {
+    let (x, y) = (a.x.max(b.x), a.y.max(b.y));
+    Rect {
+        x,
+        y,
+        width: ((a.x + a.width).min(b.x + b.width) - x).max(0.0),
+        height: ((a.y + a.height).min(b.y + b.height) - y).max(0.0),
+    }
+}
+
+/// A scroll container's scrollable overflow rectangle (§2.2): its scrollport, and the
+/// area `content` takes up, with the container's end padding past it. `origin` is where
+/// the scrollport's top left corner was before anything moved.
+fn scrollable_overflow(content: Option<Rect>, origin: (f32, f32), scrollport: Rect, end_padding: (f32, f32)) -> Rect {
+    let area = Rect {
+        x: 0.0,
+        y: 0.0,
+        width: scrollport.width,
+        height: scrollport.height,
+    };
+    let Some(content) = content else {
+        return area;
+    };
+    area.union(Rect {
+        x: content.x - origin.0,
+        y: content.y - origin.1,
+        width: content.width + end_padding.0,
+        height: content.height + end_padding.1,
+    })
+}
+
+/// The area a box and what it contains take up in its scroll container, or `None` if it
+/// escapes it, being absolutely positioned against something further out.
+fn contribution(layout_box: &LayoutBox, contains_absolute: bool) -> Option<Rect> {
+    match layout_box.style.position {
+        Position::Fixed => return None,
+        Position::Absolute if !contains_absolute => return None,
+        _ => {}
+    }
+    let own = match layout_box.display {
+        Display::Inline => None,
+        _ => Some(layout_box.dimensions.border_box()),
+    };
+    let fragments = layout_box.fragments.iter().map(|fragment| fragment.rect);
+    let contains_absolute = contains_absolute || layout_box.style.position.is_positioned();
+    let inside = layout_box.children.iter().filter_map(|child| contribution(child, contains_absolute)).reduce(Rect::union);
+    // What a box clips, and what a scroll container scrolls, doesn't overflow it
+    let inside = match inside {
+        _ if is_scroll_container(layout_box) => None,
+        Some(inside) if clips(layout_box) => Some(intersection(inside, clip_rect(layout_box))),
+        inside => inside,
+    };
+    own.into_iter().chain(fragments).chain(inside).reduce(Rect::union)
+}
+
+/// `block` and `inline` of `ScrollIntoViewOptions`: where a box goes in its scrollport.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ScrollLogicalPosition {
+    #[default]
+    Start,
+    Center,
+    End,
+    /// As little scrolling as brings it into view
+    Nearest,
+}
+
+/// How `scroll_into_view` aligns a box in each of its scroll containers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollIntoViewOptions {
+    /// Along the block axis, which is vertical
+    pub block: ScrollLogicalPosition,
+    /// Along the inline axis, which is horizontal
+    pub inline: ScrollLogicalPosition,
+}
+
+impl Default for ScrollIntoViewOptions {
+    fn default() -> ScrollIntoViewOptions {
+        ScrollIntoViewOptions {
+            block: ScrollLogicalPosition::Start,
+            inline: ScrollLogicalPosition::Nearest,
+        }
+    }
+}
+
+/// The path of child indices from `root` to the first box `node` generated that `matches`.
+fn find_box(root: &LayoutBox, node: &DomNode, matches: &impl Fn(&LayoutBox) -> bool) -> Option<Vec<usize>> {
+    if root.node.is_some_and(|root_node| std::ptr::eq(root_node, node)) && matches(root) {
+        return Some(Vec::new());
+    }
+    root.children.iter().enumerate().find_map(|(index, child)| {
+        let mut path = find_box(child, node, matches)?;
+        path.insert(0, index);
+        Some(path)
+    })
+}
+
+fn box_at<'t, 'a>(root: &'t mut LayoutBox<'a>, path: &[usize]) -> &'t mut LayoutBox<'a> {
+    path.iter().fold(root, |layout_box, &index| &mut layout_box.children[index])
+}
+
+/// Scroll the scroll container `node` generated, or the viewport if it's the root
+/// element, to `offset`, as far as it scrolls. Returns whether it's a scroll container.
+pub fn scroll_to(root: &mut LayoutBox, node: &DomNode, offset: (f32, f32)) -> bool {
+    let Some(path) = find_box(root, node, &|layout_box| layout_box.scroll.is_some()) else {
+        return false;
+    };
+    if let Some(scroll) = &mut box_at(root, &path).scroll {
+        scroll.offset = offset;
+    }
+    rescroll(root);
+    true
+}
+
+/// Scroll each scroll container that `node`'s first box is in, innermost first and the
+/// viewport last, to bring its border box into view as `options` says (CSSOM View 1
+/// §6.1). Returns whether `node` has a box.
+pub fn scroll_into_view(root: &mut LayoutBox, node: &DomNode, options: ScrollIntoViewOptions) -> bool {
+    let Some(path) = find_box(root, node, &|_| true) else {
+        return false;
+    };
+    for depth in scroll_containers(root, &path).into_iter().rev() {
+        let target = box_at(root, &path);
+        let target = match target.display {
+            Display::Inline => target.fragments.iter().map(|fragment| fragment.rect).reduce(Rect::union),
+            _ => Some(target.dimensions.border_box()),
+        };
+        let Some(target) = target else {
+            return true;
+        };
+        let scroller = box_at(root, &path[..depth]);
+        let Some(scroll) = &mut scroller.scroll else {
+            continue;
+        };
+        let port = scroll.scrollport;
+        let dx = align(options.inline, (target.x, target.width), (port.x, port.width));
+        let dy = align(options.block, (target.y, target.height), (port.y, port.height));
+        scroll.offset = (scroll.offset.0 + dx, scroll.offset.1 + dy);
+        rescroll(root);
+    }
+    true
+}
+
+/// How deep each scroll container the box at `path` is in goes, outermost first: those
+/// it's in the containing block chain of.
+fn scroll_containers(root: &LayoutBox, path: &[usize]) -> Vec<usize> {
+    // The viewport scrolls the root, and whatever the initial containing block contains
+    let mut containers = vec![0];
+    let (mut flow, mut absolute) = (vec![0], vec![0]);
+    let mut layout_box = root;
+    for (depth, &index) in path.iter().enumerate() {
+        layout_box = &layout_box.children[index];
+        containers = match layout_box.style.position {
+            Position::Fixed => Vec::new(),
+            Position::Absolute => absolute.clone(),
+            _ => flow.clone(),
+        };
+        flow = containers.clone();
+        if layout_box.scroll.is_some() {
+            flow.push(depth + 1);
+        }
+        if layout_box.style.position.is_positioned() {
+            absolute = flow.clone();
+        }
+    }
+    containers
+}
+
+/// How far to scroll along an axis to put the span `target` where `position` says in the
+/// span `port`, each a start and a size.
+fn align(position: ScrollLogicalPosition, target: (f32, f32), port: (f32, f32)) -> f32 {
+    let start = target.0 - port.0;
+    let end = target.0 + target.1 - (port.0 + port.1);
+    match position {
+        ScrollLogicalPosition::Start => start,
+        ScrollLogicalPosition::End => end,
+        ScrollLogicalPosition::Center => (start + end) / 2.0,
+        // Already in view, or covering the whole port
+        ScrollLogicalPosition::Nearest if start >= 0.0 && end <= 0.0 || start < 0.0 && end > 0.0 => 0.0,
+        // Whichever edge is out of view comes into it, or the start if it doesn't fit
+        ScrollLogicalPosition::Nearest if (start < 0.0) == (target.1 <= port.1) => start,
+        ScrollLogicalPosition::Nearest => end,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::test_support::{laid_out, rect};
+    use foamium_dom::parse_html;
+
+    #[test]
+    fn test_scroll_containers() {
+        let dom = parse_html("<div><em></em><p></p><span id=\"f\"></span><span id=\"a\"></span></div>");
+        let stylesheet = foamium_css::parse(
+            "div { display: block; width: 100px; height: 50px; padding: 5px; overflow-y: auto; }
+             p { display: block; height: 200px; }
+             #f { position: fixed; }
+             #a { position: absolute; width: 1000px; }
+             em { display: block; position: sticky; top: 0; height: 10px; }",
+        );
+        let mut root = laid_out(&dom, &stylesheet);
+        let div = &root.children[0];
+        // Scrolling along one axis means clipping along the other
+        assert_eq!(div.style.overflow_x, Overflow::Auto);
+        let scroll = div.scroll.unwrap();
+        assert_eq!(scroll.scrollport, rect(0.0, 0.0, 110.0, 60.0));
+        // The content and the end padding past it, but not what escapes to the viewport
+        assert_eq!(scroll.overflow, rect(0.0, 0.0, 110.0, 220.0));
+        assert_eq!(scroll.max_offset(), (0.0, 160.0));
+        let clips: Vec<Option<Rect>> = div.children.iter().map(|child| child.clip).collect();
+        assert_eq!(clips, [Some(scroll.scrollport), Some(scroll.scrollport), None, None]);
+
+        // Scrolling moves what the box contains, as far as it goes
+        let node = div.node.unwrap();
+        assert!(scroll_to(&mut root, node, (20.0, 100.0)));
+        let div = &root.children[0];
+        assert_eq!(div.scroll.unwrap().offset, (0.0, 100.0));
+        assert_eq!(div.children[1].dimensions.content.y, -85.0);
+        assert_eq!(div.children[3].translation, (0.0, 0.0));
+        // except sticky boxes, which stay in the scrollport
+        assert_eq!(div.children[0].dimensions.content.y, 0.0);
+        assert_eq!(div.children[0].translation, (0.0, -100.0 + 95.0));
+        assert!(scroll_to(&mut root, node, (0.0, 0.0)));
+        assert_eq!(root.children[0].children[0].dimensions.content.y, 5.0);
+    }
+
+    #[test]
+    fn test_scroll_into_view() {
+        let dom = parse_html("<p></p><div><p></p><p id=\"t\"></p></div><p></p>");
+        let stylesheet = foamium_css::parse(
+            "p { display: block; height: 1000px; }
+             div { display: block; height: 100px; overflow: hidden; }
+             #t { height: 20px; }",
+        );
+        let mut root = laid_out(&dom, &stylesheet);
+        let node = root.children[1].children[1].node.unwrap();
+        let nearest = ScrollIntoViewOptions {
+            block: ScrollLogicalPosition::Nearest,
+            ..ScrollIntoViewOptions::default()
+        };
+        assert!(scroll_into_view(&mut root, node, nearest));
+        // The div scrolls to bring it in at the bottom, then the viewport does
+        assert_eq!(root.children[1].scroll.unwrap().offset, (0.0, 920.0));
+        assert_eq!(root.scroll.unwrap().offset, (0.0, 500.0));
+        assert_eq!(root.children[1].children[1].dimensions.content.y, 580.0);
+
+        // It's already in view, so nothing moves
+        assert!(scroll_into_view(&mut root, node, nearest));
+        assert_eq!(root.scroll.unwrap().offset, (0.0, 500.0));
+        assert!(scroll_into_view(&mut root, node, ScrollIntoViewOptions::default()));
+        assert_eq!(root.scroll.unwrap().offset, (0.0, 1080.0));
+        assert_eq!(root.children[1].children[1].dimensions.content.y, 0.0);
+    }
+}
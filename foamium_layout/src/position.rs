@@ -0,0 +1,423 @@
+// Positioned layout (CSS Position 3): relative and sticky boxes are shifted from where
+// normal flow put them, and absolutely positioned boxes are taken out of flow and placed
+// against their containing block (CSS 2.1 §10.3.7 and §10.6.4)
+//
+// Normal flow skips absolutely positioned boxes, leaving their content position at their
+// static position: where the top left corner of the margin box would have been if they
+// were in flow. Once the rest of the tree has page coordinates, a last pass walks it in
+// tree order, shifting relative boxes and laying out absolute ones, so every containing
+// block is where it ends up before the boxes inside it are placed. Sticky boxes depend
+// on how far their scroll container is scrolled, so they're shifted with the scrolling.
+use crate::block::{ContainingBlock, layout_contents, make_absolute, resolve_edges};
+use crate::box_model::{computed, length_or_keyword, Sides};
+use crate::intrinsic::shrink_to_fit;
+use crate::{ComputedStyle, Dimensions, Display, LayoutBox, LengthContext, LengthPercentage, PropertyMap, Rect, TextMeasurer};
+use foamium_css::Value;
+
+/// `position`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Position {
+    #[default]
+    Static,
+    Relative,
+    Absolute,
+    Fixed,
+    Sticky,
+}
+
+impl Position {
+    fn from_keyword(keyword: &str) -> Option<Position> {
+        Some(match &*keyword.to_ascii_lowercase() {
+            "static" => Position::Static,
+            "relative" => Position::Relative,
+            "absolute" => Position::Absolute,
+            "fixed" => Position::Fixed,
+            "sticky" => Position::Sticky,
+            _ => return None,
+        })
+    }
+
+    /// Whether a box with this position is positioned, so `z-index` applies to it and it
+    /// contains absolutely positioned descendants.
+    pub fn is_positioned(self) -> bool {
+        self != Position::Static
+    }
+
+    /// Whether a box with this position is absolutely positioned, which takes it out of flow
+    /// and makes it block-level.
+    pub fn is_absolute(self) -> bool {
+        matches!(self, Position::Absolute | Position::Fixed)
+    }
+}
+
+/// Compute `position`, the insets and `z-index` into `style`. None of them inherit.
+pub(crate) fn compute_position_style(
+    style: &mut ComputedStyle,
+    values: &PropertyMap,
+    parent: &ComputedStyle,
+    context: &LengthContext,
+) {
+    style.position = computed(values, "position", &parent.position, Position::Static, |value| match value {
+        Value::Keyword(keyword) => Position::from_keyword(keyword),
+        _ => None,
+    });
+    style.inset = Sides::compute(&parent.inset, |side, parent| {
+        computed(values, side, parent, None, |value| length_or_keyword(value, "auto", context))
+    });
+    style.z_index = computed(values, "z-index", &parent.z_index, None, |value| match *value {
+        Value::Keyword(ref keyword) if keyword.eq_ignore_ascii_case("auto") => Some(None),
+        Value::Number(number) if number.fract() == 0.0 => Some(Some(number as i32)),
+        _ => None,
+    });
+}
+
+/// Whether a box is absolutely positioned, and so not laid out by its parent.
+pub(crate) fn is_out_of_flow(layout_box: &LayoutBox) -> bool {
+    layout_box.style.position.is_absolute()
+}
+
+/// Give the absolutely positioned boxes among a run of inline-level boxes, and inside
+/// them, a static position at the start of the run's first line, `y` down the container.
+pub(crate) fn place_in_inline_run(boxes: &mut [LayoutBox], y: f32) {
+    for layout_box in boxes {
+        if is_out_of_flow(layout_box) {
+            layout_box.dimensions.content.x = 0.0;
+            layout_box.dimensions.content.y = y;
+        } else if layout_box.display == Display::Inline {
+            place_in_inline_run(&mut layout_box.children, y);
+        }
+    }
+}
+
+/// The rectangles boxes are positioned against, in page coordinates.
+#[derive(Debug, Clone, Copy)]
+struct Containing {
+    /// The content box of the nearest block container, for relative and sticky boxes
+    flow: Rect,
+    /// The padding box of the nearest positioned ancestor, or the initial containing block
+    absolute: Rect,
+    /// The viewport, for fixed boxes
+    viewport: Rect,
+}
+
+/// Shift relatively positioned boxes, and lay out absolutely positioned ones, in a tree
+/// whose normal flow is laid out in page coordinates.
+pub(crate) fn layout_positioned(root: &mut LayoutBox, viewport: Rect, measurer: &dyn TextMeasurer) {
+    let containing = Containing {
+        flow: viewport,
+        absolute: viewport,
+        viewport,
+    };
+    position_box(root, containing, measurer);
+}
+
+fn position_box(layout_box: &mut LayoutBox, containing: Containing, measurer: &dyn TextMeasurer) {
+    let style = &layout_box.style;
+    match style.position {
+        Position::Static | Position::Sticky => {}
+        Position::Relative => {
+            let (dx, dy) = relative_offset(&style.inset, containing.flow);
+            translate(layout_box, dx, dy);
+        }
+        Position::Absolute => layout_absolute(layout_box, containing.absolute, measurer),
+        Position::Fixed => layout_absolute(layout_box, containing.viewport, measurer),
+    }
+
+    let mut inner = containing;
+    if layout_box.display != Display::Inline {
+        inner.flow = layout_box.dimensions.content;
+    }
+    if layout_box.style.position.is_positioned() {
+        inner.absolute = layout_box.dimensions.padding_box();
+    }
+    for child in &mut layout_box.children {
+        position_box(child, inner, measurer);
+    }
+}
+
+/// How far a relatively positioned box moves. `left` wins over `right`, and `top` over
+/// `bottom`, when neither is `auto`.
+pub(crate) fn relative_offset(inset: &Sides<Option<LengthPercentage>>, containing: Rect) -> (f32, f32) {
+    let resolve = |length: &Option<LengthPercentage>, basis: f32| length.as_ref().map(|length| length.resolve(basis));
+    let dx = resolve(&inset.left, containing.width).or_else(|| resolve(&inset.right, containing.width).map(|right| -right));
+    let dy = resolve(&inset.top, containing.height).or_else(|| resolve(&inset.bottom, containing.height).map(|bottom| -bottom));
+    (dx.unwrap_or(0.0), dy.unwrap_or(0.0))
+}
+
+/// How far a sticky box moves to keep its border box inside `scrollport` inset by its
+/// offsets, without its margin box leaving its containing block. Where both of an axis's
+/// offsets apply, the start one wins.
+pub(crate) fn sticky_offset(
+    inset: &Sides<Option<LengthPercentage>>,
+    dimensions: &Dimensions,
+    containing: Rect,
+    scrollport: Rect,
+) -> (f32, f32) {
+    let resolve = |length: &Option<LengthPercentage>, basis: f32| length.as_ref().map(|length| length.resolve(basis));
+    // Each span is a start and a size
+    let axis = |start: Option<f32>, end: Option<f32>, border: (f32, f32), margin: (f32, f32), containing: (f32, f32), scrollport: (f32, f32)| {
+        let mut offset = 0.0;
+        if let Some(end) = end {
+            let limit = scrollport.0 + scrollport.1 - end;
+            if border.0 + border.1 > limit {
+                offset = (limit - border.0 - border.1).max(containing.0 - margin.0).min(0.0);
+            }
+        }
+        if let Some(start) = start {
+            let limit = scrollport.0 + start;
+            if border.0 < limit {
+                offset = (limit - border.0).min(containing.0 + containing.1 - margin.0 - margin.1).max(0.0);
+            }
+        }
+        offset
+    };
+    let (border, margin) = (dimensions.border_box(), dimensions.margin_box());
+    let dx = axis(
+        resolve(&inset.left, scrollport.width),
+        resolve(&inset.right, scrollport.width),
+        (border.x, border.width),
+        (margin.x, margin.width),
+        (containing.x, containing.width),
+        (scrollport.x, scrollport.width),
+    );
+    let dy = axis(
+        resolve(&inset.top, scrollport.height),
+        resolve(&inset.bottom, scrollport.height),
+        (border.y, border.height),
+        (margin.y, margin.height),
+        (containing.y, containing.height),
+        (scrollport.y, scrollport.height),
+    );
+    (dx, dy)
+}
+
+/// Move a laid out box and everything inside it.
+pub(crate) fn translate(layout_box: &mut LayoutBox, dx: f32, dy: f32) {
+    let move_rect = |rect: &mut Rect| {
+        rect.x += dx;
+        rect.y += dy;
+    };
+    move_rect(&mut layout_box.dimensions.content);
+    for fragment in &mut layout_box.fragments {
+        move_rect(&mut fragment.rect);
+        fragment.baseline += dy;
+    }
+    for line in &mut layout_box.lines {
+        move_rect(&mut line.rect);
+        line.baseline += dy;
+    }
+    for child in &mut layout_box.children {
+        translate(child, dx, dy);
+    }
+}
+
+/// The sizes along one axis of an absolutely positioned box, each `None` for `auto`.
+#[derive(Debug, Clone, Copy)]
+struct Axis {
+    /// The offsets from the containing block's edges to the margin box's
+    start: Option<f32>,
+    end: Option<f32>,
+    size: Option<f32>,
+    margin_start: Option<f32>,
+    margin_end: Option<f32>,
+    /// The padding and borders on both sides
+    edges: f32,
+    containing: f32,
+    /// Where the margin box would start in normal flow
+    static_start: f32,
+}
+
+/// The solved offset of the margin box from the containing block's start edge, the
+/// content size, and the margins.
+#[derive(Debug, Clone, Copy)]
+struct Solved {
+    start: f32,
+    size: f32,
+    margin_start: f32,
+    margin_end: f32,
+}
+
+/// Solve an axis's constraint that the offsets, margins, edges and size add up to the
+/// containing block (CSS 2.1 §10.3.7 and §10.6.4). `fit` gives the size an `auto` size
+/// takes when it isn't stretched between the offsets, given the room available for it.
+fn solve_axis(axis: Axis, fit: impl Fn(f32) -> f32) -> Solved {
+    let Axis { start, end, size, margin_start, margin_end, edges, containing, static_start } = axis;
+    if let (Some(start), Some(size), Some(end)) = (start, size, end) {
+        let remaining = containing - start - size - end - edges;
+        let (margin_start, margin_end) = match (margin_start, margin_end) {
+            // Auto margins share what's left, which centres the box, unless it's negative
+            (None, None) if remaining >= 0.0 => (remaining / 2.0, remaining / 2.0),
+            (None, None) => (0.0, remaining),
+            (None, Some(margin_end)) => (remaining - margin_end, margin_end),
+            (Some(margin_start), None) => (margin_start, remaining - margin_start),
+            // Over-constrained: the end offset gives way
+            (Some(margin_start), Some(margin_end)) => (margin_start, margin_end),
+        };
+        return Solved { start, size, margin_start, margin_end };
+    }
+
+    // Otherwise auto margins are zero, and a start offset that's `auto` along with the
+    // end one is the static position
+    let (margin_start, margin_end) = (margin_start.unwrap_or(0.0), margin_end.unwrap_or(0.0));
+    let outside = margin_start + margin_end + edges;
+    let start = match (start, end) {
+        (None, None) => Some(static_start),
+        _ => start,
+    };
+    let (start, size) = match (start, size, end) {
+        (Some(start), None, Some(end)) => (start, (containing - start - end - outside).max(0.0)),
+        (Some(start), None, None) => (start, fit(containing - start - outside)),
+        (None, None, Some(end)) => {
+            let size = fit(containing - end - outside);
+            (containing - end - size - outside, size)
+        }
+        (None, Some(size), Some(end)) => (containing - end - size - outside, size),
+        (Some(start), Some(size), _) => (start, size),
+        (None, _, None) => unreachable!("the start offset is static when both are auto"),
+    };
+    Solved { start, size, margin_start, margin_end }
+}
+
+/// Lay out an absolutely positioned box against `containing`, the padding box of its
+/// containing block, in page coordinates.
+fn layout_absolute(layout_box: &mut LayoutBox, containing: Rect, measurer: &dyn TextMeasurer) {
+    let static_position = (
+        layout_box.dimensions.content.x - containing.x,
+        layout_box.dimensions.content.y - containing.y,
+    );
+    resolve_edges(layout_box, containing.width);
+    let style = &layout_box.style;
+    let dimensions = &layout_box.dimensions;
+    let resolve = |length: &Option<LengthPercentage>, basis: f32| length.as_ref().map(|length| length.resolve(basis));
+
+    let horizontal = Axis {
+        start: resolve(&style.inset.left, containing.width),
+        end: resolve(&style.inset.right, containing.width),
+        size: resolve(&style.width, containing.width).map(|width| width - style.box_sizing_adjustment(true, containing.width)),
+        margin_start: resolve(&style.margin.left, containing.width),
+        margin_end: resolve(&style.margin.right, containing.width),
+        edges: dimensions.padding.left + dimensions.padding.right + dimensions.border.left + dimensions.border.right,
+        containing: containing.width,
+        static_start: static_position.0,
+    };
+    let fit = |available: f32| shrink_to_fit(layout_box, available, measurer);
+    let mut solved_x = solve_axis(horizontal, fit);
+    // The maximum and then the minimum width apply by solving again with them as the width
+    let clamped = style.clamp_width(solved_x.size, containing.width).max(0.0);
+    if clamped != solved_x.size {
+        solved_x = solve_axis(Axis { size: Some(clamped), ..horizontal }, fit);
+    }
+    let width = solved_x.size;
+
+    let clamp_height = |height: f32| style.clamp_height(height, containing.width, Some(containing.height)).max(0.0);
+    let mut vertical = Axis {
+        start: resolve(&style.inset.top, containing.height),
+        end: resolve(&style.inset.bottom, containing.height),
+        size: style
+            .height
+            .as_ref()
+            .map(|height| height.resolve(containing.height) - style.box_sizing_adjustment(false, containing.width)),
+        margin_start: resolve(&style.margin.top, containing.width),
+        margin_end: resolve(&style.margin.bottom, containing.width),
+        edges: dimensions.padding.top + dimensions.padding.bottom + dimensions.border.top + dimensions.border.bottom,
+        containing: containing.height,
+        static_start: static_position.1,
+    };
+    // The height is known before the contents are laid out if it's given, or stretched
+    // between the top and bottom offsets
+    let definite_height = match vertical {
+        Axis { size: Some(height), .. } => Some(clamp_height(height)),
+        Axis { start: Some(top), end: Some(bottom), .. } => {
+            let outside = vertical.margin_start.unwrap_or(0.0) + vertical.margin_end.unwrap_or(0.0) + vertical.edges;
+            Some(clamp_height(containing.height - top - bottom - outside))
+        }
+        _ => None,
+    };
+    let height_limits = (clamp_height(0.0), style.clamp_height(f32::INFINITY, containing.width, Some(containing.height)));
+    layout_box.dimensions.content.width = width;
+    let content_height = layout_contents(layout_box, ContainingBlock { width, height: definite_height }, height_limits, measurer);
+    vertical.size = definite_height;
+    let solved_y = solve_axis(vertical, |_| content_height.min(height_limits.1).max(height_limits.0));
+
+    let dimensions = &mut layout_box.dimensions;
+    dimensions.margin.left = solved_x.margin_start;
+    dimensions.margin.right = solved_x.margin_end;
+    dimensions.margin.top = solved_y.margin_start;
+    dimensions.margin.bottom = solved_y.margin_end;
+    dimensions.content.width = width;
+    dimensions.content.height = solved_y.size;
+    dimensions.content.x = solved_x.start + solved_x.margin_start + dimensions.border.left + dimensions.padding.left;
+    dimensions.content.y = solved_y.start + solved_y.margin_start + dimensions.border.top + dimensions.padding.top;
+    make_absolute(layout_box, containing.x, containing.y);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{contents, laid_out, rect};
+    use foamium_dom::parse_html;
+
+    #[test]
+    fn test_relative_and_absolute() {
+        let dom = parse_html("<div><p id=\"r\"></p><p id=\"a\"></p><p id=\"b\"></p></div>");
+        let stylesheet = foamium_css::parse(
+            "div { display: block; position: relative; margin: 10px; padding: 5px; width: 200px; }
+             #r { display: block; height: 20px; position: relative; top: 5px; left: -10%; }
+             #a { position: absolute; right: 0; bottom: 10%; width: 50px; height: 30px; }
+             #b { position: absolute; inset: 0 10px; margin: auto; width: 100px; height: 40px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let div = &root.children[0];
+        // Absolutely positioned boxes take no room, and are placed in the padding box
+        assert_eq!(div.dimensions.padding_box(), rect(10.0, 10.0, 210.0, 30.0));
+        assert_eq!(
+            contents(div),
+            [
+                rect(-5.0, 20.0, 200.0, 20.0),
+                rect(170.0, 7.0, 50.0, 30.0),
+                // Auto margins centre it across, but it's too tall to centre down
+                rect(65.0, 10.0, 100.0, 40.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_static_positions_and_fixed() {
+        let dom = parse_html(
+            "<div><p>Hello</p><span id=\"s\">abs text</span><p>World</p><p id=\"f\"></p><p id=\"i\">ab<b>cd</b>ef</p></div>",
+        );
+        let stylesheet = foamium_css::parse(
+            "* { font-size: 10px; }
+             div, p { display: block; }
+             #s, b { position: absolute; }
+             #f { position: fixed; inset: auto 0 0; height: 10px; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let div = &root.children[0];
+        // Where it would have been in flow, at its shrink-to-fit width
+        assert_eq!(div.children[1].dimensions.content, rect(0.0, 12.0, 40.0, 12.0));
+        assert_eq!(div.children[2].dimensions.content.y, 12.0);
+        assert_eq!(div.children[3].dimensions.content, rect(0.0, 590.0, 800.0, 10.0));
+
+        // Out-of-flow boxes in inline content don't break the line
+        let p = &div.children[4];
+        assert_eq!(p.lines.len(), 1);
+        assert_eq!(p.children[1].dimensions.content, rect(0.0, 24.0, 10.0, 12.0));
+    }
+
+    #[test]
+    fn test_sticky() {
+        let dom = parse_html("<div><p id=\"top\"></p><p></p><p id=\"bottom\"></p></div>");
+        let stylesheet = foamium_css::parse(
+            "div { display: block; margin-top: 20px; height: 700px; }
+             p { display: block; height: 600px; }
+             #top, #bottom { height: 50px; position: sticky; }
+             #top { top: 30px; }
+             #bottom { bottom: 0; }",
+        );
+        let root = laid_out(&dom, &stylesheet);
+        let div = &root.children[0];
+        // Pushed down to 30px from the top of the viewport, and up to sit on its bottom
+        assert_eq!(div.children[0].dimensions.content.y, 30.0);
+        assert_eq!(div.children[2].dimensions.content.y, 550.0);
+    }
+}
@@ -0,0 +1,252 @@
+// Fast rule lookup for selector matching
+//
+// Every selector is filed under the rightmost id, class or tag it requires, so an
+// element only has to try the selectors filed under its own id, classes and tag, plus
+// the few that require none of them. Selectors that reach up the tree also carry hashes
+// of the ids, classes and tags their ancestors must have, which are checked against a
+// Bloom filter of the element's ancestors before walking up the tree to match them.
+use crate::{ElementContext, elem_classes, elem_id, matches};
+use foamium_css::{Combinator, Selector, SimpleSelector, Specificity, Stylesheet};
+use foamium_dom::ElementData;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// How many ancestor hashes each selector keeps. More would rarely reject anything more.
+const ANCESTOR_HASHES: usize = 4;
+
+/// A selector in a `SelectorMap`.
+struct Entry {
+    /// Index of the selector's rule in the style sheet
+    rule: usize,
+    selector: Selector,
+    specificity: Specificity,
+    /// Hashes that must all be in the ancestor filter for the selector to match.
+    /// Zero marks an unused slot.
+    ancestor_hashes: [u32; ANCESTOR_HASHES],
+}
+
+/// The selectors of a style sheet, bucketed by the rightmost id, class or tag they require.
+#[derive(Default)]
+pub(crate) struct SelectorMap {
+    ids: HashMap<String, Vec<Entry>>,
+    classes: HashMap<String, Vec<Entry>>,
+    tags: HashMap<String, Vec<Entry>>,
+    /// Selectors that don't require any id, class or tag, and must be tried on every element
+    universal: Vec<Entry>,
+}
+
+impl SelectorMap {
+    pub(crate) fn new(stylesheet: &Stylesheet) -> SelectorMap {
+        let mut map = SelectorMap::default();
+        for (rule, rule_selectors) in stylesheet.rules.iter().enumerate() {
+            for selector in &rule_selectors.selectors {
+                map.insert(rule, selector);
+            }
+        }
+        map
+    }
+
+    fn insert(&mut self, rule: usize, selector: &Selector) {
+        let entry = Entry {
+            rule,
+            selector: selector.clone(),
+            specificity: selector.specificity(),
+            ancestor_hashes: ancestor_hashes(selector),
+        };
+        let subject = match *selector {
+            Selector::Simple(ref simple) | Selector::Complex(_, _, ref simple) => simple,
+        };
+        let bucket = if let Some(ref id) = subject.id {
+            self.ids.entry(id.clone()).or_default()
+        } else if let Some(class) = subject.class.first() {
+            self.classes.entry(class.clone()).or_default()
+        } else if let Some(ref tag_name) = subject.tag_name {
+            self.tags.entry(tag_name.clone()).or_default()
+        } else {
+            &mut self.universal
+        };
+        bucket.push(entry);
+    }
+
+    /// The rules with a selector matching `elem`, in source order, each with the
+    /// specificity of its most specific matching selector. `filter` must hold the
+    /// element's ancestors.
+    pub(crate) fn matching_rules(&self, elem: &ElementContext, filter: &AncestorFilter) -> Vec<(usize, Specificity)> {
+        let element = elem.element;
+        let classes = elem_classes(element);
+        let candidates = elem_id(element)
+            .and_then(|id| self.ids.get(id))
+            .into_iter()
+            .chain(classes.iter().filter_map(|class| self.classes.get(*class)))
+            .chain(self.tags.get(&element.tag_name))
+            .flatten()
+            .chain(&self.universal);
+
+        let mut matched: Vec<_> = candidates
+            .filter(|entry| entry.ancestor_hashes.iter().all(|&hash| hash == 0 || filter.might_contain(hash)))
+            .filter(|entry| matches(elem, &entry.selector))
+            .map(|entry| (entry.rule, entry.specificity))
+            .collect();
+        matched.sort_by_key(|&(rule, specificity)| (rule, std::cmp::Reverse(specificity)));
+        matched.dedup_by_key(|&mut (rule, _)| rule);
+        matched
+    }
+}
+
+/// Hashes of the ids, classes and tags that ancestors must have for the selector to match.
+/// Any compound followed by a child or descendant combinator is an ancestor of the subject,
+/// even when a sibling combinator comes after, since siblings share their parent.
+fn ancestor_hashes(selector: &Selector) -> [u32; ANCESTOR_HASHES] {
+    let mut hashes = [0; ANCESTOR_HASHES];
+    let mut count = 0;
+    let mut current = selector;
+    while let Selector::Complex(ref left, combinator, _) = *current {
+        current = left;
+        if !matches!(combinator, Combinator::Descendant | Combinator::Child) {
+            continue;
+        }
+        let compound = match **left {
+            Selector::Simple(ref simple) | Selector::Complex(_, _, ref simple) => simple,
+        };
+        for hash in compound_hashes(compound) {
+            if count == ANCESTOR_HASHES {
+                return hashes;
+            }
+            hashes[count] = hash;
+            count += 1;
+        }
+    }
+    hashes
+}
+
+fn compound_hashes(compound: &SimpleSelector) -> impl Iterator<Item = u32> + '_ {
+    let id = compound.id.iter().map(|id| hash_name('#', id));
+    let classes = compound.class.iter().map(|class| hash_name('.', class));
+    let tag = compound.tag_name.iter().map(|tag_name| hash_name(' ', tag_name));
+    id.chain(classes).chain(tag)
+}
+
+/// Hash a name, with a prefix telling ids, classes and tags apart. Never zero.
+fn hash_name(kind: char, name: &str) -> u32 {
+    let mut hasher = DefaultHasher::new();
+    kind.hash(&mut hasher);
+    name.hash(&mut hasher);
+    (hasher.finish() as u32).max(1)
+}
+
+const KEY_BITS: u32 = 12;
+const KEY_MASK: u32 = (1 << KEY_BITS) - 1;
+
+/// A counting Bloom filter of the ids, classes and tags of the element's ancestors.
+/// Each hash sets two counters, taken from its low and high bits. Counters that reach
+/// the maximum stay there, which only costs false positives.
+pub(crate) struct AncestorFilter {
+    counters: Box<[u8; 1 << KEY_BITS]>,
+}
+
+impl AncestorFilter {
+    pub(crate) fn new() -> AncestorFilter {
+        AncestorFilter {
+            counters: Box::new([0; 1 << KEY_BITS]),
+        }
+    }
+
+    /// Add an element before descending into its children.
+    pub(crate) fn push(&mut self, element: &ElementData) {
+        for hash in element_hashes(element) {
+            for key in keys(hash) {
+                let counter = &mut self.counters[key];
+                *counter = counter.saturating_add(1);
+            }
+        }
+    }
+
+    /// Remove an element added by `push`, once its children are done.
+    pub(crate) fn pop(&mut self, element: &ElementData) {
+        for hash in element_hashes(element) {
+            for key in keys(hash) {
+                let counter = &mut self.counters[key];
+                if *counter != u8::MAX {
+                    *counter -= 1;
+                }
+            }
+        }
+    }
+
+    /// False if no ancestor has the name `hash` came from. True may be a false positive.
+    pub(crate) fn might_contain(&self, hash: u32) -> bool {
+        keys(hash).iter().all(|&key| self.counters[key] != 0)
+    }
+}
+
+fn keys(hash: u32) -> [usize; 2] {
+    [(hash & KEY_MASK) as usize, ((hash >> KEY_BITS) & KEY_MASK) as usize]
+}
+
+fn element_hashes(element: &ElementData) -> impl Iterator<Item = u32> + '_ {
+    let id = elem_id(element).map(|id| hash_name('#', id));
+    let classes = elem_classes(element).into_iter().map(|class| hash_name('.', class));
+    id.into_iter().chain(classes).chain(Some(hash_name(' ', &element.tag_name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foamium_dom::parse_html;
+    use foamium_dom::NodeType;
+
+    fn element(node: &foamium_dom::DomNode) -> &ElementData {
+        match node.node_type {
+            NodeType::Element(ref element) => element,
+            NodeType::Text(_) => panic!("expected an element"),
+        }
+    }
+
+    #[test]
+    fn test_selector_map_buckets_and_filter() {
+        let stylesheet = foamium_css::parse(
+            "#intro { color: #000000; }
+             p.note, .note { color: #111111; }
+             div p { color: #222222; }
+             .menu > * { color: #333333; }
+             * { color: #444444; }",
+        );
+        let map = SelectorMap::new(&stylesheet);
+        assert_eq!(map.ids.len(), 1);
+        assert_eq!(map.classes["note"].len(), 2);
+        assert_eq!(map.tags["p"].len(), 1);
+        assert_eq!(map.universal.len(), 2);
+        assert_eq!(map.tags["p"][0].ancestor_hashes[0], hash_name(' ', "div"));
+
+        let dom = parse_html("<div class=\"menu\"><p id=\"intro\" class=\"note\">Hi</p></div>");
+        let div = &dom.children[0];
+        let p = &div.children[0];
+
+        let mut filter = AncestorFilter::new();
+        let div_context = ElementContext {
+            element: element(div),
+            parent: None,
+            previous_siblings: &[],
+        };
+        let p_context = ElementContext {
+            element: element(p),
+            parent: Some(&div_context),
+            previous_siblings: &[],
+        };
+
+        // Without the ancestors in the filter, selectors reaching up the tree are rejected
+        let rules: Vec<_> = map.matching_rules(&p_context, &filter).iter().map(|&(rule, _)| rule).collect();
+        assert_eq!(rules, [0, 1, 4]);
+
+        filter.push(element(div));
+        assert!(filter.might_contain(hash_name('.', "menu")));
+        let matched = map.matching_rules(&p_context, &filter);
+        let rules: Vec<_> = matched.iter().map(|&(rule, _)| rule).collect();
+        assert_eq!(rules, [0, 1, 2, 3, 4]);
+        // `p.note` is the more specific selector of its rule
+        assert_eq!(matched[1].1, (0, 1, 1));
+
+        filter.pop(element(div));
+        assert!(!filter.might_contain(hash_name('.', "menu")));
+    }
+}
@@ -0,0 +1,196 @@
+// Stacking contexts and paint order (CSS 2.1 Appendix E)
+//
+// The root, positioned boxes with a `z-index`, fixed and sticky boxes, and flex and grid
+// items with a `z-index` establish stacking contexts. Inside one, boxes paint from back to
+// front: the root box, the contexts with negative z-indices, the in-flow block-level
+// boxes, the floats, the inline content, then positioned boxes and contexts with a zero or
+// `auto` z-index in tree order, and last the positive z-indices. A positioned box with
+// `z-index: auto` paints as if it established a context, except that the positioned boxes
+// inside it belong to the enclosing one. Floats and atomic inlines paint the same way,
+// except that all positioned boxes inside them belong to the enclosing context.
+use crate::floats::is_float;
+use crate::inline::{is_atomic, is_inline};
+use crate::{Display, LayoutBox, Position};
+
+/// A stacking context, or a positioned box with `z-index: auto` that paints like one.
+#[derive(Debug)]
+pub struct StackingContext<'t, 'a> {
+    /// The box that establishes it
+    pub layout_box: &'t LayoutBox<'a>,
+    /// Zero for a positioned box with `z-index: auto`
+    pub z_index: i32,
+    /// The stacking contexts and positioned boxes inside it, in paint order: by z-index,
+    /// and in tree order for equal ones. Always empty for a box with `z-index: auto`.
+    pub children: Vec<StackingContext<'t, 'a>>,
+}
+
+/// How a box paints in its parent's stacking context.
+enum Layer {
+    /// With its parent's content
+    Flow,
+    /// As a positioned box with `z-index: auto`
+    Positioned,
+    /// As a stacking context of its own
+    Context(i32),
+}
+
+fn layer(layout_box: &LayoutBox, parent: &LayoutBox) -> Layer {
+    let style = &layout_box.style;
+    let is_item = matches!(parent.display, Display::Flex | Display::InlineFlex | Display::Grid | Display::InlineGrid);
+    match (style.position, style.z_index) {
+        (Position::Fixed | Position::Sticky, z_index) => Layer::Context(z_index.unwrap_or(0)),
+        (position, Some(z_index)) if position.is_positioned() || is_item => Layer::Context(z_index),
+        (position, _) if position.is_positioned() => Layer::Positioned,
+        _ => Layer::Flow,
+    }
+}
+
+impl<'t, 'a> StackingContext<'t, 'a> {
+    /// The stacking context tree of a laid out tree, whose root establishes the root context.
+    pub fn new(root: &'t LayoutBox<'a>) -> StackingContext<'t, 'a> {
+        StackingContext::establish(root, 0)
+    }
+
+    fn establish(layout_box: &'t LayoutBox<'a>, z_index: i32) -> StackingContext<'t, 'a> {
+        let mut children = Vec::new();
+        collect(layout_box, &mut children);
+        // The sort is stable, so tree order breaks ties
+        children.sort_by_key(|child| child.z_index);
+        StackingContext {
+            layout_box,
+            z_index,
+            children,
+        }
+    }
+
+    /// The boxes in the context, from back to front. Each box paints its own background,
+    /// borders and text where it comes, clipped to its `clip`.
+    pub fn paint_order(&self) -> Vec<&'t LayoutBox<'a>> {
+        let mut boxes = Vec::new();
+        self.paint(&mut boxes);
+        boxes
+    }
+
+    fn paint(&self, boxes: &mut Vec<&'t LayoutBox<'a>>) {
+        let negative = self.children.partition_point(|child| child.z_index < 0);
+        boxes.push(self.layout_box);
+        for child in &self.children[..negative] {
+            child.paint(boxes);
+        }
+        paint_flow(self.layout_box, boxes);
+        for child in &self.children[negative..] {
+            child.paint(boxes);
+        }
+    }
+}
+
+/// Add the stacking contexts and positioned boxes inside `layout_box` to `contexts`,
+/// looking through everything that paints with it.
+fn collect<'t, 'a>(layout_box: &'t LayoutBox<'a>, contexts: &mut Vec<StackingContext<'t, 'a>>) {
+    for child in &layout_box.children {
+        match layer(child, layout_box) {
+            Layer::Context(z_index) => contexts.push(StackingContext::establish(child, z_index)),
+            Layer::Positioned => {
+                contexts.push(StackingContext {
+                    layout_box: child,
+                    z_index: 0,
+                    children: Vec::new(),
+                });
+                collect(child, contexts);
+            }
+            Layer::Flow => collect(child, contexts),
+        }
+    }
+}
+
+/// The boxes inside `layout_box` that paint with it: the block-level boxes, then the
+/// floats, then the inline-level boxes.
+fn paint_flow<'t, 'a>(layout_box: &'t LayoutBox<'a>, boxes: &mut Vec<&'t LayoutBox<'a>>) {
+    paint_blocks(layout_box, boxes);
+    paint_floats(layout_box, boxes);
+    paint_inlines(layout_box, boxes);
+}
+
+/// The in-flow block-level boxes inside `layout_box` that paint with it, in tree order.
+fn paint_blocks<'t, 'a>(layout_box: &'t LayoutBox<'a>, boxes: &mut Vec<&'t LayoutBox<'a>>) {
+    for child in &layout_box.children {
+        if matches!(layer(child, layout_box), Layer::Flow) && !is_inline(child) && !is_float(child) {
+            boxes.push(child);
+            paint_blocks(child, boxes);
+        }
+    }
+}
+
+/// The floats inside `layout_box` that paint with it, in tree order. Each paints
+/// everything inside it together.
+fn paint_floats<'t, 'a>(layout_box: &'t LayoutBox<'a>, boxes: &mut Vec<&'t LayoutBox<'a>>) {
+    for child in &layout_box.children {
+        if !matches!(layer(child, layout_box), Layer::Flow) {
+            continue;
+        }
+        if is_float(child) {
+            boxes.push(child);
+            paint_flow(child, boxes);
+        } else if !is_atomic(child) {
+            paint_floats(child, boxes);
+        }
+    }
+}
+
+/// The inline-level boxes inside `layout_box` that paint with it, in tree order. Atomic
+/// inlines paint everything inside them together.
+fn paint_inlines<'t, 'a>(layout_box: &'t LayoutBox<'a>, boxes: &mut Vec<&'t LayoutBox<'a>>) {
+    for child in &layout_box.children {
+        if !matches!(layer(child, layout_box), Layer::Flow) || is_float(child) {
+            continue;
+        }
+        if is_atomic(child) {
+            boxes.push(child);
+            paint_flow(child, boxes);
+        } else {
+            if is_inline(child) {
+                boxes.push(child);
+            }
+            paint_inlines(child, boxes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use foamium_dom::{parse_html, NodeType};
+
+    fn name(layout_box: &LayoutBox) -> String {
+        match layout_box.node.map(|node| &node.node_type) {
+            Some(NodeType::Element(element)) => element.attributes.get("id").cloned().unwrap_or(element.tag_name.clone()),
+            Some(NodeType::Text(text)) => text.clone(),
+            None => "anonymous".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_paint_order() {
+        let dom = parse_html(
+            "<div id=\"a\"><p id=\"a1\"></p></div><div id=\"b\"><p id=\"b1\"></p></div>\
+             <div id=\"c\">text</div><div id=\"d\"><p id=\"d1\"></p></div>",
+        );
+        let stylesheet = foamium_css::parse(
+            "div { display: block; }
+             #a { position: relative; z-index: 1; }
+             #a1 { position: absolute; z-index: -1; }
+             #b { position: relative; }
+             #b1 { position: absolute; z-index: 5; }
+             #d { display: flex; }
+             #d1 { z-index: -2; }",
+        );
+        let mut root = build_layout_tree(&dom, &stylesheet);
+        layout(&mut root, Rect::default());
+        let context = StackingContext::new(&root);
+        let z_indices: Vec<i32> = context.children.iter().map(|child| child.z_index).collect();
+        // `#b1` belongs to the root context, since `#b` has no z-index
+        assert_eq!(z_indices, [-2, 0, 1, 5]);
+        let order: Vec<String> = context.paint_order().into_iter().map(name).collect();
+        assert_eq!(order, ["html", "d1", "c", "d", "text", "b", "a", "a1", "b1"]);
+    }
+}
@@ -0,0 +1,1176 @@
+// Table layout (CSS 2.1 §17, CSS Tables 3)
+//
+// A table element generates a table wrapper box, which takes part in the layout around
+// it and holds the captions, and inside that a table grid box holding the row groups,
+// rows and columns. Box tree construction fixes up the table parts around whatever the
+// document puts in them, with anonymous boxes where parts are missing, and with
+// `border-collapse: collapse` it resolves each cell's borders against its neighbours'.
+//
+// Cells are placed in the grid's slots as HTML's table model does, with `colspan` and
+// `rowspan`. The columns are sized from the cells' intrinsic widths, or with
+// `table-layout: fixed` from the columns and the first row alone. Each row is as tall as
+// its tallest cell, and cells then stretch to the rows they span, with the room left
+// over going into their padding above or below the content, as `vertical-align` says.
+use crate::block::{ContainingBlock, first_baseline, layout_contents, resolve_edges};
+use crate::box_model::computed;
+use crate::intrinsic::{self, ContentSizes};
+use crate::{
+    BorderStyle, Clear, ComputedStyle, Dimensions, Display, Float, LayoutBox, LengthContext, LengthPercentage,
+    PropertyMap, Position, Rect, Sides, TextMeasurer, VerticalAlign, compute_style, is_collapsible_white_space,
+    wrap_inline_runs,
+};
+use foamium_css::{Color, Value};
+use foamium_dom::{DomNode, NodeType};
+
+/// `border-collapse`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BorderCollapse {
+    #[default]
+    Separate,
+    Collapse,
+}
+
+/// `caption-side`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CaptionSide {
+    #[default]
+    Top,
+    Bottom,
+}
+
+/// `table-layout`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TableLayout {
+    #[default]
+    Auto,
+    Fixed,
+}
+
+/// Compute the table properties into `style`.
+pub(crate) fn compute_table_style(
+    style: &mut ComputedStyle,
+    values: &PropertyMap,
+    parent: &ComputedStyle,
+    context: &LengthContext,
+) {
+    let keyword = |value: &Value| match value {
+        Value::Keyword(keyword) => Some(keyword.to_ascii_lowercase()),
+        _ => None,
+    };
+    style.border_collapse = computed(values, "border-collapse", &parent.border_collapse, BorderCollapse::Separate, |value| {
+        Some(match &*keyword(value)? {
+            "separate" => BorderCollapse::Separate,
+            "collapse" => BorderCollapse::Collapse,
+            _ => return None,
+        })
+    });
+    let spacing = |name: &str, parent: &f32| {
+        computed(values, name, parent, 0.0, |value| context.resolve(value, None).filter(|spacing| *spacing >= 0.0))
+    };
+    style.border_spacing = (
+        spacing("-webkit-border-horizontal-spacing", &parent.border_spacing.0),
+        spacing("-webkit-border-vertical-spacing", &parent.border_spacing.1),
+    );
+    style.caption_side = computed(values, "caption-side", &parent.caption_side, CaptionSide::Top, |value| {
+        Some(match &*keyword(value)? {
+            "top" => CaptionSide::Top,
+            "bottom" => CaptionSide::Bottom,
+            _ => return None,
+        })
+    });
+    style.table_layout = computed(values, "table-layout", &parent.table_layout, TableLayout::Auto, |value| {
+        Some(match &*keyword(value)? {
+            "auto" => TableLayout::Auto,
+            "fixed" => TableLayout::Fixed,
+            _ => return None,
+        })
+    });
+}
+
+fn is_row_group(display: Display) -> bool {
+    matches!(display, Display::TableRowGroup | Display::TableHeaderGroup | Display::TableFooterGroup)
+}
+
+/// Whether a box belongs directly in a table: a caption, row group, row, column group or column.
+fn is_proper_table_child(layout_box: &LayoutBox) -> bool {
+    is_row_group(layout_box.display)
+        || matches!(
+            layout_box.display,
+            Display::TableCaption | Display::TableRow | Display::TableColumnGroup | Display::TableColumn
+        )
+}
+
+/// Drop the margins of the table parts inside a table grid, which don't have any, and the
+/// padding of the ones other than cells.
+pub(crate) fn adjust_style(style: &mut ComputedStyle) {
+    if is_row_group(style.display)
+        || matches!(style.display, Display::TableRow | Display::TableColumnGroup | Display::TableColumn | Display::TableCell)
+    {
+        style.margin = Sides::all(Some(LengthPercentage::ZERO));
+    }
+    if is_row_group(style.display)
+        || matches!(style.display, Display::TableRow | Display::TableColumnGroup | Display::TableColumn)
+    {
+        style.padding = Sides::all(LengthPercentage::ZERO);
+    }
+}
+
+/// Fix up the children of a box so its table parts nest properly (CSS Tables 3 §3.9):
+/// white space between table parts is dropped, columns hold nothing, and anonymous rows,
+/// cells and tables are wrapped around whatever is missing a proper parent or child.
+pub(crate) fn fix_children<'a>(
+    children: Vec<LayoutBox<'a>>,
+    parent: &ComputedStyle,
+    context: &LengthContext,
+) -> Vec<LayoutBox<'a>> {
+    let without_white_space =
+        |children: Vec<LayoutBox<'a>>| children.into_iter().filter(|child| !is_collapsible_white_space(child)).collect();
+    match parent.display {
+        Display::Table | Display::InlineTable => wrap_runs(
+            without_white_space(children),
+            |child| !is_proper_table_child(child),
+            Display::TableRow,
+            parent,
+            context,
+        ),
+        display if is_row_group(display) => wrap_runs(
+            without_white_space(children),
+            |child| child.display != Display::TableRow,
+            Display::TableRow,
+            parent,
+            context,
+        ),
+        Display::TableRow => wrap_runs(
+            without_white_space(children),
+            |child| child.display != Display::TableCell,
+            Display::TableCell,
+            parent,
+            context,
+        ),
+        Display::TableColumnGroup => children.into_iter().filter(|child| child.display == Display::TableColumn).collect(),
+        Display::TableColumn => Vec::new(),
+        // The children are fixed up in the parent they end up in
+        Display::Contents => children,
+        _ => {
+            let children = wrap_runs(children, |child| child.display == Display::TableCell, Display::TableRow, parent, context);
+            let table = if parent.display == Display::Inline { Display::InlineTable } else { Display::Table };
+            wrap_runs(children, is_proper_table_child, table, parent, context)
+        }
+    }
+}
+
+/// Wrap each run of children that `wrap` picks out in an anonymous box of `display`. White
+/// space between them goes in the run too.
+fn wrap_runs<'a>(
+    children: Vec<LayoutBox<'a>>,
+    wrap: impl Fn(&LayoutBox) -> bool,
+    display: Display,
+    parent: &ComputedStyle,
+    context: &LengthContext,
+) -> Vec<LayoutBox<'a>> {
+    if !children.iter().any(&wrap) {
+        return children;
+    }
+    let mut wrapped = Vec::new();
+    let mut run = Vec::new();
+    let flush = |run: &mut Vec<LayoutBox<'a>>, wrapped: &mut Vec<LayoutBox<'a>>| {
+        let white_space = run.iter().rev().take_while(|child| is_collapsible_white_space(child)).count();
+        let after = run.split_off(run.len() - white_space);
+        if !run.is_empty() {
+            wrapped.push(anonymous(display, std::mem::take(run), parent, context));
+        }
+        wrapped.extend(after);
+    };
+    for child in children {
+        if wrap(&child) || !run.is_empty() && is_collapsible_white_space(&child) {
+            run.push(child);
+        } else {
+            flush(&mut run, &mut wrapped);
+            wrapped.push(child);
+        }
+    }
+    flush(&mut run, &mut wrapped);
+    wrapped
+}
+
+/// An anonymous table part of `display` around `children`, fixed up itself.
+fn anonymous<'a>(display: Display, children: Vec<LayoutBox<'a>>, parent: &ComputedStyle, context: &LengthContext) -> LayoutBox<'a> {
+    let mut style = compute_style(&PropertyMap::new(), parent.custom_properties.clone(), parent, context);
+    style.display = display;
+    let children = fix_children(children, &style, context);
+    match display {
+        Display::Table | Display::InlineTable => wrap_table(None, style, Dimensions::default(), children),
+        Display::TableCell => {
+            let children = wrap_inline_runs(children, &style, context);
+            LayoutBox::new(None, style, Dimensions::default(), children)
+        }
+        _ => LayoutBox::new(None, style, Dimensions::default(), children),
+    }
+}
+
+/// The table wrapper box for a table whose children are fixed up, holding its captions
+/// and a table grid box with the rest (CSS 2.1 §17.4). The wrapper takes the properties
+/// that place the table among the boxes around it, and the grid box the rest.
+pub(crate) fn wrap_table<'a>(
+    node: Option<&'a DomNode>,
+    style: ComputedStyle,
+    dimensions: Dimensions,
+    children: Vec<LayoutBox<'a>>,
+) -> LayoutBox<'a> {
+    let mut grid_style = style.clone();
+    grid_style.display = Display::TableGrid;
+    grid_style.margin = Sides::all(Some(LengthPercentage::ZERO));
+    grid_style.position = Position::Static;
+    grid_style.inset = Sides::all(None);
+    grid_style.z_index = None;
+    grid_style.float = Float::None;
+    grid_style.clear = Clear::None;
+
+    let mut wrapper_style = style;
+    wrapper_style.padding = Sides::all(LengthPercentage::ZERO);
+    wrapper_style.border_width = Sides::all(0.0);
+    wrapper_style.border_style = Sides::all(BorderStyle::None);
+    wrapper_style.border_color = Sides::all(None);
+    wrapper_style.background_color = None;
+    wrapper_style.background_image = Vec::new();
+    wrapper_style.width = None;
+    wrapper_style.height = None;
+    wrapper_style.min_width = None;
+    wrapper_style.min_height = None;
+    wrapper_style.max_width = None;
+    wrapper_style.max_height = None;
+
+    let (captions, parts): (Vec<LayoutBox>, Vec<LayoutBox>) =
+        children.into_iter().partition(|child| child.display == Display::TableCaption);
+    let mut grid = LayoutBox::new(node, grid_style, dimensions, parts);
+    if grid.style.border_collapse == BorderCollapse::Collapse {
+        collapse_borders(&mut grid);
+    }
+    let (mut children, bottom): (Vec<LayoutBox>, Vec<LayoutBox>) =
+        captions.into_iter().partition(|caption| caption.style.caption_side == CaptionSide::Top);
+    children.push(grid);
+    children.extend(bottom);
+    LayoutBox::new(node, wrapper_style, dimensions, children)
+}
+
+/// Where a row is among a table grid box's children: directly, or in a row group.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RowPath {
+    child: usize,
+    row: Option<usize>,
+}
+
+impl RowPath {
+    /// The group the row is in for `rowspan`: a row group, or a run of rows directly in the table.
+    fn group(self) -> Option<usize> {
+        self.row.map(|_| self.child)
+    }
+}
+
+/// A cell's slots in the grid.
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    row: usize,
+    /// Among its row's children
+    index: usize,
+    column: usize,
+    rowspan: usize,
+    colspan: usize,
+}
+
+/// The column box and column group box each column of the grid belongs to, by their
+/// indices among the grid box's children and the group's.
+#[derive(Debug, Clone, Copy, Default)]
+struct Column {
+    column: Option<(usize, Option<usize>)>,
+    group: Option<usize>,
+}
+
+/// A table's rows in the order they're displayed, with the first header group first and
+/// the first footer group last, and where its cells and columns go in the grid.
+struct Grid {
+    rows: Vec<RowPath>,
+    cells: Vec<Cell>,
+    columns: Vec<Column>,
+}
+
+impl Grid {
+    fn new(grid: &LayoutBox) -> Grid {
+        let first = |display: Display| grid.children.iter().position(|child| child.display == display);
+        let (header, footer) = (first(Display::TableHeaderGroup), first(Display::TableFooterGroup));
+        let order = header
+            .into_iter()
+            .chain((0..grid.children.len()).filter(|&child| Some(child) != header && Some(child) != footer))
+            .chain(footer);
+        let mut rows = Vec::new();
+        for child in order {
+            match grid.children[child].display {
+                Display::TableRow => rows.push(RowPath { child, row: None }),
+                display if is_row_group(display) => {
+                    rows.extend((0..grid.children[child].children.len()).map(|row| RowPath { child, row: Some(row) }))
+                }
+                _ => {}
+            }
+        }
+
+        // Each cell takes the first free slot in its row, as HTML's table model places them
+        let mut occupied: Vec<Vec<bool>> = vec![Vec::new(); rows.len()];
+        let mut cells = Vec::new();
+        for (row, &path) in rows.iter().enumerate() {
+            let group_end = row + rows[row..].iter().take_while(|other| other.group() == path.group()).count();
+            let mut column = 0;
+            for (index, cell) in row_box(grid, path).children.iter().enumerate() {
+                while occupied[row].get(column) == Some(&true) {
+                    column += 1;
+                }
+                let colspan = attribute(cell, &["td", "th"], "colspan", 1000).unwrap_or(1).max(1);
+                let rowspan = match attribute(cell, &["td", "th"], "rowspan", 65534).unwrap_or(1) {
+                    // Zero spans the rest of the group
+                    0 => group_end - row,
+                    rowspan => rowspan.min(group_end - row),
+                };
+                for slots in &mut occupied[row..row + rowspan] {
+                    if slots.len() < column + colspan {
+                        slots.resize(column + colspan, false);
+                    }
+                    slots[column..column + colspan].fill(true);
+                }
+                cells.push(Cell {
+                    row,
+                    index,
+                    column,
+                    rowspan,
+                    colspan,
+                });
+                column += colspan;
+            }
+        }
+
+        let mut columns = Vec::new();
+        for (child, part) in grid.children.iter().enumerate() {
+            let span = |part: &LayoutBox| attribute(part, &["col", "colgroup"], "span", 1000).unwrap_or(1).max(1);
+            match part.display {
+                Display::TableColumn => columns.extend((0..span(part)).map(|_| Column {
+                    column: Some((child, None)),
+                    group: None,
+                })),
+                Display::TableColumnGroup if part.children.is_empty() => {
+                    columns.extend((0..span(part)).map(|_| Column {
+                        column: None,
+                        group: Some(child),
+                    }))
+                }
+                Display::TableColumnGroup => {
+                    for (index, column) in part.children.iter().enumerate() {
+                        columns.extend((0..span(column)).map(|_| Column {
+                            column: Some((child, Some(index))),
+                            group: Some(child),
+                        }));
+                    }
+                }
+                _ => {}
+            }
+        }
+        let width = occupied.iter().map(Vec::len).max().unwrap_or(0);
+        if columns.len() < width {
+            columns.resize(width, Column::default());
+        }
+        Grid { rows, cells, columns }
+    }
+
+    /// The column box at `column`, and the column group box.
+    fn column_boxes<'g, 'a>(&self, grid: &'g LayoutBox<'a>, column: usize) -> (Option<&'g LayoutBox<'a>>, Option<&'g LayoutBox<'a>>) {
+        let Column { column, group } = self.columns[column];
+        let column = column.map(|(child, index)| match index {
+            Some(index) => &grid.children[child].children[index],
+            None => &grid.children[child],
+        });
+        (column, group.map(|group| &grid.children[group]))
+    }
+}
+
+/// A non-negative integer attribute of an element with one of `tags`, at most `max`.
+fn attribute(layout_box: &LayoutBox, tags: &[&str], name: &str, max: usize) -> Option<usize> {
+    let Some(NodeType::Element(element)) = layout_box.node.map(|node| &node.node_type) else {
+        return None;
+    };
+    if !tags.iter().any(|tag| element.tag_name.eq_ignore_ascii_case(tag)) {
+        return None;
+    }
+    let value = element.attributes.get(name)?.trim_start();
+    let digits = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    Some(value[..digits].parse::<usize>().ok()?.min(max))
+}
+
+fn row_box<'g, 'a>(grid: &'g LayoutBox<'a>, path: RowPath) -> &'g LayoutBox<'a> {
+    let child = &grid.children[path.child];
+    match path.row {
+        Some(row) => &child.children[row],
+        None => child,
+    }
+}
+
+fn row_box_mut<'g, 'a>(grid: &'g mut LayoutBox<'a>, path: RowPath) -> &'g mut LayoutBox<'a> {
+    let child = &mut grid.children[path.child];
+    match path.row {
+        Some(row) => &mut child.children[row],
+        None => child,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Side {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+impl Side {
+    fn of<T: Copy>(self, sides: &Sides<T>) -> T {
+        match self {
+            Side::Top => sides.top,
+            Side::Right => sides.right,
+            Side::Bottom => sides.bottom,
+            Side::Left => sides.left,
+        }
+    }
+
+    fn set<T>(self, sides: &mut Sides<T>, value: T) {
+        match self {
+            Side::Top => sides.top = value,
+            Side::Right => sides.right = value,
+            Side::Bottom => sides.bottom = value,
+            Side::Left => sides.left = value,
+        }
+    }
+}
+
+/// One side of a box's border, as it takes part in collapsing.
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    width: f32,
+    style: BorderStyle,
+    color: Option<Color>,
+}
+
+impl Edge {
+    const NONE: Edge = Edge {
+        width: 0.0,
+        style: BorderStyle::None,
+        color: None,
+    };
+
+    fn of(layout_box: &LayoutBox, side: Side) -> Edge {
+        let style = &layout_box.style;
+        Edge {
+            width: side.of(&style.border_width),
+            style: side.of(&style.border_style),
+            color: side.of(&style.border_color),
+        }
+    }
+
+    /// How strongly the style wins a tie between borders of the same width.
+    fn rank(self) -> u8 {
+        match self.style {
+            BorderStyle::Double => 8,
+            BorderStyle::Solid => 7,
+            BorderStyle::Dashed => 6,
+            BorderStyle::Dotted => 5,
+            BorderStyle::Ridge => 4,
+            BorderStyle::Outset => 3,
+            BorderStyle::Groove => 2,
+            BorderStyle::Inset => 1,
+            BorderStyle::None | BorderStyle::Hidden => 0,
+        }
+    }
+
+    /// The border that wins a conflict between `edges`, which come from cells before rows,
+    /// row groups, columns, column groups and the table, and from the top and left first
+    /// (CSS 2.1 §17.6.2.1). `hidden` always wins, then the widest and the strongest style.
+    fn resolve(edges: &[Edge]) -> Edge {
+        if edges.iter().any(|edge| edge.style == BorderStyle::Hidden) {
+            return Edge::NONE;
+        }
+        edges.iter().fold(Edge::NONE, |winner, &edge| {
+            if edge.width > winner.width || edge.width == winner.width && edge.rank() > winner.rank() {
+                edge
+            } else {
+                winner
+            }
+        })
+    }
+
+    /// The wider of two resolved borders along one side of a box.
+    fn max(self, other: Edge) -> Edge {
+        if other.width > self.width { other } else { self }
+    }
+
+    /// Set a box's border on `side` to half of this one, which is its share of it.
+    fn apply_half(self, style: &mut ComputedStyle, side: Side) {
+        side.set(&mut style.border_width, self.width / 2.0);
+        side.set(&mut style.border_style, self.style);
+        side.set(&mut style.border_color, self.color);
+    }
+}
+
+/// Resolve the collapsed borders of a table grid box (CSS 2.1 §17.6.2). Every cell gets
+/// half of each border it shares and the grid box half of its outer borders, so the
+/// borders take their room out of the boxes on each side. Rows, row groups and columns
+/// end up with none of their own, and the grid box with no padding.
+fn collapse_borders(grid: &mut LayoutBox) {
+    let model = Grid::new(grid);
+    let (rows, columns) = (model.rows.len(), model.columns.len());
+    let mut occupant = vec![vec![None; columns]; rows];
+    for (id, cell) in model.cells.iter().enumerate() {
+        for slots in &mut occupant[cell.row..cell.row + cell.rowspan] {
+            slots[cell.column..cell.column + cell.colspan].fill(Some(id));
+        }
+    }
+    let cell = |id: usize| &row_box(grid, model.rows[model.cells[id].row]).children[model.cells[id].index];
+    let group = |row: usize| model.rows[row].group().map(|group| &grid.children[group]);
+
+    // The border between rows `row - 1` and `row`, in `column`
+    let horizontal = |row: usize, column: usize| {
+        let (above, below) = (row.checked_sub(1), (row < rows).then_some(row));
+        let [above_cell, below_cell] = [above, below].map(|row| row.and_then(|row| occupant[row][column]));
+        if above_cell.is_some() && above_cell == below_cell {
+            return Edge::NONE;
+        }
+        let mut edges: Vec<Edge> = Vec::new();
+        edges.extend(above_cell.map(|id| Edge::of(cell(id), Side::Bottom)));
+        edges.extend(below_cell.map(|id| Edge::of(cell(id), Side::Top)));
+        edges.extend(above.map(|row| Edge::of(row_box(grid, model.rows[row]), Side::Bottom)));
+        edges.extend(below.map(|row| Edge::of(row_box(grid, model.rows[row]), Side::Top)));
+        if above.map(|row| model.rows[row].group()) != below.map(|row| model.rows[row].group()) {
+            edges.extend(above.and_then(group).map(|group| Edge::of(group, Side::Bottom)));
+            edges.extend(below.and_then(group).map(|group| Edge::of(group, Side::Top)));
+        }
+        if above.is_none() || below.is_none() {
+            let side = if above.is_none() { Side::Top } else { Side::Bottom };
+            let (column_box, column_group) = model.column_boxes(grid, column);
+            edges.extend(column_box.map(|column| Edge::of(column, side)));
+            edges.extend(column_group.map(|group| Edge::of(group, side)));
+            edges.push(Edge::of(grid, side));
+        }
+        Edge::resolve(&edges)
+    };
+    // The border between columns `column - 1` and `column`, in `row`
+    let vertical = |row: usize, column: usize| {
+        let (before, after) = (column.checked_sub(1), (column < columns).then_some(column));
+        let [before_cell, after_cell] = [before, after].map(|column| column.and_then(|column| occupant[row][column]));
+        if before_cell.is_some() && before_cell == after_cell {
+            return Edge::NONE;
+        }
+        let mut edges: Vec<Edge> = Vec::new();
+        edges.extend(before_cell.map(|id| Edge::of(cell(id), Side::Right)));
+        edges.extend(after_cell.map(|id| Edge::of(cell(id), Side::Left)));
+        let side = match (before, after) {
+            (None, _) => Some(Side::Left),
+            (_, None) => Some(Side::Right),
+            _ => None,
+        };
+        if let Some(side) = side {
+            edges.push(Edge::of(row_box(grid, model.rows[row]), side));
+            edges.extend(group(row).map(|group| Edge::of(group, side)));
+        }
+        let [before_boxes, after_boxes] = [before, after].map(|column| column.map(|column| model.column_boxes(grid, column)));
+        edges.extend(before_boxes.and_then(|(column, _)| column).map(|column| Edge::of(column, Side::Right)));
+        edges.extend(after_boxes.and_then(|(column, _)| column).map(|column| Edge::of(column, Side::Left)));
+        let [before_group, after_group] = [before, after].map(|column| column.and_then(|column| model.columns[column].group));
+        if before_group != after_group {
+            edges.extend(before_boxes.and_then(|(_, group)| group).map(|group| Edge::of(group, Side::Right)));
+            edges.extend(after_boxes.and_then(|(_, group)| group).map(|group| Edge::of(group, Side::Left)));
+        }
+        if let Some(side) = side {
+            edges.push(Edge::of(grid, side));
+        }
+        Edge::resolve(&edges)
+    };
+
+    let along = |edges: &mut dyn Iterator<Item = Edge>| edges.fold(Edge::NONE, Edge::max);
+    let cell_edges: Vec<[Edge; 4]> = model
+        .cells
+        .iter()
+        .map(|cell| {
+            let (rows, columns) = (cell.row..cell.row + cell.rowspan, cell.column..cell.column + cell.colspan);
+            [
+                along(&mut columns.clone().map(|column| horizontal(cell.row, column))),
+                along(&mut rows.clone().map(|row| vertical(row, cell.column + cell.colspan))),
+                along(&mut columns.map(|column| horizontal(cell.row + cell.rowspan, column))),
+                along(&mut rows.map(|row| vertical(row, cell.column))),
+            ]
+        })
+        .collect();
+    // The table's own borders are half the outer ones, its left and right those of the first row
+    let table_edges = (rows > 0 && columns > 0).then(|| {
+        [
+            along(&mut (0..columns).map(|column| horizontal(0, column))),
+            vertical(0, columns),
+            along(&mut (0..columns).map(|column| horizontal(rows, column))),
+            vertical(0, 0),
+        ]
+    });
+
+    let sides = [Side::Top, Side::Right, Side::Bottom, Side::Left];
+    for (cell, edges) in model.cells.iter().zip(cell_edges) {
+        let style = &mut row_box_mut(grid, model.rows[cell.row]).children[cell.index].style;
+        for (side, edge) in sides.into_iter().zip(edges) {
+            edge.apply_half(style, side);
+        }
+    }
+    for (side, edge) in sides.into_iter().zip(table_edges.unwrap_or([Edge::NONE; 4])) {
+        edge.apply_half(&mut grid.style, side);
+    }
+    grid.style.padding = Sides::all(LengthPercentage::ZERO);
+    for part in &mut grid.children {
+        part.style.border_width = Sides::all(0.0);
+        for child in &mut part.children {
+            if child.display != Display::TableCell {
+                child.style.border_width = Sides::all(0.0);
+            }
+        }
+    }
+}
+
+/// The horizontal and vertical space between cells, and around them.
+fn spacing(grid: &LayoutBox) -> (f32, f32) {
+    match grid.style.border_collapse {
+        BorderCollapse::Separate => grid.style.border_spacing,
+        BorderCollapse::Collapse => (0.0, 0.0),
+    }
+}
+
+/// The spacing across `count` columns or rows, which also goes before the first and after the last.
+fn total_spacing(spacing: f32, count: usize) -> f32 {
+    if count == 0 { 0.0 } else { spacing * (count + 1) as f32 }
+}
+
+/// Whether a table grid box's columns are sized by the fixed table layout algorithm
+/// (CSS 2.1 §17.5.2.1), which only applies when it has a width.
+fn is_fixed(grid: &LayoutBox) -> bool {
+    grid.style.table_layout == TableLayout::Fixed && grid.style.width.is_some()
+}
+
+/// The intrinsic widths of a cell's border box. Its `width` is only a minimum.
+fn cell_sizes(cell: &LayoutBox, measurer: &dyn TextMeasurer) -> ContentSizes {
+    let style = &cell.style;
+    let edges = style.padding.left.resolve(0.0)
+        + style.padding.right.resolve(0.0)
+        + style.border_width.left
+        + style.border_width.right;
+    let content = intrinsic::content_sizes(cell, measurer);
+    let outer = intrinsic::outer_sizes(cell, measurer);
+    ContentSizes {
+        min: outer.min.max(content.min + edges),
+        max: outer.max.max(content.min + edges),
+    }
+}
+
+/// The intrinsic widths of each column for the automatic table layout algorithm
+/// (CSS 2.1 §17.5.2.2), from the cells that span only it and the column's `width`. Cells
+/// that span several columns then widen them, from the narrowest span up, in proportion
+/// to their maximum widths.
+fn column_sizes(grid: &LayoutBox, model: &Grid, measurer: &dyn TextMeasurer) -> Vec<ContentSizes> {
+    let mut sizes: Vec<ContentSizes> = (0..model.columns.len())
+        .map(|column| {
+            let width = model.column_boxes(grid, column).0.and_then(|column| column.style.width.as_ref()?.resolve_against(None));
+            let width = width.unwrap_or(0.0);
+            ContentSizes { min: width, max: width }
+        })
+        .collect();
+    let mut spanning = Vec::new();
+    for cell in &model.cells {
+        let cell_sizes = cell_sizes(&row_box(grid, model.rows[cell.row]).children[cell.index], measurer);
+        if cell.colspan == 1 {
+            let sizes = &mut sizes[cell.column];
+            sizes.min = sizes.min.max(cell_sizes.min);
+            sizes.max = sizes.max.max(cell_sizes.max);
+        } else {
+            spanning.push((cell, cell_sizes));
+        }
+    }
+    spanning.sort_by_key(|(cell, _)| cell.colspan);
+    let spacing = spacing(grid).0;
+    for (cell, cell_sizes) in spanning {
+        let spanned = &mut sizes[cell.column..cell.column + cell.colspan];
+        let gaps = spacing * (cell.colspan - 1) as f32;
+        let weights: Vec<f32> = spanned.iter().map(|sizes| sizes.max).collect();
+        let min = spanned.iter().map(|sizes| sizes.min).sum::<f32>() + gaps;
+        for (sizes, extra) in spanned.iter_mut().zip(share(cell_sizes.min - min, &weights)) {
+            sizes.min += extra;
+        }
+        let max = spanned.iter().map(|sizes| sizes.max).sum::<f32>() + gaps;
+        for (sizes, extra) in spanned.iter_mut().zip(share(cell_sizes.max - max, &weights)) {
+            sizes.max = (sizes.max + extra).max(sizes.min);
+        }
+    }
+    sizes
+}
+
+/// Share out `extra` in proportion to `weights`, or evenly if they're all zero. Nothing
+/// if there's nothing to share.
+fn share(extra: f32, weights: &[f32]) -> Vec<f32> {
+    let total: f32 = weights.iter().sum();
+    weights
+        .iter()
+        .map(|weight| match extra {
+            extra if extra <= 0.0 => 0.0,
+            extra if total > 0.0 => extra * weight / total,
+            extra => extra / weights.len() as f32,
+        })
+        .collect()
+}
+
+/// The column widths of the fixed table layout algorithm: the columns' `width`s, or else
+/// the widths of the cells in the first row, with the other columns sharing what's left
+/// of `available` evenly. Columns with widths grow evenly if there's room over.
+fn fixed_columns(grid: &LayoutBox, model: &Grid, available: f32) -> Vec<f32> {
+    let mut widths: Vec<Option<f32>> = (0..model.columns.len())
+        .map(|column| {
+            let column = model.column_boxes(grid, column).0?;
+            let width = column.style.width.as_ref()?.resolve(available);
+            Some(width)
+        })
+        .collect();
+    let spacing = spacing(grid).0;
+    for cell in model.cells.iter().filter(|cell| cell.row == 0) {
+        let cell_box = &row_box(grid, model.rows[0]).children[cell.index];
+        let style = &cell_box.style;
+        let Some(width) = style.width.as_ref().map(|width| width.resolve(available)) else {
+            continue;
+        };
+        let edges = style.padding.left.resolve(available)
+            + style.padding.right.resolve(available)
+            + style.border_width.left
+            + style.border_width.right;
+        let outer = width - style.box_sizing_adjustment(true, available) + edges;
+        let each = (outer - spacing * (cell.colspan - 1) as f32) / cell.colspan as f32;
+        for width in &mut widths[cell.column..cell.column + cell.colspan] {
+            width.get_or_insert(each.max(0.0));
+        }
+    }
+    let known: f32 = widths.iter().flatten().sum();
+    let unknown = widths.iter().filter(|width| width.is_none()).count();
+    let left = (available - known).max(0.0);
+    if unknown == 0 {
+        let extra = if widths.is_empty() { 0.0 } else { left / widths.len() as f32 };
+        return widths.into_iter().map(|width| width.unwrap_or(0.0) + extra).collect();
+    }
+    widths.into_iter().map(|width| width.unwrap_or(left / unknown as f32)).collect()
+}
+
+/// The widths of a table grid box's content box: those of its columns and the spacing
+/// around them.
+pub(crate) fn content_sizes(grid: &LayoutBox, measurer: &dyn TextMeasurer) -> ContentSizes {
+    let model = Grid::new(grid);
+    let spacing = total_spacing(spacing(grid).0, model.columns.len());
+    if is_fixed(grid) {
+        let columns: f32 = fixed_columns(grid, &model, 0.0).iter().sum();
+        return ContentSizes {
+            min: columns + spacing,
+            max: columns + spacing,
+        };
+    }
+    let sizes = column_sizes(grid, &model, measurer);
+    ContentSizes {
+        min: sizes.iter().map(|sizes| sizes.min).sum::<f32>() + spacing,
+        max: sizes.iter().map(|sizes| sizes.max).sum::<f32>() + spacing,
+    }
+}
+
+/// The intrinsic widths of a table wrapper box's content box. A table's `width` is only
+/// a minimum, since it's never narrower than its columns need, nor than its captions'
+/// min-content widths.
+pub(crate) fn wrapper_content_sizes(wrapper: &LayoutBox, measurer: &dyn TextMeasurer) -> ContentSizes {
+    let mut sizes = ContentSizes::default();
+    for child in &wrapper.children {
+        let outer = intrinsic::outer_sizes(child, measurer);
+        if child.display == Display::TableGrid {
+            let columns = content_sizes(child, measurer).min + horizontal_edges(child, 0.0);
+            sizes.min = sizes.min.max(outer.min.max(columns));
+            sizes.max = sizes.max.max(outer.max.max(columns));
+        } else {
+            sizes.min = sizes.min.max(outer.min);
+            sizes.max = sizes.max.max(outer.min);
+        }
+    }
+    sizes
+}
+
+fn horizontal_edges(layout_box: &LayoutBox, containing_width: f32) -> f32 {
+    let style = &layout_box.style;
+    style.padding.left.resolve(containing_width)
+        + style.padding.right.resolve(containing_width)
+        + style.border_width.left
+        + style.border_width.right
+}
+
+/// The width of a table wrapper box's content box, which its table grid box fills: the
+/// table's `width`, or else its max-content width up to `available`, but never narrower
+/// than the table needs.
+pub(crate) fn wrapper_width(
+    wrapper: &LayoutBox,
+    containing_width: f32,
+    available: f32,
+    measurer: &dyn TextMeasurer,
+) -> f32 {
+    let sizes = wrapper_content_sizes(wrapper, measurer);
+    let Some(grid) = wrapper.children.iter().find(|child| child.display == Display::TableGrid) else {
+        return sizes.max.min(available).max(sizes.min);
+    };
+    let style = &grid.style;
+    let edges = horizontal_edges(grid, containing_width);
+    let width = match style.width {
+        Some(ref width) => width.resolve(containing_width) - style.box_sizing_adjustment(true, containing_width),
+        None => sizes.max.min(available) - edges,
+    };
+    (style.clamp_width(width, containing_width) + edges).max(sizes.min)
+}
+
+/// Lay out a table grid box's contents in its content box, which `containing` is, and
+/// return their height. A height the box is given is only a minimum, and the rows share
+/// out whatever's over.
+pub(crate) fn layout_grid(grid: &mut LayoutBox, containing: ContainingBlock, measurer: &dyn TextMeasurer) -> f32 {
+    let model = Grid::new(grid);
+    let spacing = spacing(grid);
+    let width = containing.width;
+    let available = width - total_spacing(spacing.0, model.columns.len());
+    let widths = if is_fixed(grid) {
+        fixed_columns(grid, &model, available)
+    } else {
+        distribute(&column_sizes(grid, &model, measurer), available)
+    };
+    // Where each column starts, and one past the end, so a span ends a spacing before the next
+    let mut column_x = Vec::with_capacity(widths.len() + 1);
+    let mut x = spacing.0;
+    for width in &widths {
+        column_x.push(x);
+        x += width + spacing.0;
+    }
+    column_x.push(x);
+    let span_width = |cell: &Cell| column_x[cell.column + cell.colspan] - spacing.0 - column_x[cell.column];
+
+    // Lay out each cell at the width of its columns, and find the rows' heights
+    let rows = model.rows.len();
+    let mut heights: Vec<f32> = model
+        .rows
+        .iter()
+        .map(|&path| {
+            let height = row_box(grid, path).style.height.as_ref().and_then(|height| height.resolve_against(None));
+            height.unwrap_or(0.0).max(0.0)
+        })
+        .collect();
+    // How far each row's cells aligned to the baseline reach above and below it
+    let mut above = vec![None; rows];
+    let mut below = vec![0.0f32; rows];
+    let mut cell_heights = Vec::with_capacity(model.cells.len());
+    for cell in &model.cells {
+        let cell_box = &mut row_box_mut(grid, model.rows[cell.row]).children[cell.index];
+        let height = layout_cell(cell_box, span_width(cell), width, measurer);
+        cell_heights.push(height);
+        if cell.rowspan == 1 {
+            heights[cell.row] = heights[cell.row].max(height);
+            if is_baseline_aligned(cell_box) {
+                let ascent = cell_ascent(cell_box);
+                let row_above: &mut Option<f32> = &mut above[cell.row];
+                *row_above = Some(row_above.map_or(ascent, |above| above.max(ascent)));
+                below[cell.row] = below[cell.row].max(height - ascent);
+            }
+        }
+    }
+    for row in 0..rows {
+        if let Some(above) = above[row] {
+            heights[row] = heights[row].max(above + below[row]);
+        }
+    }
+    // Cells that span rows make the last of them taller if they don't fit
+    let mut spanning: Vec<(&Cell, f32)> =
+        model.cells.iter().zip(cell_heights.iter().copied()).filter(|(cell, _)| cell.rowspan > 1).collect();
+    spanning.sort_by_key(|(cell, _)| cell.rowspan);
+    for (cell, height) in spanning {
+        let last = cell.row + cell.rowspan - 1;
+        let spanned = heights[cell.row..=last].iter().sum::<f32>() + spacing.1 * (cell.rowspan - 1) as f32;
+        if height > spanned {
+            heights[last] += height - spanned;
+        }
+    }
+    if let Some(height) = containing.height {
+        let total = heights.iter().sum::<f32>() + total_spacing(spacing.1, rows);
+        if height > total && rows > 0 {
+            for row in &mut heights {
+                *row += (height - total) / rows as f32;
+            }
+        }
+    }
+    let mut row_y = Vec::with_capacity(rows + 1);
+    let mut y = spacing.1;
+    for height in &heights {
+        row_y.push(y);
+        y += height + spacing.1;
+    }
+    row_y.push(y);
+    let bottom = if rows == 0 { 0.0 } else { y };
+
+    // Stretch the cells to their rows, with their content where `vertical-align` says
+    for cell in &model.cells {
+        let row_top = row_y[cell.row];
+        let cell_box = &mut row_box_mut(grid, model.rows[cell.row]).children[cell.index];
+        let dimensions = &mut cell_box.dimensions;
+        let extra = (row_y[cell.row + cell.rowspan] - spacing.1 - row_top - dimensions.border_box().height).max(0.0);
+        let offset = match cell_box.style.vertical_align {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Middle => extra / 2.0,
+            VerticalAlign::Bottom => extra,
+            _ => above[cell.row].map_or(0.0, |above| above - cell_ascent(cell_box)).clamp(0.0, extra),
+        };
+        let dimensions = &mut cell_box.dimensions;
+        dimensions.padding.top += offset;
+        dimensions.padding.bottom += extra - offset;
+        // Relative to the row, which starts at the first column
+        dimensions.content.x = column_x[cell.column] - spacing.0 + dimensions.border.left + dimensions.padding.left;
+        dimensions.content.y = dimensions.border.top + dimensions.padding.top;
+    }
+
+    // Rows and row groups span the columns, and columns and column groups the rows
+    let row_rect = |first: usize, last: usize| Rect {
+        x: spacing.0,
+        y: row_y[first],
+        width: (width - 2.0 * spacing.0).max(0.0),
+        height: row_y[last + 1] - spacing.1 - row_y[first],
+    };
+    for (index, &path) in model.rows.iter().enumerate() {
+        let mut rect = row_rect(index, index);
+        if path.row.is_some() {
+            let group_top = model.rows.iter().position(|other| other.child == path.child).map_or(0.0, |first| row_y[first]);
+            rect.x = 0.0;
+            rect.y -= group_top;
+        }
+        place_part(row_box_mut(grid, path), rect);
+    }
+    for child in 0..grid.children.len() {
+        if !is_row_group(grid.children[child].display) {
+            continue;
+        }
+        let first = model.rows.iter().position(|path| path.child == child);
+        let last = model.rows.iter().rposition(|path| path.child == child);
+        let rect = match first.zip(last) {
+            Some((first, last)) => row_rect(first, last),
+            None => Rect::default(),
+        };
+        place_part(&mut grid.children[child], rect);
+    }
+    let column_rect = |first: usize, last: usize| Rect {
+        x: column_x[first],
+        y: spacing.1.min(bottom),
+        width: column_x[last + 1] - spacing.0 - column_x[first],
+        height: (bottom - 2.0 * spacing.1).max(0.0),
+    };
+    for child in 0..grid.children.len() {
+        let columns = |index: Option<usize>| {
+            let matches = |column: &Column| match index {
+                Some(index) => column.column == Some((child, Some(index))),
+                None => column.column == Some((child, None)) || column.group == Some(child),
+            };
+            let first = model.columns.iter().position(matches)?;
+            Some((first, model.columns.iter().rposition(matches)?))
+        };
+        let part = &mut grid.children[child];
+        if !matches!(part.display, Display::TableColumn | Display::TableColumnGroup) {
+            continue;
+        }
+        let rect = columns(None).map_or(Rect::default(), |(first, last)| column_rect(first, last));
+        for index in 0..part.children.len() {
+            let mut column = columns(Some(index)).map_or(Rect::default(), |(first, last)| column_rect(first, last));
+            column.x -= rect.x;
+            column.y -= rect.y;
+            place_part(&mut part.children[index], column);
+        }
+        place_part(part, rect);
+    }
+    bottom
+}
+
+/// Share `available` out between columns: each gets its min-content width, then they
+/// grow towards their max-content widths together, then past them in proportion to them.
+fn distribute(sizes: &[ContentSizes], available: f32) -> Vec<f32> {
+    let min: f32 = sizes.iter().map(|sizes| sizes.min).sum();
+    let max: f32 = sizes.iter().map(|sizes| sizes.max).sum();
+    if available <= min {
+        return sizes.iter().map(|sizes| sizes.min).collect();
+    }
+    if available <= max {
+        let fraction = (available - min) / (max - min);
+        return sizes.iter().map(|sizes| sizes.min + (sizes.max - sizes.min) * fraction).collect();
+    }
+    let weights: Vec<f32> = sizes.iter().map(|sizes| sizes.max).collect();
+    sizes.iter().zip(share(available - max, &weights)).map(|(sizes, extra)| sizes.max + extra).collect()
+}
+
+/// Lay out a cell's contents at the border box width `width`, and return its border
+/// box's height before it stretches to its rows. Its `height` is only a minimum.
+fn layout_cell(cell: &mut LayoutBox, width: f32, table_width: f32, measurer: &dyn TextMeasurer) -> f32 {
+    resolve_edges(cell, table_width);
+    let dimensions = &cell.dimensions;
+    let content_width = (width - dimensions.border_box().width + dimensions.content.width).max(0.0);
+    cell.dimensions.content.width = content_width;
+    let containing = ContainingBlock {
+        width: content_width,
+        height: None,
+    };
+    let height = layout_contents(cell, containing, (0.0, f32::INFINITY), measurer);
+    let style = &cell.style;
+    let specified = style
+        .height
+        .as_ref()
+        .and_then(|height| height.resolve_against(None))
+        .map_or(0.0, |height| height - style.box_sizing_adjustment(false, table_width));
+    cell.dimensions.content.height = height.max(specified);
+    cell.dimensions.border_box().height
+}
+
+fn is_baseline_aligned(cell: &LayoutBox) -> bool {
+    !matches!(cell.style.vertical_align, VerticalAlign::Top | VerticalAlign::Middle | VerticalAlign::Bottom)
+}
+
+/// How far a cell's first line's baseline is below its top border edge, or its content
+/// box's bottom if it has no lines.
+fn cell_ascent(cell: &LayoutBox) -> f32 {
+    let dimensions = &cell.dimensions;
+    dimensions.border.top
+        + dimensions.padding.top
+        + first_baseline(cell).unwrap_or(dimensions.content.height)
+}
+
+/// Give a row, row group, column or column group its rect, relative to its parent's. They
+/// have no edges of their own.
+fn place_part(part: &mut LayoutBox, rect: Rect) {
+    part.dimensions = Dimensions {
+        content: rect,
+        ..Dimensions::default()
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::test_support::{laid_out, rect};
+    use foamium_dom::parse_html;
+
+    const TABLE: &str = "table { display: table; font-size: 10px; line-height: 10px; }
+        caption { display: table-caption; }
+        tbody { display: table-row-group; }
+        tr { display: table-row; }
+        td { display: table-cell; }
+        col { display: table-column; }";
+
+    #[test]
+    fn test_anonymous_table_objects() {
+        let dom = parse_html("<div><td>a</td><td>b</td></div><table><tbody>text<td>c</td></tbody></table>");
+        let stylesheet = foamium_css::parse(&format!("{TABLE} div {{ display: block; }}"));
+        let root = build_layout_tree(&dom, &stylesheet);
+        // Cells outside a table get an anonymous row and table around them
+        let wrapper = &root.children[0].children[0];
+        assert!(wrapper.is_anonymous());
+        assert_eq!(wrapper.display, Display::Table);
+        let grid = &wrapper.children[0];
+        assert_eq!(grid.display, Display::TableGrid);
+        let row = &grid.children[0];
+        assert_eq!((row.display, row.is_anonymous()), (Display::TableRow, true));
+        assert_eq!(row.children.len(), 2);
+
+        // Text in a row group gets a row and cell of its own, and the cell after it shares the row
+        let wrapper = &root.children[1];
+        assert!(!wrapper.is_anonymous());
+        let group = &wrapper.children[0].children[0];
+        assert_eq!(group.display, Display::TableRowGroup);
+        let row = &group.children[0];
+        assert!(row.is_anonymous());
+        let displays: Vec<(Display, bool)> = row.children.iter().map(|cell| (cell.display, cell.is_anonymous())).collect();
+        assert_eq!(displays, [(Display::TableCell, true), (Display::TableCell, false)]);
+    }
+
+    #[test]
+    fn test_auto_layout_with_spans() {
+        let dom = parse_html(
+            "<table><tr><td>aaaa</td><td>bb</td></tr><tr><td colspan=\"2\">ccccccccccccc</td></tr></table>\
+             <table><tr><td rowspan=\"2\">a</td><td>b</td></tr><tr><td>c</td></tr></table>",
+        );
+        let stylesheet = foamium_css::parse(&format!("{TABLE} table {{ border-spacing: 5px; }}"));
+        let root = laid_out(&dom, &stylesheet);
+        let table = &root.children[0];
+        // The spanning cell widens both columns in proportion to their widths
+        assert_eq!(table.dimensions.content, rect(0.0, 0.0, 75.0, 35.0));
+        let grid = &table.children[0];
+        let cells: Vec<Rect> = grid
+            .children
+            .iter()
+            .flat_map(|row| &row.children)
+            .map(|cell| cell.dimensions.content)
+            .collect();
+        assert_eq!(cells, [rect(5.0, 5.0, 40.0, 10.0), rect(50.0, 5.0, 20.0, 10.0), rect(5.0, 20.0, 65.0, 10.0)]);
+
+        // A cell spanning rows stretches over both
+        let grid = &root.children[1].children[0];
+        let spanning = &grid.children[0].children[0];
+        assert_eq!(spanning.dimensions.border_box(), rect(5.0, 40.0, 5.0, 25.0));
+        assert_eq!(grid.children[1].children[0].dimensions.content, rect(15.0, 55.0, 5.0, 10.0));
+    }
+
+    #[test]
+    fn test_fixed_layout() {
+        let dom = parse_html(
+            "<table><col id=\"c\"></col><tr><td>aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa</td><td id=\"w\"></td><td></td></tr></table>",
+        );
+        let stylesheet = foamium_css::parse(&format!(
+            "{TABLE} table {{ table-layout: fixed; width: 200px; }} #c {{ width: 50px; }} #w {{ width: 30px; }}"
+        ));
+        let root = laid_out(&dom, &stylesheet);
+        // The columns' widths come from the column and the first row, not the content
+        let row = &root.children[0].children[0].children[1];
+        let cells: Vec<(f32, f32)> =
+            row.children.iter().map(|cell| (cell.dimensions.content.x, cell.dimensions.content.width)).collect();
+        assert_eq!(cells, [(0.0, 50.0), (50.0, 30.0), (80.0, 120.0)]);
+    }
+
+    #[test]
+    fn test_border_collapse() {
+        let dom = parse_html("<table><tr><td>aa</td><td>bb</td></tr></table>");
+        let stylesheet = foamium_css::parse(&format!(
+            "{TABLE} table {{ border-collapse: collapse; border: 4px solid; border-spacing: 10px; }}
+             td {{ border: 2px solid; }}"
+        ));
+        let root = laid_out(&dom, &stylesheet);
+        let table = &root.children[0];
+        let grid = &table.children[0];
+        // The table and the cells each take half of the borders they share
+        assert_eq!(grid.style.border_width, Sides::all(2.0));
+        let row = &grid.children[0];
+        let widths: Vec<Sides<f32>> = row.children.iter().map(|cell| cell.style.border_width).collect();
+        let sides = |left, right| Sides {
+            top: 2.0,
+            right,
+            bottom: 2.0,
+            left,
+        };
+        assert_eq!(widths, [sides(2.0, 1.0), sides(1.0, 2.0)]);
+        // and there's no spacing between them
+        assert_eq!(table.dimensions.content.width, 30.0);
+        let xs: Vec<f32> = row.children.iter().map(|cell| cell.dimensions.content.x).collect();
+        assert_eq!(xs, [4.0, 16.0]);
+    }
+
+    #[test]
+    fn test_captions() {
+        let dom = parse_html("<table><caption>top</caption><caption id=\"b\">bottom</caption><tr><td>x</td></tr></table>");
+        let stylesheet = foamium_css::parse(&format!("{TABLE} #b {{ caption-side: bottom; }}"));
+        let root = laid_out(&dom, &stylesheet);
+        let table = &root.children[0];
+        let displays: Vec<Display> = table.children.iter().map(|child| child.display).collect();
+        assert_eq!(displays, [Display::TableCaption, Display::TableGrid, Display::TableCaption]);
+        // The table is at least as wide as its captions, which go above and below the grid
+        let ys: Vec<f32> = table.children.iter().map(|child| child.dimensions.content.y).collect();
+        assert_eq!(ys, [0.0, 10.0, 20.0]);
+        assert_eq!(table.dimensions.content.width, 30.0);
+    }
+}
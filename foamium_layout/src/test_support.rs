@@ -0,0 +1,20 @@
+// Fixtures shared by the layout tests
+use crate::{build_layout_tree, layout, LayoutBox, Rect};
+use foamium_css::Stylesheet;
+use foamium_dom::DomNode;
+
+pub(crate) fn rect(x: f32, y: f32, width: f32, height: f32) -> Rect {
+    Rect { x, y, width, height }
+}
+
+/// Build the layout tree for `dom` and lay it out in an 800×600 viewport.
+pub(crate) fn laid_out<'a>(dom: &'a DomNode, stylesheet: &'a Stylesheet) -> LayoutBox<'a> {
+    let mut root = build_layout_tree(dom, stylesheet);
+    layout(&mut root, rect(0.0, 0.0, 800.0, 600.0));
+    root
+}
+
+/// The content boxes of a box's children.
+pub(crate) fn contents(layout_box: &LayoutBox) -> Vec<Rect> {
+    layout_box.children.iter().map(|child| child.dimensions.content).collect()
+}
@@ -1,10 +1,18 @@
 use anyhow::Result;
+use foamium_css::{FontFace, FontSource, ImportedStylesheet};
 use reqwest::blocking::Client;
+use reqwest::Url;
 
 pub struct NetworkManager {
     client: Client,
 }
 
+impl Default for NetworkManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl NetworkManager {
     pub fn new() -> Self {
         Self {
@@ -17,4 +25,41 @@ impl NetworkManager {
         let text = response.text()?;
         Ok(text)
     }
+
+    pub fn fetch_bytes(&self, url: &str) -> Result<Vec<u8>> {
+        let response = self.client.get(url).send()?.error_for_status()?;
+        Ok(response.bytes()?.to_vec())
+    }
+
+    /// Loader for `foamium_css::parse_with_imports`: fetches `url` relative to the sheet
+    /// at `base_url`. Local `file://` sheets are read from disk.
+    pub fn load_stylesheet(&self, url: &str, base_url: &str) -> Option<ImportedStylesheet> {
+        let url = Url::parse(base_url).and_then(|base| base.join(url)).ok()?;
+        let source = if url.scheme() == "file" {
+            std::fs::read_to_string(url.to_file_path().ok()?).ok()?
+        } else {
+            self.fetch_text(url.as_str()).ok()?
+        };
+        Some(ImportedStylesheet {
+            url: url.to_string(),
+            source,
+        })
+    }
+
+    /// Fetch the data for a web font, trying each of its loadable `src` entries in order.
+    /// `local()` sources are left to the font system and skipped here.
+    pub fn load_font_face(&self, face: &FontFace, base_url: &str) -> Option<Vec<u8>> {
+        let base = Url::parse(base_url).ok()?;
+        face.loadable_sources().find_map(|source| {
+            let FontSource::Url { url, .. } = source else {
+                return None;
+            };
+            let url = base.join(url).ok()?;
+            if url.scheme() == "file" {
+                std::fs::read(url.to_file_path().ok()?).ok()
+            } else {
+                self.fetch_bytes(url.as_str()).ok()
+            }
+        })
+    }
 }